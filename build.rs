@@ -0,0 +1,5 @@
+fn main() {
+    // the gRPC service is generated from its schema, which needs protoc
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/toy_crdt.proto").unwrap();
+}
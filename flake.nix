@@ -43,6 +43,7 @@
                 cargo-flamegraph
                 cargo-deny
                 crate2nix
+                protobuf
 
                 rnix-lsp
                 nixpkgs-fmt
@@ -0,0 +1,50 @@
+/* The C interface to toy-crdt's fixed map, built with `cargo rustc --lib --features ffi
+ * --crate-type cdylib` (or staticlib). See src/ffi.rs for the details. */
+
+#ifndef TOY_CRDT_H
+#define TOY_CRDT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CRDT_OK 0
+#define CRDT_NOT_FOUND 1
+#define CRDT_ERR_NULL (-1)
+#define CRDT_ERR_CHAR (-2)
+#define CRDT_ERR_DECODE (-3)
+
+/* A replica's map, owned by the caller from crdt_map_new until crdt_map_free. */
+typedef struct FixedMap crdt_map;
+
+/* Bytes allocated by the library, freed with crdt_bytes_free. */
+typedef struct {
+    uint8_t *data;
+    size_t len;
+} crdt_bytes;
+
+crdt_map *crdt_map_new(size_t actor_id);
+void crdt_map_free(crdt_map *map);
+
+/* Effect an op on the map, writing the message for the other replicas to `op` unless it is
+ * NULL. Keys and values are Unicode scalar values. */
+int32_t crdt_map_set(crdt_map *map, uint32_t key, uint32_t value, crdt_bytes *op);
+int32_t crdt_map_delete(crdt_map *map, uint32_t key, crdt_bytes *op);
+int32_t crdt_map_get(const crdt_map *map, uint32_t key, uint32_t *value);
+
+/* Effect the ops in a message from another replica. */
+int32_t crdt_map_apply_op(crdt_map *map, const uint8_t *data, size_t len);
+
+/* Write a JSON snapshot of the map to `out`. */
+int32_t crdt_map_serialize(const crdt_map *map, crdt_bytes *out);
+
+void crdt_bytes_free(crdt_bytes *bytes);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,71 @@
+use crate::explore::Visited;
+use crate::fingerprint::Fingerprint;
+
+/// Visited states kept in a Bloom filter, so a state space can be swept in a fraction of the
+/// memory when exact answers aren't needed.
+///
+/// A new state can be wrongly taken as visited and not explored, along with anything only
+/// reachable through it, so violations can be missed, though any found are real. Parents aren't
+/// kept, so counterexamples can't be rebuilt.
+pub(crate) struct BloomVisited {
+    bits: Vec<u64>,
+    /// Number of bits.
+    len: u64,
+    hashes: u32,
+    inserted: u64,
+}
+
+impl BloomVisited {
+    /// Size the filter for the expected number of states to have the given false positive rate
+    /// once they are all inserted.
+    pub(crate) fn new(expected_states: u64, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let len = (-(expected_states.max(1) as f64) * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as u64;
+        let hashes = ((len as f64 / expected_states.max(1) as f64) * ln2)
+            .round()
+            .max(1.0) as u32;
+        println!(
+            "Bloom filter of {} bits ({} bytes) with {} hashes",
+            len,
+            (len + 7) / 8,
+            hashes
+        );
+        Self {
+            bits: vec![0; ((len + 63) / 64) as usize],
+            len,
+            hashes,
+            inserted: 0,
+        }
+    }
+}
+
+impl Visited for BloomVisited {
+    fn insert(&mut self, state: Fingerprint, _parent: Option<Fingerprint>) -> bool {
+        // the bits for a state come from double hashing its fingerprint
+        let step = (state.rotate_left(32) ^ 0x9e3779b97f4a7c15) | 1;
+        let mut new = false;
+        for i in 0..self.hashes as u64 {
+            let index = state.wrapping_add(i.wrapping_mul(step)) % self.len;
+            let (word, bit) = ((index / 64) as usize, 1 << (index % 64));
+            if self.bits[word] & bit == 0 {
+                self.bits[word] |= bit;
+                new = true;
+            }
+        }
+        if new {
+            self.inserted += 1;
+        }
+        new
+    }
+
+    fn parent(&mut self, _state: Fingerprint) -> Option<Fingerprint> {
+        None
+    }
+
+    fn false_positive_rate(&self) -> Option<f64> {
+        let filled = 1.0 - (-(self.hashes as f64) * self.inserted as f64 / self.len as f64).exp();
+        Some(filled.powi(self.hashes as i32))
+    }
+}
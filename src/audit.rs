@@ -0,0 +1,122 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use stateright::CheckerVisitor;
+use stateright::Path;
+
+use crate::fingerprint::fingerprint;
+use crate::fingerprint::Fingerprint;
+use crate::map::Map;
+use crate::model::RegisterAction;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::reduction::commute;
+use crate::MyRegisterActorState;
+use crate::MyRegisterMsg;
+use crate::PeerMsg;
+
+/// A pair of concurrent ops that gave different results depending on the order they were applied.
+#[derive(Debug)]
+struct Finding {
+    first: PeerMsg,
+    second: PeerMsg,
+    /// The server and state where the pair was first found.
+    server: usize,
+    state: Fingerprint,
+    /// How many visited states the pair didn't commute in.
+    count: usize,
+}
+
+/// Checks that every pair of concurrent ops in flight to the same server commutes on that
+/// server's state, as the checker visits states.
+///
+/// This pinpoints the op pair behind a divergence rather than leaving it to be found from the
+/// servers' final states.
+#[derive(Clone, Default)]
+pub(crate) struct CommutativityAudit(Arc<Mutex<Vec<Finding>>>);
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> CheckerVisitor<RegisterModel<M>>
+    for CommutativityAudit
+{
+    fn visit(&self, _model: &RegisterModel<M>, path: Path<RegisterState<M>, RegisterAction<M>>) {
+        let state = path.last_state();
+        for (server, actor) in state.actor_states.iter().enumerate() {
+            let map = match &**actor {
+                MyRegisterActorState::Server(map) => &**map,
+                _ => continue,
+            };
+            let ops = state
+                .network
+                .iter_deliverable()
+                .filter(|envelope| usize::from(envelope.dst) == server)
+                .filter_map(|envelope| match envelope.msg {
+                    MyRegisterMsg::Internal(msg) if msg.is_op() => Some(msg),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            for (i, first) in ops.iter().enumerate() {
+                for second in &ops[i + 1..] {
+                    if concurrent(first, second) && !commute(map, first, second) {
+                        self.record(first, second, server, fingerprint(state));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl CommutativityAudit {
+    fn record(&self, first: &PeerMsg, second: &PeerMsg, server: usize, state: Fingerprint) {
+        let mut findings = self.0.lock().unwrap();
+        let existing = findings.iter_mut().find(|finding| {
+            (&finding.first, &finding.second) == (first, second)
+                || (&finding.first, &finding.second) == (second, first)
+        });
+        match existing {
+            Some(finding) => finding.count += 1,
+            None => findings.push(Finding {
+                first: first.clone(),
+                second: second.clone(),
+                server,
+                state,
+                count: 1,
+            }),
+        }
+    }
+
+    pub(crate) fn report(&self) {
+        let findings = self.0.lock().unwrap();
+        if findings.is_empty() {
+            println!("Commutativity audit: all concurrent op pairs commuted");
+            return;
+        }
+        println!(
+            "Commutativity audit: {} concurrent op pairs didn't commute",
+            findings.len()
+        );
+        for finding in findings.iter() {
+            println!(
+                "  {:?} and {:?} on server {} (first in state {:016x}, {} states)",
+                finding.first, finding.second, finding.server, finding.state, finding.count
+            );
+        }
+    }
+}
+
+/// Whether neither op overwrites the other, so they may be applied in either order.
+fn concurrent(a: &PeerMsg, b: &PeerMsg) -> bool {
+    !overwrites(a, b) && !overwrites(b, a)
+}
+
+fn overwrites(a: &PeerMsg, b: &PeerMsg) -> bool {
+    match (a, b) {
+        (
+            PeerMsg::PutSync { context, .. } | PeerMsg::DeleteSync { context },
+            PeerMsg::PutSync { timestamp, .. },
+        ) => context.contains(timestamp),
+        _ => false,
+    }
+}
@@ -0,0 +1,41 @@
+use crate::PeerMsg;
+use crate::Value;
+
+/// A copy of a set with the same timestamp and context but a different value, which honest
+/// servers never send as each timestamp is only ever given to one set.
+pub(crate) fn forge(op: &PeerMsg) -> Option<PeerMsg> {
+    match op {
+        PeerMsg::PutSync {
+            context,
+            timestamp,
+            key,
+            value,
+        } => Some(PeerMsg::PutSync {
+            context: context.clone(),
+            timestamp: *timestamp,
+            key: *key,
+            value: forged_value(*value),
+        }),
+        _ => None,
+    }
+}
+
+/// What a byzantine server sends its other peers on receiving a set or delete: the op again, as
+/// if replaying an old message, and a forged copy of a set.
+pub(crate) fn replays(op: &PeerMsg) -> Vec<PeerMsg> {
+    match op {
+        PeerMsg::PutSync { .. } | PeerMsg::DeleteSync { .. } => {
+            Some(op.clone()).into_iter().chain(forge(op)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// A well-formed value other than the given one, so that forgeries pass the checks on values.
+fn forged_value(value: Value) -> Value {
+    if value == 'X' {
+        'Y'
+    } else {
+        'X'
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::map::Timestamp;
+use crate::Key;
+use crate::PeerMsg;
+use crate::RequestId;
+use crate::Value;
+
+/// A version token along with the key of the write it names, so that servers on other shards
+/// know which servers to ask about it.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Dependency {
+    pub(crate) timestamp: Timestamp,
+    pub(crate) key: Key,
+}
+
+/// A write a client sent to a server on a different shard to its dependency, which the server
+/// only applies once every server holding the dependency's key has confirmed that it applied it.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct PendingWrite {
+    pub(crate) client: usize,
+    pub(crate) request_id: RequestId,
+    pub(crate) key: Key,
+    pub(crate) value: Value,
+    pub(crate) dependency: Dependency,
+    /// The servers holding the dependency that haven't confirmed it yet.
+    pub(crate) unconfirmed: BTreeSet<usize>,
+}
+
+/// What a server tracks to apply writes that depend on another write, named by a version token,
+/// only once it has applied that write.
+///
+/// Servers only track this when clients pass tokens, so that states don't otherwise differ in
+/// it.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct Causal {
+    /// Timestamps of the sets this server has applied.
+    pub(crate) applied: BTreeSet<Timestamp>,
+    /// The dependency of each dependent write this server has applied, by the dependent write's
+    /// timestamp.
+    pub(crate) dependencies: BTreeMap<Timestamp, Timestamp>,
+    /// Dependent writes received before their dependency, already validated.
+    pub(crate) deferred: Vec<PeerMsg>,
+    /// With sharding, dependent writes from clients waiting for their dependency to be confirmed.
+    pub(crate) pending: Vec<PendingWrite>,
+    /// With sharding, the dependency of each dependent write this server applied once it was
+    /// confirmed, by the dependent write's timestamp.
+    pub(crate) confirmed: BTreeMap<Timestamp, Dependency>,
+    /// With sharding, the servers waiting for this one to apply a dependency, and the dependency.
+    pub(crate) checks: BTreeSet<(usize, Timestamp)>,
+}
+
+impl Causal {
+    /// Record that a set has been applied.
+    pub(crate) fn record(&mut self, op: &PeerMsg) {
+        match op {
+            PeerMsg::PutSync { timestamp, .. } => {
+                self.applied.insert(*timestamp);
+            }
+            PeerMsg::After { dependency, op } => {
+                if let PeerMsg::PutSync { timestamp, .. } = &**op {
+                    self.dependencies.insert(*timestamp, *dependency);
+                }
+                self.record(op)
+            }
+            PeerMsg::Stamped { op, .. } => self.record(op),
+            PeerMsg::Changes { changes } => {
+                for change in changes {
+                    self.record(&change.op)
+                }
+            }
+            PeerMsg::Ops { ops } => {
+                for pulled in ops {
+                    self.record(&pulled.op)
+                }
+            }
+            PeerMsg::Repair { ops } => {
+                for op in ops {
+                    self.record(op)
+                }
+            }
+            PeerMsg::DeleteSync { .. }
+            | PeerMsg::AddSync { .. }
+            | PeerMsg::RemoveSync { .. }
+            | PeerMsg::CounterState { .. }
+            | PeerMsg::RegisterSync { .. }
+            | PeerMsg::LwwRegisterSync { .. }
+            | PeerMsg::FlagSync { .. }
+            | PeerMsg::ListInsertSync { .. }
+            | PeerMsg::ListDeleteSync { .. }
+            | PeerMsg::NestedSetSync { .. }
+            | PeerMsg::NestedDeleteSync { .. }
+            | PeerMsg::StateSync { .. }
+            | PeerMsg::DeltaSync { .. }
+            | PeerMsg::Have { .. }
+            | PeerMsg::Need { .. }
+            | PeerMsg::DepCheck { .. }
+            | PeerMsg::DepOk { .. }
+            | PeerMsg::Pull { .. }
+            | PeerMsg::Join
+            | PeerMsg::Leave
+            | PeerMsg::PutSyncAck { .. }
+            | PeerMsg::DeleteSyncAck { .. }
+            | PeerMsg::Digest { .. }
+            | PeerMsg::Truncate { .. }
+            | PeerMsg::EpochClose { .. }
+            | PeerMsg::EpochDone { .. }
+            | PeerMsg::Watermark { .. } => {}
+            _ => {}
+        }
+    }
+
+    /// Whether the op has to wait for a write this server hasn't applied yet.
+    pub(crate) fn must_wait(&self, op: &PeerMsg) -> bool {
+        matches!(op, PeerMsg::After { dependency, .. } if !self.applied.contains(dependency))
+    }
+
+    /// Take the dependency checks that can be answered, as this server has applied their
+    /// dependency.
+    pub(crate) fn answerable_checks(&mut self) -> Vec<(usize, Timestamp)> {
+        let (answerable, waiting) = std::mem::take(&mut self.checks)
+            .into_iter()
+            .partition::<BTreeSet<_>, _>(|(_, dependency)| self.applied.contains(dependency));
+        self.checks = waiting;
+        answerable.into_iter().collect()
+    }
+
+    /// Record that a server confirmed it has applied a dependency, taking the pending writes that
+    /// every server holding their dependency has now confirmed.
+    pub(crate) fn confirm(&mut self, server: usize, dependency: Timestamp) -> Vec<PendingWrite> {
+        for write in &mut self.pending {
+            if write.dependency.timestamp == dependency {
+                write.unconfirmed.remove(&server);
+            }
+        }
+        let (confirmed, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|write| write.unconfirmed.is_empty());
+        self.pending = pending;
+        confirmed
+    }
+
+    /// Take the deferred writes that no longer have to wait.
+    pub(crate) fn ready(&mut self) -> Vec<PeerMsg> {
+        let (waiting, ready) = std::mem::take(&mut self.deferred)
+            .into_iter()
+            .partition::<Vec<_>, _>(|op| self.must_wait(op));
+        self.deferred = waiting;
+        ready
+    }
+}
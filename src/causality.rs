@@ -0,0 +1,248 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use stateright::actor::Envelope;
+use stateright::Property;
+
+use crate::map::Map;
+use crate::map::Timestamp;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::sync::PeerState;
+use crate::ModelCfg;
+use crate::MyRegisterActorState;
+use crate::MyRegisterMsg;
+use crate::PeerMsg;
+
+/// An op on the map, identified the same way on every server.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+enum OpId {
+    Set {
+        timestamp: Timestamp,
+        context: Vec<Timestamp>,
+    },
+    Delete {
+        origin: usize,
+        context: Vec<Timestamp>,
+    },
+}
+
+impl OpId {
+    /// The timestamp of a set, which identifies it however a server came to know of it.
+    fn timestamp(&self) -> Option<Timestamp> {
+        match self {
+            OpId::Set { timestamp, .. } => Some(*timestamp),
+            OpId::Delete { .. } => None,
+        }
+    }
+
+    /// Whether this op happens after the other: it overwrote or removed the other, or both are
+    /// sets prepared in turn by the same server. The happens-before relation is the transitive
+    /// closure of this, which checking every op against what each server applied covers.
+    fn follows(&self, other: &OpId) -> bool {
+        let other_timestamp = match other {
+            OpId::Set { timestamp, .. } => timestamp,
+            OpId::Delete { .. } => return false,
+        };
+        match self {
+            OpId::Set { timestamp, context } => {
+                context.contains(other_timestamp)
+                    || (timestamp.1 == other_timestamp.1 && timestamp.0 > other_timestamp.0)
+            }
+            OpId::Delete { context, .. } => context.contains(other_timestamp),
+        }
+    }
+}
+
+/// What the causal consistency suite needs to know about the run so far: the map ops each
+/// server has received or prepared, from which the happens-before relation between them is
+/// reconstructed.
+///
+/// This is only recorded when the suite is selected, since it makes otherwise equal states
+/// distinct.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct CausalHistory {
+    /// The ops each server has received or prepared, by actor index.
+    delivered: BTreeMap<usize, BTreeSet<OpId>>,
+}
+
+impl CausalHistory {
+    /// Record that `server` received or prepared the ops in the message, returning whether any
+    /// were new.
+    fn record(&mut self, server: usize, msg: &PeerMsg, origin: usize) -> bool {
+        let delivered = self.delivered.entry(server).or_default();
+        ops(msg, origin)
+            .into_iter()
+            .fold(false, |changed, op| delivered.insert(op) || changed)
+    }
+
+    /// The ops a server has applied: those it received or prepared, less those still waiting
+    /// for their dependencies.
+    fn applied<M: Clone + Debug + PartialEq + Hash + Map>(
+        &self,
+        index: usize,
+        server: &PeerState<M>,
+    ) -> BTreeSet<OpId> {
+        let mut applied = self.delivered.get(&index).cloned().unwrap_or_default();
+        let buffered = server.delivery.iter().flat_map(|delivery| {
+            delivery
+                .buffered
+                .iter()
+                .map(|(origin, _, op)| (*origin, op))
+        });
+        let deferred = server
+            .causal
+            .iter()
+            .flat_map(|causal| causal.deferred.iter().map(|op| (index, op)));
+        for (origin, op) in buffered.chain(deferred) {
+            for op in ops(op, origin) {
+                applied.remove(&op);
+            }
+        }
+        applied
+    }
+}
+
+/// The map ops a message brings, given the server it came from.
+fn ops(msg: &PeerMsg, origin: usize) -> Vec<OpId> {
+    match msg {
+        PeerMsg::PutSync {
+            context, timestamp, ..
+        } => vec![OpId::Set {
+            timestamp: *timestamp,
+            context: context.iter().collect(),
+        }],
+        // a truncate removes the values in its barrier as a delete would
+        PeerMsg::DeleteSync { context } | PeerMsg::Truncate { barrier: context } => {
+            vec![OpId::Delete {
+                origin,
+                context: context.iter().collect(),
+            }]
+        }
+        PeerMsg::After { op, .. } => ops(op, origin),
+        PeerMsg::Stamped { origin, op, .. } => ops(op, *origin),
+        PeerMsg::Repair { ops: repair } => repair.iter().flat_map(|op| ops(op, origin)).collect(),
+        // a merged map brings every set its sender had seen, whether it still holds them or
+        // removed them since
+        PeerMsg::StateSync { values, removed } | PeerMsg::DeltaSync { values, removed } => values
+            .iter()
+            .map(|(timestamp, _, _)| *timestamp)
+            .chain(removed.iter())
+            .map(|timestamp| OpId::Set {
+                timestamp,
+                context: Vec::new(),
+            })
+            .collect(),
+        PeerMsg::AddSync { .. }
+        | PeerMsg::RemoveSync { .. }
+        | PeerMsg::CounterState { .. }
+        | PeerMsg::RegisterSync { .. }
+        | PeerMsg::LwwRegisterSync { .. }
+        | PeerMsg::FlagSync { .. }
+        | PeerMsg::ListInsertSync { .. }
+        | PeerMsg::ListDeleteSync { .. }
+        | PeerMsg::NestedSetSync { .. }
+        | PeerMsg::NestedDeleteSync { .. }
+        | PeerMsg::Have { .. }
+        | PeerMsg::Need { .. }
+        | PeerMsg::Changes { .. }
+        | PeerMsg::DepCheck { .. }
+        | PeerMsg::DepOk { .. }
+        | PeerMsg::Pull { .. }
+        | PeerMsg::Ops { .. }
+        | PeerMsg::Join
+        | PeerMsg::Leave
+        | PeerMsg::PutSyncAck { .. }
+        | PeerMsg::DeleteSyncAck { .. }
+        | PeerMsg::Digest { .. }
+        | PeerMsg::EpochClose { .. }
+        | PeerMsg::EpochDone { .. }
+        | PeerMsg::Watermark { .. } => Vec::new(),
+        _ => Vec::new(),
+    }
+}
+
+/// Record the ops a server receives from its peers.
+pub(crate) fn record_in(
+    cfg: &ModelCfg,
+    history: &CausalHistory,
+    envelope: &Envelope<&MyRegisterMsg>,
+) -> Option<CausalHistory> {
+    if !cfg.suite.records_causal_history() {
+        return None;
+    }
+    let msg = match envelope.msg {
+        MyRegisterMsg::Internal(msg) => msg,
+        _ => return None,
+    };
+    let mut history = history.clone();
+    history
+        .record(envelope.dst.into(), msg, envelope.src.into())
+        .then(|| history)
+}
+
+/// Record the ops a server prepared itself, or already had, when it sends them on.
+pub(crate) fn record_out(
+    cfg: &ModelCfg,
+    history: &CausalHistory,
+    envelope: &Envelope<&MyRegisterMsg>,
+) -> Option<CausalHistory> {
+    if !cfg.suite.records_causal_history() {
+        return None;
+    }
+    let msg = match envelope.msg {
+        MyRegisterMsg::Internal(msg) => msg,
+        _ => return None,
+    };
+    let mut history = history.clone();
+    history
+        .record(envelope.src.into(), msg, envelope.src.into())
+        .then(|| history)
+}
+
+/// Causal consistency: every server applies each op only after the ops it happens after.
+pub(crate) fn properties<M: Clone + Debug + PartialEq + Hash + Map>(
+) -> Vec<Property<RegisterModel<M>>> {
+    vec![Property::always(
+        "causal: servers apply ops after the ops they happen after",
+        |_, state| applied_in_causal_order(state),
+    )]
+}
+
+/// Whether the ops each server has applied are closed under happens-before, among the ops that
+/// any server has received or prepared. Checked in every state, this means each server applied
+/// the ops in an order consistent with causality.
+fn applied_in_causal_order<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    let history = &state.history.causality;
+    let known = history
+        .delivered
+        .values()
+        .flatten()
+        .collect::<BTreeSet<_>>();
+    state
+        .actor_states
+        .iter()
+        .enumerate()
+        .all(|(index, actor)| match &**actor {
+            // servers that left ignore the ops they receive
+            MyRegisterActorState::Server(server) if !server.has_left() => {
+                let applied = history.applied(index, server);
+                let applied_sets = applied
+                    .iter()
+                    .filter_map(OpId::timestamp)
+                    .collect::<BTreeSet<_>>();
+                applied.iter().all(|op| {
+                    known
+                        .iter()
+                        .filter(|other| op.follows(other))
+                        .filter_map(|other| other.timestamp())
+                        .all(|timestamp| applied_sets.contains(&timestamp))
+                })
+            }
+            _ => true,
+        })
+}
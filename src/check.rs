@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::time::Duration;
+use std::time::Instant;
+
+use stateright::Checker;
+use stateright::CheckerVisitor;
+use stateright::Expectation;
+use stateright::Model;
+use stateright::Path;
+
+use crate::explain::explain;
+use crate::fingerprint::fingerprint;
+use crate::map::Map;
+use crate::model::RegisterAction;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::trace;
+
+/// How stateright's checker searches the state space.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Strategy {
+    /// Depth first, which reaches deep states sooner.
+    Dfs,
+    /// Breadth first, which finds the shortest counterexamples.
+    Bfs,
+}
+
+/// Several visitors run on each visited path, as a checker only takes one.
+pub(crate) struct Visitors<M: Clone + Debug + PartialEq + Hash + Map>(
+    pub(crate) Vec<Box<dyn CheckerVisitor<RegisterModel<M>> + Send + Sync>>,
+);
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> CheckerVisitor<RegisterModel<M>> for Visitors<M> {
+    fn visit(&self, model: &RegisterModel<M>, path: Path<RegisterState<M>, RegisterAction<M>>) {
+        for visitor in &self.0 {
+            visitor.visit(model, path.clone());
+        }
+    }
+}
+
+/// The status a check exits with when `--depth-limit` or `--max-states` cut it short and no
+/// properties were violated, so that scripts can tell it from a complete check.
+pub(crate) const TRUNCATED_EXIT: i32 = 3;
+
+/// Bounds on how much of the state space a check explores.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Bounds {
+    /// Most steps from an initial state to explore; states this deep aren't expanded.
+    pub(crate) depth: Option<usize>,
+    /// Most states to explore.
+    pub(crate) states: Option<usize>,
+}
+
+impl Bounds {
+    /// Whether a check that reached `max_depth` and explored `states` stopped at these bounds
+    /// rather than exhausting the state space, as a message saying which. Reaching the depth
+    /// limit counts even if the states there had no successors, as the checker doesn't say.
+    pub(crate) fn hit(&self, max_depth: usize, states: usize) -> Option<String> {
+        if let Some(limit) = self.states.filter(|limit| states >= *limit) {
+            Some(format!(
+                "Bounded result: state limit of {} reached, the state space was not exhausted",
+                limit
+            ))
+        } else {
+            self.depth.filter(|limit| max_depth >= *limit).map(|limit| {
+                format!(
+                    "Bounded result: depth limit of {} reached, the state space may not have been exhausted",
+                    limit
+                )
+            })
+        }
+    }
+}
+
+/// Whether the checker stopped at the bounds rather than exhausting the state space, saying so
+/// if it did.
+pub(crate) fn truncated<M, C>(checker: &C, bounds: Bounds) -> bool
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+    C: Checker<RegisterModel<M>>,
+{
+    match bounds.hit(checker.max_depth(), checker.state_count()) {
+        Some(truncated) => {
+            println!("{}", truncated);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Wait for the checker to finish, or for the time limit to pass, then report how many unique
+/// states it explored per second.
+///
+/// Returns whether the state space was fully explored.
+pub(crate) fn wait<M, C>(checker: C, time_limit: Option<Duration>) -> (C, bool)
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+    C: Checker<RegisterModel<M>>,
+{
+    let start = Instant::now();
+    let time_limit = match time_limit {
+        Some(time_limit) => time_limit,
+        None => {
+            let checker = checker.report(&mut std::io::stdout()).join();
+            print_rate(&checker, start.elapsed());
+            return (checker, true);
+        }
+    };
+
+    while !checker.is_done() && start.elapsed() < time_limit {
+        std::thread::sleep(Duration::from_secs(1).min(time_limit));
+        println!(
+            "Checking. states={}, unique={}, depth={}",
+            checker.state_count(),
+            checker.unique_state_count(),
+            checker.max_depth()
+        );
+    }
+
+    if checker.is_done() {
+        let checker = checker.join();
+        print_rate(&checker, start.elapsed());
+        (checker, true)
+    } else {
+        print_rate(&checker, start.elapsed());
+        println!(
+            "Bounded result: time limit of {:?} reached after {} states ({} unique), the state space was not exhausted",
+            time_limit,
+            checker.state_count(),
+            checker.unique_state_count()
+        );
+        // the checker threads are left running and are stopped when the process exits
+        (checker, false)
+    }
+}
+
+/// Print how fast the checker explored unique states, to compare the cost of changes to the
+/// model.
+fn print_rate<M, C>(checker: &C, elapsed: Duration)
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+    C: Checker<RegisterModel<M>>,
+{
+    let unique = checker.unique_state_count();
+    println!(
+        "Explored {} unique states in {:.2?} ({:.0} states/sec)",
+        unique,
+        elapsed,
+        unique as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+}
+
+/// Report the outcome of each property, exiting with a failure if any were violated.
+///
+/// Without `keep_going` this stops at the first violation, otherwise all violations are reported
+/// with their counterexamples. When the check wasn't `complete` properties without discoveries
+/// are labelled as bounded results.
+pub(crate) fn check_properties<M: Clone + Debug + PartialEq + Hash + Map>(
+    checker: &impl Checker<RegisterModel<M>>,
+    keep_going: bool,
+    complete: bool,
+) {
+    let discoveries = checker
+        .discoveries()
+        .into_iter()
+        .map(|(name, path)| (name, path.into_vec()))
+        .collect();
+    print_stats(checker.model(), &discoveries);
+
+    let violations = report_properties(checker.model(), discoveries, keep_going, complete);
+    if violations > 0 {
+        // the expected violations would trip the assertion too
+        if !keep_going && checker.model().cfg().expected_violations.is_empty() {
+            checker.assert_properties();
+        }
+        println!("{} properties violated", violations);
+        std::process::exit(1);
+    }
+}
+
+/// The steps of a discovery: each state along with the action taken from it, if any.
+pub(crate) type Steps<M> = Vec<(RegisterState<M>, Option<RegisterAction<M>>)>;
+
+/// Report the outcome of each property given the discoveries made, returning how many were
+/// violated, counting properties that were expected to be violated but held instead.
+pub(crate) fn report_properties<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    mut discoveries: BTreeMap<&'static str, Steps<M>>,
+    keep_going: bool,
+    complete: bool,
+) -> usize {
+    let mut violations = 0;
+    for property in model.properties() {
+        let discovery = discoveries.remove(property.name);
+        let expected = model
+            .cfg()
+            .expected_violations
+            .iter()
+            .any(|name| name == property.name);
+        match (property.expectation, discovery) {
+            (Expectation::Always | Expectation::Eventually, Some(steps)) if expected => {
+                println!("Property {:?} violated, as expected", property.name);
+                print_counterexample(&steps);
+                explain(&steps);
+            }
+            (Expectation::Always | Expectation::Eventually, None) if expected && complete => {
+                violations += 1;
+                println!(
+                    "Property {:?} holds, but was expected to be violated",
+                    property.name
+                );
+            }
+            (Expectation::Sometimes, Some(_)) if expected => {
+                violations += 1;
+                println!(
+                    "Property {:?} has an example, but was expected to be violated",
+                    property.name
+                );
+            }
+            (Expectation::Sometimes, None) if expected && complete => {
+                println!("Property {:?} violated, as expected", property.name);
+            }
+            (Expectation::Always | Expectation::Eventually, Some(steps)) => {
+                violations += 1;
+                println!("Property {:?} violated", property.name);
+                if keep_going {
+                    print_counterexample(&steps);
+                }
+                explain(&steps);
+                if let Some(dir) = &model.cfg().save_counterexamples {
+                    save_counterexample(dir, property.name, &steps);
+                }
+            }
+            (Expectation::Sometimes, None) if complete => {
+                violations += 1;
+                println!("Property {:?} violated, no example found", property.name);
+            }
+            (Expectation::Sometimes, None) => {
+                println!(
+                    "Property {:?} has no example in the explored portion (bounded result)",
+                    property.name
+                );
+            }
+            (Expectation::Always | Expectation::Eventually, None) if !complete => {
+                println!(
+                    "Property {:?} not violated in the explored portion (bounded result)",
+                    property.name
+                );
+            }
+            (Expectation::Always | Expectation::Eventually, None)
+            | (Expectation::Sometimes, Some(_)) => {
+                println!("Property {:?} holds", property.name);
+            }
+        }
+    }
+    violations
+}
+
+/// Write a counterexample to a trace file, saying where it went.
+fn save_counterexample<M: Clone + Debug + PartialEq + Hash + Map>(
+    dir: &std::path::Path,
+    property: &str,
+    steps: &Steps<M>,
+) {
+    match trace::save_counterexample(dir, property, steps) {
+        Ok((path, 0)) => println!("Saved counterexample to {:?}", path),
+        Ok((path, skipped)) => println!(
+            "Saved counterexample to {:?}, leaving out {} steps that weren't deliveries",
+            path, skipped
+        ),
+        Err(e) => eprintln!("Failed to save counterexample: {}", e),
+    }
+}
+
+/// Print each step of a counterexample with the fingerprint of the state it was taken from.
+fn print_counterexample<M: Clone + Debug + PartialEq + Hash + Map>(steps: &Steps<M>) {
+    println!("Counterexample:");
+    for (i, (state, action)) in steps.iter().enumerate() {
+        match action {
+            Some(action) => println!("  {}: [{:016x}] {:?}", i, fingerprint(state), action),
+            None => println!("  {}: [{:016x}]", i, fingerprint(state)),
+        }
+    }
+}
+
+/// Print stable fingerprints for the initial states and the final state of each discovery so that
+/// runs can be compared across machines.
+pub(crate) fn print_stats<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    discoveries: &BTreeMap<&'static str, Steps<M>>,
+) {
+    for state in model.init_states() {
+        println!("Initial state fingerprint: {:016x}", fingerprint(&state));
+    }
+    for (name, steps) in discoveries {
+        let (state, _) = steps.last().expect("discoveries have a state");
+        println!(
+            "Discovery {:?} final state fingerprint: {:016x}",
+            name,
+            fingerprint(state)
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::map::Map;
+use crate::map_broken::BrokenMap;
+use crate::map_fixed::FixedMap;
+use crate::model::RegisterState;
+use crate::trace::init_state;
+use crate::trace::replay_step;
+use crate::trace::Trace;
+use crate::ModelCfg;
+use crate::MyRegisterActorState;
+
+/// The key-value pairs visible on each server.
+type Observable = Vec<Vec<(char, char)>>;
+
+/// Replay the trace against both the broken and fixed maps, reporting the first step at which
+/// the servers' visible values differ between them.
+pub(crate) fn compare(cfg: ModelCfg, trace: &Trace) {
+    let broken = cfg.clone().into_actor_model::<BrokenMap>();
+    let fixed = cfg.into_actor_model::<FixedMap>();
+
+    let mut broken_state = init_state(&broken);
+    let mut fixed_state = init_state(&fixed);
+
+    for (i, step) in trace.steps.iter().enumerate() {
+        broken_state = match replay_step(&broken, &broken_state, step) {
+            Some(state) => state,
+            None => {
+                println!("Step {}: {:?} can't be delivered with BrokenMap", i, step);
+                return;
+            }
+        };
+        fixed_state = match replay_step(&fixed, &fixed_state, step) {
+            Some(state) => state,
+            None => {
+                println!("Step {}: {:?} can't be delivered with FixedMap", i, step);
+                return;
+            }
+        };
+
+        let broken_observable = observable(&broken_state);
+        let fixed_observable = observable(&fixed_state);
+        if broken_observable != fixed_observable {
+            println!("Diverged at step {}: {:?}", i, step);
+            for (server, (b, f)) in broken_observable.iter().zip(&fixed_observable).enumerate() {
+                let marker = if b == f { " " } else { "*" };
+                println!("{} server {}: broken={:?} fixed={:?}", marker, server, b, f);
+            }
+            return;
+        }
+    }
+
+    println!("No divergence in {} steps", trace.steps.len());
+}
+
+fn observable<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> Observable {
+    state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::Server(map) => Some(
+                map.visible_values()
+                    .into_iter()
+                    .map(|(_, k, v)| (k, v))
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .collect()
+}
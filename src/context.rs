@@ -0,0 +1,261 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::map::Timestamp;
+
+/// A set of timestamps, such as the values an op removes: the dots of a causal context, held as
+/// ranges of counters for each actor id.
+///
+/// A server has usually seen a run of each other server's ops, so the timestamps a context
+/// holds mostly come in runs too, and a range stands for a whole run. Every change leaves each
+/// actor's ranges sorted, apart and not adjacent, so contexts holding the same timestamps are
+/// equal and hash the same.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CausalContext {
+    /// The inclusive ranges of counters held, by actor id.
+    ranges: BTreeMap<usize, Vec<(u32, u32)>>,
+}
+
+impl CausalContext {
+    /// An empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the context holds no timestamps.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// How many timestamps the context holds.
+    pub fn len(&self) -> usize {
+        self.ranges
+            .values()
+            .flatten()
+            .map(|(start, end)| (end - start) as usize + 1)
+            .sum()
+    }
+
+    /// Whether the context holds `timestamp`.
+    pub fn contains(&self, timestamp: &Timestamp) -> bool {
+        let (counter, actor_id) = *timestamp;
+        self.ranges.get(&actor_id).is_some_and(|ranges| {
+            // only the last range starting at or before the counter can hold it
+            let after = ranges.partition_point(|(start, _)| *start <= counter);
+            after > 0 && ranges[after - 1].1 >= counter
+        })
+    }
+
+    /// The greatest timestamp the context holds, if it holds any.
+    pub fn max(&self) -> Option<Timestamp> {
+        self.ranges
+            .iter()
+            .filter_map(|(actor_id, ranges)| ranges.last().map(|(_, end)| (*end, *actor_id)))
+            .max()
+    }
+
+    /// The timestamps the context holds, by actor id and then counter.
+    pub fn iter(&self) -> impl Iterator<Item = Timestamp> + '_ {
+        self.ranges.iter().flat_map(|(actor_id, ranges)| {
+            ranges.iter().flat_map(move |(start, end)| {
+                (*start..=*end).map(move |counter| (counter, *actor_id))
+            })
+        })
+    }
+
+    /// Add a timestamp to the context.
+    pub fn insert(&mut self, timestamp: Timestamp) {
+        self.extend([timestamp]);
+    }
+
+    /// Add every timestamp `other` holds to this context.
+    pub fn union(&mut self, other: &CausalContext) {
+        for (actor_id, ranges) in &other.ranges {
+            self.ranges
+                .entry(*actor_id)
+                .or_default()
+                .extend(ranges.iter().copied());
+        }
+        self.compact();
+    }
+
+    /// Keep only the timestamps `keep` holds for.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Timestamp) -> bool) {
+        *self = self.iter().filter(|timestamp| keep(timestamp)).collect();
+    }
+
+    /// Merge each actor's overlapping and adjacent ranges into one and sort them, as every
+    /// change to the context does once it has added its ranges.
+    pub fn compact(&mut self) {
+        for ranges in self.ranges.values_mut() {
+            ranges.sort_unstable();
+            let mut compacted: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+            for (start, end) in ranges.drain(..) {
+                match compacted.last_mut() {
+                    Some(last) if start <= last.1.saturating_add(1) => {
+                        last.1 = core::cmp::max(last.1, end)
+                    }
+                    _ => compacted.push((start, end)),
+                }
+            }
+            *ranges = compacted;
+        }
+        self.ranges.retain(|_, ranges| !ranges.is_empty());
+    }
+}
+
+impl Extend<Timestamp> for CausalContext {
+    fn extend<I: IntoIterator<Item = Timestamp>>(&mut self, timestamps: I) {
+        for (counter, actor_id) in timestamps {
+            self.ranges
+                .entry(actor_id)
+                .or_default()
+                .push((counter, counter));
+        }
+        self.compact();
+    }
+}
+
+impl FromIterator<Timestamp> for CausalContext {
+    fn from_iter<I: IntoIterator<Item = Timestamp>>(timestamps: I) -> Self {
+        let mut context = CausalContext::new();
+        context.extend(timestamps);
+        context
+    }
+}
+
+impl From<Vec<Timestamp>> for CausalContext {
+    fn from(timestamps: Vec<Timestamp>) -> Self {
+        timestamps.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn ranges(context: &CausalContext, actor_id: usize) -> Vec<(u32, u32)> {
+        context.ranges.get(&actor_id).cloned().unwrap_or_default()
+    }
+
+    #[test]
+    fn insert_merges_adjacent_counters() {
+        let mut context = CausalContext::new();
+        context.insert((1, 0));
+        context.insert((2, 0));
+        context.insert((3, 0));
+        assert_eq!(ranges(&context, 0), vec![(1, 3)]);
+        assert_eq!(context.len(), 3);
+    }
+
+    #[test]
+    fn insert_keeps_gaps_until_filled() {
+        let mut context = CausalContext::new();
+        context.insert((4, 0));
+        context.insert((1, 0));
+        assert_eq!(ranges(&context, 0), vec![(1, 1), (4, 4)]);
+        assert!(!context.contains(&(2, 0)));
+
+        context.insert((3, 0));
+        context.insert((2, 0));
+        assert_eq!(ranges(&context, 0), vec![(1, 4)]);
+        assert!(context.contains(&(2, 0)));
+    }
+
+    #[test]
+    fn insert_is_idempotent() {
+        let mut context = CausalContext::from(vec![(1, 0), (2, 0)]);
+        context.insert((2, 0));
+        assert_eq!(ranges(&context, 0), vec![(1, 2)]);
+        assert_eq!(context.len(), 2);
+    }
+
+    #[test]
+    fn contains_checks_each_range_and_actor() {
+        let context = CausalContext::from(vec![(1, 0), (2, 0), (5, 0), (3, 1)]);
+        assert!(context.contains(&(1, 0)));
+        assert!(context.contains(&(2, 0)));
+        assert!(!context.contains(&(3, 0)));
+        assert!(!context.contains(&(4, 0)));
+        assert!(context.contains(&(5, 0)));
+        assert!(!context.contains(&(6, 0)));
+        assert!(!context.contains(&(0, 0)));
+        assert!(context.contains(&(3, 1)));
+        assert!(!context.contains(&(1, 1)));
+        assert!(!context.contains(&(1, 2)));
+    }
+
+    #[test]
+    fn union_merges_overlapping_ranges_and_keeps_other_actors() {
+        let mut a = CausalContext::from(vec![(1, 0), (2, 0), (3, 0), (7, 0), (1, 1)]);
+        let b = CausalContext::from(vec![(3, 0), (4, 0), (5, 0), (9, 0), (2, 2)]);
+        a.union(&b);
+        assert_eq!(ranges(&a, 0), vec![(1, 5), (7, 7), (9, 9)]);
+        assert_eq!(ranges(&a, 1), vec![(1, 1)]);
+        assert_eq!(ranges(&a, 2), vec![(2, 2)]);
+        assert_eq!(a.len(), 9);
+    }
+
+    #[test]
+    fn retain_splits_ranges() {
+        let mut context = CausalContext::from(vec![(1, 0), (2, 0), (3, 0), (4, 0), (1, 1)]);
+        context.retain(|timestamp| *timestamp != (2, 0) && *timestamp != (1, 1));
+        assert_eq!(ranges(&context, 0), vec![(1, 1), (3, 4)]);
+        assert!(!context.ranges.contains_key(&1));
+        assert_eq!(context.len(), 3);
+    }
+
+    #[test]
+    fn union_bridges_a_gap() {
+        let mut a = CausalContext::from(vec![(1, 0), (3, 0)]);
+        a.union(&CausalContext::from(vec![(2, 0)]));
+        assert_eq!(ranges(&a, 0), vec![(1, 3)]);
+    }
+
+    #[test]
+    fn union_with_empty_changes_nothing() {
+        let mut a = CausalContext::from(vec![(1, 0), (2, 1)]);
+        let before = a.clone();
+        a.union(&CausalContext::new());
+        assert_eq!(a, before);
+
+        let mut empty = CausalContext::new();
+        empty.union(&before);
+        assert_eq!(empty, before);
+    }
+
+    #[test]
+    fn compact_merges_unsorted_overlapping_ranges() {
+        let mut context = CausalContext::new();
+        context
+            .ranges
+            .insert(0, vec![(6, 8), (1, 2), (2, 4), (5, 5), (10, 11)]);
+        context.ranges.insert(1, Vec::new());
+        context.ranges.insert(
+            2,
+            vec![(u32::MAX - 1, u32::MAX), (3, 3), (u32::MAX, u32::MAX)],
+        );
+        context.compact();
+        assert_eq!(ranges(&context, 0), vec![(1, 8), (10, 11)]);
+        assert!(!context.ranges.contains_key(&1));
+        assert_eq!(ranges(&context, 2), vec![(3, 3), (u32::MAX - 1, u32::MAX)]);
+    }
+
+    #[test]
+    fn same_timestamps_make_equal_contexts() {
+        let a = CausalContext::from(vec![(3, 0), (1, 0), (2, 1), (2, 0)]);
+        let b = CausalContext::from(vec![(2, 1), (1, 0), (2, 0), (3, 0), (1, 0)]);
+        assert_eq!(a, b);
+        assert_eq!(
+            a.iter().collect::<Vec<_>>(),
+            vec![(1, 0), (2, 0), (3, 0), (2, 1)]
+        );
+        assert_eq!(a.max(), Some((3, 0)));
+        assert_eq!(CausalContext::new().max(), None);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::id::Id;
+
+/// The interface the checker uses to drive a counter, and the one to embed a counter with.
+///
+/// Unlike [`Map`](crate::map::Map) and [`Set`](crate::set::Set), counters are state-based: a
+/// replica updates its own state and sends the whole state on, and replicas merge the states
+/// they receive. Merging is commutative, associative and idempotent, so states can arrive in any
+/// order and any number of times.
+pub trait Counter {
+    /// A counter for the server with the given actor id, which must be unique among the servers.
+    fn new(actor_id: Id) -> Self;
+
+    /// The increments minus the decrements this replica knows of.
+    fn value(&self) -> i64;
+
+    /// Count an increment made at this replica.
+    fn increment(&mut self);
+
+    /// Count a decrement made at this replica.
+    fn decrement(&mut self);
+
+    /// Merge the state of another replica into this one.
+    fn merge(&mut self, other: &Self);
+}
@@ -0,0 +1,148 @@
+use alloc::vec::Vec;
+
+use crate::counter::Counter;
+use crate::id::Id;
+
+impl Counter for PNCounter {
+    fn new(actor_id: Id) -> Self {
+        Self::new(actor_id.into())
+    }
+
+    fn value(&self) -> i64 {
+        self.value()
+    }
+
+    fn increment(&mut self) {
+        self.increment()
+    }
+
+    fn decrement(&mut self) {
+        self.decrement()
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.receive_state(&other.increments, &other.decrements)
+    }
+}
+
+/// A positive-negative counter: each replica counts its own increments and decrements, and the
+/// value is the sum of every replica's increments minus the sum of their decrements.
+///
+/// Each replica only ever raises its own counts, so merging takes the greatest count seen for
+/// each replica.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct PNCounter {
+    actor_id: usize,
+    /// The increments made at each replica, by actor id.
+    pub increments: Vec<u64>,
+    /// The decrements made at each replica, by actor id.
+    pub decrements: Vec<u64>,
+}
+
+impl PNCounter {
+    /// A zero counter for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            increments: Vec::new(),
+            decrements: Vec::new(),
+        }
+    }
+
+    pub fn value(&self) -> i64 {
+        self.increments.iter().sum::<u64>() as i64 - self.decrements.iter().sum::<u64>() as i64
+    }
+
+    pub fn increment(&mut self) {
+        bump(&mut self.increments, self.actor_id)
+    }
+
+    pub fn decrement(&mut self) {
+        bump(&mut self.decrements, self.actor_id)
+    }
+
+    /// Merge another replica's counts into this one's.
+    pub fn receive_state(&mut self, increments: &[u64], decrements: &[u64]) {
+        merge_max(&mut self.increments, increments);
+        merge_max(&mut self.decrements, decrements);
+    }
+}
+
+fn bump(counts: &mut Vec<u64>, actor_id: usize) {
+    if counts.len() <= actor_id {
+        counts.resize(actor_id + 1, 0);
+    }
+    counts[actor_id] += 1;
+}
+
+fn merge_max(counts: &mut Vec<u64>, other: &[u64]) {
+    if counts.len() < other.len() {
+        counts.resize(other.len(), 0);
+    }
+    for (count, other) in counts.iter_mut().zip(other) {
+        *count = core::cmp::max(*count, *other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three replicas that have each counted differently.
+    fn replicas() -> [PNCounter; 3] {
+        let mut a = PNCounter::new(0);
+        a.increment();
+        a.increment();
+        a.increment();
+        let mut b = PNCounter::new(1);
+        b.decrement();
+        let mut c = PNCounter::new(2);
+        c.increment();
+        c.decrement();
+        c.decrement();
+        [a, b, c]
+    }
+
+    fn merged(a: &PNCounter, b: &PNCounter) -> PNCounter {
+        let mut merged = a.clone();
+        Counter::merge(&mut merged, b);
+        merged
+    }
+
+    #[test]
+    fn value_counts_every_replica() {
+        let [a, b, c] = replicas();
+        assert_eq!(merged(&merged(&a, &b), &c).value(), 1);
+    }
+
+    #[test]
+    fn merge_is_commutative() {
+        let [a, b, _] = replicas();
+        let ab = merged(&a, &b);
+        let ba = merged(&b, &a);
+        assert_eq!(ab.value(), ba.value());
+        assert_eq!(ab.increments, ba.increments);
+        assert_eq!(ab.decrements, ba.decrements);
+    }
+
+    #[test]
+    fn merge_is_associative_and_idempotent() {
+        let [a, b, c] = replicas();
+        let left = merged(&merged(&a, &b), &c);
+        let right = merged(&a, &merged(&b, &c));
+        assert_eq!(left.increments, right.increments);
+        assert_eq!(left.decrements, right.decrements);
+        assert_eq!(merged(&left, &left), left);
+    }
+
+    #[test]
+    fn merge_keeps_the_latest_counts() {
+        let [mut a, _, _] = replicas();
+        let old = a.clone();
+        a.increment();
+        let mut b = PNCounter::new(1);
+        b.receive_state(&a.increments, &a.decrements);
+        b.receive_state(&old.increments, &old.decrements);
+        assert_eq!(b.value(), 4);
+    }
+}
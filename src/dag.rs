@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Write;
+use std::hash::Hash;
+
+use crate::explain::applied_ops;
+use crate::explain::Op;
+use crate::map::Map;
+use crate::map::Timestamp;
+use crate::model::RegisterModel;
+use crate::trace::replay;
+use crate::trace::Trace;
+
+/// Render the ops on `key` from a replayed trace as a DOT graph, with an edge from each op to the
+/// ops whose causal context included it.
+pub(crate) fn dag<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    trace: &Trace,
+    key: char,
+) -> String {
+    let steps = replay(model, trace);
+
+    let mut ops = Vec::new();
+    for server in 0..model.cfg().servers {
+        for op in applied_ops(&steps, server) {
+            if !ops.contains(&op) {
+                ops.push(op);
+            }
+        }
+    }
+
+    let set_keys = ops
+        .iter()
+        .filter_map(|op| match op {
+            Op::Set { timestamp, key, .. } => Some((*timestamp, *key)),
+            Op::Delete { .. } => None,
+        })
+        .collect::<HashMap<_, _>>();
+    let ops = ops.into_iter().filter(|op| match op {
+        Op::Set { key: k, .. } => *k == key,
+        Op::Delete { context, .. } => context.iter().any(|t| set_keys.get(t) == Some(&key)),
+    });
+
+    let mut dot = String::new();
+    writeln!(dot, "digraph {:?} {{", format!("key {}", key)).unwrap();
+    for op in ops {
+        let (node, context) = match &op {
+            Op::Set {
+                context, timestamp, ..
+            } => (set_node(timestamp), context),
+            Op::Delete { server, context } => (format!("delete {} {:?}", server, context), context),
+        };
+        writeln!(dot, "  {:?} [label={:?}];", node, op.to_string()).unwrap();
+        for t in context {
+            writeln!(dot, "  {:?} -> {:?};", set_node(t), node).unwrap();
+        }
+    }
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+fn set_node(timestamp: &Timestamp) -> String {
+    format!("set {}.{}", timestamp.0, timestamp.1)
+}
@@ -0,0 +1,74 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::io::BufRead;
+use std::io::Write;
+
+use stateright::Expectation;
+use stateright::Model;
+
+use crate::map::Map;
+use crate::model::RegisterModel;
+use crate::render;
+
+/// Step through the model by hand, choosing which action to take next from stdin.
+///
+/// Each line of input is the index of an action to take, so a sequence of choices can also be
+/// piped in to reconstruct a specific interleaving. `q` or end of input stops the session.
+pub(crate) fn debug<M: Clone + Debug + PartialEq + Hash + Map>(model: RegisterModel<M>) {
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    let mut state = model
+        .init_states()
+        .into_iter()
+        .next()
+        .expect("actor models have an initial state");
+    let mut step = 0;
+
+    loop {
+        println!("State {}:", step);
+        print!("{}", render::server_tables(&state));
+        for property in model.properties() {
+            if let Expectation::Always = property.expectation {
+                if !(property.condition)(&model, &state) {
+                    println!("  property {:?} violated", property.name);
+                }
+            }
+        }
+
+        let mut actions = Vec::new();
+        model.actions(&state, &mut actions);
+        if actions.is_empty() {
+            println!("No more actions");
+            return;
+        }
+        for (i, action) in actions.iter().enumerate() {
+            println!("  {}: {}", i, model.format_action(action));
+        }
+
+        let choice = loop {
+            print!("> ");
+            std::io::stdout().flush().unwrap();
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(_)) | None => return,
+            };
+            let line = line.trim();
+            if line == "q" {
+                return;
+            }
+            match line.parse::<usize>() {
+                Ok(i) if i < actions.len() => break i,
+                _ => println!("Expected an action index below {}", actions.len()),
+            }
+        };
+
+        match model.next_state(&state, actions.swap_remove(choice)) {
+            Some(next) => {
+                state = next;
+                step += 1;
+            }
+            None => println!("Action didn't change the state"),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::BTreeMap;
+
+use crate::MyRegisterMsg;
+use crate::RequestId;
+
+/// What a server tracks about client requests when clients retry them.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct Requests {
+    /// The response each mutating request got, by client and request id.
+    responses: BTreeMap<(usize, RequestId), MyRegisterMsg>,
+    /// How many times each request mutated the map, by client and request id.
+    pub(crate) mutations: BTreeMap<(usize, RequestId), usize>,
+}
+
+impl Requests {
+    /// The response to a request that has already been handled.
+    ///
+    /// Every request is remembered rather than just the latest from each client, since a retry
+    /// may arrive after the client's next request.
+    pub(crate) fn response(&self, client: usize, request_id: RequestId) -> Option<&MyRegisterMsg> {
+        self.responses.get(&(client, request_id))
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        client: usize,
+        request_id: RequestId,
+        response: MyRegisterMsg,
+        mutated: bool,
+    ) {
+        self.responses.insert((client, request_id), response);
+        if mutated {
+            *self.mutations.entry((client, request_id)).or_default() += 1;
+        }
+    }
+}
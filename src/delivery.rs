@@ -0,0 +1,87 @@
+use crate::peer::VectorClock;
+use crate::PeerMsg;
+
+/// What a server tracks to deliver the ops from its peers in causal order and only once each,
+/// whatever order the network delivers them in and however often.
+///
+/// Each op a server broadcasts is stamped with its vector clock, counting the op itself. A peer
+/// delivers the op once it has delivered the op before it from the same origin and every op the
+/// origin had delivered from other servers, buffering it until then, and drops copies of ops it
+/// has already delivered or buffered.
+///
+/// Servers only track this with causal delivery, so that states don't otherwise differ in it.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct CausalDelivery {
+    id: usize,
+    pub(crate) clock: VectorClock,
+    /// Ops received before their causal dependencies, already validated, with their origin and
+    /// the clock they were stamped with.
+    pub(crate) buffered: Vec<(usize, VectorClock, PeerMsg)>,
+}
+
+impl CausalDelivery {
+    pub(crate) fn new(id: usize) -> Self {
+        Self {
+            id,
+            clock: VectorClock::new(),
+            buffered: Vec::new(),
+        }
+    }
+
+    /// Stamp an op this server applied locally with its clock, counting the op as delivered.
+    pub(crate) fn stamp(&mut self, op: PeerMsg) -> PeerMsg {
+        *self.clock.entry(self.id).or_default() += 1;
+        PeerMsg::Stamped {
+            origin: self.id,
+            clock: self.clock.clone(),
+            op: Box::new(op),
+        }
+    }
+
+    /// Receive a stamped op from its origin, returning the ops that can now be applied, in
+    /// causal order: the op itself if it is ready, followed by the buffered ops it unblocks.
+    pub(crate) fn receive(
+        &mut self,
+        origin: usize,
+        clock: VectorClock,
+        op: PeerMsg,
+    ) -> Vec<PeerMsg> {
+        let seq = count(&clock, origin);
+        let buffered = self
+            .buffered
+            .iter()
+            .any(|(o, c, _)| *o == origin && count(c, origin) == seq);
+        if seq <= count(&self.clock, origin) || buffered {
+            return Vec::new();
+        }
+        self.buffered.push((origin, clock, op));
+
+        let mut ready = Vec::new();
+        while let Some(index) = self
+            .buffered
+            .iter()
+            .position(|(origin, clock, _)| self.is_ready(*origin, clock))
+        {
+            let (origin, _, op) = self.buffered.remove(index);
+            *self.clock.entry(origin).or_default() += 1;
+            ready.push(op);
+        }
+        ready
+    }
+
+    /// Whether an op is the next from its origin and everything its origin had delivered before
+    /// it has been delivered here.
+    fn is_ready(&self, origin: usize, clock: &VectorClock) -> bool {
+        clock.iter().all(|(server, seq)| {
+            if *server == origin {
+                *seq == count(&self.clock, origin) + 1
+            } else {
+                *seq <= count(&self.clock, *server)
+            }
+        })
+    }
+}
+
+fn count(clock: &VectorClock, server: usize) -> usize {
+    clock.get(&server).copied().unwrap_or(0)
+}
@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::explore::Visited;
+use crate::fingerprint::Fingerprint;
+
+/// Bytes in each slot of the table: a state's fingerprint followed by its parent's.
+const SLOT_BYTES: u64 = 16;
+
+/// Slots in a new table, which doubles whenever it gets half full.
+const INITIAL_SLOTS: u64 = 1 << 16;
+
+/// Visited states kept in an open-addressing hash table in a file, for explorations whose visited
+/// set doesn't fit in memory.
+///
+/// Every new state costs a probe of the file, while states already visited are usually answered
+/// from an in-memory cache of recently inserted fingerprints, which is cleared when it fills.
+///
+/// Empty slots are zeroed, so a fingerprint of zero is stored as one, and initial states are
+/// stored as their own parent. The file is removed when the store is dropped.
+pub(crate) struct DiskVisited {
+    path: PathBuf,
+    file: File,
+    slots: u64,
+    len: u64,
+    cache: HashSet<Fingerprint>,
+    cache_capacity: usize,
+    cache_hits: u64,
+    probes: u64,
+}
+
+impl DiskVisited {
+    /// Create an empty table in the directory, caching up to `cache_capacity` fingerprints.
+    pub(crate) fn create(dir: &Path, cache_capacity: usize) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join("visited.table");
+        let file = create_table(&path, INITIAL_SLOTS)?;
+        Ok(Self {
+            path,
+            file,
+            slots: INITIAL_SLOTS,
+            len: 0,
+            cache: HashSet::new(),
+            cache_capacity,
+            cache_hits: 0,
+            probes: 0,
+        })
+    }
+
+    pub(crate) fn report(&self) {
+        println!(
+            "Visited states on disk: {} in a table of {} slots ({} bytes) at {:?}",
+            self.len,
+            self.slots,
+            self.slots * SLOT_BYTES,
+            self.path
+        );
+        println!(
+            "  {} lookups answered from the cache, {} slots probed on disk",
+            self.cache_hits, self.probes
+        );
+    }
+
+    fn read_slot(&mut self, slot: u64) -> io::Result<(Fingerprint, Fingerprint)> {
+        self.probes += 1;
+        read_slot(&mut self.file, slot)
+    }
+
+    /// Find the slot holding the state, or the empty slot it would go in, and whether it was
+    /// found.
+    fn find(&mut self, state: Fingerprint) -> io::Result<(u64, Option<Fingerprint>)> {
+        let mut slot = state % self.slots;
+        loop {
+            match self.read_slot(slot)? {
+                (0, _) => return Ok((slot, None)),
+                (found, parent) if found == state => return Ok((slot, Some(parent))),
+                _ => slot = (slot + 1) % self.slots,
+            }
+        }
+    }
+
+    fn insert_new(&mut self, state: Fingerprint, parent: Fingerprint) -> io::Result<bool> {
+        let (slot, found) = self.find(state)?;
+        if found.is_some() {
+            return Ok(false);
+        }
+        write_slot(&mut self.file, slot, state, parent)?;
+        self.len += 1;
+        if self.len * 2 > self.slots {
+            self.grow()?;
+        }
+        Ok(true)
+    }
+
+    /// Move the entries into a table twice the size.
+    fn grow(&mut self) -> io::Result<()> {
+        let slots = self.slots * 2;
+        let path = self.path.with_extension("grow");
+        let mut file = create_table(&path, slots)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        let mut old = BufReader::new(&self.file);
+        for _ in 0..self.slots {
+            let (state, parent) = read_entry(&mut old)?;
+            if state == 0 {
+                continue;
+            }
+            let mut slot = state % slots;
+            while read_slot(&mut file, slot)?.0 != 0 {
+                slot = (slot + 1) % slots;
+            }
+            write_slot(&mut file, slot, state, parent)?;
+        }
+        std::fs::rename(&path, &self.path)?;
+        self.file = file;
+        self.slots = slots;
+        Ok(())
+    }
+
+    fn expect<T>(&self, result: io::Result<T>) -> T {
+        result
+            .unwrap_or_else(|e| panic!("Failed to access visited states at {:?}: {}", self.path, e))
+    }
+}
+
+impl Visited for DiskVisited {
+    fn insert(&mut self, state: Fingerprint, parent: Option<Fingerprint>) -> bool {
+        let state = state.max(1);
+        if self.cache.contains(&state) {
+            self.cache_hits += 1;
+            return false;
+        }
+        let parent = parent.map_or(state, |parent| parent.max(1));
+        let result = self.insert_new(state, parent);
+        let inserted = self.expect(result);
+        if self.cache.len() >= self.cache_capacity {
+            self.cache.clear();
+        }
+        self.cache.insert(state);
+        inserted
+    }
+
+    fn parent(&mut self, state: Fingerprint) -> Option<Fingerprint> {
+        let state = state.max(1);
+        let result = self.find(state);
+        let (_, parent) = self.expect(result);
+        parent.filter(|parent| *parent != state)
+    }
+}
+
+impl Drop for DiskVisited {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn create_table(path: &Path, slots: u64) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    file.set_len(slots * SLOT_BYTES)?;
+    Ok(file)
+}
+
+fn read_slot(file: &mut File, slot: u64) -> io::Result<(Fingerprint, Fingerprint)> {
+    file.seek(SeekFrom::Start(slot * SLOT_BYTES))?;
+    read_entry(file)
+}
+
+fn read_entry(reader: &mut impl Read) -> io::Result<(Fingerprint, Fingerprint)> {
+    let mut state = [0; 8];
+    let mut parent = [0; 8];
+    reader.read_exact(&mut state)?;
+    reader.read_exact(&mut parent)?;
+    Ok((
+        Fingerprint::from_le_bytes(state),
+        Fingerprint::from_le_bytes(parent),
+    ))
+}
+
+fn write_slot(
+    file: &mut File,
+    slot: u64,
+    state: Fingerprint,
+    parent: Fingerprint,
+) -> io::Result<()> {
+    file.seek(SeekFrom::Start(slot * SLOT_BYTES))?;
+    file.write_all(&state.to_le_bytes())?;
+    file.write_all(&parent.to_le_bytes())
+}
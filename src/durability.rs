@@ -0,0 +1,71 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::map::Map;
+use crate::sync::PeerState;
+use crate::wire;
+use crate::PeerMsg;
+
+/// What a server restarts from after crashing.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Restart {
+    /// The snapshot it took after handling its last client mutation, so it only loses ops from
+    /// peers.
+    Snapshot,
+    /// Its initial state, as if its disk was lost too, so it loses every op it applied,
+    /// including its own, and its op counter starts over.
+    Empty,
+}
+
+/// What a server keeps on disk to recover from a crash: a snapshot of its state, taken whenever
+/// it has handled a client's mutation so that acknowledged ops are never lost, or after every
+/// `every` ops it applies, along with the ops it has applied since, which only reach the disk
+/// with the next snapshot.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct Durability<M> {
+    pub(crate) snapshot: Box<PeerState<M>>,
+    /// Ops applied since the snapshot, lost if the server crashes before the next one.
+    pub(crate) unflushed: Vec<PeerMsg>,
+    /// Take the next snapshot once this many ops are unflushed, rather than on client mutations.
+    pub(crate) every: Option<usize>,
+}
+
+impl<M> Durability<M> {
+    /// Whether enough ops have been applied since the snapshot to take the next one, when
+    /// snapshots are taken every so many ops.
+    pub(crate) fn due(&self) -> bool {
+        self.every
+            .map_or(false, |every| self.unflushed.len() >= every)
+    }
+}
+
+/// Read a map back as a recovering server would: its snapshot written out in the wire format and
+/// parsed again, so that any state the snapshot leaves out is lost as it would be on disk.
+pub(crate) fn reload<M: Map>(map: &mut M) {
+    let written = wire::encode(&map.snapshot());
+    map.restore(wire::decode(&written).expect("snapshots deserialize"));
+}
+
+/// How a server recovered from its crash, kept so that properties can check the recovery.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct Recovery<M> {
+    /// The server's map just before it crashed.
+    pub(crate) before: M,
+    /// The state the server recovered, from its last snapshot.
+    pub(crate) recovered: Box<PeerState<M>>,
+    /// The ops the server had applied since its last snapshot, none of which it acknowledged
+    /// unless it restarted empty.
+    pub(crate) lost: Vec<PeerMsg>,
+}
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> Recovery<M> {
+    /// Whether replaying the lost ops onto the recovered state gives back the map from before
+    /// the crash, so the recovered state only lacks the unacknowledged ops.
+    pub(crate) fn equivalent(&self) -> bool {
+        let mut replayed = (*self.recovered).clone();
+        for op in &self.lost {
+            replayed.apply(op.clone());
+        }
+        *replayed == self.before
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::map::Timestamp;
+use crate::PeerMsg;
+
+/// What a server tracks to roll into new epochs along with its peers, so that the truncation
+/// metadata from old epochs can be dropped.
+///
+/// A server closes its epoch after applying a truncate, or when a peer's marker closing it
+/// arrives, by sending every peer a marker. Channels are ordered, so once the markers from every
+/// peer have arrived so has every op they prepared in the epoch, and the server says it is done.
+/// Once every server is done no op from the epoch can still arrive anywhere, so the sets from it
+/// no longer need remembering and the server rolls over. Ops prepared meanwhile belong to the
+/// next epoch.
+///
+/// Servers only track this when epochs are enabled, so that states don't otherwise differ in
+/// it.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct Epochs {
+    pub(crate) epoch: u32,
+    /// Whether this server has sent its peers its marker closing the current epoch.
+    closing: bool,
+    /// Peers whose marker closing the current epoch has arrived.
+    closed: BTreeSet<usize>,
+    /// Peers whose marker closing the next epoch arrived before this server rolled into it.
+    closed_next: BTreeSet<usize>,
+    /// Whether this server has said it is done with the current epoch.
+    done_sent: bool,
+    /// Peers that have said they are done with the current epoch.
+    done: BTreeSet<usize>,
+    /// The sets this server has seen, by the epoch they were prepared in.
+    pub(crate) sets: BTreeMap<u32, BTreeSet<Timestamp>>,
+}
+
+impl Epochs {
+    /// Record the epoch of the sets an op brings, given the peer it came from or `None` for
+    /// ops prepared locally.
+    pub(crate) fn record(&mut self, op: &PeerMsg, from: Option<usize>) {
+        let epoch = match from {
+            Some(peer) => {
+                self.epoch
+                    + self.closed.contains(&peer) as u32
+                    + self.closed_next.contains(&peer) as u32
+            }
+            None => self.epoch + self.closing as u32,
+        };
+        match op {
+            PeerMsg::PutSync { timestamp, .. } => {
+                self.sets.entry(epoch).or_default().insert(*timestamp);
+            }
+            PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => self.record(op, from),
+            PeerMsg::DeleteSync { .. }
+            | PeerMsg::AddSync { .. }
+            | PeerMsg::RemoveSync { .. }
+            | PeerMsg::CounterState { .. }
+            | PeerMsg::RegisterSync { .. }
+            | PeerMsg::LwwRegisterSync { .. }
+            | PeerMsg::FlagSync { .. }
+            | PeerMsg::ListInsertSync { .. }
+            | PeerMsg::ListDeleteSync { .. }
+            | PeerMsg::NestedSetSync { .. }
+            | PeerMsg::NestedDeleteSync { .. }
+            | PeerMsg::StateSync { .. }
+            | PeerMsg::DeltaSync { .. }
+            | PeerMsg::Have { .. }
+            | PeerMsg::Need { .. }
+            | PeerMsg::Changes { .. }
+            | PeerMsg::DepCheck { .. }
+            | PeerMsg::DepOk { .. }
+            | PeerMsg::Pull { .. }
+            | PeerMsg::Ops { .. }
+            | PeerMsg::Join
+            | PeerMsg::Leave
+            | PeerMsg::PutSyncAck { .. }
+            | PeerMsg::DeleteSyncAck { .. }
+            | PeerMsg::Digest { .. }
+            | PeerMsg::Repair { .. }
+            | PeerMsg::Truncate { .. }
+            | PeerMsg::EpochClose { .. }
+            | PeerMsg::EpochDone { .. }
+            | PeerMsg::Watermark { .. } => {}
+            _ => {}
+        }
+    }
+
+    /// Start closing the current epoch, unless already closing it.
+    pub(crate) fn close(&mut self, peers: usize, out: &mut Vec<PeerMsg>) -> BTreeSet<Timestamp> {
+        if !self.closing {
+            self.closing = true;
+            out.push(PeerMsg::EpochClose { epoch: self.epoch });
+        }
+        self.advance(peers, out)
+    }
+
+    /// Note a peer's marker closing an epoch, closing it here too.
+    pub(crate) fn on_close(
+        &mut self,
+        peer: usize,
+        epoch: u32,
+        peers: usize,
+        out: &mut Vec<PeerMsg>,
+    ) -> BTreeSet<Timestamp> {
+        if epoch == self.epoch {
+            self.closed.insert(peer);
+            self.close(peers, out)
+        } else {
+            self.closed_next.insert(peer);
+            BTreeSet::new()
+        }
+    }
+
+    /// Note that a peer is done with the current epoch.
+    pub(crate) fn on_done(
+        &mut self,
+        peer: usize,
+        peers: usize,
+        out: &mut Vec<PeerMsg>,
+    ) -> BTreeSet<Timestamp> {
+        self.done.insert(peer);
+        self.advance(peers, out)
+    }
+
+    /// Say this server is done once every peer's marker has arrived, and roll over once every
+    /// peer is done too, returning the sets from the epochs rolled out of.
+    fn advance(&mut self, peers: usize, out: &mut Vec<PeerMsg>) -> BTreeSet<Timestamp> {
+        let mut compacted = BTreeSet::new();
+        while self.closing {
+            if self.closed.len() == peers && !self.done_sent {
+                self.done_sent = true;
+                out.push(PeerMsg::EpochDone { epoch: self.epoch });
+            }
+            if !self.done_sent || self.done.len() < peers {
+                break;
+            }
+            compacted.extend(self.sets.remove(&self.epoch).unwrap_or_default());
+            self.epoch += 1;
+            self.closed = std::mem::take(&mut self.closed_next);
+            self.done.clear();
+            self.done_sent = false;
+            // a peer already closed the new epoch, so this server has to as well
+            self.closing = !self.closed.is_empty();
+            if self.closing {
+                out.push(PeerMsg::EpochClose { epoch: self.epoch });
+            }
+        }
+        compacted
+    }
+}
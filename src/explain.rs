@@ -0,0 +1,352 @@
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::hash::Hash;
+
+use serde::Deserialize;
+use serde::Serialize;
+use stateright::actor::ActorModelAction;
+
+use crate::map::Map;
+use crate::map::MapOp;
+use crate::map::Timestamp;
+use crate::model::RegisterAction;
+use crate::model::RegisterState;
+use crate::MyRegisterActorState;
+use crate::MyRegisterMsg;
+use crate::PeerMsg;
+
+/// An operation as applied to a server, identified independently of which server applied it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub(crate) enum Op {
+    Set {
+        /// The values this set overwrote.
+        context: Vec<Timestamp>,
+        timestamp: Timestamp,
+        key: char,
+        value: char,
+    },
+    Delete {
+        /// The server that issued the delete.
+        #[serde(default)]
+        server: usize,
+        context: Vec<Timestamp>,
+    },
+}
+
+impl Op {
+    /// Apply the op to a map as if it had been received from its origin.
+    pub(crate) fn apply<M: Map>(&self, map: &mut M) {
+        match self {
+            Op::Set {
+                context,
+                timestamp,
+                key,
+                value,
+            } => map.effect(MapOp::Set {
+                context: context.iter().copied().collect(),
+                timestamp: *timestamp,
+                key: *key,
+                value: *value,
+            }),
+            Op::Delete { server: _, context } => map.effect(MapOp::Delete {
+                context: context.iter().copied().collect(),
+            }),
+        }
+    }
+}
+
+impl PartialEq for Op {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // the broken map doesn't send contexts for sets, so only the timestamp identifies them
+            (Op::Set { timestamp: a, .. }, Op::Set { timestamp: b, .. }) => a == b,
+            (
+                Op::Delete {
+                    server: a_server,
+                    context: a_context,
+                },
+                Op::Delete {
+                    server: b_server,
+                    context: b_context,
+                },
+            ) => a_server == b_server && a_context == b_context,
+            (Op::Set { .. }, Op::Delete { .. }) | (Op::Delete { .. }, Op::Set { .. }) => false,
+        }
+    }
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Op::Set {
+                context: _,
+                timestamp,
+                key,
+                value,
+            } => write!(
+                f,
+                "set {:?}={:?} at {:?} by server {}",
+                key, value, timestamp, timestamp.1
+            ),
+            Op::Delete { server, context } => {
+                write!(f, "delete of {:?} by server {}", context, server)
+            }
+        }
+    }
+}
+
+/// Explain why the servers in the final state of a counterexample diverged, by finding the pair
+/// of ops that two of the servers applied in different orders.
+pub(crate) fn explain<M: Clone + Debug + PartialEq + Hash + Map>(
+    steps: &[(RegisterState<M>, Option<RegisterAction<M>>)],
+) {
+    let (last, _) = match steps.last() {
+        Some(last) => last,
+        None => return,
+    };
+    let servers = last
+        .actor_states
+        .iter()
+        .enumerate()
+        .filter_map(|(i, actor)| match &**actor {
+            MyRegisterActorState::Server(map) => Some((i, map.visible_values())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (i, (a, a_values)) in servers.iter().enumerate() {
+        for (b, b_values) in &servers[i + 1..] {
+            if a_values != b_values {
+                explain_pair(steps, *a, *b);
+                return;
+            }
+        }
+    }
+}
+
+fn explain_pair<M: Clone + Debug + PartialEq + Hash + Map>(
+    steps: &[(RegisterState<M>, Option<RegisterAction<M>>)],
+    a: usize,
+    b: usize,
+) {
+    println!("Servers {} and {} diverged", a, b);
+    let a_ops = applied_ops(steps, a);
+    let b_ops = applied_ops(steps, b);
+
+    for (i, first) in a_ops.iter().enumerate() {
+        for second in &a_ops[i + 1..] {
+            let b_first = b_ops.iter().position(|op| op == first);
+            let b_second = b_ops.iter().position(|op| op == second);
+            if let (Some(b_first), Some(b_second)) = (b_first, b_second) {
+                if b_second < b_first {
+                    println!("  server {} applied {} before {}", a, first, second);
+                    println!("  server {} applied {} before {}", b, second, first);
+                    return;
+                }
+            }
+        }
+    }
+
+    for op in &a_ops {
+        if !b_ops.contains(op) {
+            println!("  server {} applied {} but server {} didn't", a, op, b);
+        }
+    }
+    for op in &b_ops {
+        if !a_ops.contains(op) {
+            println!("  server {} applied {} but server {} didn't", b, op, a);
+        }
+    }
+}
+
+/// The ops that a server applied, in the order it applied them.
+pub(crate) fn applied_ops<M: Clone + Debug + PartialEq + Hash + Map>(
+    steps: &[(RegisterState<M>, Option<RegisterAction<M>>)],
+    server: usize,
+) -> Vec<Op> {
+    let mut ops = Vec::new();
+    for window in steps.windows(2) {
+        let (before, after) = (&window[0].0, &window[1].0);
+        let (src, msg) = match &window[0].1 {
+            Some(ActorModelAction::Deliver { src, dst, msg }) if usize::from(*dst) == server => {
+                (usize::from(*src), msg)
+            }
+            _ => continue,
+        };
+        match msg {
+            MyRegisterMsg::Internal(PeerMsg::Changes { changes }) => {
+                // only the changes that were new to the server got applied
+                let before = match &*before.actor_states[server] {
+                    MyRegisterActorState::Server(before) => before,
+                    _ => continue,
+                };
+                for change in changes {
+                    if !before.has_change(change.hash()) {
+                        push_remote_op(&mut ops, src, &change.op);
+                    }
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::Ops { ops: pulled_ops }) => {
+                // only the ops next from their origin got applied, and the sender needn't be it
+                let before = match &*before.actor_states[server] {
+                    MyRegisterActorState::Server(before) => before,
+                    _ => continue,
+                };
+                let mut vector = before
+                    .pulls
+                    .as_ref()
+                    .map(|pulls| pulls.vector())
+                    .unwrap_or_default();
+                for pulled in pulled_ops {
+                    let next = vector.entry(pulled.origin).or_default();
+                    if pulled.seq == *next {
+                        *next += 1;
+                        push_remote_op(&mut ops, pulled.origin, &pulled.op);
+                    }
+                }
+            }
+            MyRegisterMsg::Internal(msg) => push_remote_op(&mut ops, src, msg),
+            MyRegisterMsg::Put(_, _, _)
+            | MyRegisterMsg::PutAfter(_, _, _, _)
+            | MyRegisterMsg::PutResolved(_, _, _, _)
+            | MyRegisterMsg::Delete(_, _)
+            | MyRegisterMsg::Truncate(_) => {
+                // local ops are identified by how they changed the server's values
+                let (before, after) =
+                    match (&*before.actor_states[server], &*after.actor_states[server]) {
+                        (
+                            MyRegisterActorState::Server(before),
+                            MyRegisterActorState::Server(after),
+                        ) => (before.values(), after.values()),
+                        _ => continue,
+                    };
+                let context = before
+                    .iter()
+                    .filter(|value| !after.contains(value))
+                    .map(|(t, _, _)| *t)
+                    .collect::<Vec<_>>();
+                if let Some((timestamp, key, value)) =
+                    after.iter().find(|value| !before.contains(value))
+                {
+                    ops.push(Op::Set {
+                        context,
+                        timestamp: *timestamp,
+                        key: *key,
+                        value: *value,
+                    });
+                } else if !context.is_empty() {
+                    ops.push(Op::Delete { server, context });
+                }
+            }
+            MyRegisterMsg::Get(_, _)
+            | MyRegisterMsg::PutOk(_, _)
+            | MyRegisterMsg::GetOk(_, _)
+            | MyRegisterMsg::DeleteOk(_)
+            | MyRegisterMsg::GetAll(_, _)
+            | MyRegisterMsg::GetAllOk(_, _)
+            | MyRegisterMsg::Subscribe(_)
+            | MyRegisterMsg::Siblings(_, _)
+            | MyRegisterMsg::Token(_)
+            | MyRegisterMsg::Add(_, _)
+            | MyRegisterMsg::Remove(_, _)
+            | MyRegisterMsg::Contains(_, _)
+            | MyRegisterMsg::ContainsOk(_, _)
+            | MyRegisterMsg::Increment(_)
+            | MyRegisterMsg::Decrement(_)
+            | MyRegisterMsg::Read(_)
+            | MyRegisterMsg::CountOk(_, _)
+            | MyRegisterMsg::WriteRegister(_, _)
+            | MyRegisterMsg::ReadRegister(_)
+            | MyRegisterMsg::Enable(_)
+            | MyRegisterMsg::Disable(_)
+            | MyRegisterMsg::ReadFlag(_)
+            | MyRegisterMsg::FlagOk(_, _)
+            | MyRegisterMsg::Insert(_, _, _)
+            | MyRegisterMsg::DeleteAt(_, _)
+            | MyRegisterMsg::ReadList(_)
+            | MyRegisterMsg::ListOk(_, _)
+            | MyRegisterMsg::SetPath(_, _, _)
+            | MyRegisterMsg::DeletePath(_, _)
+            | MyRegisterMsg::GetPath(_, _) => {}
+        }
+    }
+    ops
+}
+
+fn push_remote_op(ops: &mut Vec<Op>, src: usize, msg: &PeerMsg) {
+    match msg {
+        PeerMsg::PutSync {
+            context,
+            timestamp,
+            key,
+            value,
+        } => ops.push(Op::Set {
+            context: context.iter().collect(),
+            timestamp: *timestamp,
+            key: *key,
+            value: *value,
+        }),
+        PeerMsg::DeleteSync { context } => ops.push(Op::Delete {
+            server: src,
+            context: context.iter().collect(),
+        }),
+        PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => push_remote_op(ops, src, op),
+        // repaired ops are relayed by the peer that replied to the digest
+        PeerMsg::Repair { ops: repaired } => {
+            for op in repaired {
+                push_remote_op(ops, src, op)
+            }
+        }
+        // a truncate removes the values in its barrier as a delete would
+        PeerMsg::Truncate { barrier } => ops.push(Op::Delete {
+            server: src,
+            context: barrier.iter().collect(),
+        }),
+        // merging a state or a delta sets each of its values, then removes what the sender
+        // removed
+        PeerMsg::StateSync { values, removed } | PeerMsg::DeltaSync { values, removed } => {
+            for (timestamp, key, value) in values {
+                ops.push(Op::Set {
+                    context: Vec::new(),
+                    timestamp: *timestamp,
+                    key: *key,
+                    value: *value,
+                })
+            }
+            if !removed.is_empty() {
+                ops.push(Op::Delete {
+                    server: src,
+                    context: removed.iter().collect(),
+                })
+            }
+        }
+        // set ops don't touch the map
+        PeerMsg::AddSync { .. }
+        | PeerMsg::RemoveSync { .. }
+        | PeerMsg::CounterState { .. }
+        | PeerMsg::RegisterSync { .. }
+        | PeerMsg::LwwRegisterSync { .. }
+        | PeerMsg::FlagSync { .. }
+        | PeerMsg::ListInsertSync { .. }
+        | PeerMsg::ListDeleteSync { .. }
+        | PeerMsg::NestedSetSync { .. }
+        | PeerMsg::NestedDeleteSync { .. }
+        | PeerMsg::Have { .. }
+        | PeerMsg::Need { .. }
+        | PeerMsg::Changes { .. }
+        | PeerMsg::DepCheck { .. }
+        | PeerMsg::DepOk { .. }
+        | PeerMsg::Pull { .. }
+        | PeerMsg::Ops { .. }
+        | PeerMsg::Join
+        | PeerMsg::Leave
+        | PeerMsg::PutSyncAck { .. }
+        | PeerMsg::DeleteSyncAck { .. }
+        | PeerMsg::Digest { .. }
+        | PeerMsg::EpochClose { .. }
+        | PeerMsg::EpochDone { .. }
+        | PeerMsg::Watermark { .. } => {}
+        _ => {}
+    }
+}
@@ -0,0 +1,396 @@
+use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::BTreeMap;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::time::Duration;
+use std::time::Instant;
+
+use stateright::Expectation;
+use stateright::Model;
+use stateright::Property;
+
+use crate::check::print_stats;
+use crate::check::report_properties;
+use crate::check::Bounds;
+use crate::check::Steps;
+use crate::fingerprint::fingerprint;
+use crate::fingerprint::Fingerprint;
+use crate::guide::Heuristic;
+use crate::map::Map;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+
+/// The states an exploration has visited, by fingerprint, along with the state each was first
+/// reached from so that paths to discoveries can be rebuilt.
+pub(crate) trait Visited {
+    /// Record a state reached from `parent`, or an initial state when there is no parent,
+    /// returning whether it hadn't been visited before.
+    fn insert(&mut self, state: Fingerprint, parent: Option<Fingerprint>) -> bool;
+
+    /// The state a visited state was first reached from, `None` for initial states.
+    fn parent(&mut self, state: Fingerprint) -> Option<Fingerprint>;
+
+    /// For stores that may wrongly report a new state as visited, the estimated chance that
+    /// they did. Such stores don't keep parents.
+    fn false_positive_rate(&self) -> Option<f64> {
+        None
+    }
+}
+
+/// Visited states held in memory, for guided exploration without `--disk-visited`.
+#[derive(Default)]
+pub(crate) struct InMemory(HashMap<Fingerprint, Option<Fingerprint>>);
+
+impl Visited for InMemory {
+    fn insert(&mut self, state: Fingerprint, parent: Option<Fingerprint>) -> bool {
+        match self.0.entry(state) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(parent);
+                true
+            }
+        }
+    }
+
+    fn parent(&mut self, state: Fingerprint) -> Option<Fingerprint> {
+        self.0.get(&state).copied().flatten()
+    }
+}
+
+/// A state waiting to be explored, with its depth and the eventually properties not yet seen to
+/// hold on its path.
+struct Item<M: Clone + Debug + PartialEq + Hash + Map> {
+    state: RegisterState<M>,
+    state_fp: Fingerprint,
+    depth: usize,
+    pending: Vec<usize>,
+}
+
+/// Guided items are ordered by score, and then by when they were added so that states with equal
+/// scores are explored breadth first.
+struct Prioritized<M: Clone + Debug + PartialEq + Hash + Map> {
+    score: usize,
+    seq: Reverse<usize>,
+    item: Item<M>,
+}
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> PartialEq for Prioritized<M> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.score, self.seq) == (other.score, other.seq)
+    }
+}
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> Eq for Prioritized<M> {}
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> PartialOrd for Prioritized<M> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> Ord for Prioritized<M> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.score, self.seq).cmp(&(other.score, other.seq))
+    }
+}
+
+enum Frontier<M: Clone + Debug + PartialEq + Hash + Map> {
+    Fifo(VecDeque<Item<M>>),
+    Guided {
+        heuristic: Heuristic,
+        items: BinaryHeap<Prioritized<M>>,
+        seq: usize,
+    },
+}
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> Frontier<M> {
+    fn push(&mut self, item: Item<M>) {
+        match self {
+            Frontier::Fifo(items) => items.push_back(item),
+            Frontier::Guided {
+                heuristic,
+                items,
+                seq,
+            } => {
+                *seq += 1;
+                items.push(Prioritized {
+                    score: heuristic.score(&item.state),
+                    seq: Reverse(*seq),
+                    item,
+                });
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Option<Item<M>> {
+        match self {
+            Frontier::Fifo(items) => items.pop_front(),
+            Frontier::Guided { items, .. } => items.pop().map(|prioritized| prioritized.item),
+        }
+    }
+}
+
+/// The outcome of an exploration.
+pub(crate) struct Exploration<M: Clone + Debug + PartialEq + Hash + Map> {
+    states: usize,
+    unique: usize,
+    max_depth: usize,
+    /// Whether the state space was fully explored, rather than stopped by the time limit or the
+    /// bounds.
+    complete: bool,
+    /// Why the bounds cut the exploration short, if they did.
+    pub(crate) truncated: Option<String>,
+    /// The final state of the first discovery of each property.
+    discoveries: BTreeMap<&'static str, (Fingerprint, RegisterState<M>)>,
+}
+
+/// Explore the model on a single thread, keeping the visited states in the given store rather
+/// than in the checker's in-memory set.
+///
+/// Without a heuristic this explores breadth first, otherwise it explores the states that score
+/// highest first, to reach violations sooner when they lie along the paths the heuristic favours.
+/// Counterexamples found with a heuristic needn't be the shortest.
+///
+/// Properties are evaluated as stateright's checkers do, so results are comparable, including
+/// eventually properties only being checked at terminal states.
+pub(crate) fn explore<M, V>(
+    model: &RegisterModel<M>,
+    visited: &mut V,
+    time_limit: Option<Duration>,
+    bounds: Bounds,
+    heuristic: Option<Heuristic>,
+) -> Exploration<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+    V: Visited,
+{
+    let properties = model.properties();
+    let eventually = properties
+        .iter()
+        .enumerate()
+        .filter(|(_, property)| matches!(property.expectation, Expectation::Eventually))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let mut exploration = Exploration {
+        states: 0,
+        unique: 0,
+        max_depth: 0,
+        complete: true,
+        truncated: None,
+        discoveries: BTreeMap::new(),
+    };
+    let mut frontier = match heuristic {
+        Some(heuristic) => Frontier::Guided {
+            heuristic,
+            items: BinaryHeap::new(),
+            seq: 0,
+        },
+        None => Frontier::Fifo(VecDeque::new()),
+    };
+    for state in model.init_states() {
+        let state_fp = fingerprint(&state);
+        if visited.insert(state_fp, None) {
+            exploration.unique += 1;
+            frontier.push(Item {
+                state,
+                state_fp,
+                depth: 0,
+                pending: eventually.clone(),
+            });
+        }
+    }
+
+    let start = Instant::now();
+    let mut last_progress = start;
+    let mut actions = Vec::new();
+    while let Some(Item {
+        state,
+        state_fp,
+        depth,
+        mut pending,
+    }) = frontier.pop()
+    {
+        if exploration.discoveries.len() == properties.len() {
+            break;
+        }
+        if time_limit.map_or(false, |time_limit| start.elapsed() >= time_limit) {
+            exploration.complete = false;
+            break;
+        }
+        if bounds
+            .states
+            .map_or(false, |limit| exploration.states >= limit)
+        {
+            break;
+        }
+        if last_progress.elapsed() >= Duration::from_secs(1) {
+            println!(
+                "Checking. states={}, unique={}, depth={}",
+                exploration.states, exploration.unique, exploration.max_depth
+            );
+            last_progress = Instant::now();
+        }
+        exploration.states += 1;
+        exploration.max_depth = exploration.max_depth.max(depth);
+
+        for name in evaluate(
+            model,
+            &properties,
+            &state,
+            &mut pending,
+            &exploration.discoveries,
+        ) {
+            exploration
+                .discoveries
+                .insert(name, (state_fp, state.clone()));
+        }
+        if bounds.depth.map_or(false, |limit| depth >= limit) {
+            continue;
+        }
+
+        model.actions(&state, &mut actions);
+        let mut terminal = true;
+        for action in actions.drain(..) {
+            let next = match model.next_state(&state, action) {
+                Some(next) if model.within_boundary(&next) => next,
+                _ => continue,
+            };
+            terminal = false;
+            let next_fp = fingerprint(&next);
+            if visited.insert(next_fp, Some(state_fp)) {
+                exploration.unique += 1;
+                frontier.push(Item {
+                    state: next,
+                    state_fp: next_fp,
+                    depth: depth + 1,
+                    pending: pending.clone(),
+                });
+            }
+        }
+        if terminal {
+            for i in pending {
+                exploration
+                    .discoveries
+                    .entry(properties[i].name)
+                    .or_insert_with(|| (state_fp, state.clone()));
+            }
+        }
+    }
+    exploration.truncated = bounds.hit(exploration.max_depth, exploration.states);
+    exploration.complete &= exploration.truncated.is_none();
+    exploration
+}
+
+/// Evaluate the properties that have no discovery yet on a state, returning those it is a
+/// discovery for and removing the eventually properties that hold from `pending`.
+pub(crate) fn evaluate<M, T>(
+    model: &RegisterModel<M>,
+    properties: &[Property<RegisterModel<M>>],
+    state: &RegisterState<M>,
+    pending: &mut Vec<usize>,
+    discoveries: &BTreeMap<&'static str, T>,
+) -> Vec<&'static str>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    let mut discovered = Vec::new();
+    for (i, property) in properties.iter().enumerate() {
+        if discoveries.contains_key(property.name) {
+            continue;
+        }
+        let holds = (property.condition)(model, state);
+        match property.expectation {
+            Expectation::Always if !holds => discovered.push(property.name),
+            Expectation::Sometimes if holds => discovered.push(property.name),
+            Expectation::Eventually if holds => pending.retain(|pending| *pending != i),
+            _ => {}
+        }
+    }
+    discovered
+}
+
+/// Report the outcome of each property as [`crate::check::check_properties`] does for
+/// stateright's checkers, returning how many were violated.
+pub(crate) fn check_properties<M, V>(
+    model: &RegisterModel<M>,
+    exploration: Exploration<M>,
+    visited: &mut V,
+    keep_going: bool,
+) -> usize
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+    V: Visited,
+{
+    println!(
+        "Done. states={}, unique={}, max_depth={}",
+        exploration.states, exploration.unique, exploration.max_depth
+    );
+    match &exploration.truncated {
+        Some(truncated) => println!("{}", truncated),
+        None if !exploration.complete => {
+            println!("Bounded result: time limit reached, the state space was not exhausted")
+        }
+        None => {}
+    }
+    let false_positive_rate = visited.false_positive_rate();
+    if let Some(rate) = false_positive_rate {
+        println!(
+            "Approximate verification: about {:.4}% of new states may have been skipped as already visited, so results are labelled as bounded and counterexamples only show their final state",
+            rate * 100.0
+        );
+    }
+    let discoveries = exploration
+        .discoveries
+        .into_iter()
+        .map(|(name, (state_fp, state))| match false_positive_rate {
+            Some(_) => (name, vec![(state, None)]),
+            None => (name, path(model, visited, state_fp)),
+        })
+        .collect();
+    print_stats(model, &discoveries);
+
+    let complete = exploration.complete && false_positive_rate.is_none();
+    report_properties(model, discoveries, keep_going, complete)
+}
+
+/// Rebuild the path to a visited state by following its parents back to an initial state and
+/// replaying the actions between them.
+fn path<M, V>(model: &RegisterModel<M>, visited: &mut V, state_fp: Fingerprint) -> Steps<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+    V: Visited,
+{
+    let mut fingerprints = vec![state_fp];
+    while let Some(parent) = visited.parent(*fingerprints.last().unwrap()) {
+        fingerprints.push(parent);
+    }
+    fingerprints.reverse();
+
+    let mut state = model
+        .init_states()
+        .into_iter()
+        .find(|state| fingerprint(state) == fingerprints[0])
+        .expect("paths start at an initial state");
+    let mut steps = Vec::new();
+    for next_fp in &fingerprints[1..] {
+        let mut actions = Vec::new();
+        model.actions(&state, &mut actions);
+        let (action, next) = actions
+            .into_iter()
+            .find_map(|action| {
+                let next = model.next_state(&state, action.clone())?;
+                (fingerprint(&next) == *next_fp).then(|| (action, next))
+            })
+            .expect("visited states are reachable from their parent");
+        steps.push((state, Some(action)));
+        state = next;
+    }
+    steps.push((state, None));
+    steps
+}
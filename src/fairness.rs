@@ -0,0 +1,32 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::map::Map;
+use crate::model::RegisterState;
+
+/// Assumptions about which paths through the model are fair, and so which states are allowed to
+/// be the end of a path when checking liveness properties.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Fairness {
+    /// No assumptions: the network may stop delivering messages at any point, so any state may be
+    /// the last one.
+    None,
+    /// Messages that stay deliverable are eventually delivered (or dropped by a lossy network)
+    /// and set timers eventually fire, so a path only ends once the network is empty and no
+    /// timers are set.
+    WeakDelivery,
+}
+
+/// Whether a fair path may end in this state.
+pub(crate) fn is_fair_end<M: Clone + Debug + PartialEq + Hash + Map>(
+    fairness: Fairness,
+    state: &RegisterState<M>,
+) -> bool {
+    match fairness {
+        Fairness::None => true,
+        Fairness::WeakDelivery => {
+            state.network.iter_deliverable().next().is_none()
+                && state.is_timer_set.iter().all(|set| !set)
+        }
+    }
+}
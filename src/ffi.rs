@@ -0,0 +1,305 @@
+//! A C interface to the fixed map, built with the `ffi` feature.
+//!
+//! A map is created with [`crdt_map_new`] and owned by the caller until it is passed to
+//! [`crdt_map_free`]. [`crdt_map_set`] and [`crdt_map_delete`] effect an op on the map and give
+//! the message to send the other replicas, which they effect with [`crdt_map_apply_op`]. Messages
+//! and the snapshots from [`crdt_map_serialize`] are [`PeerMsg`]s and [`MapSnapshot`]s encoded as
+//! JSON, handed out as [`CrdtBytes`] that the caller frees with [`crdt_bytes_free`].
+//!
+//! Keys and values are Unicode scalar values passed as `uint32_t`. Every function other than
+//! the constructor and destructors returns [`CRDT_OK`] or one of the negative `CRDT_ERR_` codes,
+//! leaving its outputs untouched on error. `include/toy_crdt.h` declares the interface for C.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ptr;
+use core::slice;
+
+use crate::id::Id;
+use crate::map::Map;
+use crate::map::MapSnapshot;
+use crate::map_fixed::FixedMap;
+use crate::peer::publish;
+use crate::peer::receive_sync;
+use crate::peer::PeerMsg;
+
+/// The call succeeded.
+pub const CRDT_OK: i32 = 0;
+/// The key isn't in the map, for gets and deletes.
+pub const CRDT_NOT_FOUND: i32 = 1;
+/// A pointer that must not be null was.
+pub const CRDT_ERR_NULL: i32 = -1;
+/// A key or value isn't a Unicode scalar value.
+pub const CRDT_ERR_CHAR: i32 = -2;
+/// A message couldn't be decoded.
+pub const CRDT_ERR_DECODE: i32 = -3;
+
+/// Bytes allocated by the library, to be freed with [`crdt_bytes_free`].
+#[repr(C)]
+pub struct CrdtBytes {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl CrdtBytes {
+    fn new(bytes: Vec<u8>) -> Self {
+        let bytes = Box::into_raw(bytes.into_boxed_slice());
+        Self {
+            data: bytes as *mut u8,
+            len: bytes.len(),
+        }
+    }
+}
+
+/// Create an empty map for the replica with the given actor id.
+#[no_mangle]
+pub extern "C" fn crdt_map_new(actor_id: usize) -> *mut FixedMap {
+    Box::into_raw(Box::new(<FixedMap as Map>::new(Id::from(actor_id))))
+}
+
+/// Free a map from [`crdt_map_new`]. Null is ignored.
+///
+/// # Safety
+///
+/// `map` must be null or a map from [`crdt_map_new`] that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn crdt_map_free(map: *mut FixedMap) {
+    if !map.is_null() {
+        drop(Box::from_raw(map))
+    }
+}
+
+/// Set `key` to `value`, writing the message for the other replicas to `op` unless it is null.
+///
+/// # Safety
+///
+/// `map` must be a live map from [`crdt_map_new`], and `op` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn crdt_map_set(
+    map: *mut FixedMap,
+    key: u32,
+    value: u32,
+    op: *mut CrdtBytes,
+) -> i32 {
+    let map = match map.as_mut() {
+        Some(map) => map,
+        None => return CRDT_ERR_NULL,
+    };
+    let (key, value) = match (char::from_u32(key), char::from_u32(value)) {
+        (Some(key), Some(value)) => (key, value),
+        _ => return CRDT_ERR_CHAR,
+    };
+    let set = Map::prepare_set(map, key, value);
+    let msg = publish(map, set);
+    write_msg(&msg, op);
+    CRDT_OK
+}
+
+/// Delete `key`, writing the message for the other replicas to `op` unless it is null.
+///
+/// # Safety
+///
+/// `map` must be a live map from [`crdt_map_new`], and `op` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn crdt_map_delete(map: *mut FixedMap, key: u32, op: *mut CrdtBytes) -> i32 {
+    let map = match map.as_mut() {
+        Some(map) => map,
+        None => return CRDT_ERR_NULL,
+    };
+    let key = match char::from_u32(key) {
+        Some(key) => key,
+        None => return CRDT_ERR_CHAR,
+    };
+    let delete = match Map::prepare_delete(map, &key) {
+        Some(delete) => delete,
+        None => return CRDT_NOT_FOUND,
+    };
+    let msg = publish(map, delete);
+    write_msg(&msg, op);
+    CRDT_OK
+}
+
+/// Read the value `key` resolves to into `value`.
+///
+/// # Safety
+///
+/// `map` must be a live map from [`crdt_map_new`], and `value` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn crdt_map_get(map: *const FixedMap, key: u32, value: *mut u32) -> i32 {
+    let map = match map.as_ref() {
+        Some(map) => map,
+        None => return CRDT_ERR_NULL,
+    };
+    if value.is_null() {
+        return CRDT_ERR_NULL;
+    }
+    let key = match char::from_u32(key) {
+        Some(key) => key,
+        None => return CRDT_ERR_CHAR,
+    };
+    match Map::get(map, &key) {
+        Some(found) => {
+            *value = u32::from(*found);
+            CRDT_OK
+        }
+        None => CRDT_NOT_FOUND,
+    }
+}
+
+/// Effect the ops in a message from another replica, as given by [`crdt_map_set`] or
+/// [`crdt_map_delete`].
+///
+/// # Safety
+///
+/// `map` must be a live map from [`crdt_map_new`], and `data` valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn crdt_map_apply_op(map: *mut FixedMap, data: *const u8, len: usize) -> i32 {
+    let map = match map.as_mut() {
+        Some(map) => map,
+        None => return CRDT_ERR_NULL,
+    };
+    if data.is_null() {
+        return CRDT_ERR_NULL;
+    }
+    match serde_json::from_slice::<PeerMsg>(slice::from_raw_parts(data, len)) {
+        Ok(msg) => {
+            receive_sync(map, msg);
+            CRDT_OK
+        }
+        Err(_) => CRDT_ERR_DECODE,
+    }
+}
+
+/// Write a snapshot of the map to `out`.
+///
+/// # Safety
+///
+/// `map` must be a live map from [`crdt_map_new`], and `out` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn crdt_map_serialize(map: *const FixedMap, out: *mut CrdtBytes) -> i32 {
+    let map = match map.as_ref() {
+        Some(map) => map,
+        None => return CRDT_ERR_NULL,
+    };
+    if out.is_null() {
+        return CRDT_ERR_NULL;
+    }
+    let snapshot: MapSnapshot = Map::snapshot(map);
+    *out = CrdtBytes::new(serde_json::to_vec(&snapshot).expect("snapshots serialize"));
+    CRDT_OK
+}
+
+/// Free bytes handed out by the library, leaving `bytes` empty. Null and empty bytes are
+/// ignored.
+///
+/// # Safety
+///
+/// `bytes` must be null or bytes from this library that haven't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn crdt_bytes_free(bytes: *mut CrdtBytes) {
+    let bytes = match bytes.as_mut() {
+        Some(bytes) => bytes,
+        None => return,
+    };
+    if !bytes.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            bytes.data, bytes.len,
+        )));
+    }
+    *bytes = CrdtBytes {
+        data: ptr::null_mut(),
+        len: 0,
+    };
+}
+
+unsafe fn write_msg(msg: &PeerMsg, op: *mut CrdtBytes) {
+    if let Some(op) = op.as_mut() {
+        *op = CrdtBytes::new(serde_json::to_vec(msg).expect("messages serialize"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty() -> CrdtBytes {
+        CrdtBytes {
+            data: ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn get(map: *const FixedMap, key: char) -> Option<char> {
+        let mut value = 0;
+        match unsafe { crdt_map_get(map, key.into(), &mut value) } {
+            CRDT_OK => char::from_u32(value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn ops_replicate() {
+        let a = crdt_map_new(0);
+        let b = crdt_map_new(1);
+        let mut op = empty();
+        unsafe {
+            assert_eq!(crdt_map_set(a, 'x'.into(), 'A'.into(), &mut op), CRDT_OK);
+            assert_eq!(crdt_map_apply_op(b, op.data, op.len), CRDT_OK);
+            crdt_bytes_free(&mut op);
+        }
+        assert!(op.data.is_null());
+        assert_eq!(get(b, 'x'), Some('A'));
+
+        unsafe {
+            assert_eq!(crdt_map_delete(b, 'x'.into(), &mut op), CRDT_OK);
+            assert_eq!(crdt_map_apply_op(a, op.data, op.len), CRDT_OK);
+            crdt_bytes_free(&mut op);
+        }
+        assert_eq!(get(a, 'x'), None);
+        unsafe {
+            crdt_map_free(a);
+            crdt_map_free(b);
+        }
+    }
+
+    #[test]
+    fn serialize_gives_a_snapshot() {
+        let map = crdt_map_new(0);
+        let mut out = empty();
+        unsafe {
+            assert_eq!(
+                crdt_map_set(map, 'x'.into(), 'A'.into(), ptr::null_mut()),
+                CRDT_OK
+            );
+            assert_eq!(crdt_map_serialize(map, &mut out), CRDT_OK);
+            let snapshot: MapSnapshot =
+                serde_json::from_slice(slice::from_raw_parts(out.data, out.len)).unwrap();
+            assert_eq!(snapshot.values, [((1, 0), 'x', 'A')]);
+            crdt_bytes_free(&mut out);
+            crdt_map_free(map);
+        }
+    }
+
+    #[test]
+    fn errors_leave_outputs_untouched() {
+        let map = crdt_map_new(0);
+        let mut op = empty();
+        unsafe {
+            assert_eq!(
+                crdt_map_set(ptr::null_mut(), 'x'.into(), 'A'.into(), &mut op),
+                CRDT_ERR_NULL
+            );
+            assert_eq!(
+                crdt_map_set(map, 0xd800, 'A'.into(), &mut op),
+                CRDT_ERR_CHAR
+            );
+            assert_eq!(crdt_map_delete(map, 'x'.into(), &mut op), CRDT_NOT_FOUND);
+            assert!(op.data.is_null());
+            assert_eq!(crdt_map_apply_op(map, b"{".as_ptr(), 1), CRDT_ERR_DECODE);
+            assert_eq!(crdt_map_apply_op(map, ptr::null(), 0), CRDT_ERR_NULL);
+            crdt_bytes_free(ptr::null_mut());
+            crdt_map_free(ptr::null_mut());
+            crdt_map_free(map);
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use core::hash::Hash;
+use core::hash::Hasher;
+
+/// A fingerprint of a state that stays the same across runs, machines and architectures.
+pub type Fingerprint = u64;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// FNV-1a hasher with fixed parameters.
+///
+/// Integers are always fed in little-endian and `usize`/`isize` are widened to 64 bits so the
+/// result doesn't depend on the platform, unlike the randomly seeded std and ahash hashers.
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64)
+    }
+}
+
+/// Fingerprint a value with the [`StableHasher`].
+///
+/// Values should only contain ordered collections (`Vec`, `BTreeSet`, ...) so that equal states
+/// are always fed to the hasher in the same order.
+pub fn fingerprint<T: Hash>(value: &T) -> Fingerprint {
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
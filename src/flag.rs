@@ -0,0 +1,121 @@
+use crate::register_mv::MVRegister;
+use crate::register_mv::Write;
+
+/// Which way a [`Flag`] resolves an enable and a disable that are concurrent.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Bias {
+    /// The flag is enabled if any write that hasn't been overwritten enables it.
+    EnableWins,
+    /// The flag is disabled if any write that hasn't been overwritten disables it, or if it has
+    /// never been written.
+    DisableWins,
+}
+
+/// An enable or a disable of a [`Flag`]: a register write of whether the flag is enabled.
+pub type FlagWrite = Write<bool>;
+
+/// A boolean flag that can be enabled and disabled: a multi-value register of booleans, whose
+/// siblings, the enables and disables that are concurrent, are resolved by its [`Bias`].
+///
+/// An enable or disable overwrites every write its replica had seen, so a flag that is disabled
+/// after being enabled is disabled whatever its bias; the bias only decides between writes that
+/// didn't see each other.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Flag {
+    bias: Bias,
+    /// The enables and disables, as the siblings of a register.
+    pub register: MVRegister<bool>,
+}
+
+impl Flag {
+    /// A flag for the server with the given actor id, which has never been enabled.
+    pub fn new(actor_id: usize, bias: Bias) -> Self {
+        Self {
+            bias,
+            register: MVRegister::new(actor_id),
+        }
+    }
+
+    /// Which way the flag resolves concurrent writes.
+    pub fn bias(&self) -> Bias {
+        self.bias
+    }
+
+    /// Whether the flag is enabled, resolving the siblings by the bias.
+    pub fn value(&self) -> bool {
+        let writes = &self.register.writes;
+        match self.bias {
+            Bias::EnableWins => writes.iter().any(|write| write.value),
+            Bias::DisableWins => !writes.is_empty() && writes.iter().all(|write| write.value),
+        }
+    }
+
+    /// The highest entry in any sibling's clock.
+    pub fn max_counter(&self) -> u32 {
+        self.register.max_counter()
+    }
+
+    /// The write enabling or disabling the flag, following every sibling, without changing the
+    /// flag.
+    pub fn prepare(&self, enable: bool) -> FlagWrite {
+        self.register.prepare_write(enable)
+    }
+
+    /// Effect a write, whether prepared here or by another server.
+    pub fn receive(&mut self, write: FlagWrite) {
+        self.register.receive_write(write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two flags with the given bias, where the first has been enabled and the second
+    /// concurrently disabled, each having seen the other's write.
+    fn conflicting(bias: Bias) -> (Flag, Flag) {
+        let mut a = Flag::new(0, bias);
+        let mut b = Flag::new(1, bias);
+        let enable = a.prepare(true);
+        let disable = b.prepare(false);
+        a.receive(enable.clone());
+        a.receive(disable.clone());
+        b.receive(disable);
+        b.receive(enable);
+        (a, b)
+    }
+
+    #[test]
+    fn enable_wins_over_a_concurrent_disable() {
+        let (a, b) = conflicting(Bias::EnableWins);
+        assert_eq!(a.register.writes.len(), 2);
+        assert!(a.value());
+        assert!(b.value());
+    }
+
+    #[test]
+    fn disable_wins_over_a_concurrent_enable() {
+        let (a, b) = conflicting(Bias::DisableWins);
+        assert_eq!(a.register.writes.len(), 2);
+        assert!(!a.value());
+        assert!(!b.value());
+    }
+
+    #[test]
+    fn a_later_write_wins_whatever_the_bias() {
+        for bias in [Bias::EnableWins, Bias::DisableWins] {
+            let (mut a, _) = conflicting(bias);
+            a.receive(a.prepare(false));
+            assert!(!a.value());
+            a.receive(a.prepare(true));
+            assert!(a.value());
+            assert_eq!(a.register.writes.len(), 1);
+        }
+    }
+
+    #[test]
+    fn a_new_flag_is_disabled() {
+        assert!(!Flag::new(0, Bias::EnableWins).value());
+        assert!(!Flag::new(0, Bias::DisableWins).value());
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeSet;
+
+use stateright::actor::Id;
+
+use crate::context::CausalContext;
+use crate::map::Timestamp;
+use crate::Key;
+use crate::PeerMsg;
+use crate::Value;
+
+/// What a server tracks to repair its map by anti-entropy: every so often it pushes a digest of
+/// the sets it holds and has removed to a peer, which replies with the ops it is missing.
+///
+/// Peers are taken in turn rather than at random, so that the model stays deterministic. A peer
+/// that replies with nothing new is left out of later rounds until this server's map changes
+/// again, so gossip settles once every server has everything.
+///
+/// Servers only track this with anti-entropy, so that states don't otherwise differ in it.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct Gossip {
+    /// The index among the server's peers of the one to push the next digest to.
+    next: usize,
+    /// Peers whose reply to this server's latest digest had nothing new.
+    quiet: BTreeSet<usize>,
+}
+
+impl Gossip {
+    /// The next peer in turn that may still have ops this server is missing, if any.
+    pub(crate) fn next_peer(&mut self, peers: &[Id]) -> Option<Id> {
+        for _ in 0..peers.len() {
+            let peer = peers[self.next % peers.len()];
+            self.next = (self.next + 1) % peers.len();
+            if !self.quiet.contains(&peer.into()) {
+                return Some(peer);
+            }
+        }
+        None
+    }
+
+    /// Note whether a peer's reply to a digest changed this server's map.
+    pub(crate) fn replied(&mut self, peer: usize, changed: bool) {
+        if changed {
+            self.disturb()
+        } else {
+            self.quiet.insert(peer);
+        }
+    }
+
+    /// Gossip with a peer again, as it has seen values this server hasn't.
+    pub(crate) fn wake(&mut self, peer: usize) {
+        self.quiet.remove(&peer);
+    }
+
+    /// Gossip with every peer again, as this server's map changed.
+    pub(crate) fn disturb(&mut self) {
+        self.quiet.clear()
+    }
+}
+
+/// The ops that a peer with the given digest is missing from a map holding `values` that has
+/// removed `removed`: a set for each held value the peer hasn't seen, and a delete of the values
+/// the map has removed that the peer doesn't know are, so that it drops them even if they only
+/// arrive later.
+///
+/// The sets carry no context, as the values they overwrote are covered by the delete.
+pub(crate) fn repair(
+    values: &[(Timestamp, Key, Value)],
+    removed: &BTreeSet<Timestamp>,
+    held: &CausalContext,
+    their_removed: &CausalContext,
+) -> Vec<PeerMsg> {
+    let mut ops = values
+        .iter()
+        .filter(|(timestamp, _, _)| !held.contains(timestamp) && !their_removed.contains(timestamp))
+        .map(|(timestamp, key, value)| PeerMsg::PutSync {
+            context: CausalContext::new(),
+            timestamp: *timestamp,
+            key: *key,
+            value: *value,
+        })
+        .collect::<Vec<_>>();
+    let context = removed
+        .iter()
+        .filter(|timestamp| !their_removed.contains(timestamp))
+        .copied()
+        .collect::<CausalContext>();
+    if !context.is_empty() {
+        ops.push(PeerMsg::DeleteSync { context });
+    }
+    ops
+}
+
+/// Whether a peer with the given digest has seen values that a map holding `values` and having
+/// removed `removed` hasn't, or has removed values that the map doesn't know are.
+pub(crate) fn is_behind(
+    values: &[(Timestamp, Key, Value)],
+    removed: &BTreeSet<Timestamp>,
+    held: &CausalContext,
+    their_removed: &CausalContext,
+) -> bool {
+    let holds = |timestamp: &Timestamp| values.iter().any(|(t, _, _)| t == timestamp);
+    held.iter()
+        .any(|timestamp| !holds(&timestamp) && !removed.contains(&timestamp))
+        || their_removed
+            .iter()
+            .any(|timestamp| !removed.contains(&timestamp))
+}
@@ -0,0 +1,224 @@
+use std::net::SocketAddrV4;
+use std::pin::Pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use stateright::actor::Id;
+use tokio::net::TcpListener;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_stream::Stream;
+use tonic::transport::Server;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+
+use crate::map::Timestamp;
+use crate::watch;
+use crate::wire;
+use crate::Key;
+use crate::MyRegisterMsg;
+use crate::RequestId;
+
+mod proto {
+    tonic::include_proto!("toy_crdt");
+}
+
+use proto::store_server::Store;
+use proto::store_server::StoreServer;
+
+/// How long to wait for the server to answer a request before giving up on it. Requests and
+/// responses travel over UDP, so either may be lost.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How many changes to buffer for a watcher that is slow to take them, before the feed waits on
+/// it.
+const WATCH_BUFFER: usize = 64;
+
+/// A client of a server run with `run`, translating the calls of the `Store` service into the
+/// server's own requests: `Get` into `Get`, `Put` into `Put`, `Delete` into `Delete` and `GetAll`
+/// into `GetAll`. `Watch` follows the server's [`watch::Feed`] instead, as the server only
+/// notifies its clients of conflicts.
+///
+/// Each call sends its request from a socket of its own, so calls are handled concurrently.
+struct Frontend {
+    server: SocketAddrV4,
+    next_request_id: AtomicUsize,
+}
+
+/// Serve gRPC on the given address for the server at `server`, on a thread of its own.
+pub(crate) fn serve(listen: SocketAddrV4, server: SocketAddrV4) {
+    let runtime = tokio::runtime::Runtime::new().unwrap_or_else(|e| {
+        eprintln!("Failed to start a runtime for gRPC: {}", e);
+        std::process::exit(2)
+    });
+    let listener = runtime
+        .block_on(TcpListener::bind(listen))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to listen for gRPC on {}: {}", listen, e);
+            std::process::exit(2)
+        });
+    let frontend = Frontend {
+        server,
+        next_request_id: AtomicUsize::new(0),
+    };
+    println!("Serving the Store gRPC service on {}", listen);
+    std::thread::spawn(move || {
+        let result = runtime.block_on(
+            Server::builder()
+                .add_service(StoreServer::new(frontend))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        if let Err(e) = result {
+            eprintln!("Failed to serve gRPC: {}", e);
+        }
+    });
+}
+
+impl Frontend {
+    /// Send a request to the server and wait for its response.
+    async fn request(
+        &self,
+        request: impl FnOnce(RequestId) -> MyRegisterMsg,
+    ) -> Result<MyRegisterMsg, Status> {
+        let request_id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        socket
+            .send_to(&wire::encode(&request(request_id)), self.server)
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+        let mut buf = [0; 65_535];
+        let response = async {
+            loop {
+                let len = socket.recv(&mut buf).await?;
+                match wire::decode(&buf[..len]) {
+                    Ok(
+                        response @ (MyRegisterMsg::GetOk(id, _)
+                        | MyRegisterMsg::GetAllOk(id, _)
+                        | MyRegisterMsg::PutOk(id, _)
+                        | MyRegisterMsg::DeleteOk(id)),
+                    ) if id == request_id => return Ok::<_, std::io::Error>(response),
+                    _ => {}
+                }
+            }
+        };
+        match tokio::time::timeout(TIMEOUT, response).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(e)) => Err(Status::unavailable(e.to_string())),
+            Err(_) => Err(Status::deadline_exceeded("The server didn't respond")),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Store for Frontend {
+    async fn get(&self, request: Request<proto::Key>) -> Result<Response<proto::Value>, Status> {
+        let key = parse_char(&request.get_ref().key).ok_or_else(invalid_char)?;
+        // the server only responds to a get when the key has a value, so tell a missing key
+        // from a lost response by its siblings
+        match self.request(|id| MyRegisterMsg::Get(id, key)).await {
+            Ok(MyRegisterMsg::GetOk(_, value)) => Ok(Response::new(proto::Value {
+                value: value.to_string(),
+            })),
+            Ok(_) => Err(Status::internal("Unexpected response")),
+            Err(e) => match self.request(|id| MyRegisterMsg::GetAll(id, key)).await {
+                Ok(MyRegisterMsg::GetAllOk(_, siblings)) if siblings.is_empty() => {
+                    Err(Status::not_found(format!("{:?} has no value", key)))
+                }
+                _ => Err(e),
+            },
+        }
+    }
+
+    async fn put(
+        &self,
+        request: Request<proto::PutRequest>,
+    ) -> Result<Response<proto::Version>, Status> {
+        let key = parse_char(&request.get_ref().key).ok_or_else(invalid_char)?;
+        let value = parse_char(&request.get_ref().value).ok_or_else(invalid_char)?;
+        match self
+            .request(|id| MyRegisterMsg::Put(id, key, value))
+            .await?
+        {
+            MyRegisterMsg::PutOk(_, timestamp) => Ok(Response::new(version(timestamp))),
+            _ => Err(Status::internal("Unexpected response")),
+        }
+    }
+
+    async fn delete(&self, request: Request<proto::Key>) -> Result<Response<proto::Empty>, Status> {
+        let key = parse_char(&request.get_ref().key).ok_or_else(invalid_char)?;
+        self.request(|id| MyRegisterMsg::Delete(id, key)).await?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn get_all(
+        &self,
+        request: Request<proto::Key>,
+    ) -> Result<Response<proto::Siblings>, Status> {
+        let key = parse_char(&request.get_ref().key).ok_or_else(invalid_char)?;
+        match self.request(|id| MyRegisterMsg::GetAll(id, key)).await? {
+            MyRegisterMsg::GetAllOk(_, siblings) => Ok(Response::new(proto::Siblings {
+                siblings: siblings
+                    .into_iter()
+                    .map(|(timestamp, value)| proto::Sibling {
+                        value: value.to_string(),
+                        version: Some(version(timestamp)),
+                    })
+                    .collect(),
+            })),
+            _ => Err(Status::internal("Unexpected response")),
+        }
+    }
+
+    type WatchStream = Pin<Box<dyn Stream<Item = Result<proto::Change, Status>> + Send>>;
+
+    async fn watch(
+        &self,
+        request: Request<proto::Key>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let key = parse_char(&request.get_ref().key).ok_or_else(invalid_char)?;
+        let events = watch::subscribe_feed();
+        let (sender, receiver) = mpsc::channel(WATCH_BUFFER);
+        // the feed is a blocking channel, so follow it on a thread of its own, until a change
+        // finds the watcher gone
+        tokio::task::spawn_blocking(move || {
+            for event in events.iter().filter(|event| event.key == key) {
+                let change = proto::Change {
+                    key: event.key.to_string(),
+                    value: event.value.map(|value| value.to_string()),
+                    version: event.timestamp.map(version),
+                };
+                if sender.blocking_send(Ok(change)).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+}
+
+/// The version naming a write, with the address of the server it was made at.
+fn version((counter, server): Timestamp) -> proto::Version {
+    proto::Version {
+        counter,
+        server: SocketAddrV4::from(Id::from(server)).to_string(),
+    }
+}
+
+/// The key or value a string holds, if it is a single character.
+fn parse_char(s: &str) -> Option<Key> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+fn invalid_char() -> Status {
+    Status::invalid_argument("Keys and values are single characters")
+}
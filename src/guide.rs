@@ -0,0 +1,57 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::map::Map;
+use crate::model::RegisterState;
+use crate::MyRegisterActorState;
+use crate::MyRegisterMsg;
+
+/// How guided exploration orders its frontier, exploring states with higher scores first.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Heuristic {
+    /// Pairs of servers whose visible values differ, so paths along which servers drift apart are
+    /// followed first.
+    Divergence,
+    /// Sync messages still in the network, so states with more reorderings left to try come
+    /// first.
+    PendingSync,
+    /// Both, with divergence weighing more.
+    Combined,
+}
+
+impl Heuristic {
+    pub(crate) fn score<M: Clone + Debug + PartialEq + Hash + Map>(
+        &self,
+        state: &RegisterState<M>,
+    ) -> usize {
+        match self {
+            Heuristic::Divergence => divergence(state),
+            Heuristic::PendingSync => pending_sync(state),
+            Heuristic::Combined => divergence(state) * 16 + pending_sync(state),
+        }
+    }
+}
+
+fn divergence<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> usize {
+    let observable = state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::Server(server) => Some(server.observable()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    observable
+        .iter()
+        .enumerate()
+        .map(|(i, a)| observable[i + 1..].iter().filter(|b| a != *b).count())
+        .sum()
+}
+
+fn pending_sync<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> usize {
+    state
+        .network
+        .iter_all()
+        .filter(|envelope| matches!(envelope.msg, MyRegisterMsg::Internal(_)))
+        .count()
+}
@@ -0,0 +1,128 @@
+use crate::map::Timestamp;
+
+/// How many of a counter's low bits hold the logical part of a reading.
+pub const LOGICAL_BITS: u32 = 8;
+
+/// A hybrid logical clock, which maps can draw the counters of their timestamps from instead of
+/// their own clock.
+///
+/// A reading pairs the latest wall clock reading known, this server's own or one carried by an
+/// op it has received, with a logical part that orders the readings sharing it. Every reading is
+/// later than the readings of the ops already seen, as with a Lamport clock, so a set is always
+/// ordered after the values it overwrites, while staying close to wall clock time so that
+/// concurrent sets are ordered by when they happened. Readings are packed into the counter with
+/// the logical part in the low [`LOGICAL_BITS`], so counters order as readings do.
+///
+/// The wall clock is modelled as in [`LwwMap`](crate::LwwMap), some skew ahead of true time, but
+/// only ticks as this server prepares ops: the clock catches up with the rest through the
+/// readings it sees instead.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Hlc {
+    actor_id: usize,
+    /// This server's wall clock reading.
+    wall: u32,
+    /// The latest reading seen, as its wall clock and logical parts.
+    latest: (u32, u32),
+}
+
+impl Hlc {
+    /// A clock for the server with the given actor id, with no skew.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            wall: 0,
+            latest: (0, 0),
+        }
+    }
+
+    /// Set how far ahead of true time this server's wall clock runs.
+    pub fn set_skew(&mut self, skew: u32) {
+        self.wall = skew;
+    }
+
+    /// This server's wall clock reading, to persist alongside the highest counter seen.
+    pub fn wall(&self) -> u32 {
+        self.wall
+    }
+
+    /// Put the clock back as it was from the wall clock reading and the highest counter seen.
+    pub fn restore(&mut self, wall: u32, max_counter: u32) {
+        self.wall = wall;
+        self.latest = unpack(max_counter);
+    }
+
+    /// The counter for the next op this server prepares: its wall clock reading after the tick
+    /// preparing takes, or the latest reading seen with the logical part bumped if that is
+    /// later. Once the logical part has no room left, the reading moves on to the next wall
+    /// clock reading instead, running ahead of the wall clock until it catches up.
+    pub fn next(&self) -> u32 {
+        let wall = self.wall + 1;
+        if wall > self.latest.0 {
+            pack(wall, 0)
+        } else if self.latest.1 + 1 < 1 << LOGICAL_BITS {
+            pack(self.latest.0, self.latest.1 + 1)
+        } else {
+            pack(self.latest.0 + 1, 0)
+        }
+    }
+
+    /// Move past a timestamp, whether this server prepared the op or another did.
+    pub fn observe(&mut self, timestamp: Timestamp) {
+        let reading = unpack(timestamp.0);
+        if reading <= self.latest {
+            return;
+        }
+        if timestamp.1 == self.actor_id {
+            // preparing the op took a tick of the wall clock
+            self.wall += 1;
+        }
+        self.latest = reading;
+    }
+}
+
+fn pack(wall: u32, logical: u32) -> u32 {
+    debug_assert!(logical < 1 << LOGICAL_BITS, "logical part overflowed");
+    if wall > u32::MAX >> LOGICAL_BITS {
+        // the counter has no room for a later reading, so it saturates at the last one
+        return u32::MAX;
+    }
+    (wall << LOGICAL_BITS) | logical
+}
+
+fn unpack(counter: u32) -> (u32, u32) {
+    (counter >> LOGICAL_BITS, counter & ((1 << LOGICAL_BITS) - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_bumps_the_logical_part_behind_a_later_reading() {
+        let mut hlc = Hlc::new(0);
+        hlc.observe((pack(5, 0), 1));
+        assert_eq!(unpack(hlc.next()), (5, 1));
+    }
+
+    #[test]
+    fn next_moves_to_the_next_wall_reading_once_the_logical_part_is_full() {
+        let mut hlc = Hlc::new(0);
+        let full = (1 << LOGICAL_BITS) - 1;
+        hlc.observe((pack(5, full), 1));
+        let next = hlc.next();
+        assert_eq!(unpack(next), (6, 0));
+        assert!(next > pack(5, full));
+
+        // the reading stays ahead of the wall clock, which only ticks once
+        hlc.observe((next, 0));
+        assert_eq!(hlc.wall(), 1);
+        assert_eq!(unpack(hlc.next()), (6, 1));
+    }
+
+    #[test]
+    fn next_saturates_once_the_counter_is_full() {
+        let mut hlc = Hlc::new(0);
+        hlc.observe((u32::MAX, 1));
+        assert_eq!(hlc.next(), u32::MAX);
+    }
+}
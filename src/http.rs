@@ -0,0 +1,188 @@
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::io::Write;
+use std::net::SocketAddrV4;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use crate::wire;
+use crate::Key;
+use crate::MyRegisterMsg;
+use crate::RequestId;
+
+/// How long to wait for the server to answer a request before giving up on it. Requests and
+/// responses travel over UDP, so either may be lost.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long to wait on a client sending its request, so that a slow one can't hold up the
+/// others, as connections are handled one at a time.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The largest body accepted, in bytes. Values are single characters, so anything much larger
+/// is refused before reading it rather than buffered.
+const MAX_BODY: usize = 1024;
+
+/// A client of a server run with `run`, translating HTTP requests on `/keys/{k}` into the
+/// server's own requests: `GET` into `GetAll`, `PUT` with the value as the body into `Put`, and
+/// `DELETE` into `Delete`.
+///
+/// A `GET` responds with the key's values, one per line, as there is more than one while
+/// concurrent writes are unresolved, or 404 if it has none.
+struct Frontend {
+    server: SocketAddrV4,
+    socket: UdpSocket,
+    next_request_id: RequestId,
+}
+
+/// Serve HTTP on the given address for the server at `server`, on a thread of its own, handling
+/// one connection at a time.
+pub(crate) fn serve(listen: SocketAddrV4, server: SocketAddrV4) {
+    let listener = TcpListener::bind(listen).unwrap_or_else(|e| {
+        eprintln!("Failed to listen for HTTP on {}: {}", listen, e);
+        std::process::exit(2)
+    });
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| socket.set_read_timeout(Some(TIMEOUT)).map(|()| socket))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to bind a socket for HTTP requests: {}", e);
+            std::process::exit(2)
+        });
+    let mut frontend = Frontend {
+        server,
+        socket,
+        next_request_id: 0,
+    };
+    println!("Serving keys on http://{}/keys/", listen);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| frontend.handle(stream));
+            if let Err(e) = result {
+                eprintln!("Failed to handle HTTP request: {}", e);
+            }
+        }
+    });
+}
+
+impl Frontend {
+    fn handle(&mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        if content_length > MAX_BODY {
+            return write_response(stream, "413 Payload Too Large", "");
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let path = parts.next().unwrap_or_default();
+        let (status, body) = self.respond(method, path, &String::from_utf8_lossy(&body));
+        write_response(stream, status, &body)
+    }
+
+    /// The status and body to respond to a request with.
+    fn respond(&mut self, method: &str, path: &str, body: &str) -> (&'static str, String) {
+        let key = match parse_key(path) {
+            Some(key) => key,
+            None => return ("404 Not Found", "Only /keys/{k} is served\n".to_owned()),
+        };
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        let request = match method {
+            "GET" => MyRegisterMsg::GetAll(request_id, key),
+            "PUT" => {
+                let mut chars = body.trim().chars();
+                match (chars.next(), chars.next()) {
+                    (Some(value), None) => MyRegisterMsg::Put(request_id, key, value),
+                    _ => {
+                        return (
+                            "400 Bad Request",
+                            "Values are single characters\n".to_owned(),
+                        )
+                    }
+                }
+            }
+            "DELETE" => MyRegisterMsg::Delete(request_id, key),
+            _ => return ("405 Method Not Allowed", String::new()),
+        };
+        match self.request(request_id, &request) {
+            Ok(MyRegisterMsg::GetAllOk(_, siblings)) if siblings.is_empty() => {
+                ("404 Not Found", String::new())
+            }
+            Ok(MyRegisterMsg::GetAllOk(_, siblings)) => (
+                "200 OK",
+                siblings
+                    .iter()
+                    .map(|(_, value)| format!("{}\n", value))
+                    .collect(),
+            ),
+            Ok(_) => ("204 No Content", String::new()),
+            Err(e) => ("504 Gateway Timeout", format!("{}\n", e)),
+        }
+    }
+
+    /// Send a request to the server and wait for its response.
+    fn request(
+        &self,
+        request_id: RequestId,
+        request: &MyRegisterMsg,
+    ) -> Result<MyRegisterMsg, String> {
+        self.socket
+            .send_to(&wire::encode(request), self.server)
+            .map_err(|e| e.to_string())?;
+        let mut buf = [0; 65_535];
+        loop {
+            let (len, _) = self
+                .socket
+                .recv_from(&mut buf)
+                .map_err(|_| "The server didn't respond".to_owned())?;
+            // skip late responses to requests that timed out
+            match wire::decode(&buf[..len]) {
+                Ok(
+                    response @ (MyRegisterMsg::GetAllOk(id, _)
+                    | MyRegisterMsg::PutOk(id, _)
+                    | MyRegisterMsg::DeleteOk(id)),
+                ) if id == request_id => return Ok(response),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The key a path names, if it is of the form `/keys/{k}` with a single character key.
+fn parse_key(path: &str) -> Option<Key> {
+    let mut chars = path.strip_prefix("/keys/")?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(key), None) => Some(key),
+        _ => None,
+    }
+}
+
+fn write_response(mut stream: TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
@@ -0,0 +1,22 @@
+#[cfg(feature = "std")]
+pub use stateright::actor::Id;
+
+/// The id of the server a CRDT belongs to, standing in for stateright's actor id when built
+/// without std, which stateright needs.
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Id(u64);
+
+#[cfg(not(feature = "std"))]
+impl From<usize> for Id {
+    fn from(index: usize) -> Self {
+        Id(index as u64)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl From<Id> for usize {
+    fn from(id: Id) -> Self {
+        id.0 as usize
+    }
+}
@@ -0,0 +1,415 @@
+//! A small expression language for ad-hoc invariants over the servers' states.
+//!
+//! ```text
+//! invariant  := expr
+//! expr       := ("forall" | "exists") IDENT ":" expr | or
+//! or         := and ("||" and)*
+//! and        := not ("&&" not)*
+//! not        := "!" not | comparison
+//! comparison := sum (("==" | "!=" | "<=" | ">=" | "<" | ">") sum)?
+//! sum        := atom (("+" | "-") atom)*
+//! atom       := NUMBER | 'c' | "true" | "false" | "none" | "servers()"
+//!             | IDENT "." METHOD "(" args ")" | "(" expr ")"
+//! ```
+//!
+//! Quantifiers range over the servers, which have the methods:
+//!
+//! - `len()`: the number of stored values, including conflicting ones
+//! - `visible()`: the number of visible values
+//! - `get('k')`: the value for the key, or `none`
+//! - `siblings('k')`: the number of stored values for the key
+//!
+//! For example `forall s: s.len() <= 3` or `forall a: forall b: a.get('k') == b.get('k')`.
+
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::map::Map;
+use crate::model::RegisterState;
+use crate::MyRegisterActorState;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Invariant {
+    pub(crate) source: String,
+    expr: Expr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Int(i64),
+    Bool(bool),
+    Char(Option<char>),
+    Servers,
+    Quantifier {
+        forall: bool,
+        var: String,
+        body: Box<Expr>,
+    },
+    Not(Box<Expr>),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+    Method {
+        var: String,
+        method: Method,
+        key: Option<char>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Le,
+    Ge,
+    Lt,
+    Gt,
+    Add,
+    Sub,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Method {
+    Len,
+    Visible,
+    Get,
+    Siblings,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value {
+    Int(i64),
+    Bool(bool),
+    Char(Option<char>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Int(i64),
+    Char(char),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: &[&str] = &[
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "+", "-", "(", ")", ":", ".", ",",
+];
+
+impl Invariant {
+    pub(crate) fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+        };
+        let expr = parser.expr()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected {:?} in invariant {:?}", token, source));
+        }
+        Ok(Self {
+            source: source.to_owned(),
+            expr,
+        })
+    }
+
+    pub(crate) fn eval<M: Clone + Debug + PartialEq + Hash + Map>(
+        &self,
+        state: &RegisterState<M>,
+    ) -> Result<bool, String> {
+        let servers = state
+            .actor_states
+            .iter()
+            .filter_map(|actor| match &**actor {
+                MyRegisterActorState::Server(map) => Some(&**map),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        match eval(&self.expr, &servers, &mut Vec::new())? {
+            Value::Bool(b) => Ok(b),
+            value => Err(format!(
+                "invariant {:?} evaluated to {:?} rather than a boolean",
+                self.source, value
+            )),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    'outer: while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let digits = chars[start..i].iter().collect::<String>();
+            tokens.push(Token::Int(digits.parse().map_err(|e| format!("{}", e))?));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '\'' {
+            if i + 2 >= chars.len() || chars[i + 2] != '\'' {
+                return Err(format!("unterminated character literal at {}", i));
+            }
+            tokens.push(Token::Char(chars[i + 1]));
+            i += 3;
+        } else {
+            for symbol in SYMBOLS {
+                let len = symbol.len();
+                if i + len <= chars.len() && chars[i..i + len].iter().copied().eq(symbol.chars()) {
+                    tokens.push(Token::Symbol(*symbol));
+                    i += len;
+                    continue 'outer;
+                }
+            }
+            return Err(format!("unexpected {:?} at {}", c, i));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        if let Some(Token::Symbol(s)) = self.peek() {
+            if *s == symbol {
+                self.pos += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(format!("expected {:?}, found {:?}", symbol, self.peek()))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, String> {
+        match self.peek().cloned() {
+            Some(Token::Ident(ident)) => {
+                self.pos += 1;
+                Ok(ident)
+            }
+            token => Err(format!("expected an identifier, found {:?}", token)),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        if let Some(Token::Ident(ident)) = self.peek() {
+            let forall = match ident.as_str() {
+                "forall" => Some(true),
+                "exists" => Some(false),
+                _ => None,
+            };
+            if let Some(forall) = forall {
+                self.pos += 1;
+                let var = self.ident()?;
+                self.expect_symbol(":")?;
+                let body = Box::new(self.expr()?);
+                return Ok(Expr::Quantifier { forall, var, body });
+            }
+        }
+        self.or()
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.and()?;
+        while self.eat_symbol("||") {
+            lhs = Expr::Binary(Box::new(lhs), BinOp::Or, Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.not()?;
+        while self.eat_symbol("&&") {
+            lhs = Expr::Binary(Box::new(lhs), BinOp::And, Box::new(self.not()?));
+        }
+        Ok(lhs)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat_symbol("!") {
+            Ok(Expr::Not(Box::new(self.not()?)))
+        } else {
+            self.comparison()
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let lhs = self.sum()?;
+        let ops = [
+            ("==", BinOp::Eq),
+            ("!=", BinOp::Ne),
+            ("<=", BinOp::Le),
+            (">=", BinOp::Ge),
+            ("<", BinOp::Lt),
+            (">", BinOp::Gt),
+        ];
+        for (symbol, op) in ops {
+            if self.eat_symbol(symbol) {
+                return Ok(Expr::Binary(Box::new(lhs), op, Box::new(self.sum()?)));
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.atom()?;
+        loop {
+            if self.eat_symbol("+") {
+                lhs = Expr::Binary(Box::new(lhs), BinOp::Add, Box::new(self.atom()?));
+            } else if self.eat_symbol("-") {
+                lhs = Expr::Binary(Box::new(lhs), BinOp::Sub, Box::new(self.atom()?));
+            } else {
+                return Ok(lhs);
+            }
+        }
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.peek().cloned() {
+            Some(Token::Int(i)) => {
+                self.pos += 1;
+                Ok(Expr::Int(i))
+            }
+            Some(Token::Char(c)) => {
+                self.pos += 1;
+                Ok(Expr::Char(Some(c)))
+            }
+            Some(Token::Symbol("(")) => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            }
+            Some(Token::Ident(ident)) => {
+                self.pos += 1;
+                match ident.as_str() {
+                    "true" => return Ok(Expr::Bool(true)),
+                    "false" => return Ok(Expr::Bool(false)),
+                    "none" => return Ok(Expr::Char(None)),
+                    "servers" => {
+                        self.expect_symbol("(")?;
+                        self.expect_symbol(")")?;
+                        return Ok(Expr::Servers);
+                    }
+                    _ => {}
+                }
+                self.expect_symbol(".")?;
+                let method = match self.ident()?.as_str() {
+                    "len" => Method::Len,
+                    "visible" => Method::Visible,
+                    "get" => Method::Get,
+                    "siblings" => Method::Siblings,
+                    method => return Err(format!("unknown method {:?}", method)),
+                };
+                self.expect_symbol("(")?;
+                let key = match method {
+                    Method::Len | Method::Visible => None,
+                    Method::Get | Method::Siblings => match self.peek().cloned() {
+                        Some(Token::Char(c)) => {
+                            self.pos += 1;
+                            Some(c)
+                        }
+                        token => return Err(format!("expected a key, found {:?}", token)),
+                    },
+                };
+                self.expect_symbol(")")?;
+                Ok(Expr::Method {
+                    var: ident,
+                    method,
+                    key,
+                })
+            }
+            token => Err(format!("unexpected {:?}", token)),
+        }
+    }
+}
+
+fn eval<M: Map>(
+    expr: &Expr,
+    servers: &[&M],
+    bindings: &mut Vec<(String, usize)>,
+) -> Result<Value, String> {
+    Ok(match expr {
+        Expr::Int(i) => Value::Int(*i),
+        Expr::Bool(b) => Value::Bool(*b),
+        Expr::Char(c) => Value::Char(*c),
+        Expr::Servers => Value::Int(servers.len() as i64),
+        Expr::Quantifier { forall, var, body } => {
+            let mut result = *forall;
+            for server in 0..servers.len() {
+                bindings.push((var.clone(), server));
+                let holds = eval(body, servers, bindings);
+                bindings.pop();
+                match holds? {
+                    Value::Bool(holds) if holds != *forall => {
+                        result = holds;
+                        break;
+                    }
+                    Value::Bool(_) => {}
+                    value => return Err(format!("quantifier body gave {:?}", value)),
+                }
+            }
+            Value::Bool(result)
+        }
+        Expr::Not(inner) => match eval(inner, servers, bindings)? {
+            Value::Bool(b) => Value::Bool(!b),
+            value => return Err(format!("can't negate {:?}", value)),
+        },
+        Expr::Binary(lhs, op, rhs) => {
+            let lhs = eval(lhs, servers, bindings)?;
+            let rhs = eval(rhs, servers, bindings)?;
+            match (op, lhs, rhs) {
+                (BinOp::Or, Value::Bool(a), Value::Bool(b)) => Value::Bool(a || b),
+                (BinOp::And, Value::Bool(a), Value::Bool(b)) => Value::Bool(a && b),
+                (BinOp::Eq, a, b) => Value::Bool(a == b),
+                (BinOp::Ne, a, b) => Value::Bool(a != b),
+                (BinOp::Le, Value::Int(a), Value::Int(b)) => Value::Bool(a <= b),
+                (BinOp::Ge, Value::Int(a), Value::Int(b)) => Value::Bool(a >= b),
+                (BinOp::Lt, Value::Int(a), Value::Int(b)) => Value::Bool(a < b),
+                (BinOp::Gt, Value::Int(a), Value::Int(b)) => Value::Bool(a > b),
+                (BinOp::Add, Value::Int(a), Value::Int(b)) => Value::Int(a + b),
+                (BinOp::Sub, Value::Int(a), Value::Int(b)) => Value::Int(a - b),
+                (op, a, b) => return Err(format!("can't apply {:?} to {:?} and {:?}", op, a, b)),
+            }
+        }
+        Expr::Method { var, method, key } => {
+            let server = bindings
+                .iter()
+                .rev()
+                .find(|(name, _)| name == var)
+                .map(|(_, server)| servers[*server])
+                .ok_or_else(|| format!("unbound variable {:?}", var))?;
+            match (method, key) {
+                (Method::Len, _) => Value::Int(server.values().len() as i64),
+                (Method::Visible, _) => Value::Int(server.visible_values().len() as i64),
+                (Method::Get, Some(key)) => Value::Char(server.get(key).cloned()),
+                (Method::Siblings, Some(key)) => {
+                    Value::Int(server.values().iter().filter(|(_, k, _)| k == key).count() as i64)
+                }
+                (Method::Get | Method::Siblings, None) => unreachable!("parsed with a key"),
+            }
+        }
+    })
+}
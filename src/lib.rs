@@ -0,0 +1,101 @@
+//! The CRDTs that the `toy-crdt` binary model checks, for embedding directly.
+//!
+//! Each server keeps a map created with its own actor id. A change is prepared from a server's
+//! current state with [`Map::prepare_set`] or [`Map::prepare_delete`], giving a [`MapOp`] to send
+//! to the other servers, and is then effected with [`Map::effect`] exactly once on every server,
+//! the one that prepared it included. How the ops get to the servers is up to the embedder, though
+//! [`publish`] gives the [`PeerMsg`] to send for an op effected locally, and [`receive_sync`]
+//! effects the ops in a message from another server, with [`Barriers`] keeping what truncates
+//! removed; the sync protocols the binary checks stay in the binary, though their messages are
+//! all [`PeerMsg`]s. Maps can instead be
+//! synced by state with [`Map::merge`], though it is up to the embedder to remove the values the
+//! other replica had removed, or by the [`MapDelta`]s of their changes. A map's state can be
+//! persisted as a [`MapSnapshot`] and read back with [`Map::restore`]. The values an op removes
+//! are named by a [`CausalContext`], which holds runs of timestamps as ranges.
+//!
+//! [`FixedMap`] is the map that converges. [`BrokenMap`] is the first version of it, which
+//! doesn't, and [`LwwMap`] picks a winner by wall clock readings instead of keeping siblings.
+//! [`NestedMap`] holds maps as values, with its ops addressed by paths of keys. The fixed and
+//! last-writer-wins maps can draw their timestamps from a [`Hlc`] instead, with [`Map::set_clock`].
+//! The fixed map is add-wins, and can have deletes win over concurrent sets instead with
+//! [`Map::set_policy`].
+//!
+//! [`ORSet`] is an add-wins observed-remove set, driven through [`Set`] in the same way, as are
+//! the simpler [`GSet`], which only grows, and [`TwoPhaseSet`], which can't add an element back
+//! once it is removed.
+//! [`PNCounter`] is a state-based counter, driven through [`Counter`]: replicas send their whole
+//! state on and merge the states they receive. [`MVRegister`] is a multi-value register that
+//! keeps concurrent writes as siblings, telling them apart by version vectors, and
+//! [`LwwRegister`] keeps only the latest of them instead. [`Flag`] is a boolean flag kept the
+//! same way, resolving a concurrent enable and disable by its [`Bias`]. [`Rga`] is a list, driven
+//! through [`List`] as the set is.
+//!
+//! The `std` feature, on by default, builds the checker binary. Without it the library only
+//! needs `core` and `alloc`, for embedding in `no_std` targets, and [`Id`] is its own type rather
+//! than stateright's actor id. The `ffi` feature adds a C interface to the fixed map, and the
+//! `python` feature a Python module, which needs `std` whichever features are on.
+
+#![cfg_attr(not(any(feature = "std", feature = "python")), no_std)]
+
+extern crate alloc;
+
+pub mod context;
+pub mod counter;
+pub mod counter_pn;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fingerprint;
+pub mod flag;
+pub mod hlc;
+pub mod id;
+pub mod list;
+pub mod list_rga;
+pub mod map;
+pub mod map_broken;
+pub mod map_fixed;
+pub mod map_lww;
+pub mod map_nested;
+pub mod peer;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod register_lww;
+pub mod register_mv;
+pub mod set;
+pub mod set_gset;
+pub mod set_orset;
+pub mod set_twophase;
+
+pub use context::CausalContext;
+pub use counter::Counter;
+pub use counter_pn::PNCounter;
+pub use flag::Bias;
+pub use flag::Flag;
+pub use flag::FlagWrite;
+pub use hlc::Hlc;
+pub use id::Id;
+pub use list::List;
+pub use list::ListOp;
+pub use list_rga::Rga;
+pub use map::ClockKind;
+pub use map::Map;
+pub use map::MapDelta;
+pub use map::MapOp;
+pub use map::MapSnapshot;
+pub use map::Policy;
+pub use map::Timestamp;
+pub use map_broken::BrokenMap;
+pub use map_fixed::FixedMap;
+pub use map_lww::LwwMap;
+pub use map_nested::NestedMap;
+pub use map_nested::NestedOp;
+pub use peer::publish;
+pub use peer::receive_sync;
+pub use peer::Barriers;
+pub use peer::PeerMsg;
+pub use register_lww::LwwRegister;
+pub use register_mv::MVRegister;
+pub use set::Set;
+pub use set::SetOp;
+pub use set_gset::GSet;
+pub use set_orset::ORSet;
+pub use set_twophase::TwoPhaseSet;
@@ -0,0 +1,53 @@
+use alloc::vec::Vec;
+
+use crate::id::Id;
+use crate::map::Timestamp;
+
+/// An operation on a list, prepared at its origin and then effected exactly once at every
+/// server, the origin included, as map ops are.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ListOp {
+    /// Insert an element with a unique id after the element with id `after`, or at the start.
+    Insert {
+        id: Timestamp,
+        after: Option<Timestamp>,
+        value: char,
+    },
+    /// Delete the element with the id.
+    Delete { id: Timestamp },
+}
+
+impl ListOp {
+    /// The id of the element the op inserts or deletes.
+    pub fn id(&self) -> Timestamp {
+        match self {
+            ListOp::Insert { id, .. } | ListOp::Delete { id } => *id,
+        }
+    }
+}
+
+/// The interface the checker uses to drive a list, and the one to embed a list with.
+///
+/// As with [`Map`](crate::map::Map), a change is prepared with `prepare_insert` or
+/// `prepare_delete`, which leave the list as it is, and the op is then effected exactly once on
+/// every server's list, the preparing server's included.
+pub trait List {
+    /// A list for the server with the given actor id, which must be unique among the servers.
+    fn new(actor_id: Id) -> Self;
+
+    /// The elements that haven't been deleted, in order.
+    fn values(&self) -> Vec<char>;
+
+    /// Prepare inserting `value` so that it is at `index` among the elements, without changing
+    /// the list. Indices past the end insert at the end.
+    fn prepare_insert(&self, index: usize, value: char) -> ListOp;
+
+    /// Prepare deleting the element at `index`, without changing the list, if there is one.
+    fn prepare_delete(&self, index: usize) -> Option<ListOp>;
+
+    /// Apply an op, whether it was prepared by this list or received from another server.
+    fn effect(&mut self, op: ListOp);
+
+    /// The highest op counter this list has seen.
+    fn max_counter(&self) -> u32;
+}
@@ -0,0 +1,235 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use crate::id::Id;
+use crate::list::List;
+use crate::list::ListOp;
+use crate::map::Timestamp;
+
+impl List for Rga {
+    fn new(actor_id: Id) -> Self {
+        Self::new(actor_id.into())
+    }
+
+    fn values(&self) -> Vec<char> {
+        self.values()
+    }
+
+    fn prepare_insert(&self, index: usize, value: char) -> ListOp {
+        let (id, after) = self.prepare_insert(index);
+        ListOp::Insert { id, after, value }
+    }
+
+    fn prepare_delete(&self, index: usize) -> Option<ListOp> {
+        self.prepare_delete(index).map(|id| ListOp::Delete { id })
+    }
+
+    fn effect(&mut self, op: ListOp) {
+        match op {
+            ListOp::Insert { id, after, value } => self.receive_insert(id, after, value),
+            ListOp::Delete { id } => self.receive_delete(id),
+        }
+    }
+
+    fn max_counter(&self) -> u32 {
+        self.max_op
+    }
+}
+
+/// An element of an [`Rga`], kept as a tombstone once deleted so that inserts after it still
+/// find their place.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Element {
+    pub id: Timestamp,
+    pub value: char,
+    pub deleted: bool,
+}
+
+/// A replicated growable array: each element has a unique id, and is inserted after the element
+/// its server saw at the position, skipping over elements with greater ids that were inserted
+/// after the same element concurrently, so that every server orders them the same way.
+///
+/// Ops aren't delivered in causal order, so an insert can arrive before the element it goes
+/// after, and a delete before the element it deletes. Such inserts wait until their element
+/// arrives, and such deletes are remembered so that the element is deleted when it arrives.
+/// Nor are they delivered exactly once, so an insert of an element already held or waiting is
+/// ignored.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Rga {
+    actor_id: usize,
+    max_op: u32,
+    /// Every element inserted, in order, including the deleted ones.
+    pub elements: Vec<Element>,
+    /// Inserts waiting for the element they go after.
+    pub pending: BTreeSet<(Timestamp, Timestamp, char)>,
+    /// Deletes of elements that haven't arrived yet.
+    pub early_deletes: BTreeSet<Timestamp>,
+}
+
+impl Rga {
+    /// An empty list for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            max_op: 0,
+            elements: Vec::new(),
+            pending: BTreeSet::new(),
+            early_deletes: BTreeSet::new(),
+        }
+    }
+
+    /// The elements that haven't been deleted, in order.
+    pub fn values(&self) -> Vec<char> {
+        self.visible().map(|element| element.value).collect()
+    }
+
+    fn visible(&self) -> impl Iterator<Item = &Element> {
+        self.elements.iter().filter(|element| !element.deleted)
+    }
+
+    /// The id for inserting an element at `index`, globally unique once effected, and the id of
+    /// the element to insert it after, if any.
+    pub fn prepare_insert(&self, index: usize) -> (Timestamp, Option<Timestamp>) {
+        let after = match index {
+            0 => None,
+            // the element before the index, or the last one if the index is past the end
+            _ => self.visible().take(index).last().map(|element| element.id),
+        };
+        ((self.max_op + 1, self.actor_id), after)
+    }
+
+    /// The id of the element at `index`, if any.
+    pub fn prepare_delete(&self, index: usize) -> Option<Timestamp> {
+        self.visible().nth(index).map(|element| element.id)
+    }
+
+    /// Effect an insert, whether prepared here or by another server. Inserting an element again
+    /// changes nothing.
+    pub fn receive_insert(&mut self, id: Timestamp, after: Option<Timestamp>, value: char) {
+        self.max_op = core::cmp::max(self.max_op, id.0);
+
+        // a redelivered insert
+        if self.elements.iter().any(|e| e.id == id)
+            || self.pending.iter().any(|(_, pending, _)| *pending == id)
+        {
+            return;
+        }
+
+        let start = match after {
+            None => 0,
+            Some(after) => match self.elements.iter().position(|e| e.id == after) {
+                Some(position) => position + 1,
+                None => {
+                    // the element to insert after hasn't arrived yet
+                    self.pending.insert((after, id, value));
+                    return;
+                }
+            },
+        };
+        // elements inserted concurrently after the same element, with greater ids, go first,
+        // along with the elements inserted after them, which have greater ids still
+        let position = self.elements[start..]
+            .iter()
+            .position(|e| e.id < id)
+            .map_or(self.elements.len(), |offset| start + offset);
+        let deleted = self.early_deletes.remove(&id);
+        self.elements
+            .insert(position, Element { id, value, deleted });
+
+        let waiting = self
+            .pending
+            .iter()
+            .filter(|(after, _, _)| *after == id)
+            .copied()
+            .collect::<Vec<_>>();
+        for (after, id, value) in waiting {
+            self.pending.remove(&(after, id, value));
+            self.receive_insert(id, Some(after), value);
+        }
+    }
+
+    /// Effect a delete, whether prepared here or by another server.
+    pub fn receive_delete(&mut self, id: Timestamp) {
+        self.max_op = core::cmp::max(self.max_op, id.0);
+
+        match self.elements.iter_mut().find(|e| e.id == id) {
+            Some(element) => element.deleted = true,
+            None => {
+                self.early_deletes.insert(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn duplicate_insert_is_ignored() {
+        let mut list = Rga::new(0);
+        let (id, after) = list.prepare_insert(0);
+        list.receive_insert(id, after, 'x');
+        list.receive_insert(id, after, 'x');
+        assert_eq!(list.values(), vec!['x']);
+        assert_eq!(list.elements.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_insert_waiting_for_its_element_is_ignored() {
+        let mut list = Rga::new(0);
+        list.receive_insert((2, 1), Some((1, 1)), 'b');
+        list.receive_insert((2, 1), Some((1, 1)), 'b');
+        assert_eq!(list.pending.len(), 1);
+
+        list.receive_insert((1, 1), None, 'a');
+        list.receive_insert((2, 1), Some((1, 1)), 'b');
+        assert_eq!(list.values(), vec!['a', 'b']);
+        assert!(list.pending.is_empty());
+    }
+
+    #[test]
+    fn insert_after_a_missing_element_waits_for_it() {
+        let mut list = Rga::new(0);
+        list.receive_insert((3, 1), Some((2, 1)), 'c');
+        list.receive_insert((2, 1), Some((1, 1)), 'b');
+        assert!(list.values().is_empty());
+
+        list.receive_insert((1, 1), None, 'a');
+        assert_eq!(list.values(), vec!['a', 'b', 'c']);
+        assert!(list.pending.is_empty());
+    }
+
+    #[test]
+    fn delete_before_insert_deletes_the_element_when_it_arrives() {
+        let mut list = Rga::new(0);
+        list.receive_delete((1, 1));
+        assert!(list.early_deletes.contains(&(1, 1)));
+
+        list.receive_insert((1, 1), None, 'a');
+        list.receive_insert((2, 1), Some((1, 1)), 'b');
+        assert_eq!(list.values(), vec!['b']);
+        assert!(list.early_deletes.is_empty());
+    }
+
+    #[test]
+    fn concurrent_inserts_converge_in_any_order() {
+        let ops = [
+            ((1, 0), None, 'a'),
+            ((1, 1), None, 'b'),
+            ((2, 0), Some((1, 0)), 'c'),
+        ];
+        let mut forwards = Rga::new(0);
+        for (id, after, value) in ops {
+            forwards.receive_insert(id, after, value);
+        }
+        let mut backwards = Rga::new(1);
+        for (id, after, value) in ops.into_iter().rev() {
+            backwards.receive_insert(id, after, value);
+        }
+        assert_eq!(forwards.values(), backwards.values());
+        assert_eq!(forwards.values(), vec!['b', 'a', 'c']);
+    }
+}
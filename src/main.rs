@@ -1,255 +1,676 @@
+use approx::BloomVisited;
+use audit::CommutativityAudit;
+use causal::Dependency;
+use causal::PendingWrite;
+use check::Bounds;
+use check::Strategy;
 use clap::Parser;
+use disk::DiskVisited;
+use durability::Durability;
+use durability::Restart;
+use explore::InMemory;
+use fairness::Fairness;
+use guide::Heuristic;
+use invariant::Invariant;
+use map::merge_reads;
+use map::ClockKind;
 use map::Map;
+use map::MapOp;
+use map::Policy;
 use map::Timestamp;
 use map_broken::BrokenMap;
 use map_fixed::FixedMap;
+use map_lww::LwwMap;
+use membership::Membership;
+use membership::Role;
+use membership::Status;
+use memory::MemoryAccounting;
+use model::History;
+use model::RegisterModel;
+use model::RegisterState;
+use partition::Isolation;
+use peer::receive_sync;
+use peer::PeerMsg;
+use profile::Profiler;
+use properties::Suite;
+#[cfg(feature = "scripting")]
+use script::Script;
+use serde::Deserialize;
+use serde::Serialize;
+use shape::ShapeRecorder;
+use shard::Placement;
+use sim::SimCfg;
 use stateright::actor::model_peers;
 use stateright::actor::Actor;
 use stateright::actor::ActorModel;
-use stateright::actor::ActorModelState;
+use stateright::actor::LossyNetwork;
 use stateright::actor::Network;
 use stateright::actor::Out;
 use stateright::Checker;
-use stateright::CheckerBuilder;
+use stateright::CheckerVisitor;
 use stateright::{actor::Id, Model};
 use std::borrow::Cow;
 use std::fmt::Debug;
 use std::hash::Hash;
+use std::hash::Hasher;
 use std::marker::PhantomData;
+use std::net::SocketAddrV4;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+#[cfg(feature = "scripting")]
 use std::sync::Arc;
+use std::time::Duration;
+use sync::PeerState;
+use sync::SyncMode;
+use timer::Timer;
+use toy_crdt::context;
+use toy_crdt::counter;
+use toy_crdt::counter_pn;
+use toy_crdt::fingerprint;
+use toy_crdt::flag;
+use toy_crdt::hlc;
+use toy_crdt::list;
+use toy_crdt::list_rga;
+use toy_crdt::map;
+use toy_crdt::map_broken;
+use toy_crdt::map_fixed;
+use toy_crdt::map_lww;
+use toy_crdt::map_nested;
+use toy_crdt::peer;
+use toy_crdt::register_lww;
+use toy_crdt::register_mv;
+use toy_crdt::set;
+use toy_crdt::set_gset;
+use toy_crdt::set_orset;
+use toy_crdt::set_twophase;
+use trace::Trace;
+use validate::Validation;
+use workload::parse_mix;
+use workload::KeyWorkload;
+use workload::MixedOp;
 
 const KEY: char = 'k';
 
+/// The element that set clients add and remove, the same for every client so that their adds
+/// and removes race.
+const SET_ELEMENT: Value = 'S';
+
+/// The key nested map clients set values under and delete, the same for every client so that
+/// their sets and deletes race.
+const NESTED_KEY: Key = 'n';
+
+/// The changes a counter client makes in turn, increments as 1 and decrements as -1, before
+/// reading the counter back.
+const COUNTER_CHANGES: [i64; 3] = [1, -1, 1];
+
+/// How long an open-loop client waits between requests. The checker explores timeouts in any
+/// order, so this only matters when running the actors for real.
+const OPEN_LOOP_INTERVAL: Range<Duration> = Duration::from_millis(100)..Duration::from_millis(200);
+
+/// How long a server waits after applying an op locally before pulling from its peers with
+/// `--sync-mode pull`, letting further ops batch up in the meantime.
+const PULL_INTERVAL: Range<Duration> = Duration::from_millis(100)..Duration::from_millis(200);
+
+/// How long a server waits after applying an op locally before sending its peers the deltas
+/// buffered for them with `--sync-mode delta`, letting further deltas join them meanwhile.
+const DELTA_INTERVAL: Range<Duration> = Duration::from_millis(100)..Duration::from_millis(200);
+
+/// How long a late joiner waits before joining, and a leaver before leaving. The checker explores
+/// timeouts in any order, so this can happen between any two steps.
+const MEMBERSHIP_INTERVAL: Range<Duration> = Duration::from_secs(1)..Duration::from_secs(2);
+
+/// How long a server waits for its peers to acknowledge sets and deletes with `--retransmit`
+/// before sending those still unacknowledged again.
+const RETRANSMIT_INTERVAL: Range<Duration> = Duration::from_millis(100)..Duration::from_millis(200);
+
+/// How long a server waits between pushing digests of its map to its peers with
+/// `--anti-entropy`, for as long as some peer may have ops it is missing.
+const GOSSIP_INTERVAL: Range<Duration> = Duration::from_millis(100)..Duration::from_millis(200);
+
+/// How long a server runs before crashing with `--crash`. The checker explores timeouts in any
+/// order, so the crash can happen between any two steps.
+const CRASH_INTERVAL: Range<Duration> = Duration::from_secs(1)..Duration::from_secs(2);
+
+/// How long a server runs before being cut off from its peers with `--partitions`, and then
+/// before healing. The checker explores timeouts in any order, so either can happen between any
+/// two steps.
+const PARTITION_INTERVAL: Range<Duration> = Duration::from_secs(1)..Duration::from_secs(2);
+
+/// How long `profile` checks for when no time limit is given.
+const PROFILE_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// How long a closed-loop client with retries waits for a response before resending its request.
+const RETRY_INTERVAL: Range<Duration> = Duration::from_millis(500)..Duration::from_millis(1000);
+
 type RequestId = usize;
 type Key = char;
 type Value = char;
 
-mod map;
-mod map_broken;
-mod map_fixed;
+mod approx;
+mod audit;
+mod byzantine;
+mod causal;
+mod causality;
+mod check;
+mod compare;
+mod dag;
+mod debug;
+mod dedup;
+mod delivery;
+mod disk;
+mod durability;
+mod epoch;
+mod explain;
+mod explore;
+mod fairness;
+mod gossip;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod guide;
+mod http;
+mod invariant;
+mod membership;
+mod memory;
+mod model;
+mod node;
+mod oplog;
+mod partition;
+mod profile;
+mod properties;
+mod reduction;
+mod register;
+mod render;
+mod repl;
+mod report;
+mod retransmit;
+#[cfg(feature = "scripting")]
+mod script;
+mod sec;
+mod shape;
+mod shard;
+mod sim;
+mod simulate;
+mod stability;
+mod swarm;
+mod sync;
+mod timer;
+mod trace;
+mod truncate;
+mod validate;
+mod watch;
+mod websocket;
+mod wire;
+mod workload;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 struct Peer<M> {
     peers: Vec<Id>,
+    sync_mode: SyncMode,
+    validation: Validation,
+    /// How far ahead of true time each server's wall clock runs, for maps that use them.
+    clock_skews: Vec<u32>,
+    /// Which clock the map draws timestamps from.
+    clock: Clock,
+    /// How the map resolves a set and a delete that are concurrent.
+    map_policy: MapPolicy,
+    /// Whether clients pass version tokens, so dependent writes have to wait for their
+    /// dependency.
+    causal_tokens: bool,
+    /// Whether ops on the map are stamped with vector clocks, so that peers deliver them in
+    /// causal order and only once each.
+    causal_delivery: bool,
+    /// Whether servers repair their maps by gossiping digests of them with their peers.
+    anti_entropy: bool,
+    /// Whether servers retransmit sets and deletes until their peers acknowledge them.
+    retransmit: bool,
+    /// Whether clients retry requests, so servers keep track of the requests they handle.
+    retries: bool,
+    /// Whether retried requests get their original response rather than being applied again.
+    dedup: bool,
+    /// Whether clients can subscribe to a key to be notified when ops from peers give it new
+    /// siblings.
+    conflict_notifications: bool,
+    /// Which servers hold each key, so ops are only sent to the other servers holding theirs.
+    placement: Placement,
+    /// Whether clients can truncate the whole map, so servers keep track of the sets they have
+    /// seen.
+    truncate: bool,
+    /// Whether servers roll into a new epoch after each truncate, dropping the sets from old
+    /// epochs from what they keep track of.
+    epochs: bool,
+    /// Whether servers collect the truncation metadata about sets once every server has
+    /// delivered them.
+    gc: bool,
+    /// Whether each server crashes once and recovers from its last snapshot.
+    crash: bool,
+    /// What servers restart from when they crash.
+    restart: Restart,
+    /// Take snapshots after this many ops applied rather than on each client mutation.
+    snapshot_every: Option<usize>,
+    /// Whether each server is cut off from its peers once and then heals.
+    partitions: bool,
+    /// Whether the server misbehaves, replaying the sets and deletes it receives and forging
+    /// conflicting copies of sets, its own included.
+    byzantine: bool,
+    /// Whether there are set clients, so servers hold a set alongside their map.
+    set: bool,
+    /// Which set servers hold for the set clients.
+    set_impl: SetImpl,
+    /// Whether there are counter clients, so servers hold a counter alongside their map.
+    counter: bool,
+    /// Whether there are multi-value register clients, so servers hold a register alongside
+    /// their map.
+    mv_register: bool,
+    /// Which register servers hold for the register clients.
+    register_impl: RegisterImpl,
+    /// Whether there are flag clients, so servers hold a flag alongside their map.
+    flag: bool,
+    /// Which way servers' flags resolve concurrent enables and disables.
+    flag_bias: FlagBias,
+    /// Whether there are list clients, so servers hold a list alongside their map.
+    list: bool,
+    /// Whether there are nested map clients, so servers hold a nested map alongside their map.
+    nested: bool,
+    /// How the server joins or leaves and which servers it starts out sending ops to, when
+    /// servers join and leave.
+    membership: Option<Membership>,
     _t: PhantomData<M>,
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
-enum PeerMsg {
-    PutSync {
-        context: Vec<Timestamp>,
-        timestamp: Timestamp,
-        key: char,
-        value: char,
-    },
-    DeleteSync {
-        context: Vec<Timestamp>,
-    },
-}
-
-impl<M> Actor for Peer<M>
+impl<M> Peer<M>
 where
     M: Clone + Debug + PartialEq + Hash + Map,
 {
-    type Msg = MyRegisterMsg;
-
-    type State = M;
+    /// Let the other peers holding the key know about an op applied locally. Ops on the whole map
+    /// have no key and go to every peer.
+    fn publish(
+        &self,
+        state: &mut Cow<PeerState<M>>,
+        key: Option<Key>,
+        op: PeerMsg,
+        o: &mut Out<Self>,
+    ) {
+        let peers = self
+            .peers
+            .iter()
+            .filter(|peer| key.map_or(true, |key| self.placement.holds((**peer).into(), key)))
+            .filter(|peer| {
+                state
+                    .membership
+                    .as_ref()
+                    .map_or(true, |membership| membership.includes(**peer))
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        if state.epochs.is_some() {
+            state.to_mut().record_epoch(&op, None);
+        }
+        let forged = self.byzantine.then(|| byzantine::forge(&op)).flatten();
+        let is_op = op.is_op();
+        if is_op && state.outbound.is_some() {
+            // keep the op until each peer acknowledges it, resending it when the timer fires
+            state
+                .to_mut()
+                .buffer_outbound(peers.iter().map(|peer| usize::from(*peer)), &op);
+            timer::set(&mut state.to_mut().timers, o, Timer::Retransmit)
+        }
+        match self.sync_mode {
+            SyncMode::Op if op.is_op() && state.delivery.is_some() => {
+                let stamped = state.to_mut().stamp(op);
+                o.broadcast(&peers, &MyRegisterMsg::Internal(stamped))
+            }
+            SyncMode::Op => o.broadcast(&peers, &MyRegisterMsg::Internal(op)),
+            SyncMode::HaveNeed => {
+                let have = state.to_mut().record(op);
+                o.broadcast(&peers, &MyRegisterMsg::Internal(have))
+            }
+            SyncMode::Pull => {
+                // peers only find out about the op when this server next pulls from them
+                state.to_mut().log_local(op);
+                timer::set(&mut state.to_mut().timers, o, Timer::Pull)
+            }
+            // only the map is synced by state, the other CRDTs still broadcast their ops
+            SyncMode::State if op.is_op() => {
+                o.broadcast(&peers, &MyRegisterMsg::Internal(state.state_sync()))
+            }
+            SyncMode::State => o.broadcast(&peers, &MyRegisterMsg::Internal(op)),
+            SyncMode::Delta if op.is_op() => {
+                // peers only get the op's delta when this server next flushes its buffers
+                state.to_mut().buffer_delta(&peers, &op);
+                timer::set(&mut state.to_mut().timers, o, Timer::Delta)
+            }
+            SyncMode::Delta => o.broadcast(&peers, &MyRegisterMsg::Internal(op)),
+        }
+        if let Some(forged) = forged {
+            // equivocate, sending each peer a second set under the same timestamp
+            o.broadcast(&peers, &MyRegisterMsg::Internal(forged))
+        }
+        if is_op && state.gossip.is_some() {
+            // peers find out about the op from this server's digest if the broadcast is lost
+            if let Some(gossip) = &mut state.to_mut().gossip {
+                gossip.disturb();
+            }
+            timer::set(&mut state.to_mut().timers, o, Timer::Gossip)
+        }
+    }
 
-    fn on_start(&self, id: Id, _o: &mut Out<Self>) -> Self::State {
-        Self::State::new(id)
+    /// Respond to a client's request, remembering the response in case the request is retried.
+    fn respond(
+        &self,
+        state: &mut Cow<PeerState<M>>,
+        client: Id,
+        request_id: RequestId,
+        response: MyRegisterMsg,
+        mutated: bool,
+        o: &mut Out<Self>,
+    ) {
+        if state.requests.is_some() {
+            if let Some(requests) = &mut state.to_mut().requests {
+                requests.record(client.into(), request_id, response.clone(), mutated);
+            }
+        }
+        o.send(client, response)
     }
 
-    fn on_msg(
+    /// Apply a dependent write from a client now that its dependency has been confirmed by every
+    /// server holding it, which makes it safe to publish as a plain set.
+    fn apply_confirmed(
         &self,
-        _id: Id,
-        state: &mut std::borrow::Cow<Self::State>,
-        src: Id,
-        msg: Self::Msg,
+        state: &mut Cow<PeerState<M>>,
+        write: PendingWrite,
         o: &mut Out<Self>,
     ) {
-        match msg {
-            MyRegisterMsg::Put(id, key, value) => {
-                // apply the op locally
-                let (context, timestamp) = state.to_mut().set(key, value);
+        let op = state.prepare_set(write.key, write.value);
+        let token = op.timestamp().expect("sets are timestamped");
+        let state_mut = state.to_mut();
+        state_mut.apply(op.clone().into());
+        if let Some(causal) = &mut state_mut.causal {
+            causal.confirmed.insert(token, write.dependency);
+        }
 
-                // respond to the query (not totally necessary for this)
-                o.send(src, MyRegisterMsg::PutOk(id));
+        let response = MyRegisterMsg::PutOk(write.request_id, token);
+        self.respond(
+            state,
+            Id::from(write.client),
+            write.request_id,
+            response,
+            true,
+            o,
+        );
 
-                o.broadcast(
-                    &self.peers,
-                    &MyRegisterMsg::Internal(PeerMsg::PutSync {
-                        context,
-                        timestamp,
-                        key,
-                        value,
-                    }),
+        self.publish(state, Some(write.key), op.into(), o)
+    }
+
+    /// Tell the servers waiting on a dependency that this server has now applied it.
+    fn answer_checks(&self, state: &mut Cow<PeerState<M>>, o: &mut Out<Self>) {
+        if state
+            .causal
+            .as_ref()
+            .map_or(true, |causal| causal.checks.is_empty())
+        {
+            return;
+        }
+        if let Some(causal) = &mut state.to_mut().causal {
+            for (server, dependency) in causal.answerable_checks() {
+                o.send(
+                    Id::from(server),
+                    MyRegisterMsg::Internal(PeerMsg::DepOk { dependency }),
                 )
             }
-            MyRegisterMsg::Get(id, key) => {
-                if let Some(value) = state.get(&key) {
-                    // respond to the query (not totally necessary for this)
-                    o.send(src, MyRegisterMsg::GetOk(id, *value))
-                }
-            }
-            MyRegisterMsg::Delete(id, key) => {
-                // apply the op locally
-                let timestamp = state.to_mut().delete(&key);
+        }
+    }
 
-                // respond to the query (not totally necessary for this)
-                o.send(src, MyRegisterMsg::DeleteOk(id));
+    /// Start closing the current epoch after a truncate, if servers roll into new epochs.
+    fn close_epoch(&self, state: &mut Cow<PeerState<M>>, o: &mut Out<Self>) {
+        if state.epochs.is_none() {
+            return;
+        }
+        for msg in state.to_mut().close_epoch(self.peers.len()) {
+            o.broadcast(&self.peers, &MyRegisterMsg::Internal(msg))
+        }
+    }
 
-                if let Some(context) = timestamp {
-                    o.broadcast(
-                        &self.peers,
-                        &MyRegisterMsg::Internal(PeerMsg::DeleteSync { context }),
+    /// Notify the subscribers of each key whose siblings changed from those in `before`, taken
+    /// before applying ops from a peer, and that now has more than one.
+    fn notify_conflicts(
+        &self,
+        state: &PeerState<M>,
+        before: Vec<(Key, Vec<(Timestamp, Value)>)>,
+        o: &mut Out<Self>,
+    ) {
+        let subscribers = match &state.subscribers {
+            Some(subscribers) => subscribers,
+            None => return,
+        };
+        for (key, siblings) in before {
+            let after = state.siblings(key);
+            if after.len() > 1 && after != siblings {
+                for client in &subscribers[&key] {
+                    o.send(
+                        Id::from(*client),
+                        MyRegisterMsg::Siblings(key, after.clone()),
                     )
                 }
             }
-            MyRegisterMsg::Internal(PeerMsg::PutSync {
-                context,
-                timestamp,
-                key,
-                value,
-            }) => state.to_mut().receive_set(context, timestamp, key, value),
-            MyRegisterMsg::Internal(PeerMsg::DeleteSync { context }) => {
-                state.to_mut().receive_delete(context)
-            }
-            MyRegisterMsg::PutOk(_id) => {}
-            MyRegisterMsg::GetOk(_id, _value) => {}
-            MyRegisterMsg::DeleteOk(_id) => {}
         }
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-enum MyRegisterActor<M> {
-    PutClient {
-        put_count: usize,
-        /// Whether to send a get request after each mutation
-        follow_up_gets: bool,
-        server_count: usize,
-    },
-    DeleteClient {
-        delete_count: usize,
-        /// Whether to send a get request after each mutation
-        follow_up_gets: bool,
-        server_count: usize,
-    },
-    Server(Peer<M>),
-}
-
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
-enum MyRegisterActorState<M>
-where
-    M: Clone + Debug + PartialEq + Hash + Map,
-{
-    PutClient {
-        awaiting: Option<RequestId>,
-        op_count: usize,
-    },
-    DeleteClient {
-        awaiting: Option<RequestId>,
-        op_count: usize,
-    },
-    Server(<Peer<M> as Actor>::State),
-}
-
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
-enum MyRegisterMsg {
-    /// A message specific to the register system's internal protocol.
-    Internal(PeerMsg),
-
-    /// Indicates that a value should be written.
-    Put(RequestId, Key, Value),
-    /// Indicates that a value should be retrieved.
-    Get(RequestId, Key),
-    /// Indicates that a value should be deleted.
-    Delete(RequestId, Key),
-
-    /// Indicates a successful `Put`. Analogous to an HTTP 2XX.
-    PutOk(RequestId),
-    /// Indicates a successful `Get`. Analogous to an HTTP 2XX.
-    GetOk(RequestId, Value),
-    /// Indicates a successful `Delete`. Analogous to an HTTP 2XX.
-    DeleteOk(RequestId),
-}
-
-impl<M> Actor for MyRegisterActor<M>
+impl<M> Actor for Peer<M>
 where
     M: Clone + Debug + PartialEq + Hash + Map,
 {
     type Msg = MyRegisterMsg;
 
-    type State = MyRegisterActorState<M>;
+    type State = PeerState<M>;
 
     fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
-        match self {
-            MyRegisterActor::PutClient {
-                put_count,
-                // don't issue reads from this so don't worry about this
-                follow_up_gets: _,
-                server_count,
-            } => {
-                let server_count = *server_count;
-
-                let index: usize = id.into();
-                if index < server_count {
-                    panic!("MyRegisterActor clients must be added to the model after servers.");
-                }
+        let mut state = Self::State::new(id);
+        // the hybrid logical clock reads the skews, so pick it first
+        state.set_clock(match self.clock {
+            Clock::Lamport => ClockKind::Lamport,
+            Clock::Hlc => ClockKind::Hlc,
+        });
+        if self.map_policy == MapPolicy::RemoveWins {
+            state.set_policy(Policy::RemoveWins);
+        }
+        state.set_clock_skews(&self.clock_skews);
+        if self.causal_tokens {
+            state.track_causality();
+        }
+        if self.causal_delivery {
+            state.track_delivery(id);
+        }
+        if self.anti_entropy {
+            state.track_removed();
+            state.track_gossip();
+        }
+        if self.retransmit {
+            state.track_removed();
+            state.track_outbound();
+        }
+        if self.retries {
+            state.track_requests();
+        }
+        if self.conflict_notifications {
+            state.track_subscriptions();
+        }
+        if self.sync_mode == SyncMode::Pull {
+            state.track_pulls(id);
+        }
+        if self.sync_mode == SyncMode::State || self.sync_mode == SyncMode::Delta {
+            state.track_removed();
+        }
+        if self.sync_mode == SyncMode::Delta {
+            state.track_deltas();
+        }
+        if self.truncate {
+            state.track_truncation();
+        }
+        if self.epochs {
+            state.track_epochs();
+        }
+        if self.gc {
+            state.track_stability(self.peers.len() + 1);
+        }
+        if self.set {
+            state.track_set(id, self.set_impl);
+        }
+        if self.counter {
+            state.track_counter(id);
+        }
+        if self.mv_register {
+            match self.register_impl {
+                RegisterImpl::Mv => state.track_mv_register(id),
+                RegisterImpl::Lww => state.track_lww_register(id),
+            }
+        }
+        if self.flag {
+            state.track_flag(id, self.flag_bias);
+        }
+        if self.list {
+            state.track_list(id);
+        }
+        if self.nested {
+            state.track_nested(id);
+        }
+        if self.partitions {
+            state.track_isolation();
+            timer::set(&mut state.timers, o, Timer::Partition);
+        }
+        if let Some(membership) = &self.membership {
+            // joiners catch up by merging in the maps of the servers they join
+            state.track_removed();
+            if membership.role != Role::Member {
+                timer::set(&mut state.timers, o, Timer::Membership);
+            }
+            state.track_membership(membership.clone());
+        }
+        if self.crash {
+            // the checker fires the timer at any point, so the crash can happen anywhere, though
+            // after the server is cut off and after it joins or leaves
+            state.track_durability(self.snapshot_every);
+            timer::set(&mut state.timers, o, Timer::Crash);
+        }
+        state
+    }
 
-                if *put_count > 0 {
-                    let unique_request_id = index; // next will be 2 * index
-                    let value = (b'A' + (index % server_count) as u8) as char;
-                    o.send(
-                        Id::from(index % server_count),
-                        MyRegisterMsg::Put(unique_request_id, KEY, value),
-                    );
-                    MyRegisterActorState::PutClient {
-                        awaiting: Some(unique_request_id),
-                        op_count: 1,
+    fn on_timeout(&self, _id: Id, state: &mut Cow<Self::State>, o: &mut Out<Self>) {
+        let timer = match state.to_mut().timers.fire() {
+            Some(timer) => timer,
+            None => return,
+        };
+        // the actor's timer stays set while any of the server's are
+        if let Some(next) = state.timers.next() {
+            o.set_timer(next.interval())
+        }
+        match timer {
+            Timer::Membership => {
+                let membership = match &state.membership {
+                    Some(membership) => membership,
+                    None => return,
+                };
+                match (membership.role, membership.status) {
+                    (Role::Joiner, Status::Joining) => {
+                        state.to_mut().join();
+                        o.broadcast(&self.peers, &MyRegisterMsg::Internal(PeerMsg::Join))
                     }
-                } else {
-                    MyRegisterActorState::PutClient {
-                        awaiting: None,
-                        op_count: 0,
+                    (Role::Leaver, Status::Member) => {
+                        let members = state.to_mut().leave();
+                        o.broadcast(&members, &MyRegisterMsg::Internal(PeerMsg::Leave))
                     }
+                    _ => {}
                 }
             }
-            MyRegisterActor::DeleteClient {
-                delete_count,
-                follow_up_gets: _,
-                server_count,
-            } => {
-                let server_count = *server_count;
-
-                let index: usize = id.into();
-                if index < server_count {
-                    panic!("MyRegisterActor clients must be added to the model after servers.");
-                }
-
-                if *delete_count > 0 {
-                    let unique_request_id = index; // next will be 2 * index
-                    o.send(
-                        Id::from(index % server_count),
-                        MyRegisterMsg::Delete(unique_request_id, KEY),
-                    );
-                    MyRegisterActorState::DeleteClient {
-                        awaiting: Some(unique_request_id),
-                        op_count: 1,
-                    }
+            Timer::Partition => {
+                // the server's first timeout cuts it off and the second heals it
+                if state.isolation == Some(Isolation::Pending) {
+                    state.to_mut().isolation = Some(Isolation::Isolated);
+                    timer::set(&mut state.to_mut().timers, o, Timer::Partition);
                 } else {
-                    MyRegisterActorState::DeleteClient {
-                        awaiting: None,
-                        op_count: 0,
+                    state.to_mut().isolation = Some(Isolation::Healed);
+                }
+            }
+            Timer::Crash => {
+                // announce the changes the recovered state has, so that peers send back those lost
+                state.to_mut().crash();
+                if self.sync_mode == SyncMode::HaveNeed {
+                    o.broadcast(&self.peers, &MyRegisterMsg::Internal(state.have()));
+                }
+                // or pull them back, and resend and gossip what the recovered state holds
+                if self.sync_mode == SyncMode::Pull {
+                    timer::set(&mut state.to_mut().timers, o, Timer::Pull);
+                }
+                if state.outbound.is_some() {
+                    timer::set(&mut state.to_mut().timers, o, Timer::Retransmit);
+                }
+                if let Some(gossip) = &mut state.to_mut().gossip {
+                    gossip.disturb();
+                }
+                if state.gossip.is_some() {
+                    timer::set(&mut state.to_mut().timers, o, Timer::Gossip);
+                }
+            }
+            Timer::Delta => {
+                // send peers the deltas buffered since applying ops locally
+                for (peer, delta) in state.to_mut().flush_deltas() {
+                    o.send(peer, MyRegisterMsg::Internal(delta))
+                }
+            }
+            Timer::Retransmit => {
+                // resend the sets and deletes peers are yet to acknowledge, until they all have,
+                // unless this server has left
+                let unacked = match &state.outbound {
+                    Some(outbound) if !state.has_left() => outbound.unacked(),
+                    _ => return,
+                };
+                if !unacked.is_empty() {
+                    for (peer, op) in unacked {
+                        o.send(Id::from(peer), MyRegisterMsg::Internal(op))
                     }
+                    timer::set(&mut state.to_mut().timers, o, Timer::Retransmit)
                 }
             }
-            MyRegisterActor::Server(server_actor) => {
-                let mut server_out = Out::new();
-                let state =
-                    MyRegisterActorState::Server(server_actor.on_start(id, &mut server_out));
-                o.append(&mut server_out);
-                state
+            Timer::Gossip => {
+                // push a digest to the next member that may have ops this server is missing, and
+                // keep gossiping until every member has had nothing new
+                if state.has_left() {
+                    return;
+                }
+                let members = self
+                    .peers
+                    .iter()
+                    .filter(|peer| {
+                        state
+                            .membership
+                            .as_ref()
+                            .is_none_or(|membership| membership.includes(**peer))
+                    })
+                    .copied()
+                    .collect::<Vec<_>>();
+                if let Some(peer) = state
+                    .to_mut()
+                    .gossip
+                    .as_mut()
+                    .and_then(|gossip| gossip.next_peer(&members))
+                {
+                    o.send(peer, MyRegisterMsg::Internal(state.digest()));
+                    timer::set(&mut state.to_mut().timers, o, Timer::Gossip)
+                }
+            }
+            Timer::Pull => {
+                // pull from every peer after applying ops locally, which sends the server's
+                // version vector so that those missing the ops pull them back
+                for peer in &self.peers {
+                    if let Some(pulls) = &mut state.to_mut().pulls {
+                        if let Some(pull) = pulls.pull((*peer).into()) {
+                            o.send(*peer, MyRegisterMsg::Internal(pull))
+                        }
+                    }
+                }
             }
         }
     }
@@ -262,122 +683,2770 @@ where
         msg: Self::Msg,
         o: &mut Out<Self>,
     ) {
-        use MyRegisterActor as A;
-        use MyRegisterActorState as S;
+        // a server that left no longer syncs with the members
+        if state.has_left() && matches!(msg, MyRegisterMsg::Internal(_)) {
+            return;
+        }
 
-        match (self, &**state) {
-            (
-                A::PutClient {
-                    put_count,
-                    follow_up_gets,
-                    server_count,
-                },
-                S::PutClient {
-                    awaiting: Some(awaiting),
-                    op_count,
-                },
-            ) => {
-                let server_count = *server_count;
-                match msg {
-                    MyRegisterMsg::PutOk(request_id) if &request_id == awaiting => {
-                        let index: usize = id.into();
-                        let unique_request_id = (op_count + 1) * index;
-                        if *op_count < *put_count {
-                            let value = (b'Z' - (index % server_count) as u8) as char;
-                            o.send(
-                                Id::from(index % server_count),
-                                MyRegisterMsg::Put(unique_request_id, KEY, value),
-                            );
-                            *state = Cow::Owned(MyRegisterActorState::PutClient {
-                                awaiting: Some(unique_request_id),
-                                op_count: op_count + 1,
-                            });
-                        } else if *follow_up_gets {
-                            o.send(
-                                Id::from(index % server_count),
-                                MyRegisterMsg::Get(unique_request_id, KEY),
-                            );
-                            *state = Cow::Owned(MyRegisterActorState::PutClient {
-                                awaiting: Some(unique_request_id),
-                                op_count: op_count + 1,
-                            });
-                        } else {
-                            *state = Cow::Owned(MyRegisterActorState::PutClient {
-                                awaiting: None,
-                                op_count: op_count + 1,
-                            });
+        // a retried request gets the response it got the first time, without being applied again
+        if let (Some(request_id), Some(requests), true) =
+            (msg.mutation_request_id(), &state.requests, self.dedup)
+        {
+            if let Some(response) = requests.response(src.into(), request_id) {
+                o.send(src, response.clone());
+                return;
+            }
+        }
+
+        // mutations reach the disk before the step ends, along with their acknowledgement,
+        // unless the disk is lost in the crash anyway
+        let flush = msg.mutation_request_id().is_some()
+            && state.durability.is_some()
+            && self.restart == Restart::Snapshot
+            && self.snapshot_every.is_none();
+
+        match msg {
+            MyRegisterMsg::Put(request_id, key, value) => {
+                // prepare the op and effect it locally, the only time it is effected here
+                let op = state.prepare_set(key, value);
+                let token = op.timestamp().expect("sets are timestamped");
+                state.to_mut().apply(op.clone().into());
+
+                // respond to the query (not totally necessary for this)
+                let response = MyRegisterMsg::PutOk(request_id, token);
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, Some(key), op.into(), o)
+            }
+            MyRegisterMsg::PutAfter(request_id, key, value, dependency)
+                if self.placement.is_sharded() =>
+            {
+                // the dependency may be on another shard, so hold the write until every server
+                // holding the dependency's key has applied it, asking this server too if it is
+                // one of them
+                let replicas = self.placement.replicas(dependency.key);
+                if let Some(causal) = &mut state.to_mut().causal {
+                    causal.pending.push(PendingWrite {
+                        client: src.into(),
+                        request_id,
+                        key,
+                        value,
+                        dependency,
+                        unconfirmed: replicas.iter().copied().collect(),
+                    });
+                }
+                let check = MyRegisterMsg::Internal(PeerMsg::DepCheck {
+                    dependency: dependency.timestamp,
+                });
+                for replica in replicas {
+                    o.send(Id::from(replica), check.clone())
+                }
+            }
+            MyRegisterMsg::PutAfter(request_id, key, value, dependency) => {
+                // the op waits here too if this server hasn't applied the dependency yet
+                let op = state.prepare_set(key, value);
+                let token = op.timestamp().expect("sets are timestamped");
+                let op = PeerMsg::After {
+                    dependency: dependency.timestamp,
+                    op: Box::new(op.into()),
+                };
+                state.to_mut().apply(op.clone());
+
+                let response = MyRegisterMsg::PutOk(request_id, token);
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, Some(key), op, o)
+            }
+            MyRegisterMsg::PutResolved(request_id, key, value, siblings) => {
+                // overwrite only the siblings the client saw, so that writes it didn't see are kept
+                let mut op = state.prepare_set(key, value);
+                if let MapOp::Set { context, .. } = &mut op {
+                    *context = siblings.into();
+                }
+                let token = op.timestamp().expect("sets are timestamped");
+                state.to_mut().apply(op.clone().into());
+
+                let response = MyRegisterMsg::PutOk(request_id, token);
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, Some(key), op.into(), o)
+            }
+            MyRegisterMsg::GetAll(request_id, key) => o.send(
+                src,
+                MyRegisterMsg::GetAllOk(request_id, state.siblings(key)),
+            ),
+            MyRegisterMsg::Subscribe(key) => {
+                if state.subscribers.is_some() {
+                    state.to_mut().subscribe(key, src.into())
+                }
+            }
+            MyRegisterMsg::Get(request_id, key) => {
+                if let Some(value) = state.get(&key) {
+                    // respond to the query (not totally necessary for this)
+                    o.send(src, MyRegisterMsg::GetOk(request_id, *value))
+                }
+            }
+            MyRegisterMsg::Delete(request_id, key) => {
+                // prepare the op and effect it locally, the only time it is effected here
+                let op = state.prepare_delete(&key);
+                if let Some(op) = &op {
+                    state.to_mut().apply(op.clone().into());
+                }
+
+                // respond to the query (not totally necessary for this)
+                let response = MyRegisterMsg::DeleteOk(request_id);
+                self.respond(state, src, request_id, response, op.is_some(), o);
+
+                if let Some(op) = op {
+                    self.publish(state, Some(key), op.into(), o)
+                }
+            }
+            MyRegisterMsg::Truncate(request_id) => {
+                // prepare the barrier and effect it locally, the only time it is effected here
+                let op = state.prepare_truncate();
+                if let Some(op) = &op {
+                    state.to_mut().apply(op.clone());
+                }
+
+                let response = MyRegisterMsg::DeleteOk(request_id);
+                self.respond(state, src, request_id, response, op.is_some(), o);
+
+                if let Some(op) = op {
+                    self.publish(state, None, op, o);
+                    self.close_epoch(state, o)
+                }
+            }
+            MyRegisterMsg::Add(request_id, element) => {
+                // prepare the add and effect it locally, the only time it is effected here
+                let op = state.prepare_add(element);
+                let tag = op.tag().expect("adds are tagged");
+                state.to_mut().apply(op.clone().into());
+
+                // the tag names the add as a version token names a set
+                let response = MyRegisterMsg::PutOk(request_id, tag);
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, None, op.into(), o)
+            }
+            MyRegisterMsg::Remove(request_id, element) => {
+                // prepare the remove and effect it locally, the only time it is effected here
+                let op = state.prepare_remove(&element);
+                if let Some(op) = &op {
+                    state.to_mut().apply(op.clone().into());
+                }
+
+                let response = MyRegisterMsg::DeleteOk(request_id);
+                self.respond(state, src, request_id, response, op.is_some(), o);
+
+                if let Some(op) = op {
+                    self.publish(state, None, op.into(), o)
+                }
+            }
+            MyRegisterMsg::Contains(request_id, element) => o.send(
+                src,
+                MyRegisterMsg::ContainsOk(request_id, state.set_contains(element)),
+            ),
+            MyRegisterMsg::Increment(request_id) => {
+                // merge the counter's new state locally, then send it on for peers to merge
+                let op = state.prepare_increment();
+                state.to_mut().apply(op.clone());
+
+                let response = MyRegisterMsg::CountOk(request_id, state.counter_value());
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, None, op, o)
+            }
+            MyRegisterMsg::Decrement(request_id) => {
+                let op = state.prepare_decrement();
+                state.to_mut().apply(op.clone());
+
+                let response = MyRegisterMsg::CountOk(request_id, state.counter_value());
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, None, op, o)
+            }
+            MyRegisterMsg::Read(request_id) => o.send(
+                src,
+                MyRegisterMsg::CountOk(request_id, state.counter_value()),
+            ),
+            MyRegisterMsg::WriteRegister(request_id, value) => {
+                // prepare the write and effect it locally, the only time it is effected here
+                let (timestamp, write) = state.prepare_write(value);
+                state.to_mut().apply(write.clone());
+
+                let response = MyRegisterMsg::PutOk(request_id, timestamp);
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, None, write, o)
+            }
+            MyRegisterMsg::ReadRegister(request_id) => o.send(
+                src,
+                MyRegisterMsg::GetAllOk(request_id, state.register_values()),
+            ),
+            MyRegisterMsg::Enable(request_id) => {
+                // prepare the write and effect it locally, the only time it is effected here
+                let write = state.prepare_flag(true);
+                let timestamp = write.timestamp;
+                state.to_mut().apply(write.clone().into());
+
+                let response = MyRegisterMsg::PutOk(request_id, timestamp);
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, None, write.into(), o)
+            }
+            MyRegisterMsg::Disable(request_id) => {
+                let write = state.prepare_flag(false);
+                let timestamp = write.timestamp;
+                state.to_mut().apply(write.clone().into());
+
+                let response = MyRegisterMsg::PutOk(request_id, timestamp);
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, None, write.into(), o)
+            }
+            MyRegisterMsg::ReadFlag(request_id) => {
+                o.send(src, MyRegisterMsg::FlagOk(request_id, state.flag_value()))
+            }
+            MyRegisterMsg::Insert(request_id, index, value) => {
+                // prepare the insert and effect it locally, the only time it is effected here
+                let op = state.prepare_insert(index, value);
+                let id = op.id();
+                state.to_mut().apply(op.clone().into());
+
+                let response = MyRegisterMsg::PutOk(request_id, id);
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, None, op.into(), o)
+            }
+            MyRegisterMsg::DeleteAt(request_id, index) => {
+                // prepare the delete and effect it locally, the only time it is effected here
+                let op = state.prepare_delete_at(index);
+                if let Some(op) = &op {
+                    state.to_mut().apply(op.clone().into());
+                }
+
+                let response = MyRegisterMsg::DeleteOk(request_id);
+                self.respond(state, src, request_id, response, op.is_some(), o);
+
+                if let Some(op) = op {
+                    self.publish(state, None, op.into(), o)
+                }
+            }
+            MyRegisterMsg::ReadList(request_id) => {
+                o.send(src, MyRegisterMsg::ListOk(request_id, state.list_values()))
+            }
+            MyRegisterMsg::SetPath(request_id, path, value) => {
+                // prepare the set and effect it locally, the only time it is effected here
+                let op = state.prepare_nested_set(path, value);
+                let timestamp = op.timestamp().expect("sets have a timestamp");
+                state.to_mut().apply(op.clone().into());
+
+                let response = MyRegisterMsg::PutOk(request_id, timestamp);
+                self.respond(state, src, request_id, response, true, o);
+
+                self.publish(state, None, op.into(), o)
+            }
+            MyRegisterMsg::DeletePath(request_id, path) => {
+                // prepare the delete and effect it locally, the only time it is effected here
+                let op = state.prepare_nested_delete(&path);
+                if let Some(op) = &op {
+                    state.to_mut().apply(op.clone().into());
+                }
+
+                let response = MyRegisterMsg::DeleteOk(request_id);
+                self.respond(state, src, request_id, response, op.is_some(), o);
+
+                if let Some(op) = op {
+                    self.publish(state, None, op.into(), o)
+                }
+            }
+            MyRegisterMsg::GetPath(request_id, path) => o.send(
+                src,
+                MyRegisterMsg::GetAllOk(request_id, state.nested_values(&path)),
+            ),
+            MyRegisterMsg::Internal(PeerMsg::Have { heads, bloom }) => {
+                for reply in state.on_have(&heads, &bloom) {
+                    o.send(src, MyRegisterMsg::Internal(reply))
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::Need { hashes }) => {
+                if let Some(reply) = state.on_need(&hashes) {
+                    o.send(src, MyRegisterMsg::Internal(reply))
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::DepCheck { dependency }) => {
+                let applied = state
+                    .causal
+                    .as_ref()
+                    .map_or(false, |causal| causal.applied.contains(&dependency));
+                if applied {
+                    o.send(src, MyRegisterMsg::Internal(PeerMsg::DepOk { dependency }))
+                } else if let Some(causal) = &mut state.to_mut().causal {
+                    causal.checks.insert((src.into(), dependency));
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::DepOk { dependency }) => {
+                let confirmed = match &mut state.to_mut().causal {
+                    Some(causal) => causal.confirm(src.into(), dependency),
+                    None => Vec::new(),
+                };
+                for write in confirmed {
+                    self.apply_confirmed(state, write, o)
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::Pull { vector }) => {
+                let pulls = match &state.pulls {
+                    Some(pulls) => pulls,
+                    None => return,
+                };
+                o.send(
+                    src,
+                    MyRegisterMsg::Internal(PeerMsg::Ops {
+                        ops: pulls.missing(&vector),
+                    }),
+                );
+                // the puller has ops that this server is missing, so pull them back
+                if pulls.is_behind(&vector) {
+                    if let Some(pulls) = &mut state.to_mut().pulls {
+                        if let Some(pull) = pulls.pull(src.into()) {
+                            o.send(src, MyRegisterMsg::Internal(pull))
+                        }
+                    }
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::Ops { ops }) => {
+                let before = state.subscribed_siblings();
+                let state_mut = state.to_mut();
+                state_mut.on_pulled(ops, &self.validation);
+                if let Some(pulls) = &mut state_mut.pulls {
+                    if let Some(pull) = pulls.pulled(src.into()) {
+                        o.send(src, MyRegisterMsg::Internal(pull))
+                    }
+                }
+                self.notify_conflicts(state, before, o);
+                self.answer_checks(state, o)
+            }
+            MyRegisterMsg::Internal(
+                ack @ (PeerMsg::PutSyncAck { .. } | PeerMsg::DeleteSyncAck { .. }),
+            ) => {
+                if state.outbound.is_some() {
+                    state.to_mut().on_ack(src.into(), &ack)
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::Join) => {
+                if let Some(state_sync) = state.to_mut().on_join(src.into()) {
+                    o.send(src, MyRegisterMsg::Internal(state_sync))
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::Leave) => state.to_mut().on_leave(src.into()),
+            MyRegisterMsg::Internal(PeerMsg::Digest { held, removed }) => {
+                if state.gossip.is_none() {
+                    return;
+                }
+                let (repair, behind) = state.to_mut().on_digest(src.into(), &held, &removed);
+                o.send(src, MyRegisterMsg::Internal(repair));
+                // the pusher has seen values this server hasn't, so gossip with it to get them
+                if behind {
+                    timer::set(&mut state.to_mut().timers, o, Timer::Gossip)
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::Repair { ops }) => {
+                let before = state.subscribed_siblings();
+                if state.to_mut().on_repair(src.into(), ops, &self.validation) {
+                    // the repaired values may be new to other peers too
+                    timer::set(&mut state.to_mut().timers, o, Timer::Gossip)
+                }
+                self.notify_conflicts(state, before, o);
+                self.answer_checks(state, o)
+            }
+            MyRegisterMsg::Internal(PeerMsg::Changes { changes }) => {
+                // changes that are all known already leave the state as it is, so don't copy it
+                if changes.iter().all(|change| state.has_change(change.hash())) {
+                    return;
+                }
+                let before = state.subscribed_siblings();
+                if let Some(need) = state.to_mut().on_changes(changes, &self.validation) {
+                    o.send(src, MyRegisterMsg::Internal(need))
+                }
+                self.notify_conflicts(state, before, o)
+            }
+            MyRegisterMsg::Internal(PeerMsg::EpochClose { epoch }) => {
+                let out = state
+                    .to_mut()
+                    .on_epoch_close(src.into(), epoch, self.peers.len());
+                for msg in out {
+                    o.broadcast(&self.peers, &MyRegisterMsg::Internal(msg))
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::EpochDone { .. }) => {
+                let out = state.to_mut().on_epoch_done(src.into(), self.peers.len());
+                for msg in out {
+                    o.broadcast(&self.peers, &MyRegisterMsg::Internal(msg))
+                }
+            }
+            MyRegisterMsg::Internal(PeerMsg::Watermark { clock }) => {
+                if state.stability.is_some() {
+                    state.to_mut().on_watermark(src.into(), &clock)
+                }
+            }
+            MyRegisterMsg::Internal(msg) => {
+                debug_assert!(
+                    !msg.prepared_by(id),
+                    "{:?} would be effected a second time at its origin",
+                    msg
+                );
+                let truncate = matches!(msg, PeerMsg::Truncate { .. });
+                let replays = if self.byzantine {
+                    byzantine::replays(&msg)
+                } else {
+                    Vec::new()
+                };
+                // retransmitted copies are acknowledged too, in case the first ack got lost
+                let ack = state.outbound.as_ref().and_then(|_| retransmit::ack(&msg));
+                if state.epochs.is_some() {
+                    state.to_mut().record_epoch(&msg, Some(src.into()));
+                }
+                let watermark = state.watermark();
+                let before = state.subscribed_siblings();
+                state
+                    .to_mut()
+                    .receive(msg, Some(src.into()), &self.validation);
+                self.notify_conflicts(state, before, o);
+                self.answer_checks(state, o);
+                // tell peers about the ops delivered, so they can tell when sets are stable
+                let delivered = state.watermark();
+                if delivered != watermark {
+                    if let Some(delivered) = delivered {
+                        o.broadcast(&self.peers, &MyRegisterMsg::Internal(delivered))
+                    }
+                }
+                if let Some(ack) = ack {
+                    o.send(src, MyRegisterMsg::Internal(ack))
+                }
+                if truncate {
+                    self.close_epoch(state, o)
+                }
+                // passed on to the peers other than the op's sender, which would otherwise get
+                // back its own op
+                for replay in replays {
+                    for peer in self.peers.iter().filter(|peer| **peer != src) {
+                        o.send(*peer, MyRegisterMsg::Internal(replay.clone()))
+                    }
+                }
+            }
+            MyRegisterMsg::PutOk(_id, _token) => {}
+            MyRegisterMsg::GetOk(_id, _value) => {}
+            MyRegisterMsg::DeleteOk(_id) => {}
+            MyRegisterMsg::GetAllOk(_id, _siblings) => {}
+            MyRegisterMsg::Siblings(_key, _siblings) => {}
+            MyRegisterMsg::Token(_token) => {}
+            MyRegisterMsg::ContainsOk(_id, _contains) => {}
+            MyRegisterMsg::CountOk(_id, _value) => {}
+            MyRegisterMsg::ListOk(_id, _values) => {}
+            MyRegisterMsg::FlagOk(_id, _enabled) => {}
+        }
+
+        if flush || state.durability.as_ref().map_or(false, Durability::due) {
+            state.to_mut().flush()
+        }
+    }
+}
+
+/// The value a put client's `op`th put writes.
+fn put_value(index: usize, server_count: usize, op: usize) -> Value {
+    if op == 0 {
+        (b'A' + (index % server_count) as u8) as char
+    } else {
+        (b'Z' - (index % server_count) as u8) as char
+    }
+}
+
+/// The value a put client writes once it is passed a version token to depend on.
+fn dependent_value(index: usize, server_count: usize) -> Value {
+    (b'N' + (index % server_count) as u8) as char
+}
+
+/// A mixed client's `op`th request, with the key it operates on. Gets ask for every sibling,
+/// which servers answer even when they don't hold the key, so the client never waits on a
+/// response that won't come.
+fn mixed_request(
+    script: &[MixedOp],
+    keys: &[Key],
+    index: usize,
+    server_count: usize,
+    op: usize,
+) -> (Key, MyRegisterMsg) {
+    let request_id = (op + 1) * index;
+    let key = client_key(keys, index, op);
+    let msg = match script[op] {
+        MixedOp::Put => MyRegisterMsg::Put(request_id, key, put_value(index, server_count, op)),
+        MixedOp::Delete => MyRegisterMsg::Delete(request_id, key),
+        MixedOp::Get => MyRegisterMsg::GetAll(request_id, key),
+    };
+    (key, msg)
+}
+
+/// The key a client's `op`th request operates on.
+fn client_key(keys: &[Key], index: usize, op: usize) -> Key {
+    keys[(index + op) % keys.len()]
+}
+
+/// A delete client's request, deleting the key or truncating the whole map. Truncates are still
+/// sent to a server holding the key, so they spread over the servers as deletes do.
+fn delete_request(truncate: bool, request_id: RequestId, key: Key) -> MyRegisterMsg {
+    if truncate {
+        MyRegisterMsg::Truncate(request_id)
+    } else {
+        MyRegisterMsg::Delete(request_id, key)
+    }
+}
+
+/// A counter client's request making one of `COUNTER_CHANGES`.
+fn counter_request(request_id: RequestId, change: i64) -> MyRegisterMsg {
+    if change > 0 {
+        MyRegisterMsg::Increment(request_id)
+    } else {
+        MyRegisterMsg::Decrement(request_id)
+    }
+}
+
+/// How many conflicts a subscribed resolve client resolves, as each resolution can itself
+/// conflict with writes the client hasn't seen.
+const MAX_RESOLUTIONS: usize = 2;
+
+/// Resolve a key's siblings deterministically, to the greatest value, by writing it over them.
+/// Returns the resolve client's state while it waits for the write to be acknowledged.
+fn resolve<M: Clone + Debug + PartialEq + Hash + Map>(
+    id: Id,
+    placement: &Placement,
+    key: Key,
+    siblings: Vec<(Timestamp, Value)>,
+    resolved: &[Timestamp],
+    resolutions: usize,
+    abstracted: bool,
+    o: &mut Out<MyRegisterActor<M>>,
+) -> MyRegisterActorState<M> {
+    let index: usize = id.into();
+    let unique_request_id = (resolutions + 2) * index;
+    let value = siblings.iter().map(|(_, value)| *value).max().unwrap();
+    let siblings = siblings
+        .into_iter()
+        .map(|(timestamp, _)| timestamp)
+        .collect::<Vec<_>>();
+    o.send(
+        placement.route(key, index),
+        MyRegisterMsg::PutResolved(unique_request_id, key, value, siblings.clone()),
+    );
+    MyRegisterActorState::ResolveClient {
+        awaiting: Some(unique_request_id),
+        resolved: resolved.iter().copied().chain(siblings).collect(),
+        resolutions: resolutions + 1,
+        abstracted,
+    }
+}
+
+/// How a client paces its requests.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum ClientLoop {
+    /// Wait for the response to each request before sending the next.
+    Closed,
+    /// Send each request when a timer fires, regardless of responses.
+    Open,
+}
+
+/// Which map implementation is checked.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum MapImpl {
+    /// Sets and deletes remove exactly the values their server had seen, so concurrent sets are
+    /// kept as siblings and servers converge.
+    Fixed,
+    /// The first version of the map from the tweet, which doesn't converge.
+    Broken,
+    /// Last-writer-wins by wall clock readings.
+    Lww,
+}
+
+/// How the map resolves a set and a delete that are concurrent.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum MapPolicy {
+    /// The set's value is kept, as the delete only removes the values it had seen.
+    AddWins,
+    /// The delete removes the set's value too.
+    RemoveWins,
+}
+
+/// Which set the servers hold for set clients.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum SetImpl {
+    /// An add-wins observed-remove set.
+    Or,
+    /// A grow-only set, which ignores removes.
+    G,
+    /// A two-phase set, where an element can't be added again once removed.
+    TwoPhase,
+}
+
+/// Which register the servers hold for register clients.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum RegisterImpl {
+    /// Concurrent writes are kept as siblings, told apart by version vectors.
+    Mv,
+    /// The write with the greatest timestamp wins, concurrent or not.
+    Lww,
+}
+
+/// Which way the servers' flags resolve a concurrent enable and disable.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum FlagBias {
+    /// The flag is enabled.
+    EnableWins,
+    /// The flag is disabled.
+    DisableWins,
+}
+
+/// Which clock the maps draw the counters of their timestamps from.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Clock {
+    /// Each map's own clock: a Lamport clock, one past the highest counter seen, or the wall
+    /// clock for the last-writer-wins map.
+    Lamport,
+    /// A hybrid logical clock, later than every timestamp seen while staying close to the wall
+    /// clock.
+    Hlc,
+}
+
+/// How the network delivers messages between actors.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum NetworkKind {
+    /// Messages from one actor to another arrive in the order they were sent, once each.
+    Ordered,
+    /// Messages arrive in any order, once each.
+    Unordered,
+    /// Messages arrive in any order, any number of times.
+    Duplicating,
+    /// Messages arrive in any order, at most once each, as the network may drop them.
+    Lossy,
+}
+
+impl NetworkKind {
+    fn network(self) -> Network<MyRegisterMsg> {
+        match self {
+            NetworkKind::Ordered => Network::new_ordered(vec![]),
+            NetworkKind::Unordered | NetworkKind::Lossy => {
+                Network::new_unordered_nonduplicating(vec![])
+            }
+            NetworkKind::Duplicating => Network::new_unordered_duplicating(vec![]),
+        }
+    }
+
+    fn lossy(self) -> LossyNetwork {
+        if self == NetworkKind::Lossy {
+            LossyNetwork::Yes
+        } else {
+            LossyNetwork::No
+        }
+    }
+
+    /// Whether delivered messages stay in the network to be delivered again.
+    fn duplicates(self) -> bool {
+        self == NetworkKind::Duplicating
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum MyRegisterActor<M> {
+    PutClient {
+        put_count: usize,
+        /// Whether to send a get request after each mutation
+        follow_up_gets: bool,
+        server_count: usize,
+        client_loop: ClientLoop,
+        /// Keys to operate on, cycled through starting at the client's index.
+        keys: Vec<Key>,
+        /// Client to pass the version token of the first acknowledged put to.
+        token_to: Option<Id>,
+        /// Whether to resend a request once if its response hasn't arrived when a timer fires.
+        retries: bool,
+        /// Which servers to send requests on each key to.
+        placement: Placement,
+        /// Whether to leave the client's progress out of its states' fingerprints.
+        abstracted: bool,
+    },
+    DeleteClient {
+        delete_count: usize,
+        /// Whether to send a get request after each mutation
+        follow_up_gets: bool,
+        server_count: usize,
+        client_loop: ClientLoop,
+        /// Keys to operate on, cycled through starting at the client's index.
+        keys: Vec<Key>,
+        /// Whether to resend a request once if its response hasn't arrived when a timer fires.
+        retries: bool,
+        /// Which servers to send requests on each key to.
+        placement: Placement,
+        /// Whether to truncate the whole map rather than delete a key.
+        truncate: bool,
+        abstracted: bool,
+    },
+    /// Reads all siblings of a key and, if there is more than one, writes back the greatest
+    /// value over them.
+    ResolveClient {
+        placement: Placement,
+        key: Key,
+        /// Whether to subscribe to the key's conflicts and resolve each one the server notifies
+        /// it of, rather than reading the key once.
+        subscribe: bool,
+        abstracted: bool,
+    },
+    /// Reads all siblings of a key from every server holding it and merges the answers.
+    ReadClient {
+        placement: Placement,
+        key: Key,
+        abstracted: bool,
+    },
+    /// Adds an element to the set, removes it again and then checks whether the set holds it,
+    /// adding it once more before checking if `re_add`.
+    SetClient {
+        server_count: usize,
+        re_add: bool,
+        abstracted: bool,
+    },
+    /// Makes the changes in `COUNTER_CHANGES` to the counter and then reads it.
+    CounterClient {
+        server_count: usize,
+        abstracted: bool,
+    },
+    /// Writes a value to the multi-value register and then reads its siblings.
+    MvRegisterClient {
+        server_count: usize,
+        abstracted: bool,
+    },
+    /// Enables the flag, or disables it if not `enable`, and then reads it.
+    FlagClient {
+        server_count: usize,
+        enable: bool,
+        abstracted: bool,
+    },
+    /// Inserts a value at the start of the list and another after it, then reads the list.
+    ListInsertClient {
+        server_count: usize,
+        abstracted: bool,
+    },
+    /// Deletes the first element of the list, then reads the list.
+    ListDeleteClient {
+        server_count: usize,
+        abstracted: bool,
+    },
+    /// Sets a value inside the nested map under `NESTED_KEY`, deletes the subtree there and then
+    /// reads what is left of it.
+    NestedClient {
+        server_count: usize,
+        abstracted: bool,
+    },
+    /// Gets a key, cycling through `keys`, every so often without waiting on responses, as
+    /// servers don't answer gets of keys they don't hold.
+    GetClient {
+        get_count: usize,
+        server_count: usize,
+        /// Keys to read, cycled through starting at the client's index.
+        keys: Vec<Key>,
+        /// Which servers to send requests on each key to.
+        placement: Placement,
+        abstracted: bool,
+    },
+    /// Makes the requests in `script` in turn, waiting for each response, so that one client
+    /// can delete a key and then put it again.
+    MixedClient {
+        script: Vec<MixedOp>,
+        server_count: usize,
+        /// Keys to operate on, cycled through starting at the client's index.
+        keys: Vec<Key>,
+        /// Which servers to send requests on each key to.
+        placement: Placement,
+        abstracted: bool,
+    },
+    Server(Peer<M>),
+}
+
+#[derive(Clone, Eq, PartialEq)]
+enum MyRegisterActorState<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    PutClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        /// The key, value and timestamp of the client's last acknowledged put, kept only when it
+        /// follows up with a get so that the get can be checked against it.
+        last_write: Option<(Key, Value, Timestamp)>,
+        /// Whether the client's progress is left out of state fingerprints, so that states
+        /// only differing in how far along the clients are count as the same state.
+        abstracted: bool,
+    },
+    DeleteClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        abstracted: bool,
+    },
+    ResolveClient {
+        awaiting: Option<RequestId>,
+        /// The siblings the client's resolutions overwrote, once it has written one.
+        resolved: Vec<Timestamp>,
+        /// How many resolutions the client has written.
+        resolutions: usize,
+        abstracted: bool,
+    },
+    ReadClient {
+        awaiting: Option<RequestId>,
+        /// The siblings each server answered with, in the order the answers arrived.
+        responses: Vec<Vec<(Timestamp, Value)>>,
+        /// The merge of the responses, once they have all arrived.
+        merged: Option<(Timestamp, Value)>,
+        abstracted: bool,
+    },
+    SetClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        abstracted: bool,
+    },
+    CounterClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        /// The increments minus the decrements acknowledged to this client.
+        net: i64,
+        abstracted: bool,
+    },
+    MvRegisterClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        abstracted: bool,
+    },
+    FlagClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        abstracted: bool,
+    },
+    ListInsertClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        abstracted: bool,
+    },
+    ListDeleteClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        abstracted: bool,
+    },
+    NestedClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        abstracted: bool,
+    },
+    GetClient {
+        /// How many gets the client has sent.
+        op_count: usize,
+        /// The values the client has read, in the order the responses arrived.
+        reads: Vec<Value>,
+        abstracted: bool,
+    },
+    MixedClient {
+        awaiting: Option<RequestId>,
+        op_count: usize,
+        abstracted: bool,
+    },
+    Server(<Peer<M> as Actor>::State),
+}
+
+/// Whether servers are shown as a summary of their resolved map and buffers in Debug output,
+/// which the web explorer shows states with, rather than as their full state.
+static SUMMARIZE_SERVERS: AtomicBool = AtomicBool::new(false);
+
+impl<M> MyRegisterActor<M> {
+    fn retries(&self) -> bool {
+        match self {
+            MyRegisterActor::PutClient { retries, .. }
+            | MyRegisterActor::DeleteClient { retries, .. } => *retries,
+            MyRegisterActor::ResolveClient { .. }
+            | MyRegisterActor::ReadClient { .. }
+            | MyRegisterActor::SetClient { .. }
+            | MyRegisterActor::CounterClient { .. }
+            | MyRegisterActor::MvRegisterClient { .. }
+            | MyRegisterActor::FlagClient { .. }
+            | MyRegisterActor::ListInsertClient { .. }
+            | MyRegisterActor::ListDeleteClient { .. }
+            | MyRegisterActor::NestedClient { .. }
+            | MyRegisterActor::GetClient { .. }
+            | MyRegisterActor::MixedClient { .. }
+            | MyRegisterActor::Server(_) => false,
+        }
+    }
+
+    /// Whether the client's states leave its progress out of their fingerprints.
+    fn abstracts_clients(&self) -> bool {
+        match self {
+            MyRegisterActor::PutClient { abstracted, .. }
+            | MyRegisterActor::DeleteClient { abstracted, .. }
+            | MyRegisterActor::ResolveClient { abstracted, .. }
+            | MyRegisterActor::ReadClient { abstracted, .. }
+            | MyRegisterActor::SetClient { abstracted, .. }
+            | MyRegisterActor::CounterClient { abstracted, .. }
+            | MyRegisterActor::MvRegisterClient { abstracted, .. }
+            | MyRegisterActor::FlagClient { abstracted, .. }
+            | MyRegisterActor::ListInsertClient { abstracted, .. }
+            | MyRegisterActor::ListDeleteClient { abstracted, .. }
+            | MyRegisterActor::NestedClient { abstracted, .. }
+            | MyRegisterActor::GetClient { abstracted, .. }
+            | MyRegisterActor::MixedClient { abstracted, .. } => *abstracted,
+            MyRegisterActor::Server(_) => false,
+        }
+    }
+}
+
+impl<M> MyRegisterActorState<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    /// The request a client is waiting on a response to.
+    fn awaiting(&self) -> Option<RequestId> {
+        match self {
+            MyRegisterActorState::PutClient { awaiting, .. }
+            | MyRegisterActorState::DeleteClient { awaiting, .. }
+            | MyRegisterActorState::ResolveClient { awaiting, .. }
+            | MyRegisterActorState::ReadClient { awaiting, .. }
+            | MyRegisterActorState::SetClient { awaiting, .. }
+            | MyRegisterActorState::CounterClient { awaiting, .. }
+            | MyRegisterActorState::MvRegisterClient { awaiting, .. }
+            | MyRegisterActorState::FlagClient { awaiting, .. }
+            | MyRegisterActorState::ListInsertClient { awaiting, .. }
+            | MyRegisterActorState::ListDeleteClient { awaiting, .. }
+            | MyRegisterActorState::NestedClient { awaiting, .. }
+            | MyRegisterActorState::MixedClient { awaiting, .. } => *awaiting,
+            MyRegisterActorState::GetClient { .. } | MyRegisterActorState::Server(_) => None,
+        }
+    }
+}
+
+impl<M> Debug for MyRegisterActorState<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MyRegisterActorState::PutClient {
+                awaiting,
+                op_count,
+                last_write,
+                abstracted: _,
+            } => f
+                .debug_struct("PutClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .field("last_write", last_write)
+                .finish(),
+            MyRegisterActorState::DeleteClient {
+                awaiting,
+                op_count,
+                abstracted: _,
+            } => f
+                .debug_struct("DeleteClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .finish(),
+            MyRegisterActorState::ResolveClient {
+                awaiting,
+                resolved,
+                resolutions,
+                abstracted: _,
+            } => f
+                .debug_struct("ResolveClient")
+                .field("awaiting", awaiting)
+                .field("resolved", resolved)
+                .field("resolutions", resolutions)
+                .finish(),
+            MyRegisterActorState::ReadClient {
+                awaiting,
+                responses,
+                merged,
+                abstracted: _,
+            } => f
+                .debug_struct("ReadClient")
+                .field("awaiting", awaiting)
+                .field("responses", responses)
+                .field("merged", merged)
+                .finish(),
+            MyRegisterActorState::SetClient {
+                awaiting,
+                op_count,
+                abstracted: _,
+            } => f
+                .debug_struct("SetClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .finish(),
+            MyRegisterActorState::CounterClient {
+                awaiting,
+                op_count,
+                net,
+                abstracted: _,
+            } => f
+                .debug_struct("CounterClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .field("net", net)
+                .finish(),
+            MyRegisterActorState::MvRegisterClient {
+                awaiting,
+                op_count,
+                abstracted: _,
+            } => f
+                .debug_struct("MvRegisterClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .finish(),
+            MyRegisterActorState::FlagClient {
+                awaiting,
+                op_count,
+                abstracted: _,
+            } => f
+                .debug_struct("FlagClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .finish(),
+            MyRegisterActorState::ListInsertClient {
+                awaiting,
+                op_count,
+                abstracted: _,
+            } => f
+                .debug_struct("ListInsertClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .finish(),
+            MyRegisterActorState::ListDeleteClient {
+                awaiting,
+                op_count,
+                abstracted: _,
+            } => f
+                .debug_struct("ListDeleteClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .finish(),
+            MyRegisterActorState::NestedClient {
+                awaiting,
+                op_count,
+                abstracted: _,
+            } => f
+                .debug_struct("NestedClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .finish(),
+            MyRegisterActorState::GetClient {
+                op_count,
+                reads,
+                abstracted: _,
+            } => f
+                .debug_struct("GetClient")
+                .field("op_count", op_count)
+                .field("reads", reads)
+                .finish(),
+            MyRegisterActorState::MixedClient {
+                awaiting,
+                op_count,
+                abstracted: _,
+            } => f
+                .debug_struct("MixedClient")
+                .field("awaiting", awaiting)
+                .field("op_count", op_count)
+                .finish(),
+            MyRegisterActorState::Server(server) => {
+                if SUMMARIZE_SERVERS.load(Ordering::Relaxed) {
+                    render::server_summary(server, f)
+                } else {
+                    f.debug_tuple("Server").field(server).finish()
+                }
+            }
+        }
+    }
+}
+
+impl<M> Hash for MyRegisterActorState<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            MyRegisterActorState::PutClient {
+                awaiting,
+                op_count,
+                last_write,
+                abstracted,
+            } => {
+                if !*abstracted {
+                    awaiting.hash(state);
+                    op_count.hash(state);
+                    last_write.hash(state);
+                }
+            }
+            MyRegisterActorState::DeleteClient {
+                awaiting,
+                op_count,
+                abstracted,
+            }
+            | MyRegisterActorState::SetClient {
+                awaiting,
+                op_count,
+                abstracted,
+            }
+            | MyRegisterActorState::MvRegisterClient {
+                awaiting,
+                op_count,
+                abstracted,
+            }
+            | MyRegisterActorState::FlagClient {
+                awaiting,
+                op_count,
+                abstracted,
+            }
+            | MyRegisterActorState::ListInsertClient {
+                awaiting,
+                op_count,
+                abstracted,
+            }
+            | MyRegisterActorState::ListDeleteClient {
+                awaiting,
+                op_count,
+                abstracted,
+            }
+            | MyRegisterActorState::NestedClient {
+                awaiting,
+                op_count,
+                abstracted,
+            }
+            | MyRegisterActorState::MixedClient {
+                awaiting,
+                op_count,
+                abstracted,
+            } => {
+                if !*abstracted {
+                    awaiting.hash(state);
+                    op_count.hash(state);
+                }
+            }
+            MyRegisterActorState::GetClient {
+                op_count,
+                reads,
+                abstracted,
+            } => {
+                if !*abstracted {
+                    op_count.hash(state);
+                    reads.hash(state);
+                }
+            }
+            MyRegisterActorState::ResolveClient {
+                awaiting,
+                resolved,
+                resolutions,
+                abstracted,
+            } => {
+                if !*abstracted {
+                    awaiting.hash(state);
+                    resolved.hash(state);
+                    resolutions.hash(state);
+                }
+            }
+            MyRegisterActorState::ReadClient {
+                awaiting,
+                responses,
+                merged,
+                abstracted,
+            } => {
+                if !*abstracted {
+                    awaiting.hash(state);
+                    responses.hash(state);
+                    merged.hash(state);
+                }
+            }
+            MyRegisterActorState::CounterClient {
+                awaiting,
+                op_count,
+                net,
+                abstracted,
+            } => {
+                if !*abstracted {
+                    awaiting.hash(state);
+                    op_count.hash(state);
+                    net.hash(state);
+                }
+            }
+            MyRegisterActorState::Server(server) => server.hash(state),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+enum MyRegisterMsg {
+    /// A message specific to the register system's internal protocol.
+    Internal(PeerMsg),
+
+    /// Indicates that a value should be written.
+    Put(RequestId, Key, Value),
+    /// Indicates that a value should be written, but only made visible after the write with the
+    /// given version token.
+    PutAfter(RequestId, Key, Value, Dependency),
+    /// Indicates that a value should be retrieved.
+    Get(RequestId, Key),
+    /// Indicates that a value should be deleted.
+    Delete(RequestId, Key),
+    /// Indicates that every value the server has seen should be removed, wherever it is, while
+    /// values written concurrently or later are kept. Answered with `DeleteOk`.
+    Truncate(RequestId),
+    /// Indicates that all of a key's concurrent values (siblings) should be retrieved.
+    GetAll(RequestId, Key),
+    /// Indicates that a value should be written, overwriting the given siblings, to resolve a
+    /// conflict the client has seen.
+    PutResolved(RequestId, Key, Value, Vec<Timestamp>),
+
+    /// Indicates a successful `Put`, with a version token naming the write. Analogous to an HTTP
+    /// 2XX.
+    PutOk(RequestId, Timestamp),
+    /// Indicates a successful `Get`. Analogous to an HTTP 2XX.
+    GetOk(RequestId, Value),
+    /// Indicates a successful `Delete`. Analogous to an HTTP 2XX.
+    DeleteOk(RequestId),
+    /// Indicates a successful `GetAll`, with the timestamp of each sibling.
+    GetAllOk(RequestId, Vec<(Timestamp, Value)>),
+
+    /// Indicates that the client should be notified when ops from other servers give the key new
+    /// siblings.
+    Subscribe(Key),
+    /// Notifies a subscribed client of a key's siblings after they changed.
+    Siblings(Key, Vec<(Timestamp, Value)>),
+
+    /// Passes a version token from one client to another.
+    Token(Dependency),
+
+    /// Indicates that an element should be added to the set. Answered with `PutOk`, with the
+    /// add's tag.
+    Add(RequestId, Value),
+    /// Indicates that the adds of an element seen so far should be removed from the set.
+    /// Answered with `DeleteOk`.
+    Remove(RequestId, Value),
+    /// Indicates that whether the set holds an element should be retrieved.
+    Contains(RequestId, Value),
+    /// Indicates a successful `Contains`, with whether the set holds the element.
+    ContainsOk(RequestId, bool),
+
+    /// Indicates that the counter should be incremented. Answered with `CountOk`.
+    Increment(RequestId),
+    /// Indicates that the counter should be decremented. Answered with `CountOk`.
+    Decrement(RequestId),
+    /// Indicates that the counter's value should be retrieved.
+    Read(RequestId),
+    /// Indicates a successful `Increment`, `Decrement` or `Read`, with the counter's value at the
+    /// server afterwards.
+    CountOk(RequestId, i64),
+
+    /// Indicates that a value should be written to the multi-value register. Answered with
+    /// `PutOk`, with the write's timestamp.
+    WriteRegister(RequestId, Value),
+    /// Indicates that the multi-value register's siblings should be retrieved. Answered with
+    /// `GetAllOk`, with every sibling.
+    ReadRegister(RequestId),
+
+    /// Indicates that the flag should be enabled. Answered with `PutOk`, with the write's
+    /// timestamp.
+    Enable(RequestId),
+    /// Indicates that the flag should be disabled. Answered with `PutOk`, with the write's
+    /// timestamp.
+    Disable(RequestId),
+    /// Indicates that whether the flag is enabled should be retrieved.
+    ReadFlag(RequestId),
+    /// Indicates a successful `ReadFlag`, with whether the flag is enabled.
+    FlagOk(RequestId, bool),
+
+    /// Indicates that a value should be inserted at an index in the list. Answered with `PutOk`,
+    /// with the new element's id.
+    Insert(RequestId, usize, Value),
+    /// Indicates that the element at an index in the list should be deleted. Answered with
+    /// `DeleteOk`.
+    DeleteAt(RequestId, usize),
+    /// Indicates that the list's elements should be retrieved.
+    ReadList(RequestId),
+    /// Indicates a successful `ReadList`, with the list's elements.
+    ListOk(RequestId, Vec<Value>),
+
+    /// Indicates that the key at the end of a path in the nested map should be set. Answered
+    /// with `PutOk`, with the set's timestamp.
+    SetPath(RequestId, Vec<Key>, Value),
+    /// Indicates that the subtree at a path in the nested map should be deleted. Answered with
+    /// `DeleteOk`.
+    DeletePath(RequestId, Vec<Key>),
+    /// Indicates that the values at a path in the nested map and under it should be retrieved.
+    /// Answered with `GetAllOk`.
+    GetPath(RequestId, Vec<Key>),
+}
+
+impl MyRegisterMsg {
+    /// The id of a client request that changes the map.
+    fn mutation_request_id(&self) -> Option<RequestId> {
+        match self {
+            MyRegisterMsg::Put(request_id, _, _)
+            | MyRegisterMsg::PutAfter(request_id, _, _, _)
+            | MyRegisterMsg::PutResolved(request_id, _, _, _)
+            | MyRegisterMsg::Delete(request_id, _)
+            | MyRegisterMsg::Truncate(request_id)
+            | MyRegisterMsg::Add(request_id, _)
+            | MyRegisterMsg::Remove(request_id, _)
+            | MyRegisterMsg::Increment(request_id)
+            | MyRegisterMsg::Decrement(request_id)
+            | MyRegisterMsg::WriteRegister(request_id, _)
+            | MyRegisterMsg::Enable(request_id)
+            | MyRegisterMsg::Disable(request_id)
+            | MyRegisterMsg::Insert(request_id, _, _)
+            | MyRegisterMsg::DeleteAt(request_id, _)
+            | MyRegisterMsg::SetPath(request_id, _, _)
+            | MyRegisterMsg::DeletePath(request_id, _) => Some(*request_id),
+            _ => None,
+        }
+    }
+}
+
+impl<M> Actor for MyRegisterActor<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    type Msg = MyRegisterMsg;
+
+    type State = MyRegisterActorState<M>;
+
+    fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
+        match self {
+            MyRegisterActor::PutClient {
+                put_count,
+                // don't issue reads from this so don't worry about this
+                follow_up_gets: _,
+                server_count,
+                client_loop,
+                keys,
+                token_to: _,
+                retries,
+                placement,
+                abstracted: _,
+            } => {
+                let server_count = *server_count;
+
+                let index: usize = id.into();
+                if index < server_count {
+                    panic!("MyRegisterActor clients must be added to the model after servers.");
+                }
+
+                if *put_count > 0 {
+                    let unique_request_id = index; // next will be 2 * index
+                    let value = put_value(index, server_count, 0);
+                    let key = client_key(keys, index, 0);
+                    o.send(
+                        placement.route(key, index),
+                        MyRegisterMsg::Put(unique_request_id, key, value),
+                    );
+                    match client_loop {
+                        ClientLoop::Closed => {
+                            if *retries {
+                                o.set_timer(RETRY_INTERVAL);
+                            }
+                            MyRegisterActorState::PutClient {
+                                awaiting: Some(unique_request_id),
+                                op_count: 1,
+                                last_write: None,
+                                abstracted: self.abstracts_clients(),
+                            }
+                        }
+                        ClientLoop::Open => {
+                            o.set_timer(OPEN_LOOP_INTERVAL);
+                            MyRegisterActorState::PutClient {
+                                awaiting: None,
+                                op_count: 1,
+                                last_write: None,
+                                abstracted: self.abstracts_clients(),
+                            }
+                        }
+                    }
+                } else {
+                    MyRegisterActorState::PutClient {
+                        awaiting: None,
+                        op_count: 0,
+                        last_write: None,
+                        abstracted: self.abstracts_clients(),
+                    }
+                }
+            }
+            MyRegisterActor::DeleteClient {
+                delete_count,
+                follow_up_gets: _,
+                server_count,
+                client_loop,
+                keys,
+                retries,
+                placement,
+                truncate,
+                abstracted: _,
+            } => {
+                let server_count = *server_count;
+
+                let index: usize = id.into();
+                if index < server_count {
+                    panic!("MyRegisterActor clients must be added to the model after servers.");
+                }
+
+                if *delete_count > 0 {
+                    let unique_request_id = index; // next will be 2 * index
+                    let key = client_key(keys, index, 0);
+                    o.send(
+                        placement.route(key, index),
+                        delete_request(*truncate, unique_request_id, key),
+                    );
+                    match client_loop {
+                        ClientLoop::Closed => {
+                            if *retries {
+                                o.set_timer(RETRY_INTERVAL);
+                            }
+                            MyRegisterActorState::DeleteClient {
+                                awaiting: Some(unique_request_id),
+                                op_count: 1,
+                                abstracted: self.abstracts_clients(),
+                            }
+                        }
+                        ClientLoop::Open => {
+                            o.set_timer(OPEN_LOOP_INTERVAL);
+                            MyRegisterActorState::DeleteClient {
+                                awaiting: None,
+                                op_count: 1,
+                                abstracted: self.abstracts_clients(),
+                            }
+                        }
+                    }
+                } else {
+                    MyRegisterActorState::DeleteClient {
+                        awaiting: None,
+                        op_count: 0,
+                        abstracted: self.abstracts_clients(),
+                    }
+                }
+            }
+            MyRegisterActor::ResolveClient {
+                placement,
+                key,
+                subscribe,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                if *subscribe {
+                    o.send(placement.route(*key, index), MyRegisterMsg::Subscribe(*key));
+                    return MyRegisterActorState::ResolveClient {
+                        awaiting: None,
+                        resolved: Vec::new(),
+                        resolutions: 0,
+                        abstracted: self.abstracts_clients(),
+                    };
+                }
+                o.send(
+                    placement.route(*key, index),
+                    MyRegisterMsg::GetAll(index, *key),
+                );
+                MyRegisterActorState::ResolveClient {
+                    awaiting: Some(index),
+                    resolved: Vec::new(),
+                    resolutions: 0,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::ReadClient {
+                placement,
+                key,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                for replica in placement.replicas(*key) {
+                    o.send(Id::from(replica), MyRegisterMsg::GetAll(index, *key));
+                }
+                MyRegisterActorState::ReadClient {
+                    awaiting: Some(index),
+                    responses: Vec::new(),
+                    merged: None,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::SetClient { server_count, .. } => {
+                let index: usize = id.into();
+                o.send(
+                    Id::from(index % server_count),
+                    MyRegisterMsg::Add(index, SET_ELEMENT),
+                );
+                MyRegisterActorState::SetClient {
+                    awaiting: Some(index),
+                    op_count: 1,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::CounterClient {
+                server_count,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                o.send(
+                    Id::from(index % server_count),
+                    counter_request(index, COUNTER_CHANGES[0]),
+                );
+                MyRegisterActorState::CounterClient {
+                    awaiting: Some(index),
+                    op_count: 1,
+                    net: 0,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::MvRegisterClient {
+                server_count,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                // clients write different values, so that concurrent writes show up as siblings
+                let value = (b'A' + (index % 26) as u8) as char;
+                o.send(
+                    Id::from(index % server_count),
+                    MyRegisterMsg::WriteRegister(index, value),
+                );
+                MyRegisterActorState::MvRegisterClient {
+                    awaiting: Some(index),
+                    op_count: 1,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::FlagClient {
+                server_count,
+                enable,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                let request = if *enable {
+                    MyRegisterMsg::Enable(index)
+                } else {
+                    MyRegisterMsg::Disable(index)
+                };
+                o.send(Id::from(index % server_count), request);
+                MyRegisterActorState::FlagClient {
+                    awaiting: Some(index),
+                    op_count: 1,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::ListInsertClient {
+                server_count,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                let value = (b'A' + (index % 26) as u8) as char;
+                o.send(
+                    Id::from(index % server_count),
+                    MyRegisterMsg::Insert(index, 0, value),
+                );
+                MyRegisterActorState::ListInsertClient {
+                    awaiting: Some(index),
+                    op_count: 1,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::ListDeleteClient {
+                server_count,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                o.send(
+                    Id::from(index % server_count),
+                    MyRegisterMsg::DeleteAt(index, 0),
+                );
+                MyRegisterActorState::ListDeleteClient {
+                    awaiting: Some(index),
+                    op_count: 1,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::NestedClient {
+                server_count,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                // each client sets its own key under the shared one, which they all delete
+                let key = (b'a' + (index % 26) as u8) as char;
+                let value = (b'A' + (index % 26) as u8) as char;
+                o.send(
+                    Id::from(index % server_count),
+                    MyRegisterMsg::SetPath(index, vec![NESTED_KEY, key], value),
+                );
+                MyRegisterActorState::NestedClient {
+                    awaiting: Some(index),
+                    op_count: 1,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::GetClient {
+                get_count,
+                server_count,
+                keys,
+                placement,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                if index < *server_count {
+                    panic!("MyRegisterActor clients must be added to the model after servers.");
+                }
+
+                if *get_count > 0 {
+                    let key = client_key(keys, index, 0);
+                    o.send(placement.route(key, index), MyRegisterMsg::Get(index, key));
+                    if *get_count > 1 {
+                        o.set_timer(OPEN_LOOP_INTERVAL);
+                    }
+                }
+                MyRegisterActorState::GetClient {
+                    op_count: (*get_count > 0) as usize,
+                    reads: Vec::new(),
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::MixedClient {
+                script,
+                server_count,
+                keys,
+                placement,
+                abstracted: _,
+            } => {
+                let index: usize = id.into();
+                if index < *server_count {
+                    panic!("MyRegisterActor clients must be added to the model after servers.");
+                }
+
+                if script.is_empty() {
+                    return MyRegisterActorState::MixedClient {
+                        awaiting: None,
+                        op_count: 0,
+                        abstracted: self.abstracts_clients(),
+                    };
+                }
+                let (key, msg) = mixed_request(script, keys, index, *server_count, 0);
+                o.send(placement.route(key, index), msg);
+                MyRegisterActorState::MixedClient {
+                    awaiting: Some(index),
+                    op_count: 1,
+                    abstracted: self.abstracts_clients(),
+                }
+            }
+            MyRegisterActor::Server(server_actor) => {
+                let mut server_out = Out::new();
+                let state =
+                    MyRegisterActorState::Server(server_actor.on_start(id, &mut server_out));
+                o.append(&mut server_out);
+                state
+            }
+        }
+    }
+
+    fn on_msg(
+        &self,
+        id: Id,
+        state: &mut Cow<Self::State>,
+        src: Id,
+        msg: Self::Msg,
+        o: &mut Out<Self>,
+    ) {
+        use MyRegisterActor as A;
+        use MyRegisterActorState as S;
+
+        // version tokens are passed on and used however far along the client is
+        match (self, &msg) {
+            (
+                A::PutClient {
+                    token_to: Some(token_to),
+                    keys,
+                    ..
+                },
+                MyRegisterMsg::PutOk(request_id, token),
+            ) if *request_id == usize::from(id) => {
+                // the first put's request id is the client's index
+                let dependency = Dependency {
+                    timestamp: *token,
+                    key: client_key(keys, *request_id, 0),
+                };
+                o.send(*token_to, MyRegisterMsg::Token(dependency))
+            }
+            (
+                A::PutClient {
+                    put_count,
+                    server_count,
+                    keys,
+                    placement,
+                    ..
+                },
+                MyRegisterMsg::Token(dependency),
+            ) => {
+                let index: usize = id.into();
+                let value = dependent_value(index, *server_count);
+                let key = client_key(keys, index, 0);
+                o.send(
+                    placement.route(key, index),
+                    MyRegisterMsg::PutAfter((put_count + 2) * index, key, value, *dependency),
+                );
+                return;
+            }
+            _ => {}
+        }
+
+        let awaiting = state.awaiting();
+        match (self, &**state) {
+            (
+                A::PutClient {
+                    put_count,
+                    follow_up_gets,
+                    server_count,
+                    client_loop: ClientLoop::Closed,
+                    keys,
+                    token_to: _,
+                    retries: _,
+                    placement,
+                    abstracted: _,
+                },
+                S::PutClient {
+                    awaiting: Some(awaiting),
+                    op_count,
+                    last_write,
+                    abstracted: _,
+                },
+            ) => {
+                let server_count = *server_count;
+                match msg {
+                    MyRegisterMsg::PutOk(request_id, timestamp) if &request_id == awaiting => {
+                        let index: usize = id.into();
+                        let unique_request_id = (op_count + 1) * index;
+                        if *op_count < *put_count {
+                            let value = put_value(index, server_count, *op_count);
+                            let key = client_key(keys, index, *op_count);
+                            o.send(
+                                placement.route(key, index),
+                                MyRegisterMsg::Put(unique_request_id, key, value),
+                            );
+                            *state = Cow::Owned(MyRegisterActorState::PutClient {
+                                awaiting: Some(unique_request_id),
+                                op_count: op_count + 1,
+                                last_write: None,
+                                abstracted: self.abstracts_clients(),
+                            });
+                        } else if *follow_up_gets {
+                            let key = client_key(keys, index, *op_count - 1);
+                            let value = put_value(index, server_count, *op_count - 1);
+                            o.send(
+                                placement.route(key, index),
+                                MyRegisterMsg::Get(unique_request_id, key),
+                            );
+                            *state = Cow::Owned(MyRegisterActorState::PutClient {
+                                awaiting: Some(unique_request_id),
+                                op_count: op_count + 1,
+                                last_write: Some((key, value, timestamp)),
+                                abstracted: self.abstracts_clients(),
+                            });
+                        } else {
+                            *state = Cow::Owned(MyRegisterActorState::PutClient {
+                                awaiting: None,
+                                op_count: op_count + 1,
+                                last_write: None,
+                                abstracted: self.abstracts_clients(),
+                            });
+                        }
+                    }
+                    MyRegisterMsg::GetOk(request_id, _value) if &request_id == awaiting => {
+                        // finished
+                        *state = Cow::Owned(MyRegisterActorState::PutClient {
+                            awaiting: None,
+                            op_count: op_count + 1,
+                            last_write: *last_write,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::DeleteOk(request_id) if &request_id == awaiting => {}
+                    MyRegisterMsg::PutOk(_, _) => {}
+                    MyRegisterMsg::GetOk(_, _) => {}
+                    MyRegisterMsg::DeleteOk(_) => {}
+                    MyRegisterMsg::Put(_, _, _) => {}
+                    MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                    MyRegisterMsg::Get(_, _) => {}
+                    MyRegisterMsg::Delete(_, _) => {}
+                    MyRegisterMsg::Truncate(_) => {}
+                    MyRegisterMsg::GetAll(_, _) => {}
+                    MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                    MyRegisterMsg::GetAllOk(_, _) => {}
+                    MyRegisterMsg::Subscribe(_) => {}
+                    MyRegisterMsg::Siblings(_, _) => {}
+                    MyRegisterMsg::Internal(_) => {}
+                    MyRegisterMsg::Token(_) => {}
+                    MyRegisterMsg::Add(_, _) => {}
+                    MyRegisterMsg::Remove(_, _) => {}
+                    MyRegisterMsg::Contains(_, _) => {}
+                    MyRegisterMsg::ContainsOk(_, _) => {}
+                    MyRegisterMsg::Increment(_) => {}
+                    MyRegisterMsg::Decrement(_) => {}
+                    MyRegisterMsg::Read(_) => {}
+                    MyRegisterMsg::CountOk(_, _) => {}
+                    MyRegisterMsg::WriteRegister(_, _) => {}
+                    MyRegisterMsg::ReadRegister(_) => {}
+                    MyRegisterMsg::Enable(_) => {}
+                    MyRegisterMsg::Disable(_) => {}
+                    MyRegisterMsg::ReadFlag(_) => {}
+                    MyRegisterMsg::FlagOk(_, _) => {}
+                    MyRegisterMsg::Insert(_, _, _) => {}
+                    MyRegisterMsg::DeleteAt(_, _) => {}
+                    MyRegisterMsg::ReadList(_) => {}
+                    MyRegisterMsg::ListOk(_, _) => {}
+                    MyRegisterMsg::SetPath(_, _, _) => {}
+                    MyRegisterMsg::DeletePath(_, _) => {}
+                    MyRegisterMsg::GetPath(_, _) => {}
+                }
+            }
+            (
+                A::DeleteClient {
+                    delete_count,
+                    follow_up_gets,
+                    server_count: _,
+                    client_loop: ClientLoop::Closed,
+                    keys,
+                    retries: _,
+                    placement,
+                    truncate,
+                    abstracted: _,
+                },
+                S::DeleteClient {
+                    awaiting: Some(awaiting),
+                    op_count,
+                    abstracted: _,
+                },
+            ) => {
+                match msg {
+                    MyRegisterMsg::PutOk(_, _) => {}
+                    MyRegisterMsg::GetOk(request_id, _value) if &request_id == awaiting => {
+                        // finished
+                        *state = Cow::Owned(MyRegisterActorState::DeleteClient {
+                            awaiting: None,
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::DeleteOk(request_id) if &request_id == awaiting => {
+                        let index: usize = id.into();
+                        let unique_request_id = (op_count + 1) * index;
+                        if *op_count < *delete_count {
+                            let key = client_key(keys, index, *op_count);
+                            o.send(
+                                placement.route(key, index),
+                                delete_request(*truncate, unique_request_id, key),
+                            );
+                        } else if *follow_up_gets {
+                            let key = client_key(keys, index, *op_count - 1);
+                            o.send(
+                                placement.route(key, index),
+                                MyRegisterMsg::Get(unique_request_id, key),
+                            );
+                            *state = Cow::Owned(MyRegisterActorState::DeleteClient {
+                                awaiting: Some(unique_request_id),
+                                op_count: op_count + 1,
+                                abstracted: self.abstracts_clients(),
+                            });
+                        } else {
+                            *state = Cow::Owned(MyRegisterActorState::DeleteClient {
+                                awaiting: None,
+                                op_count: op_count + 1,
+                                abstracted: self.abstracts_clients(),
+                            });
+                        }
+                    }
+                    MyRegisterMsg::GetOk(_, _) => {}
+                    MyRegisterMsg::DeleteOk(_) => {}
+                    MyRegisterMsg::Put(_, _, _) => {}
+                    MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                    MyRegisterMsg::Get(_, _) => {}
+                    MyRegisterMsg::Delete(_, _) => {}
+                    MyRegisterMsg::Truncate(_) => {}
+                    MyRegisterMsg::GetAll(_, _) => {}
+                    MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                    MyRegisterMsg::GetAllOk(_, _) => {}
+                    MyRegisterMsg::Subscribe(_) => {}
+                    MyRegisterMsg::Siblings(_, _) => {}
+                    MyRegisterMsg::Internal(_) => {}
+                    MyRegisterMsg::Token(_) => {}
+                    MyRegisterMsg::Add(_, _) => {}
+                    MyRegisterMsg::Remove(_, _) => {}
+                    MyRegisterMsg::Contains(_, _) => {}
+                    MyRegisterMsg::ContainsOk(_, _) => {}
+                    MyRegisterMsg::Increment(_) => {}
+                    MyRegisterMsg::Decrement(_) => {}
+                    MyRegisterMsg::Read(_) => {}
+                    MyRegisterMsg::CountOk(_, _) => {}
+                    MyRegisterMsg::WriteRegister(_, _) => {}
+                    MyRegisterMsg::ReadRegister(_) => {}
+                    MyRegisterMsg::Enable(_) => {}
+                    MyRegisterMsg::Disable(_) => {}
+                    MyRegisterMsg::ReadFlag(_) => {}
+                    MyRegisterMsg::FlagOk(_, _) => {}
+                    MyRegisterMsg::Insert(_, _, _) => {}
+                    MyRegisterMsg::DeleteAt(_, _) => {}
+                    MyRegisterMsg::ReadList(_) => {}
+                    MyRegisterMsg::ListOk(_, _) => {}
+                    MyRegisterMsg::SetPath(_, _, _) => {}
+                    MyRegisterMsg::DeletePath(_, _) => {}
+                    MyRegisterMsg::GetPath(_, _) => {}
+                }
+            }
+            (
+                A::ResolveClient {
+                    placement,
+                    key,
+                    subscribe: _,
+                    abstracted: _,
+                },
+                S::ResolveClient {
+                    awaiting: Some(awaiting),
+                    resolved,
+                    resolutions,
+                    abstracted: _,
+                },
+            ) => match msg {
+                MyRegisterMsg::GetAllOk(request_id, siblings) if &request_id == awaiting => {
+                    let next = if siblings.len() > 1 {
+                        resolve(
+                            id,
+                            placement,
+                            *key,
+                            siblings,
+                            resolved,
+                            *resolutions,
+                            self.abstracts_clients(),
+                            o,
+                        )
+                    } else {
+                        MyRegisterActorState::ResolveClient {
+                            awaiting: None,
+                            resolved: Vec::new(),
+                            resolutions: 0,
+                            abstracted: self.abstracts_clients(),
+                        }
+                    };
+                    *state = Cow::Owned(next);
+                }
+                MyRegisterMsg::PutOk(request_id, _) if &request_id == awaiting => {
+                    *state = Cow::Owned(MyRegisterActorState::ResolveClient {
+                        awaiting: None,
+                        resolved: resolved.clone(),
+                        resolutions: *resolutions,
+                        abstracted: self.abstracts_clients(),
+                    });
+                }
+                MyRegisterMsg::PutOk(_, _) => {}
+                MyRegisterMsg::GetOk(_, _) => {}
+                MyRegisterMsg::DeleteOk(_) => {}
+                MyRegisterMsg::GetAllOk(_, _) => {}
+                MyRegisterMsg::Put(_, _, _) => {}
+                MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                MyRegisterMsg::Get(_, _) => {}
+                MyRegisterMsg::Delete(_, _) => {}
+                MyRegisterMsg::Truncate(_) => {}
+                MyRegisterMsg::GetAll(_, _) => {}
+                MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                MyRegisterMsg::Subscribe(_) => {}
+                // conflicts notified while a resolution is in flight are left to the next one
+                MyRegisterMsg::Siblings(_, _) => {}
+                MyRegisterMsg::Internal(_) => {}
+                MyRegisterMsg::Token(_) => {}
+                MyRegisterMsg::Add(_, _) => {}
+                MyRegisterMsg::Remove(_, _) => {}
+                MyRegisterMsg::Contains(_, _) => {}
+                MyRegisterMsg::ContainsOk(_, _) => {}
+                MyRegisterMsg::Increment(_) => {}
+                MyRegisterMsg::Decrement(_) => {}
+                MyRegisterMsg::Read(_) => {}
+                MyRegisterMsg::CountOk(_, _) => {}
+                MyRegisterMsg::WriteRegister(_, _) => {}
+                MyRegisterMsg::ReadRegister(_) => {}
+                MyRegisterMsg::Enable(_) => {}
+                MyRegisterMsg::Disable(_) => {}
+                MyRegisterMsg::ReadFlag(_) => {}
+                MyRegisterMsg::FlagOk(_, _) => {}
+                MyRegisterMsg::Insert(_, _, _) => {}
+                MyRegisterMsg::DeleteAt(_, _) => {}
+                MyRegisterMsg::ReadList(_) => {}
+                MyRegisterMsg::ListOk(_, _) => {}
+                MyRegisterMsg::SetPath(_, _, _) => {}
+                MyRegisterMsg::DeletePath(_, _) => {}
+                MyRegisterMsg::GetPath(_, _) => {}
+            },
+            (
+                A::ResolveClient {
+                    placement,
+                    key,
+                    subscribe: true,
+                    abstracted: _,
+                },
+                S::ResolveClient {
+                    awaiting: None,
+                    resolved,
+                    resolutions,
+                    abstracted: _,
+                },
+            ) => {
+                if let MyRegisterMsg::Siblings(_, siblings) = msg {
+                    if *resolutions < MAX_RESOLUTIONS {
+                        let next = resolve(
+                            id,
+                            placement,
+                            *key,
+                            siblings,
+                            resolved,
+                            *resolutions,
+                            self.abstracts_clients(),
+                            o,
+                        );
+                        *state = Cow::Owned(next);
+                    }
+                }
+            }
+            (A::ResolveClient { .. }, S::ResolveClient { awaiting: None, .. }) => {}
+            (
+                A::ReadClient {
+                    placement,
+                    key,
+                    abstracted: _,
+                },
+                S::ReadClient {
+                    awaiting: Some(awaiting),
+                    responses,
+                    merged: _,
+                    abstracted: _,
+                },
+            ) => match msg {
+                MyRegisterMsg::GetAllOk(request_id, siblings) if &request_id == awaiting => {
+                    let mut responses = responses.clone();
+                    responses.push(siblings);
+                    let next = if responses.len() == placement.replicas(*key).len() {
+                        MyRegisterActorState::ReadClient {
+                            awaiting: None,
+                            merged: merge_reads(&responses),
+                            responses,
+                            abstracted: self.abstracts_clients(),
                         }
+                    } else {
+                        MyRegisterActorState::ReadClient {
+                            awaiting: Some(*awaiting),
+                            responses,
+                            merged: None,
+                            abstracted: self.abstracts_clients(),
+                        }
+                    };
+                    *state = Cow::Owned(next);
+                }
+                MyRegisterMsg::PutOk(_, _) => {}
+                MyRegisterMsg::GetOk(_, _) => {}
+                MyRegisterMsg::DeleteOk(_) => {}
+                MyRegisterMsg::GetAllOk(_, _) => {}
+                MyRegisterMsg::Put(_, _, _) => {}
+                MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                MyRegisterMsg::Get(_, _) => {}
+                MyRegisterMsg::Delete(_, _) => {}
+                MyRegisterMsg::Truncate(_) => {}
+                MyRegisterMsg::GetAll(_, _) => {}
+                MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                MyRegisterMsg::Subscribe(_) => {}
+                MyRegisterMsg::Siblings(_, _) => {}
+                MyRegisterMsg::Internal(_) => {}
+                MyRegisterMsg::Token(_) => {}
+                MyRegisterMsg::Add(_, _) => {}
+                MyRegisterMsg::Remove(_, _) => {}
+                MyRegisterMsg::Contains(_, _) => {}
+                MyRegisterMsg::ContainsOk(_, _) => {}
+                MyRegisterMsg::Increment(_) => {}
+                MyRegisterMsg::Decrement(_) => {}
+                MyRegisterMsg::Read(_) => {}
+                MyRegisterMsg::CountOk(_, _) => {}
+                MyRegisterMsg::WriteRegister(_, _) => {}
+                MyRegisterMsg::ReadRegister(_) => {}
+                MyRegisterMsg::Enable(_) => {}
+                MyRegisterMsg::Disable(_) => {}
+                MyRegisterMsg::ReadFlag(_) => {}
+                MyRegisterMsg::FlagOk(_, _) => {}
+                MyRegisterMsg::Insert(_, _, _) => {}
+                MyRegisterMsg::DeleteAt(_, _) => {}
+                MyRegisterMsg::ReadList(_) => {}
+                MyRegisterMsg::ListOk(_, _) => {}
+                MyRegisterMsg::SetPath(_, _, _) => {}
+                MyRegisterMsg::DeletePath(_, _) => {}
+                MyRegisterMsg::GetPath(_, _) => {}
+            },
+            (A::ReadClient { .. }, S::ReadClient { awaiting: None, .. }) => {}
+            (
+                A::SetClient {
+                    server_count,
+                    re_add,
+                    abstracted: _,
+                },
+                S::SetClient {
+                    awaiting: Some(awaiting),
+                    op_count,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                let server = Id::from(index % server_count);
+                let unique_request_id = (op_count + 1) * index;
+                match msg {
+                    MyRegisterMsg::PutOk(request_id, _) if &request_id == awaiting => {
+                        // the first add is followed by the remove, an add after it by the check
+                        let request = if *op_count == 1 {
+                            MyRegisterMsg::Remove(unique_request_id, SET_ELEMENT)
+                        } else {
+                            MyRegisterMsg::Contains(unique_request_id, SET_ELEMENT)
+                        };
+                        o.send(server, request);
+                        *state = Cow::Owned(MyRegisterActorState::SetClient {
+                            awaiting: Some(unique_request_id),
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::DeleteOk(request_id) if &request_id == awaiting => {
+                        let request = if *re_add {
+                            MyRegisterMsg::Add(unique_request_id, SET_ELEMENT)
+                        } else {
+                            MyRegisterMsg::Contains(unique_request_id, SET_ELEMENT)
+                        };
+                        o.send(server, request);
+                        *state = Cow::Owned(MyRegisterActorState::SetClient {
+                            awaiting: Some(unique_request_id),
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::ContainsOk(request_id, _) if &request_id == awaiting => {
+                        // finished
+                        *state = Cow::Owned(MyRegisterActorState::SetClient {
+                            awaiting: None,
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::PutOk(_, _) => {}
+                    MyRegisterMsg::GetOk(_, _) => {}
+                    MyRegisterMsg::DeleteOk(_) => {}
+                    MyRegisterMsg::GetAllOk(_, _) => {}
+                    MyRegisterMsg::Put(_, _, _) => {}
+                    MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                    MyRegisterMsg::Get(_, _) => {}
+                    MyRegisterMsg::Delete(_, _) => {}
+                    MyRegisterMsg::Truncate(_) => {}
+                    MyRegisterMsg::GetAll(_, _) => {}
+                    MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                    MyRegisterMsg::Subscribe(_) => {}
+                    MyRegisterMsg::Siblings(_, _) => {}
+                    MyRegisterMsg::Internal(_) => {}
+                    MyRegisterMsg::Token(_) => {}
+                    MyRegisterMsg::Add(_, _) => {}
+                    MyRegisterMsg::Remove(_, _) => {}
+                    MyRegisterMsg::Contains(_, _) => {}
+                    MyRegisterMsg::ContainsOk(_, _) => {}
+                    MyRegisterMsg::Increment(_) => {}
+                    MyRegisterMsg::Decrement(_) => {}
+                    MyRegisterMsg::Read(_) => {}
+                    MyRegisterMsg::CountOk(_, _) => {}
+                    MyRegisterMsg::WriteRegister(_, _) => {}
+                    MyRegisterMsg::ReadRegister(_) => {}
+                    MyRegisterMsg::Enable(_) => {}
+                    MyRegisterMsg::Disable(_) => {}
+                    MyRegisterMsg::ReadFlag(_) => {}
+                    MyRegisterMsg::FlagOk(_, _) => {}
+                    MyRegisterMsg::Insert(_, _, _) => {}
+                    MyRegisterMsg::DeleteAt(_, _) => {}
+                    MyRegisterMsg::ReadList(_) => {}
+                    MyRegisterMsg::ListOk(_, _) => {}
+                    MyRegisterMsg::SetPath(_, _, _) => {}
+                    MyRegisterMsg::DeletePath(_, _) => {}
+                    MyRegisterMsg::GetPath(_, _) => {}
+                }
+            }
+            (A::SetClient { .. }, S::SetClient { awaiting: None, .. }) => {}
+            (
+                A::CounterClient {
+                    server_count,
+                    abstracted: _,
+                },
+                S::CounterClient {
+                    awaiting: Some(awaiting),
+                    op_count,
+                    net,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                let server = Id::from(index % server_count);
+                let unique_request_id = (op_count + 1) * index;
+                match msg {
+                    MyRegisterMsg::CountOk(request_id, _) if &request_id == awaiting => {
+                        // the last request is the read, which changes nothing
+                        let net = net + COUNTER_CHANGES.get(op_count - 1).unwrap_or(&0);
+                        let awaiting = if *op_count <= COUNTER_CHANGES.len() {
+                            let request = match COUNTER_CHANGES.get(*op_count) {
+                                Some(change) => counter_request(unique_request_id, *change),
+                                None => MyRegisterMsg::Read(unique_request_id),
+                            };
+                            o.send(server, request);
+                            Some(unique_request_id)
+                        } else {
+                            // finished
+                            None
+                        };
+                        *state = Cow::Owned(MyRegisterActorState::CounterClient {
+                            awaiting,
+                            op_count: op_count + 1,
+                            net,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::CountOk(_, _) => {}
+                    MyRegisterMsg::PutOk(_, _) => {}
+                    MyRegisterMsg::GetOk(_, _) => {}
+                    MyRegisterMsg::DeleteOk(_) => {}
+                    MyRegisterMsg::GetAllOk(_, _) => {}
+                    MyRegisterMsg::Put(_, _, _) => {}
+                    MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                    MyRegisterMsg::Get(_, _) => {}
+                    MyRegisterMsg::Delete(_, _) => {}
+                    MyRegisterMsg::Truncate(_) => {}
+                    MyRegisterMsg::GetAll(_, _) => {}
+                    MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                    MyRegisterMsg::Subscribe(_) => {}
+                    MyRegisterMsg::Siblings(_, _) => {}
+                    MyRegisterMsg::Internal(_) => {}
+                    MyRegisterMsg::Token(_) => {}
+                    MyRegisterMsg::Add(_, _) => {}
+                    MyRegisterMsg::Remove(_, _) => {}
+                    MyRegisterMsg::Contains(_, _) => {}
+                    MyRegisterMsg::ContainsOk(_, _) => {}
+                    MyRegisterMsg::Increment(_) => {}
+                    MyRegisterMsg::Decrement(_) => {}
+                    MyRegisterMsg::Read(_) => {}
+                    MyRegisterMsg::WriteRegister(_, _) => {}
+                    MyRegisterMsg::ReadRegister(_) => {}
+                    MyRegisterMsg::Enable(_) => {}
+                    MyRegisterMsg::Disable(_) => {}
+                    MyRegisterMsg::ReadFlag(_) => {}
+                    MyRegisterMsg::FlagOk(_, _) => {}
+                    MyRegisterMsg::Insert(_, _, _) => {}
+                    MyRegisterMsg::DeleteAt(_, _) => {}
+                    MyRegisterMsg::ReadList(_) => {}
+                    MyRegisterMsg::ListOk(_, _) => {}
+                    MyRegisterMsg::SetPath(_, _, _) => {}
+                    MyRegisterMsg::DeletePath(_, _) => {}
+                    MyRegisterMsg::GetPath(_, _) => {}
+                }
+            }
+            (A::CounterClient { .. }, S::CounterClient { awaiting: None, .. }) => {}
+            (
+                A::MvRegisterClient {
+                    server_count,
+                    abstracted: _,
+                },
+                S::MvRegisterClient {
+                    awaiting: Some(awaiting),
+                    op_count,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                let server = Id::from(index % server_count);
+                let unique_request_id = (op_count + 1) * index;
+                match msg {
+                    MyRegisterMsg::PutOk(request_id, _) if &request_id == awaiting => {
+                        o.send(server, MyRegisterMsg::ReadRegister(unique_request_id));
+                        *state = Cow::Owned(MyRegisterActorState::MvRegisterClient {
+                            awaiting: Some(unique_request_id),
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::GetAllOk(request_id, _) if &request_id == awaiting => {
+                        // finished
+                        *state = Cow::Owned(MyRegisterActorState::MvRegisterClient {
+                            awaiting: None,
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::PutOk(_, _) => {}
+                    MyRegisterMsg::GetAllOk(_, _) => {}
+                    MyRegisterMsg::GetOk(_, _) => {}
+                    MyRegisterMsg::DeleteOk(_) => {}
+                    MyRegisterMsg::Put(_, _, _) => {}
+                    MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                    MyRegisterMsg::Get(_, _) => {}
+                    MyRegisterMsg::Delete(_, _) => {}
+                    MyRegisterMsg::Truncate(_) => {}
+                    MyRegisterMsg::GetAll(_, _) => {}
+                    MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                    MyRegisterMsg::Subscribe(_) => {}
+                    MyRegisterMsg::Siblings(_, _) => {}
+                    MyRegisterMsg::Internal(_) => {}
+                    MyRegisterMsg::Token(_) => {}
+                    MyRegisterMsg::Add(_, _) => {}
+                    MyRegisterMsg::Remove(_, _) => {}
+                    MyRegisterMsg::Contains(_, _) => {}
+                    MyRegisterMsg::ContainsOk(_, _) => {}
+                    MyRegisterMsg::Increment(_) => {}
+                    MyRegisterMsg::Decrement(_) => {}
+                    MyRegisterMsg::Read(_) => {}
+                    MyRegisterMsg::CountOk(_, _) => {}
+                    MyRegisterMsg::WriteRegister(_, _) => {}
+                    MyRegisterMsg::ReadRegister(_) => {}
+                    MyRegisterMsg::Enable(_) => {}
+                    MyRegisterMsg::Disable(_) => {}
+                    MyRegisterMsg::ReadFlag(_) => {}
+                    MyRegisterMsg::FlagOk(_, _) => {}
+                    MyRegisterMsg::Insert(_, _, _) => {}
+                    MyRegisterMsg::DeleteAt(_, _) => {}
+                    MyRegisterMsg::ReadList(_) => {}
+                    MyRegisterMsg::ListOk(_, _) => {}
+                    MyRegisterMsg::SetPath(_, _, _) => {}
+                    MyRegisterMsg::DeletePath(_, _) => {}
+                    MyRegisterMsg::GetPath(_, _) => {}
+                }
+            }
+            (A::MvRegisterClient { .. }, S::MvRegisterClient { awaiting: None, .. }) => {}
+            (
+                A::FlagClient { server_count, .. },
+                S::FlagClient {
+                    awaiting: Some(awaiting),
+                    op_count,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                let server = Id::from(index % server_count);
+                let unique_request_id = (op_count + 1) * index;
+                match msg {
+                    MyRegisterMsg::PutOk(request_id, _) if &request_id == awaiting => {
+                        o.send(server, MyRegisterMsg::ReadFlag(unique_request_id));
+                        *state = Cow::Owned(MyRegisterActorState::FlagClient {
+                            awaiting: Some(unique_request_id),
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
                     }
-                    MyRegisterMsg::GetOk(request_id, _value) if &request_id == awaiting => {
+                    MyRegisterMsg::FlagOk(request_id, _) if &request_id == awaiting => {
                         // finished
-                        *state = Cow::Owned(MyRegisterActorState::PutClient {
+                        *state = Cow::Owned(MyRegisterActorState::FlagClient {
                             awaiting: None,
                             op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
                         });
                     }
-                    MyRegisterMsg::DeleteOk(request_id) if &request_id == awaiting => {}
-                    MyRegisterMsg::PutOk(_) => {}
+                    MyRegisterMsg::PutOk(_, _) => {}
+                    MyRegisterMsg::FlagOk(_, _) => {}
                     MyRegisterMsg::GetOk(_, _) => {}
                     MyRegisterMsg::DeleteOk(_) => {}
                     MyRegisterMsg::Put(_, _, _) => {}
+                    MyRegisterMsg::PutAfter(_, _, _, _) => {}
                     MyRegisterMsg::Get(_, _) => {}
                     MyRegisterMsg::Delete(_, _) => {}
+                    MyRegisterMsg::Truncate(_) => {}
+                    MyRegisterMsg::GetAll(_, _) => {}
+                    MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                    MyRegisterMsg::GetAllOk(_, _) => {}
+                    MyRegisterMsg::Subscribe(_) => {}
+                    MyRegisterMsg::Siblings(_, _) => {}
                     MyRegisterMsg::Internal(_) => {}
+                    MyRegisterMsg::Token(_) => {}
+                    MyRegisterMsg::Add(_, _) => {}
+                    MyRegisterMsg::Remove(_, _) => {}
+                    MyRegisterMsg::Contains(_, _) => {}
+                    MyRegisterMsg::ContainsOk(_, _) => {}
+                    MyRegisterMsg::Increment(_) => {}
+                    MyRegisterMsg::Decrement(_) => {}
+                    MyRegisterMsg::Read(_) => {}
+                    MyRegisterMsg::CountOk(_, _) => {}
+                    MyRegisterMsg::WriteRegister(_, _) => {}
+                    MyRegisterMsg::ReadRegister(_) => {}
+                    MyRegisterMsg::Enable(_) => {}
+                    MyRegisterMsg::Disable(_) => {}
+                    MyRegisterMsg::ReadFlag(_) => {}
+                    MyRegisterMsg::Insert(_, _, _) => {}
+                    MyRegisterMsg::DeleteAt(_, _) => {}
+                    MyRegisterMsg::ReadList(_) => {}
+                    MyRegisterMsg::ListOk(_, _) => {}
+                    MyRegisterMsg::SetPath(_, _, _) => {}
+                    MyRegisterMsg::DeletePath(_, _) => {}
+                    MyRegisterMsg::GetPath(_, _) => {}
                 }
             }
+            (A::FlagClient { .. }, S::FlagClient { awaiting: None, .. }) => {}
             (
-                A::DeleteClient {
-                    delete_count,
-                    follow_up_gets,
+                A::ListInsertClient {
                     server_count,
+                    abstracted: _,
                 },
-                S::DeleteClient {
+                S::ListInsertClient {
                     awaiting: Some(awaiting),
                     op_count,
+                    abstracted: _,
                 },
             ) => {
-                let server_count = *server_count;
+                let index: usize = id.into();
+                let server = Id::from(index % server_count);
+                let unique_request_id = (op_count + 1) * index;
                 match msg {
-                    MyRegisterMsg::PutOk(_) => {}
-                    MyRegisterMsg::GetOk(request_id, _value) if &request_id == awaiting => {
+                    MyRegisterMsg::PutOk(request_id, _) if &request_id == awaiting => {
+                        let request = if *op_count == 1 {
+                            // insert after the first value, wherever it ended up at the server
+                            let value = (b'Z' - (index % 26) as u8) as char;
+                            MyRegisterMsg::Insert(unique_request_id, 1, value)
+                        } else {
+                            MyRegisterMsg::ReadList(unique_request_id)
+                        };
+                        o.send(server, request);
+                        *state = Cow::Owned(MyRegisterActorState::ListInsertClient {
+                            awaiting: Some(unique_request_id),
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::ListOk(request_id, _) if &request_id == awaiting => {
                         // finished
-                        *state = Cow::Owned(MyRegisterActorState::DeleteClient {
+                        *state = Cow::Owned(MyRegisterActorState::ListInsertClient {
                             awaiting: None,
                             op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
                         });
                     }
+                    MyRegisterMsg::PutOk(_, _) => {}
+                    MyRegisterMsg::ListOk(_, _) => {}
+                    MyRegisterMsg::DeleteOk(_) => {}
+                    MyRegisterMsg::GetOk(_, _) => {}
+                    MyRegisterMsg::GetAllOk(_, _) => {}
+                    MyRegisterMsg::Put(_, _, _) => {}
+                    MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                    MyRegisterMsg::Get(_, _) => {}
+                    MyRegisterMsg::Delete(_, _) => {}
+                    MyRegisterMsg::Truncate(_) => {}
+                    MyRegisterMsg::GetAll(_, _) => {}
+                    MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                    MyRegisterMsg::Subscribe(_) => {}
+                    MyRegisterMsg::Siblings(_, _) => {}
+                    MyRegisterMsg::Internal(_) => {}
+                    MyRegisterMsg::Token(_) => {}
+                    MyRegisterMsg::Add(_, _) => {}
+                    MyRegisterMsg::Remove(_, _) => {}
+                    MyRegisterMsg::Contains(_, _) => {}
+                    MyRegisterMsg::ContainsOk(_, _) => {}
+                    MyRegisterMsg::Increment(_) => {}
+                    MyRegisterMsg::Decrement(_) => {}
+                    MyRegisterMsg::Read(_) => {}
+                    MyRegisterMsg::CountOk(_, _) => {}
+                    MyRegisterMsg::WriteRegister(_, _) => {}
+                    MyRegisterMsg::ReadRegister(_) => {}
+                    MyRegisterMsg::Enable(_) => {}
+                    MyRegisterMsg::Disable(_) => {}
+                    MyRegisterMsg::ReadFlag(_) => {}
+                    MyRegisterMsg::FlagOk(_, _) => {}
+                    MyRegisterMsg::Insert(_, _, _) => {}
+                    MyRegisterMsg::DeleteAt(_, _) => {}
+                    MyRegisterMsg::ReadList(_) => {}
+                    MyRegisterMsg::SetPath(_, _, _) => {}
+                    MyRegisterMsg::DeletePath(_, _) => {}
+                    MyRegisterMsg::GetPath(_, _) => {}
+                }
+            }
+            (A::ListInsertClient { .. }, S::ListInsertClient { awaiting: None, .. }) => {}
+            (
+                A::ListDeleteClient {
+                    server_count,
+                    abstracted: _,
+                },
+                S::ListDeleteClient {
+                    awaiting: Some(awaiting),
+                    op_count,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                let server = Id::from(index % server_count);
+                let unique_request_id = (op_count + 1) * index;
+                match msg {
                     MyRegisterMsg::DeleteOk(request_id) if &request_id == awaiting => {
-                        let index: usize = id.into();
-                        let unique_request_id = (op_count + 1) * index;
-                        if *op_count < *delete_count {
-                            o.send(
-                                Id::from(index % server_count),
-                                MyRegisterMsg::Delete(unique_request_id, KEY),
-                            );
-                        } else if *follow_up_gets {
-                            o.send(
-                                Id::from(index % server_count),
-                                MyRegisterMsg::Get(unique_request_id, KEY),
-                            );
-                            *state = Cow::Owned(MyRegisterActorState::DeleteClient {
-                                awaiting: Some(unique_request_id),
-                                op_count: op_count + 1,
-                            });
-                        } else {
-                            *state = Cow::Owned(MyRegisterActorState::DeleteClient {
-                                awaiting: None,
-                                op_count: op_count + 1,
-                            });
-                        }
+                        o.send(server, MyRegisterMsg::ReadList(unique_request_id));
+                        *state = Cow::Owned(MyRegisterActorState::ListDeleteClient {
+                            awaiting: Some(unique_request_id),
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::ListOk(request_id, _) if &request_id == awaiting => {
+                        // finished
+                        *state = Cow::Owned(MyRegisterActorState::ListDeleteClient {
+                            awaiting: None,
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
                     }
+                    MyRegisterMsg::DeleteOk(_) => {}
+                    MyRegisterMsg::ListOk(_, _) => {}
+                    MyRegisterMsg::PutOk(_, _) => {}
                     MyRegisterMsg::GetOk(_, _) => {}
+                    MyRegisterMsg::GetAllOk(_, _) => {}
+                    MyRegisterMsg::Put(_, _, _) => {}
+                    MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                    MyRegisterMsg::Get(_, _) => {}
+                    MyRegisterMsg::Delete(_, _) => {}
+                    MyRegisterMsg::Truncate(_) => {}
+                    MyRegisterMsg::GetAll(_, _) => {}
+                    MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                    MyRegisterMsg::Subscribe(_) => {}
+                    MyRegisterMsg::Siblings(_, _) => {}
+                    MyRegisterMsg::Internal(_) => {}
+                    MyRegisterMsg::Token(_) => {}
+                    MyRegisterMsg::Add(_, _) => {}
+                    MyRegisterMsg::Remove(_, _) => {}
+                    MyRegisterMsg::Contains(_, _) => {}
+                    MyRegisterMsg::ContainsOk(_, _) => {}
+                    MyRegisterMsg::Increment(_) => {}
+                    MyRegisterMsg::Decrement(_) => {}
+                    MyRegisterMsg::Read(_) => {}
+                    MyRegisterMsg::CountOk(_, _) => {}
+                    MyRegisterMsg::WriteRegister(_, _) => {}
+                    MyRegisterMsg::ReadRegister(_) => {}
+                    MyRegisterMsg::Enable(_) => {}
+                    MyRegisterMsg::Disable(_) => {}
+                    MyRegisterMsg::ReadFlag(_) => {}
+                    MyRegisterMsg::FlagOk(_, _) => {}
+                    MyRegisterMsg::Insert(_, _, _) => {}
+                    MyRegisterMsg::DeleteAt(_, _) => {}
+                    MyRegisterMsg::ReadList(_) => {}
+                    MyRegisterMsg::SetPath(_, _, _) => {}
+                    MyRegisterMsg::DeletePath(_, _) => {}
+                    MyRegisterMsg::GetPath(_, _) => {}
+                }
+            }
+            (A::ListDeleteClient { .. }, S::ListDeleteClient { awaiting: None, .. }) => {}
+            (
+                A::NestedClient {
+                    server_count,
+                    abstracted: _,
+                },
+                S::NestedClient {
+                    awaiting: Some(awaiting),
+                    op_count,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                let server = Id::from(index % server_count);
+                let unique_request_id = (op_count + 1) * index;
+                match msg {
+                    MyRegisterMsg::PutOk(request_id, _) if &request_id == awaiting => {
+                        o.send(
+                            server,
+                            MyRegisterMsg::DeletePath(unique_request_id, vec![NESTED_KEY]),
+                        );
+                        *state = Cow::Owned(MyRegisterActorState::NestedClient {
+                            awaiting: Some(unique_request_id),
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::DeleteOk(request_id) if &request_id == awaiting => {
+                        o.send(
+                            server,
+                            MyRegisterMsg::GetPath(unique_request_id, vec![NESTED_KEY]),
+                        );
+                        *state = Cow::Owned(MyRegisterActorState::NestedClient {
+                            awaiting: Some(unique_request_id),
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::GetAllOk(request_id, _) if &request_id == awaiting => {
+                        // finished
+                        *state = Cow::Owned(MyRegisterActorState::NestedClient {
+                            awaiting: None,
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                    MyRegisterMsg::PutOk(_, _) => {}
                     MyRegisterMsg::DeleteOk(_) => {}
+                    MyRegisterMsg::GetAllOk(_, _) => {}
+                    MyRegisterMsg::GetOk(_, _) => {}
+                    MyRegisterMsg::ListOk(_, _) => {}
                     MyRegisterMsg::Put(_, _, _) => {}
+                    MyRegisterMsg::PutAfter(_, _, _, _) => {}
                     MyRegisterMsg::Get(_, _) => {}
                     MyRegisterMsg::Delete(_, _) => {}
+                    MyRegisterMsg::Truncate(_) => {}
+                    MyRegisterMsg::GetAll(_, _) => {}
+                    MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                    MyRegisterMsg::Subscribe(_) => {}
+                    MyRegisterMsg::Siblings(_, _) => {}
                     MyRegisterMsg::Internal(_) => {}
+                    MyRegisterMsg::Token(_) => {}
+                    MyRegisterMsg::Add(_, _) => {}
+                    MyRegisterMsg::Remove(_, _) => {}
+                    MyRegisterMsg::Contains(_, _) => {}
+                    MyRegisterMsg::ContainsOk(_, _) => {}
+                    MyRegisterMsg::Increment(_) => {}
+                    MyRegisterMsg::Decrement(_) => {}
+                    MyRegisterMsg::Read(_) => {}
+                    MyRegisterMsg::CountOk(_, _) => {}
+                    MyRegisterMsg::WriteRegister(_, _) => {}
+                    MyRegisterMsg::ReadRegister(_) => {}
+                    MyRegisterMsg::Enable(_) => {}
+                    MyRegisterMsg::Disable(_) => {}
+                    MyRegisterMsg::ReadFlag(_) => {}
+                    MyRegisterMsg::FlagOk(_, _) => {}
+                    MyRegisterMsg::Insert(_, _, _) => {}
+                    MyRegisterMsg::DeleteAt(_, _) => {}
+                    MyRegisterMsg::ReadList(_) => {}
+                    MyRegisterMsg::SetPath(_, _, _) => {}
+                    MyRegisterMsg::DeletePath(_, _) => {}
+                    MyRegisterMsg::GetPath(_, _) => {}
                 }
             }
+            (A::NestedClient { .. }, S::NestedClient { awaiting: None, .. }) => {}
+            (
+                A::GetClient { .. },
+                S::GetClient {
+                    op_count,
+                    reads,
+                    abstracted: _,
+                },
+            ) => match msg {
+                MyRegisterMsg::GetOk(_, value) => {
+                    let mut reads = reads.clone();
+                    reads.push(value);
+                    *state = Cow::Owned(MyRegisterActorState::GetClient {
+                        op_count: *op_count,
+                        reads,
+                        abstracted: self.abstracts_clients(),
+                    });
+                }
+                MyRegisterMsg::PutOk(_, _) => {}
+                MyRegisterMsg::DeleteOk(_) => {}
+                MyRegisterMsg::GetAllOk(_, _) => {}
+                MyRegisterMsg::ListOk(_, _) => {}
+                MyRegisterMsg::Put(_, _, _) => {}
+                MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                MyRegisterMsg::Get(_, _) => {}
+                MyRegisterMsg::Delete(_, _) => {}
+                MyRegisterMsg::Truncate(_) => {}
+                MyRegisterMsg::GetAll(_, _) => {}
+                MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                MyRegisterMsg::Subscribe(_) => {}
+                MyRegisterMsg::Siblings(_, _) => {}
+                MyRegisterMsg::Internal(_) => {}
+                MyRegisterMsg::Token(_) => {}
+                MyRegisterMsg::Add(_, _) => {}
+                MyRegisterMsg::Remove(_, _) => {}
+                MyRegisterMsg::Contains(_, _) => {}
+                MyRegisterMsg::ContainsOk(_, _) => {}
+                MyRegisterMsg::Increment(_) => {}
+                MyRegisterMsg::Decrement(_) => {}
+                MyRegisterMsg::Read(_) => {}
+                MyRegisterMsg::CountOk(_, _) => {}
+                MyRegisterMsg::WriteRegister(_, _) => {}
+                MyRegisterMsg::ReadRegister(_) => {}
+                MyRegisterMsg::Enable(_) => {}
+                MyRegisterMsg::Disable(_) => {}
+                MyRegisterMsg::ReadFlag(_) => {}
+                MyRegisterMsg::FlagOk(_, _) => {}
+                MyRegisterMsg::Insert(_, _, _) => {}
+                MyRegisterMsg::DeleteAt(_, _) => {}
+                MyRegisterMsg::ReadList(_) => {}
+                MyRegisterMsg::SetPath(_, _, _) => {}
+                MyRegisterMsg::DeletePath(_, _) => {}
+                MyRegisterMsg::GetPath(_, _) => {}
+            },
+            (
+                A::MixedClient {
+                    script,
+                    server_count,
+                    keys,
+                    placement,
+                    abstracted: _,
+                },
+                S::MixedClient {
+                    awaiting: Some(awaiting),
+                    op_count,
+                    abstracted: _,
+                },
+            ) => match msg {
+                MyRegisterMsg::PutOk(request_id, _)
+                | MyRegisterMsg::DeleteOk(request_id)
+                | MyRegisterMsg::GetAllOk(request_id, _)
+                    if &request_id == awaiting =>
+                {
+                    let index: usize = id.into();
+                    if *op_count < script.len() {
+                        let (key, msg) =
+                            mixed_request(script, keys, index, *server_count, *op_count);
+                        o.send(placement.route(key, index), msg);
+                        *state = Cow::Owned(MyRegisterActorState::MixedClient {
+                            awaiting: Some((op_count + 1) * index),
+                            op_count: op_count + 1,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    } else {
+                        // finished
+                        *state = Cow::Owned(MyRegisterActorState::MixedClient {
+                            awaiting: None,
+                            op_count: *op_count,
+                            abstracted: self.abstracts_clients(),
+                        });
+                    }
+                }
+                MyRegisterMsg::PutOk(_, _) => {}
+                MyRegisterMsg::DeleteOk(_) => {}
+                MyRegisterMsg::GetAllOk(_, _) => {}
+                MyRegisterMsg::GetOk(_, _) => {}
+                MyRegisterMsg::ListOk(_, _) => {}
+                MyRegisterMsg::Put(_, _, _) => {}
+                MyRegisterMsg::PutAfter(_, _, _, _) => {}
+                MyRegisterMsg::Get(_, _) => {}
+                MyRegisterMsg::Delete(_, _) => {}
+                MyRegisterMsg::Truncate(_) => {}
+                MyRegisterMsg::GetAll(_, _) => {}
+                MyRegisterMsg::PutResolved(_, _, _, _) => {}
+                MyRegisterMsg::Subscribe(_) => {}
+                MyRegisterMsg::Siblings(_, _) => {}
+                MyRegisterMsg::Internal(_) => {}
+                MyRegisterMsg::Token(_) => {}
+                MyRegisterMsg::Add(_, _) => {}
+                MyRegisterMsg::Remove(_, _) => {}
+                MyRegisterMsg::Contains(_, _) => {}
+                MyRegisterMsg::ContainsOk(_, _) => {}
+                MyRegisterMsg::Increment(_) => {}
+                MyRegisterMsg::Decrement(_) => {}
+                MyRegisterMsg::Read(_) => {}
+                MyRegisterMsg::CountOk(_, _) => {}
+                MyRegisterMsg::WriteRegister(_, _) => {}
+                MyRegisterMsg::ReadRegister(_) => {}
+                MyRegisterMsg::Enable(_) => {}
+                MyRegisterMsg::Disable(_) => {}
+                MyRegisterMsg::ReadFlag(_) => {}
+                MyRegisterMsg::FlagOk(_, _) => {}
+                MyRegisterMsg::Insert(_, _, _) => {}
+                MyRegisterMsg::DeleteAt(_, _) => {}
+                MyRegisterMsg::ReadList(_) => {}
+                MyRegisterMsg::SetPath(_, _, _) => {}
+                MyRegisterMsg::DeletePath(_, _) => {}
+                MyRegisterMsg::GetPath(_, _) => {}
+            },
+            (A::MixedClient { .. }, S::MixedClient { awaiting: None, .. }) => {}
             (A::Server(server_actor), S::Server(server_state)) => {
                 let mut server_state = Cow::Borrowed(server_state);
                 let mut server_out = Out::new();
@@ -391,15 +3460,214 @@ where
             (A::Server(_), S::DeleteClient { .. }) => {}
             (A::PutClient { .. }, S::Server(_)) => {}
             (A::DeleteClient { .. }, S::Server(_)) => {}
+            (A::Server(_), S::ResolveClient { .. }) => {}
+            (A::PutClient { .. }, S::ResolveClient { .. }) => {}
+            (A::DeleteClient { .. }, S::ResolveClient { .. }) => {}
+            (A::ResolveClient { .. }, S::Server(_)) => {}
+            (A::ResolveClient { .. }, S::PutClient { .. }) => {}
+            (A::ResolveClient { .. }, S::DeleteClient { .. }) => {}
+            (A::ReadClient { .. }, S::Server(_)) => {}
+            (A::ReadClient { .. }, S::PutClient { .. }) => {}
+            (A::ReadClient { .. }, S::DeleteClient { .. }) => {}
+            (A::ReadClient { .. }, S::ResolveClient { .. }) => {}
+            (A::Server(_), S::ReadClient { .. }) => {}
+            (A::PutClient { .. }, S::ReadClient { .. }) => {}
+            (A::DeleteClient { .. }, S::ReadClient { .. }) => {}
+            (A::ResolveClient { .. }, S::ReadClient { .. }) => {}
+            (A::SetClient { .. }, S::Server(_)) => {}
+            (A::SetClient { .. }, S::PutClient { .. }) => {}
+            (A::SetClient { .. }, S::DeleteClient { .. }) => {}
+            (A::SetClient { .. }, S::ResolveClient { .. }) => {}
+            (A::SetClient { .. }, S::ReadClient { .. }) => {}
+            (A::Server(_), S::SetClient { .. }) => {}
+            (A::PutClient { .. }, S::SetClient { .. }) => {}
+            (A::DeleteClient { .. }, S::SetClient { .. }) => {}
+            (A::ResolveClient { .. }, S::SetClient { .. }) => {}
+            (A::ReadClient { .. }, S::SetClient { .. }) => {}
+            (A::CounterClient { .. }, S::Server(_)) => {}
+            (A::CounterClient { .. }, S::PutClient { .. }) => {}
+            (A::CounterClient { .. }, S::DeleteClient { .. }) => {}
+            (A::CounterClient { .. }, S::ResolveClient { .. }) => {}
+            (A::CounterClient { .. }, S::ReadClient { .. }) => {}
+            (A::CounterClient { .. }, S::SetClient { .. }) => {}
+            (A::Server(_), S::CounterClient { .. }) => {}
+            (A::PutClient { .. }, S::CounterClient { .. }) => {}
+            (A::DeleteClient { .. }, S::CounterClient { .. }) => {}
+            (A::ResolveClient { .. }, S::CounterClient { .. }) => {}
+            (A::ReadClient { .. }, S::CounterClient { .. }) => {}
+            (A::SetClient { .. }, S::CounterClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::Server(_)) => {}
+            (A::MvRegisterClient { .. }, S::PutClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::DeleteClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::ResolveClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::ReadClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::SetClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::CounterClient { .. }) => {}
+            (A::Server(_), S::MvRegisterClient { .. }) => {}
+            (A::PutClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::DeleteClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::ResolveClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::ReadClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::SetClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::CounterClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::Server(_)) => {}
+            (A::ListInsertClient { .. }, S::PutClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::DeleteClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::ResolveClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::ReadClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::SetClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::CounterClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::Server(_), S::ListInsertClient { .. }) => {}
+            (A::PutClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::DeleteClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::ResolveClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::ReadClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::SetClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::CounterClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::Server(_)) => {}
+            (A::ListDeleteClient { .. }, S::PutClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::DeleteClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::ResolveClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::ReadClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::SetClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::CounterClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::Server(_), S::ListDeleteClient { .. }) => {}
+            (A::PutClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::DeleteClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::ResolveClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::ReadClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::SetClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::CounterClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::NestedClient { .. }, S::Server(_)) => {}
+            (A::NestedClient { .. }, S::PutClient { .. }) => {}
+            (A::NestedClient { .. }, S::DeleteClient { .. }) => {}
+            (A::NestedClient { .. }, S::ResolveClient { .. }) => {}
+            (A::NestedClient { .. }, S::ReadClient { .. }) => {}
+            (A::NestedClient { .. }, S::SetClient { .. }) => {}
+            (A::NestedClient { .. }, S::CounterClient { .. }) => {}
+            (A::NestedClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::NestedClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::NestedClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::Server(_), S::NestedClient { .. }) => {}
+            (A::PutClient { .. }, S::NestedClient { .. }) => {}
+            (A::DeleteClient { .. }, S::NestedClient { .. }) => {}
+            (A::ResolveClient { .. }, S::NestedClient { .. }) => {}
+            (A::ReadClient { .. }, S::NestedClient { .. }) => {}
+            (A::SetClient { .. }, S::NestedClient { .. }) => {}
+            (A::CounterClient { .. }, S::NestedClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::NestedClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::NestedClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::NestedClient { .. }) => {}
+            (A::GetClient { .. }, S::Server(_)) => {}
+            (A::GetClient { .. }, S::PutClient { .. }) => {}
+            (A::GetClient { .. }, S::DeleteClient { .. }) => {}
+            (A::GetClient { .. }, S::ResolveClient { .. }) => {}
+            (A::GetClient { .. }, S::ReadClient { .. }) => {}
+            (A::GetClient { .. }, S::SetClient { .. }) => {}
+            (A::GetClient { .. }, S::CounterClient { .. }) => {}
+            (A::GetClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::GetClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::GetClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::GetClient { .. }, S::NestedClient { .. }) => {}
+            (A::Server(_), S::GetClient { .. }) => {}
+            (A::PutClient { .. }, S::GetClient { .. }) => {}
+            (A::DeleteClient { .. }, S::GetClient { .. }) => {}
+            (A::ResolveClient { .. }, S::GetClient { .. }) => {}
+            (A::ReadClient { .. }, S::GetClient { .. }) => {}
+            (A::SetClient { .. }, S::GetClient { .. }) => {}
+            (A::CounterClient { .. }, S::GetClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::GetClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::GetClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::GetClient { .. }) => {}
+            (A::NestedClient { .. }, S::GetClient { .. }) => {}
+            (A::MixedClient { .. }, S::Server(_)) => {}
+            (A::MixedClient { .. }, S::PutClient { .. }) => {}
+            (A::MixedClient { .. }, S::DeleteClient { .. }) => {}
+            (A::MixedClient { .. }, S::ResolveClient { .. }) => {}
+            (A::MixedClient { .. }, S::ReadClient { .. }) => {}
+            (A::MixedClient { .. }, S::SetClient { .. }) => {}
+            (A::MixedClient { .. }, S::CounterClient { .. }) => {}
+            (A::MixedClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::MixedClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::MixedClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::MixedClient { .. }, S::NestedClient { .. }) => {}
+            (A::MixedClient { .. }, S::GetClient { .. }) => {}
+            (A::Server(_), S::MixedClient { .. }) => {}
+            (A::PutClient { .. }, S::MixedClient { .. }) => {}
+            (A::DeleteClient { .. }, S::MixedClient { .. }) => {}
+            (A::ResolveClient { .. }, S::MixedClient { .. }) => {}
+            (A::ReadClient { .. }, S::MixedClient { .. }) => {}
+            (A::SetClient { .. }, S::MixedClient { .. }) => {}
+            (A::CounterClient { .. }, S::MixedClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::MixedClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::MixedClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::MixedClient { .. }) => {}
+            (A::NestedClient { .. }, S::MixedClient { .. }) => {}
+            (A::GetClient { .. }, S::MixedClient { .. }) => {}
+            (A::FlagClient { .. }, S::Server(_)) => {}
+            (A::FlagClient { .. }, S::PutClient { .. }) => {}
+            (A::FlagClient { .. }, S::DeleteClient { .. }) => {}
+            (A::FlagClient { .. }, S::ResolveClient { .. }) => {}
+            (A::FlagClient { .. }, S::ReadClient { .. }) => {}
+            (A::FlagClient { .. }, S::SetClient { .. }) => {}
+            (A::FlagClient { .. }, S::CounterClient { .. }) => {}
+            (A::FlagClient { .. }, S::MvRegisterClient { .. }) => {}
+            (A::FlagClient { .. }, S::ListInsertClient { .. }) => {}
+            (A::FlagClient { .. }, S::ListDeleteClient { .. }) => {}
+            (A::FlagClient { .. }, S::NestedClient { .. }) => {}
+            (A::FlagClient { .. }, S::GetClient { .. }) => {}
+            (A::FlagClient { .. }, S::MixedClient { .. }) => {}
+            (A::Server(_), S::FlagClient { .. }) => {}
+            (A::PutClient { .. }, S::FlagClient { .. }) => {}
+            (A::DeleteClient { .. }, S::FlagClient { .. }) => {}
+            (A::ResolveClient { .. }, S::FlagClient { .. }) => {}
+            (A::ReadClient { .. }, S::FlagClient { .. }) => {}
+            (A::SetClient { .. }, S::FlagClient { .. }) => {}
+            (A::CounterClient { .. }, S::FlagClient { .. }) => {}
+            (A::MvRegisterClient { .. }, S::FlagClient { .. }) => {}
+            (A::ListInsertClient { .. }, S::FlagClient { .. }) => {}
+            (A::ListDeleteClient { .. }, S::FlagClient { .. }) => {}
+            (A::NestedClient { .. }, S::FlagClient { .. }) => {}
+            (A::GetClient { .. }, S::FlagClient { .. }) => {}
+            (A::MixedClient { .. }, S::FlagClient { .. }) => {}
+            // open-loop clients don't wait on responses
+            (
+                A::PutClient {
+                    client_loop: ClientLoop::Open,
+                    ..
+                },
+                S::PutClient { .. },
+            ) => {}
+            (
+                A::DeleteClient {
+                    client_loop: ClientLoop::Open,
+                    ..
+                },
+                S::DeleteClient { .. },
+            ) => {}
             (
                 A::PutClient {
                     put_count: _,
                     follow_up_gets: _,
                     server_count: _,
+                    client_loop: _,
+                    keys: _,
+                    token_to: _,
+                    retries: _,
+                    placement: _,
+                    abstracted: _,
                 },
                 S::PutClient {
                     awaiting: None,
                     op_count: _,
+                    last_write: _,
+                    abstracted: _,
                 },
             ) => {}
             (
@@ -407,10 +3675,17 @@ where
                     delete_count: _,
                     follow_up_gets: _,
                     server_count: _,
+                    client_loop: _,
+                    keys: _,
+                    retries: _,
+                    placement: _,
+                    truncate: _,
+                    abstracted: _,
                 },
                 S::DeleteClient {
                     awaiting: None,
                     op_count: _,
+                    abstracted: _,
                 },
             ) => {}
             (
@@ -418,10 +3693,17 @@ where
                     put_count: _,
                     follow_up_gets: _,
                     server_count: _,
+                    client_loop: _,
+                    keys: _,
+                    token_to: _,
+                    retries: _,
+                    placement: _,
+                    abstracted: _,
                 },
                 S::DeleteClient {
                     awaiting: _,
                     op_count: _,
+                    abstracted: _,
                 },
             ) => {}
             (
@@ -429,19 +3711,191 @@ where
                     delete_count: _,
                     follow_up_gets: _,
                     server_count: _,
+                    client_loop: _,
+                    keys: _,
+                    retries: _,
+                    placement: _,
+                    truncate: _,
+                    abstracted: _,
                 },
                 S::PutClient {
                     awaiting: _,
                     op_count: _,
+                    last_write: _,
+                    abstracted: _,
                 },
             ) => {}
         }
+
+        // closed-loop clients with retries time each request they wait on
+        if self.retries() && state.awaiting() != awaiting {
+            match state.awaiting() {
+                Some(_) => o.set_timer(RETRY_INTERVAL),
+                None => o.cancel_timer(),
+            }
+        }
     }
 
     fn on_timeout(&self, id: Id, state: &mut Cow<Self::State>, o: &mut Out<Self>) {
         use MyRegisterActor as A;
         use MyRegisterActorState as S;
         match (self, &**state) {
+            (
+                A::PutClient {
+                    put_count,
+                    follow_up_gets,
+                    server_count,
+                    client_loop: ClientLoop::Open,
+                    keys,
+                    token_to: _,
+                    retries: _,
+                    placement,
+                    abstracted: _,
+                },
+                S::PutClient {
+                    awaiting,
+                    op_count,
+                    last_write,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                let unique_request_id = (op_count + 1) * index;
+                if op_count < put_count {
+                    let value = put_value(index, *server_count, *op_count);
+                    let key = client_key(keys, index, *op_count);
+                    o.send(
+                        placement.route(key, index),
+                        MyRegisterMsg::Put(unique_request_id, key, value),
+                    );
+                } else if *follow_up_gets && op_count == put_count {
+                    let key = client_key(keys, index, *op_count - 1);
+                    o.send(
+                        placement.route(key, index),
+                        MyRegisterMsg::Get(unique_request_id, key),
+                    );
+                } else {
+                    return;
+                }
+                if op_count + 1 < *put_count || (*follow_up_gets && op_count < put_count) {
+                    o.set_timer(OPEN_LOOP_INTERVAL);
+                }
+                *state = Cow::Owned(MyRegisterActorState::PutClient {
+                    awaiting: *awaiting,
+                    op_count: op_count + 1,
+                    last_write: *last_write,
+                    abstracted: self.abstracts_clients(),
+                });
+            }
+            (
+                A::DeleteClient {
+                    delete_count,
+                    follow_up_gets,
+                    server_count: _,
+                    client_loop: ClientLoop::Open,
+                    keys,
+                    retries: _,
+                    placement,
+                    truncate,
+                    abstracted: _,
+                },
+                S::DeleteClient {
+                    awaiting,
+                    op_count,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                let unique_request_id = (op_count + 1) * index;
+                if op_count < delete_count {
+                    let key = client_key(keys, index, *op_count);
+                    o.send(
+                        placement.route(key, index),
+                        delete_request(*truncate, unique_request_id, key),
+                    );
+                } else if *follow_up_gets && op_count == delete_count {
+                    let key = client_key(keys, index, *op_count - 1);
+                    o.send(
+                        placement.route(key, index),
+                        MyRegisterMsg::Get(unique_request_id, key),
+                    );
+                } else {
+                    return;
+                }
+                if op_count + 1 < *delete_count || (*follow_up_gets && op_count < delete_count) {
+                    o.set_timer(OPEN_LOOP_INTERVAL);
+                }
+                *state = Cow::Owned(MyRegisterActorState::DeleteClient {
+                    awaiting: *awaiting,
+                    op_count: op_count + 1,
+                    abstracted: self.abstracts_clients(),
+                });
+            }
+            // resend the request being waited on, once
+            (
+                A::PutClient {
+                    put_count,
+                    server_count,
+                    client_loop: ClientLoop::Closed,
+                    keys,
+                    retries: true,
+                    placement,
+                    ..
+                },
+                S::PutClient {
+                    awaiting: Some(request_id),
+                    op_count,
+                    last_write: _,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                if op_count <= put_count {
+                    let value = put_value(index, *server_count, op_count - 1);
+                    let key = client_key(keys, index, op_count - 1);
+                    o.send(
+                        placement.route(key, index),
+                        MyRegisterMsg::Put(*request_id, key, value),
+                    );
+                } else {
+                    let key = client_key(keys, index, op_count - 2);
+                    o.send(
+                        placement.route(key, index),
+                        MyRegisterMsg::Get(*request_id, key),
+                    );
+                }
+            }
+            (
+                A::DeleteClient {
+                    delete_count,
+                    client_loop: ClientLoop::Closed,
+                    keys,
+                    retries: true,
+                    placement,
+                    truncate,
+                    ..
+                },
+                S::DeleteClient {
+                    awaiting: Some(request_id),
+                    op_count,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                if op_count <= delete_count {
+                    let key = client_key(keys, index, op_count - 1);
+                    o.send(
+                        placement.route(key, index),
+                        delete_request(*truncate, *request_id, key),
+                    );
+                } else {
+                    let key = client_key(keys, index, op_count - 2);
+                    o.send(
+                        placement.route(key, index),
+                        MyRegisterMsg::Get(*request_id, key),
+                    );
+                }
+            }
             (A::PutClient { .. }, S::PutClient { .. }) => {}
             (A::PutClient { .. }, S::DeleteClient { .. }) => {}
             (A::DeleteClient { .. }, S::DeleteClient { .. }) => {}
@@ -459,127 +3913,422 @@ where
             (A::Server(_), S::DeleteClient { .. }) => {}
             (A::PutClient { .. }, S::Server(_)) => {}
             (A::DeleteClient { .. }, S::Server(_)) => {}
+            (
+                A::GetClient {
+                    get_count,
+                    server_count: _,
+                    keys,
+                    placement,
+                    abstracted: _,
+                },
+                S::GetClient {
+                    op_count,
+                    reads,
+                    abstracted: _,
+                },
+            ) => {
+                let index: usize = id.into();
+                if op_count >= get_count {
+                    return;
+                }
+                let key = client_key(keys, index, *op_count);
+                o.send(
+                    placement.route(key, index),
+                    MyRegisterMsg::Get((op_count + 1) * index, key),
+                );
+                if op_count + 1 < *get_count {
+                    o.set_timer(OPEN_LOOP_INTERVAL);
+                }
+                *state = Cow::Owned(MyRegisterActorState::GetClient {
+                    op_count: op_count + 1,
+                    reads: reads.clone(),
+                    abstracted: self.abstracts_clients(),
+                });
+            }
+            (A::GetClient { .. }, _) => {}
+            (_, S::GetClient { .. }) => {}
+            // resolve, read, set, counter, register, flag, list, nested map and mixed clients don't
+            // set timers
+            (A::ResolveClient { .. }, _) => {}
+            (_, S::ResolveClient { .. }) => {}
+            (A::ReadClient { .. }, _) => {}
+            (_, S::ReadClient { .. }) => {}
+            (A::SetClient { .. }, _) => {}
+            (_, S::SetClient { .. }) => {}
+            (A::CounterClient { .. }, _) => {}
+            (_, S::CounterClient { .. }) => {}
+            (A::MvRegisterClient { .. }, _) => {}
+            (_, S::MvRegisterClient { .. }) => {}
+            (A::FlagClient { .. }, _) => {}
+            (_, S::FlagClient { .. }) => {}
+            (A::ListInsertClient { .. }, _) => {}
+            (_, S::ListInsertClient { .. }) => {}
+            (A::ListDeleteClient { .. }, _) => {}
+            (_, S::ListDeleteClient { .. }) => {}
+            (A::NestedClient { .. }, _) => {}
+            (_, S::NestedClient { .. }) => {}
+            (A::MixedClient { .. }, _) => {}
+            (_, S::MixedClient { .. }) => {}
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ModelCfg {
+    put_clients: usize,
+    delete_clients: usize,
+    /// Whether delete clients truncate the whole map rather than deleting a key.
+    truncate: bool,
+    /// Whether servers roll into a new epoch after each truncate.
+    epochs: bool,
+    /// Whether servers collect truncation metadata once it is causally stable.
+    gc: bool,
+    /// Whether each server crashes once and recovers from its last snapshot.
+    crash: bool,
+    /// What servers restart from when they crash.
+    restart: Restart,
+    /// Take snapshots after this many ops applied rather than on each client mutation.
+    snapshot_every: Option<usize>,
+    /// Whether each server is cut off from its peers once and then heals.
+    partitions: bool,
+    /// Whether the first server misbehaves.
+    byzantine: bool,
+    /// Servers, counted among `servers`, that only join once their timer fires.
+    late_joiners: usize,
+    /// Servers, counted among `servers`, that leave once their timer fires.
+    leavers: usize,
+    /// Whether servers join, leave and crash together, for the churn property.
+    churn: bool,
+    /// Clients that resolve the siblings of a key, added after the other clients.
+    resolve_clients: usize,
+    /// Whether resolve clients subscribe to conflicts rather than reading their key once.
+    conflict_notifications: bool,
+    /// Clients that read a key from every server holding it and merge the answers, added after
+    /// the resolve clients.
+    read_clients: usize,
+    /// Clients that add an element to the set and remove it again, added after the read
+    /// clients.
+    set_clients: usize,
+    /// Which set the servers hold for the set clients.
+    set_impl: SetImpl,
+    /// Whether set clients add the element again after removing it.
+    set_re_add: bool,
+    /// Clients that increment and decrement the counter and read it back, added after the set
+    /// clients.
+    counter_clients: usize,
+    /// Clients that write to the multi-value register and read its siblings, added after the
+    /// counter clients.
+    mv_register_clients: usize,
+    /// Which register the servers hold for the register clients.
+    register_impl: RegisterImpl,
+    /// Clients that enable the flag and read it back, added after the multi-value register
+    /// clients.
+    flag_enable_clients: usize,
+    /// Clients that disable the flag and read it back, added after the flag enable clients.
+    flag_disable_clients: usize,
+    /// Which way the servers' flags resolve concurrent enables and disables.
+    flag_bias: FlagBias,
+    /// Clients that insert into the list, added after the flag disable clients.
+    list_insert_clients: usize,
+    /// Clients that delete from the list, added after the list insert clients.
+    list_delete_clients: usize,
+    /// Clients that set a value in the nested map and delete the subtree it is in, added after
+    /// the list delete clients.
+    nested_clients: usize,
+    /// Clients that only get keys, added after the nested map clients.
+    get_clients: usize,
+    /// Clients that make the requests in `mix` in turn, added after the get clients.
+    mixed_clients: usize,
+    /// The puts, deletes and gets each mixed client makes, in order.
+    mix: Vec<MixedOp>,
+    /// How many servers hold each key when keys are sharded; all of them otherwise.
+    replication_factor: Option<usize>,
+    servers: usize,
+    follow_up_gets: bool,
+    /// Which keys clients operate on.
+    key_workload: KeyWorkload,
+    /// How each client paces its requests, put clients first; unlisted clients are closed-loop.
+    client_loops: Vec<ClientLoop>,
+    /// Deliveries that exploration must follow before branching out.
+    prefix: Trace,
+    /// Which paths liveness properties are checked over.
+    fairness: Fairness,
+    /// How servers reconcile their maps.
+    sync_mode: SyncMode,
+    /// How the network delivers messages.
+    network: NetworkKind,
+    /// Which properties to check.
+    suite: Suite,
+    /// Limits on the ops servers accept from their peers.
+    validation: Validation,
+    /// How far ahead of true time each server's wall clock runs; unlisted servers have no skew.
+    clock_skews: Vec<u32>,
+    /// Which clock the maps draw timestamps from.
+    clock: Clock,
+    /// How the maps resolve a set and a delete that are concurrent.
+    map_policy: MapPolicy,
+    /// Whether the first put client passes the version token of its first put to the second,
+    /// which makes a write that depends on it.
+    causal_tokens: bool,
+    /// Whether servers stamp ops on the map with vector clocks and deliver them in causal order.
+    causal_delivery: bool,
+    /// Whether servers gossip digests of their maps to repair them.
+    anti_entropy: bool,
+    /// Whether servers retransmit sets and deletes until their peers acknowledge them.
+    retransmit: bool,
+    /// Whether closed-loop clients resend unanswered requests.
+    retries: bool,
+    /// Whether servers answer retried requests without applying them again.
+    dedup: bool,
+    /// Whether to only explore one order of sync deliveries that commute.
+    reduction: bool,
+    /// Whether client progress is left out of state fingerprints.
+    abstract_clients: bool,
+    /// How stateright's checker searches the state space, when checking with it.
+    strategy: Option<Strategy>,
+    /// User-defined invariants checked as always properties.
+    invariants: Vec<Invariant>,
+    /// Properties that are meant to be violated, so that a check fails if they hold instead.
+    expected_violations: Vec<String>,
+    /// Directory to write the counterexamples of violated properties to, as trace files.
+    save_counterexamples: Option<PathBuf>,
+    /// Script defining extra properties.
+    #[cfg(feature = "scripting")]
+    script: Option<Arc<Script>>,
+}
+
+impl ModelCfg {
+    /// The keys that clients operate on.
+    fn workload_keys(&self) -> Vec<Key> {
+        self.key_workload.keys()
+    }
+
+    /// How the client with the given index (counting put clients first) paces its requests.
+    fn client_loop(&self, client: usize) -> ClientLoop {
+        self.client_loops
+            .get(client)
+            .copied()
+            .unwrap_or(ClientLoop::Closed)
+    }
+
+    /// How many servers clients send their requests to: the first ones, which neither join late
+    /// nor leave.
+    fn client_servers(&self) -> usize {
+        self.servers - self.late_joiners - self.leavers
+    }
+
+    /// What part the server with the given index plays when servers join and leave, if they do.
+    /// Leavers come after the servers clients send requests to, and late joiners last.
+    fn role(&self, server: usize) -> Option<Role> {
+        if self.late_joiners + self.leavers == 0 {
+            return None;
+        }
+        Some(if server < self.client_servers() {
+            Role::Member
+        } else if server < self.client_servers() + self.leavers {
+            Role::Leaver
+        } else {
+            Role::Joiner
+        })
+    }
+
+    /// Which servers hold each key.
+    fn placement(&self) -> Placement {
+        match self.replication_factor {
+            Some(replication) => Placement::sharded(self.servers, replication),
+            None => Placement::full(self.servers),
+        }
+    }
+
+    /// The server with the given index, sending its ops to the given peers.
+    fn server<M>(&self, i: usize, peers: Vec<Id>) -> Peer<M> {
+        Peer {
+            peers,
+            sync_mode: self.sync_mode,
+            validation: self.validation.clone(),
+            clock_skews: self.clock_skews.clone(),
+            clock: self.clock,
+            map_policy: self.map_policy,
+            causal_tokens: self.causal_tokens,
+            causal_delivery: self.causal_delivery,
+            anti_entropy: self.anti_entropy,
+            retransmit: self.retransmit,
+            retries: self.retries,
+            dedup: self.dedup,
+            conflict_notifications: self.conflict_notifications,
+            placement: self.placement(),
+            truncate: self.truncate,
+            epochs: self.epochs,
+            gc: self.gc,
+            crash: self.crash,
+            restart: self.restart,
+            snapshot_every: self.snapshot_every,
+            partitions: self.partitions,
+            byzantine: self.byzantine && i == 0,
+            set: self.set_clients > 0,
+            set_impl: self.set_impl,
+            counter: self.counter_clients > 0,
+            mv_register: self.mv_register_clients > 0,
+            register_impl: self.register_impl,
+            flag: self.flag_enable_clients + self.flag_disable_clients > 0,
+            flag_bias: self.flag_bias,
+            list: self.list_insert_clients + self.list_delete_clients > 0,
+            nested: self.nested_clients > 0,
+            membership: self.role(i).map(|role| {
+                let initial = 0..self.servers - self.late_joiners;
+                Membership::new(role, initial.filter(|j| *j != i).map(Id::from))
+            }),
+            _t: PhantomData::default(),
+        }
+    }
+
+    fn into_actor_model<M: Clone + Debug + PartialEq + Hash + Map>(self) -> RegisterModel<M> {
+        let keys = self.key_workload.schedule();
+        let placement = self.placement();
+        let mut model = ActorModel::new(self.clone(), History::default());
+        for i in 0..self.servers {
+            model = model.actor(MyRegisterActor::Server(
+                self.server(i, model_peers(i, self.servers)),
+            ))
+        }
+
+        for i in 0..self.put_clients {
+            model = model.actor(MyRegisterActor::PutClient {
+                put_count: 2,
+                follow_up_gets: self.follow_up_gets,
+                server_count: self.client_servers(),
+                client_loop: self.client_loop(i),
+                keys: keys.clone(),
+                // the first put client passes its token to the second
+                token_to: (self.causal_tokens && i == 0 && self.put_clients > 1)
+                    .then(|| Id::from(self.servers + 1)),
+                retries: self.retries,
+                placement,
+                abstracted: self.abstract_clients,
+            })
+        }
+
+        for i in 0..self.delete_clients {
+            model = model.actor(MyRegisterActor::DeleteClient {
+                delete_count: 2,
+                follow_up_gets: self.follow_up_gets,
+                server_count: self.client_servers(),
+                client_loop: self.client_loop(self.put_clients + i),
+                keys: keys.clone(),
+                retries: self.retries,
+                placement,
+                truncate: self.truncate,
+                abstracted: self.abstract_clients,
+            })
+        }
+
+        for i in 0..self.resolve_clients {
+            let index = self.servers + self.put_clients + self.delete_clients + i;
+            model = model.actor(MyRegisterActor::ResolveClient {
+                placement,
+                key: client_key(&keys, index, 0),
+                subscribe: self.conflict_notifications,
+                abstracted: self.abstract_clients,
+            })
+        }
+
+        for i in 0..self.read_clients {
+            let index =
+                self.servers + self.put_clients + self.delete_clients + self.resolve_clients + i;
+            model = model.actor(MyRegisterActor::ReadClient {
+                placement,
+                key: client_key(&keys, index, 0),
+                abstracted: self.abstract_clients,
+            })
+        }
+
+        for _ in 0..self.set_clients {
+            model = model.actor(MyRegisterActor::SetClient {
+                server_count: self.client_servers(),
+                re_add: self.set_re_add,
+                abstracted: self.abstract_clients,
+            })
+        }
+
+        for _ in 0..self.counter_clients {
+            model = model.actor(MyRegisterActor::CounterClient {
+                server_count: self.client_servers(),
+                abstracted: self.abstract_clients,
+            })
+        }
+
+        for _ in 0..self.mv_register_clients {
+            model = model.actor(MyRegisterActor::MvRegisterClient {
+                server_count: self.client_servers(),
+                abstracted: self.abstract_clients,
+            })
+        }
+
+        for _ in 0..self.flag_enable_clients {
+            model = model.actor(MyRegisterActor::FlagClient {
+                server_count: self.client_servers(),
+                enable: true,
+                abstracted: self.abstract_clients,
+            })
+        }
+
+        for _ in 0..self.flag_disable_clients {
+            model = model.actor(MyRegisterActor::FlagClient {
+                server_count: self.client_servers(),
+                enable: false,
+                abstracted: self.abstract_clients,
+            })
         }
-    }
-}
 
-struct ModelCfg {
-    put_clients: usize,
-    delete_clients: usize,
-    servers: usize,
-    follow_up_gets: bool,
-}
+        for _ in 0..self.list_insert_clients {
+            model = model.actor(MyRegisterActor::ListInsertClient {
+                server_count: self.client_servers(),
+                abstracted: self.abstract_clients,
+            })
+        }
 
-impl ModelCfg {
-    fn into_actor_model<M: Clone + Debug + PartialEq + Hash + Map>(
-        self,
-    ) -> ActorModel<MyRegisterActor<M>, (), ()> {
-        let mut model = ActorModel::new((), ());
-        for i in 0..self.servers {
-            model = model.actor(MyRegisterActor::Server(Peer {
-                peers: model_peers(i, self.servers),
-                _t: PhantomData::default(),
-            }))
+        for _ in 0..self.list_delete_clients {
+            model = model.actor(MyRegisterActor::ListDeleteClient {
+                server_count: self.client_servers(),
+                abstracted: self.abstract_clients,
+            })
         }
 
-        for _ in 0..self.put_clients {
-            model = model.actor(MyRegisterActor::PutClient {
-                put_count: 2,
-                follow_up_gets: self.follow_up_gets,
-                server_count: self.servers,
+        for _ in 0..self.nested_clients {
+            model = model.actor(MyRegisterActor::NestedClient {
+                server_count: self.client_servers(),
+                abstracted: self.abstract_clients,
             })
         }
 
-        for _ in 0..self.delete_clients {
-            model = model.actor(MyRegisterActor::DeleteClient {
-                delete_count: 2,
-                follow_up_gets: self.follow_up_gets,
-                server_count: self.servers,
+        for _ in 0..self.get_clients {
+            model = model.actor(MyRegisterActor::GetClient {
+                get_count: 2,
+                server_count: self.client_servers(),
+                keys: keys.clone(),
+                placement,
+                abstracted: self.abstract_clients,
             })
         }
 
-        model
-            .property(
-                stateright::Expectation::Eventually,
-                "all actors have the same value for all keys",
-                |_, state| all_same_state(&state.actor_states),
-            )
-            // only valid for broken one as conflicting values are retained in the fixed version
-            // .property(
-            //     stateright::Expectation::Always,
-            //     "only have one value for each key",
-            //     |_, state| only_one_of_each_key(&state.actor_states),
-            // )
-            .property(
-                stateright::Expectation::Always,
-                "in sync when syncing is done and no in-flight requests",
-                |_, state| syncing_done_and_in_sync(state),
-            )
-            .init_network(Network::new_ordered(vec![]))
-    }
-}
-
-fn all_same_state<M: Clone + Debug + PartialEq + Hash + Map>(
-    actors: &[Arc<MyRegisterActorState<M>>],
-) -> bool {
-    actors.windows(2).all(|w| match (&*w[0], &*w[1]) {
-        (MyRegisterActorState::PutClient { .. }, MyRegisterActorState::PutClient { .. }) => true,
-        (MyRegisterActorState::PutClient { .. }, MyRegisterActorState::DeleteClient { .. }) => true,
-        (MyRegisterActorState::PutClient { .. }, MyRegisterActorState::Server(_)) => true,
-        (MyRegisterActorState::DeleteClient { .. }, MyRegisterActorState::DeleteClient { .. }) => {
-            true
-        }
-        (MyRegisterActorState::DeleteClient { .. }, MyRegisterActorState::PutClient { .. }) => true,
-        (MyRegisterActorState::DeleteClient { .. }, MyRegisterActorState::Server(_)) => true,
-        (MyRegisterActorState::Server(_), MyRegisterActorState::PutClient { .. }) => true,
-        (MyRegisterActorState::Server(_), MyRegisterActorState::DeleteClient { .. }) => true,
-        (MyRegisterActorState::Server(a), MyRegisterActorState::Server(b)) => {
-            a.visible_values() == b.visible_values()
+        for _ in 0..self.mixed_clients {
+            model = model.actor(MyRegisterActor::MixedClient {
+                script: self.mix.clone(),
+                server_count: self.client_servers(),
+                keys: keys.clone(),
+                placement,
+                abstracted: self.abstract_clients,
+            })
         }
-    })
-}
 
-// fn only_one_of_each_key<M: Clone + Debug + PartialEq + Hash + Map>(
-//     actors: &[Arc<MyRegisterActorState<M>>],
-// ) -> bool {
-//     for actor in actors {
-//         if let MyRegisterActorState::Server(actor) = &**actor {
-//             let keys = actor
-//                 .values()
-//                 .into_iter()
-//                 .map(|(_, k, _)| k)
-//                 .collect::<HashSet<_>>();
-//             if keys.len() != actor.values().len() {
-//                 return false;
-//             }
-//         }
-//     }
-//     true
-// }
-
-fn syncing_done_and_in_sync<M: Clone + Debug + PartialEq + Hash + Map>(
-    state: &ActorModelState<MyRegisterActor<M>>,
-) -> bool {
-    // first check that the network has no sync messages in-flight.
-    for envelope in state.network.iter_deliverable() {
-        match envelope.msg {
-            MyRegisterMsg::Internal(PeerMsg::PutSync { .. }) => {
-                return true;
-            }
-            MyRegisterMsg::Internal(PeerMsg::DeleteSync { .. }) => {
-                return true;
-            }
-            MyRegisterMsg::Put(_, _, _)
-            | MyRegisterMsg::Get(_, _)
-            | MyRegisterMsg::Delete(_, _)
-            | MyRegisterMsg::PutOk(_)
-            | MyRegisterMsg::GetOk(_, _)
-            | MyRegisterMsg::DeleteOk(_) => {}
-        }
-    }
-
-    // next, check that all actors are in the same states (using sub-property checker)
-    all_same_state(&state.actor_states)
+        let actor_model = model
+            .init_network(self.network.network())
+            .lossy_network(self.network.lossy())
+            .record_msg_in(model::record_msg_in)
+            .record_msg_out(model::record_msg_out)
+            .within_boundary(|_, state| trace::follows_prefix(&state.history.prefix));
+        RegisterModel { actor_model }
+    }
 }
 
 #[derive(Parser)]
@@ -593,74 +4342,1135 @@ struct Opts {
     #[clap(long, short, global = true, default_value = "2")]
     delete_clients: usize,
 
+    /// Have delete clients truncate the whole map, removing every value their server has seen,
+    /// rather than deleting a key.
+    #[clap(long, global = true)]
+    truncate: bool,
+
+    /// Have servers roll into a new epoch after each truncate, agreeing on it with their peers,
+    /// so they can forget the sets from old epochs.
+    #[clap(long, global = true)]
+    epochs: bool,
+
+    /// Have servers forget the sets they remember for truncates once the sets are causally
+    /// stable, delivered at every server, exchanging their vector clocks to tell.
+    #[clap(long, global = true)]
+    gc: bool,
+
+    /// Have each server crash once at an arbitrary point and recover from the snapshot it took
+    /// after its last client mutation, losing the ops from peers applied since.
+    #[clap(long, global = true)]
+    crash: bool,
+
+    /// What servers restart from with `--crash`: their last snapshot, or their initial state as
+    /// if their disk was lost too.
+    #[clap(long, arg_enum, global = true, default_value = "snapshot")]
+    restart: Restart,
+
+    /// With `--crash`, have servers snapshot their state after every this many ops they apply,
+    /// whether from clients or peers, rather than whenever they handle a client's mutation.
+    #[clap(long, global = true)]
+    snapshot_every: Option<usize>,
+
+    /// Have each server be cut off from the other servers once at an arbitrary point, and heal
+    /// at an arbitrary later point. Messages between them wait in the network meanwhile.
+    #[clap(long, global = true)]
+    partitions: bool,
+
+    /// Have the first server misbehave: it sends its other peers the sets and deletes it
+    /// receives again along with forged copies of the sets, and follows each of its own sets
+    /// with a conflicting one under the same timestamp.
+    #[clap(long, global = true)]
+    byzantine: bool,
+
+    /// How many of the servers only join at an arbitrary point, catching up by merging in the
+    /// maps of the servers they join. Clients don't send requests to them.
+    #[clap(long, global = true, default_value = "0")]
+    late_joiners: usize,
+
+    /// How many of the servers leave at an arbitrary point, after which the others stop sending
+    /// them ops. Clients don't send requests to them.
+    #[clap(long, global = true, default_value = "0")]
+    leavers: usize,
+
+    /// Have servers join, leave and crash throughout the run, repairing what crashes lose by
+    /// anti-entropy, and check that the servers that stay converge. Implies at least one late
+    /// joiner and one leaver, `--crash` and `--anti-entropy`.
+    #[clap(long, global = true)]
+    churn: bool,
+
     #[clap(long, short, global = true, default_value = "2")]
     servers: usize,
 
+    /// Clients that read all siblings of a key and write back a resolution over them.
+    #[clap(long, global = true, default_value = "0")]
+    resolve_clients: usize,
+
+    /// Have resolve clients subscribe to their key and resolve each conflict their server
+    /// notifies them of.
+    #[clap(long, global = true)]
+    conflict_notifications: bool,
+
+    /// Clients that read a key from every server holding it and merge the answers.
+    #[clap(long, global = true, default_value = "0")]
+    read_clients: usize,
+
+    /// Clients that add an element to a set held by the servers, remove it again and check
+    /// whether it is still there.
+    #[clap(long, global = true, default_value = "0")]
+    set_clients: usize,
+
+    /// Which set the servers hold for `--set-clients`: the observed-remove set, or the
+    /// grow-only or two-phase set it improves on.
+    #[clap(long, arg_enum, global = true, default_value = "or")]
+    set_impl: SetImpl,
+
+    /// Have set clients add the element again after removing it, before checking whether the
+    /// set holds it.
+    #[clap(long, global = true)]
+    set_re_add: bool,
+
+    /// Clients that increment and decrement a state-based counter held by the servers and read
+    /// it back.
+    #[clap(long, global = true, default_value = "0")]
+    counter_clients: usize,
+
+    /// Clients that write to a multi-value register held by the servers and read back its
+    /// siblings.
+    #[clap(long, global = true, default_value = "0")]
+    mv_register_clients: usize,
+
+    /// Which register the servers hold for `--mv-register-clients`: the multi-value register,
+    /// or a last-writer-wins register to compare it with.
+    #[clap(long, arg_enum, global = true, default_value = "mv")]
+    register_impl: RegisterImpl,
+
+    /// Clients that enable a flag held by the servers and read it back.
+    #[clap(long, global = true, default_value = "0")]
+    flag_enable_clients: usize,
+
+    /// Clients that disable the flag held by the servers and read it back.
+    #[clap(long, global = true, default_value = "0")]
+    flag_disable_clients: usize,
+
+    /// Which way the servers' flag resolves an enable and a disable that are concurrent.
+    #[clap(long, arg_enum, global = true, default_value = "enable-wins")]
+    flag_bias: FlagBias,
+
+    /// Clients that insert two values into a list held by the servers and read it back.
+    #[clap(long, global = true, default_value = "0")]
+    list_insert_clients: usize,
+
+    /// Clients that delete the first element of the list held by the servers and read it back.
+    #[clap(long, global = true, default_value = "0")]
+    list_delete_clients: usize,
+
+    /// Clients that set a value inside a nested map held by the servers, delete the subtree it
+    /// is in and read back what is left of it.
+    #[clap(long, global = true, default_value = "0")]
+    nested_clients: usize,
+
+    /// Clients that only get keys, every so often without waiting on responses.
+    #[clap(long, global = true, default_value = "0")]
+    get_clients: usize,
+
+    /// Clients that each make the requests in `--mix` in turn, waiting for each response.
+    #[clap(long, global = true, default_value = "0")]
+    mixed_clients: usize,
+
+    /// The requests mixed clients make, either in order, e.g. `put,delete,put,get`, or as a
+    /// ratio spread out over a schedule, e.g. `put=5,delete=3,get=2`.
+    #[clap(long, global = true, default_value = "put,delete,put,get")]
+    mix: String,
+
+    /// Shard keys across the servers, each held and synced by this many of them.
+    #[clap(long, global = true)]
+    replication_factor: Option<usize>,
+
     #[clap(long, global = true)]
     follow_up_gets: bool,
 
-    /// Use the broken map.
+    /// How each client paces its requests, given once per client with put clients first.
+    /// Clients without a mode wait for each response (closed loop).
+    #[clap(long = "client-loop", arg_enum, global = true)]
+    client_loops: Vec<ClientLoop>,
+
+    /// Spread requests evenly over this many keys (`a`, `b`, ...), each client cycling through
+    /// them from its own starting point.
+    #[clap(long, global = true, conflicts_with_all = &["key_weights", "zipf_keys"])]
+    keys: Option<usize>,
+
+    /// Relative popularity of the keys clients operate on, e.g. `a=3,b=1`.
+    #[clap(long, global = true, conflicts_with = "zipf_keys")]
+    key_weights: Option<String>,
+
+    /// Spread requests over this many keys (`a`, `b`, ...) with Zipfian popularity.
+    #[clap(long, global = true)]
+    zipf_keys: Option<usize>,
+
+    /// Exponent of the Zipfian key popularity; higher means hotter hot keys.
+    #[clap(long, global = true, default_value = "1.0")]
+    zipf_exponent: f64,
+
+    /// Which map implementation to check.
+    #[clap(long, arg_enum, global = true, default_value = "fixed")]
+    map_impl: MapImpl,
+
+    /// Use the broken map, short for `--map-impl broken`.
     #[clap(long, global = true)]
     broken: bool,
+
+    /// Use the last-writer-wins map, which timestamps ops with wall clocks, short for
+    /// `--map-impl lww`.
+    #[clap(long, global = true, conflicts_with = "broken")]
+    lww: bool,
+
+    /// How many ticks ahead of true time each server's wall clock runs, given once per server.
+    /// Only the last-writer-wins map uses wall clocks.
+    #[clap(long = "clock-skew", global = true)]
+    clock_skews: Vec<u32>,
+
+    /// Which clock the maps draw the counters of their timestamps from, to compare how they
+    /// order concurrent sets. The broken map only has its Lamport clock.
+    #[clap(long, arg_enum, global = true, default_value = "lamport")]
+    clock: Clock,
+
+    /// How the map resolves a set and a delete that are concurrent, where both removed a value
+    /// neither had seen the other remove. Only the fixed map can have removes win.
+    #[clap(long, arg_enum, global = true, default_value = "add-wins")]
+    map_policy: MapPolicy,
+
+    /// Have the first put client pass the version token of its first put to the second put
+    /// client, which then makes a write that must only be visible after it.
+    #[clap(long, global = true)]
+    causal_tokens: bool,
+
+    /// Stamp ops on the map with the vector clock of the server that prepared them, and have
+    /// peers buffer ops until the ops they causally depend on have been delivered, dropping
+    /// copies of ops already delivered.
+    #[clap(long, global = true)]
+    causal_delivery: bool,
+
+    /// Have servers repeatedly push a digest of the values their map holds and has removed to
+    /// each of their peers in turn after applying an op, with peers replying with the ops the
+    /// server is missing, until no peer has anything new.
+    #[clap(long, global = true)]
+    anti_entropy: bool,
+
+    /// Have peers acknowledge each set and delete they receive, and servers send the sets and
+    /// deletes that peers are yet to acknowledge again whenever a timer fires.
+    #[clap(long, global = true)]
+    retransmit: bool,
+
+    /// Have closed-loop clients resend a request once if its response hasn't arrived when a
+    /// timer fires.
+    #[clap(long, global = true)]
+    retries: bool,
+
+    /// Apply retried requests again rather than answering them with their original response.
+    #[clap(long, global = true)]
+    no_dedup: bool,
+
+    /// Report every violated property rather than stopping at the first.
+    #[clap(long, global = true)]
+    keep_going: bool,
+
+    /// A property that is meant to be violated, e.g. convergence with `--broken`: its
+    /// counterexample is printed and the check only fails if it holds.
+    #[clap(long = "expect-violation", global = true)]
+    expected_violations: Vec<String>,
+
+    /// Write the counterexample of each unexpectedly violated property to a JSON trace file in
+    /// this directory, which `replay` and `--resume-from` take.
+    #[clap(long, global = true)]
+    save_counterexamples: Option<PathBuf>,
+
+    /// Only explore states reachable after following the deliveries in this trace file.
+    #[clap(long, global = true)]
+    resume_from: Option<PathBuf>,
+
+    /// Fairness assumed when checking liveness properties.
+    #[clap(long, arg_enum, global = true, default_value = "weak-delivery")]
+    fairness: Fairness,
+
+    /// Stop checking after this many seconds and report a bounded result.
+    #[clap(long, global = true)]
+    time_limit: Option<u64>,
+
+    /// Don't explore beyond this many steps from the initial states, reporting a bounded result
+    /// and exiting with status 3 if the limit was reached.
+    #[clap(long, global = true)]
+    depth_limit: Option<usize>,
+
+    /// Stop checking after exploring this many states, reporting a bounded result and exiting
+    /// with status 3 if the limit was reached.
+    #[clap(long, global = true)]
+    max_states: Option<usize>,
+
+    /// Keep the visited states in a file in this directory rather than in memory, exploring
+    /// on a single thread. Only `check-bfs` and `check-guided` support this.
+    #[clap(
+        long,
+        global = true,
+        conflicts_with_all = &["shape", "memory", "audit_commutativity", "report"]
+    )]
+    disk_visited: Option<PathBuf>,
+
+    /// Keep the visited states in a Bloom filter sized for this many states, trading exact
+    /// results for memory. Only `check-bfs` and `check-guided` support this.
+    #[clap(long, global = true, conflicts_with_all = &["disk_visited", "shape", "memory", "audit_commutativity", "report"])]
+    bloom: Option<u64>,
+
+    /// False positive rate of the `--bloom` filter once it holds the expected states.
+    #[clap(long, global = true, default_value = "0.001")]
+    bloom_fp_rate: f64,
+
+    /// How many visited fingerprints to cache in memory with `--disk-visited`.
+    #[clap(long, global = true, default_value = "1000000")]
+    visited_cache: usize,
+
+    /// Report the shape of the state space (states per depth, branching, most reached states).
+    #[clap(long, global = true)]
+    shape: bool,
+
+    /// Report the approximate memory taken by the visited states, by component.
+    #[clap(long, global = true)]
+    memory: bool,
+
+    /// Check that every pair of concurrent ops in flight to a server commutes, and report the
+    /// pairs that don't.
+    #[clap(long, global = true)]
+    audit_commutativity: bool,
+
+    /// Ignore client progress when deduplicating states, for server-focused properties.
+    #[clap(long, global = true)]
+    abstract_clients: bool,
+
+    /// How servers reconcile their maps.
+    #[clap(long, arg_enum, global = true, default_value = "op")]
+    sync_mode: SyncMode,
+
+    /// How the network delivers messages: in order, in any order, any number of times, or
+    /// possibly not at all.
+    #[clap(long, arg_enum, global = true, default_value = "ordered")]
+    network: NetworkKind,
+
+    /// Which properties to check.
+    #[clap(long, arg_enum, global = true, default_value = "default")]
+    suite: Suite,
+
+    /// Reject ops with counters this far ahead of the highest counter the receiving server has
+    /// seen.
+    #[clap(long, global = true, default_value = "16")]
+    max_counter_gap: u32,
+
+    /// Explore every order of sync deliveries, even those that commute.
+    #[clap(long, global = true)]
+    no_reduction: bool,
+
+    /// Write a markdown report of the check to this file.
+    #[clap(long, global = true)]
+    report: Option<PathBuf>,
+
+    /// An invariant over the servers' states to check, e.g. `forall s: s.len() <= 3`.
+    #[clap(long = "invariant", global = true)]
+    invariants: Vec<String>,
+
+    /// Rhai script defining extra properties.
+    #[cfg(feature = "scripting")]
+    #[clap(long, global = true)]
+    script: Option<PathBuf>,
 }
 
 #[derive(clap::Subcommand)]
 enum SubCmd {
     Serve,
+    /// Check every state with stateright's checker.
+    Check {
+        /// How to search the state space.
+        #[clap(long, arg_enum, default_value = "dfs")]
+        strategy: Strategy,
+    },
     CheckDfs,
     CheckBfs,
+    /// Check on a single thread, exploring the states a heuristic scores highest first, to find
+    /// convergence violations sooner.
+    CheckGuided {
+        /// How to score states.
+        #[clap(long, arg_enum, default_value = "divergence")]
+        heuristic: Heuristic,
+    },
+    /// Check breadth first across worker processes started with `swarm-worker` and the same
+    /// options, partitioning the states between them by fingerprint.
+    Swarm {
+        /// Address to listen for workers on.
+        #[clap(long, default_value = "127.0.0.1:7878")]
+        listen: String,
+        /// Number of workers to wait for before checking.
+        #[clap(long, default_value = "2")]
+        workers: usize,
+    },
+    /// Explore states for a `swarm` coordinator.
+    SwarmWorker {
+        /// Address of the coordinator.
+        #[clap(long, default_value = "127.0.0.1:7878")]
+        coordinator: String,
+    },
+    /// Run a server for real, exchanging messages with its peers over UDP.
+    Run {
+        /// Address to listen on, which also names the server to its peers.
+        #[clap(long)]
+        listen: SocketAddrV4,
+        /// Addresses of the other servers.
+        #[clap(long)]
+        peers: Vec<SocketAddrV4>,
+        #[clap(flatten)]
+        frontends: node::Frontends,
+    },
+    /// Check random walks through the model rather than every state, to look for violations in
+    /// configurations too large to check exhaustively.
+    Simulate {
+        /// Seed for choosing the walks; simulations with the same seed and options are
+        /// identical.
+        #[clap(long, default_value = "0")]
+        seed: u64,
+        /// Number of walks to take.
+        #[clap(long, default_value = "1000")]
+        runs: usize,
+        /// Most steps to take in each walk.
+        #[clap(long, default_value = "100")]
+        max_depth: usize,
+    },
+    /// Step through the model interactively, choosing each action from stdin.
+    Debug,
+    /// Check breadth first for a bounded time (`--time-limit`, default 10 seconds) and report
+    /// the time spent evaluating each property and hashing each part of the states.
+    Profile,
+    /// Check the servers on a single key with stateright's register clients and linearizability
+    /// tester.
+    CheckRegister,
+    /// Replay a trace against both maps and report where their visible values first differ.
+    Compare {
+        /// Trace file of deliveries to replay.
+        trace: PathBuf,
+    },
+    /// Replay a trace and print how each delivery changes the actors' states.
+    Replay {
+        /// Trace file of deliveries to replay, such as a saved counterexample.
+        trace: PathBuf,
+    },
+    /// Replay a trace and print the ops the servers generated as JSON Lines.
+    ExportOps {
+        /// Trace file of deliveries to replay.
+        trace: PathBuf,
+    },
+    /// Apply a JSON list of ops to a fresh map and print the resulting state.
+    ApplyOps {
+        /// File with the ops to apply.
+        ops: PathBuf,
+        /// Print each change to the resolved value of this key as the ops are applied.
+        #[clap(long)]
+        watch: Vec<char>,
+    },
+    /// Replay a trace and print the causal history of ops on a key as a DOT graph.
+    Dag {
+        /// Trace file of deliveries to replay.
+        trace: PathBuf,
+        /// Key to show the ops for.
+        #[clap(long, default_value = "k")]
+        key: char,
+    },
+    /// Experiment with replicas of the map by hand, setting, deleting and syncing them with
+    /// commands from stdin.
+    Repl {
+        /// Number of replicas.
+        #[clap(long, default_value = "3")]
+        replicas: usize,
+    },
+    /// Simulate many replicas of the map in virtual time, without the checker, and report how
+    /// long ops take to converge and how the metadata grows.
+    ScaleSim {
+        /// Number of replicas.
+        #[clap(long, default_value = "1000")]
+        replicas: usize,
+        /// Number of client ops to issue, on the keys from the key workload options.
+        #[clap(long, default_value = "1000")]
+        ops: usize,
+        /// Seed for the scheduler; runs with the same seed and options are identical.
+        #[clap(long, default_value = "0")]
+        seed: u64,
+        /// Least virtual milliseconds for a message to arrive.
+        #[clap(long, default_value = "1")]
+        min_latency: u64,
+        /// Most virtual milliseconds for a message to arrive.
+        #[clap(long, default_value = "50")]
+        max_latency: u64,
+        /// Virtual milliseconds between client ops.
+        #[clap(long, default_value = "1")]
+        op_interval: u64,
+    },
+}
+
+impl SubCmd {
+    /// The search strategy of subcommands that check with stateright's checker.
+    fn strategy(&self) -> Option<Strategy> {
+        match self {
+            SubCmd::Check { strategy } => Some(*strategy),
+            SubCmd::CheckDfs => Some(Strategy::Dfs),
+            SubCmd::CheckBfs => Some(Strategy::Bfs),
+            _ => None,
+        }
+    }
 }
 
 fn main() {
-    let opts = Opts::parse();
-
-    if opts.broken {
-        let model = ModelCfg {
-            put_clients: opts.put_clients,
-            delete_clients: opts.delete_clients,
-            servers: opts.servers,
-            follow_up_gets: opts.follow_up_gets,
-        }
-        .into_actor_model::<BrokenMap>()
-        .checker()
-        .threads(num_cpus::get());
-        run(opts, model)
+    let mut opts = Opts::parse();
+    if opts.churn {
+        opts.late_joiners = opts.late_joiners.max(1);
+        opts.leavers = opts.leavers.max(1);
+        opts.crash = true;
+        opts.anti_entropy = true;
+        if opts.late_joiners + opts.leavers >= opts.servers {
+            eprintln!("--churn needs a server for clients besides the joiners and leavers");
+            std::process::exit(2)
+        }
+    }
+
+    if let Some(replication) = opts.replication_factor {
+        if replication == 0 || replication > opts.servers {
+            eprintln!("--replication-factor must be between 1 and the number of servers");
+            std::process::exit(2)
+        }
+        // have/need announces every change a server has and pulls are answered with every op
+        // the puller is missing, either of which would leak keys across shards
+        if opts.sync_mode != SyncMode::Op {
+            eprintln!("--replication-factor needs --sync-mode op");
+            std::process::exit(2)
+        }
+        // truncates remove values on every shard
+        if opts.truncate {
+            eprintln!("--replication-factor can't be used with --truncate");
+            std::process::exit(2)
+        }
+    }
+
+    if opts.epochs && !opts.truncate {
+        eprintln!("--epochs needs --truncate");
+        std::process::exit(2)
+    }
+    // stability is told from the clocks stamped on ops, and writes held back for the ops they
+    // depend on, or lost in a crash, would be applied after their sets are thought stable
+    if opts.gc && (!opts.truncate || !opts.causal_delivery || opts.causal_tokens || opts.crash) {
+        eprintln!(
+            "--gc needs --truncate and --causal-delivery, and can't be used with --causal-tokens \
+             or --crash"
+        );
+        std::process::exit(2)
+    }
+    // the markers closing an epoch rely on each server sending its own ops to its peers
+    if opts.epochs && opts.sync_mode != SyncMode::Op {
+        eprintln!("--epochs needs --sync-mode op");
+        std::process::exit(2)
+    }
+    // merged states and deltas don't carry what truncates removed or which writes clients
+    // depend on
+    if matches!(opts.sync_mode, SyncMode::State | SyncMode::Delta)
+        && (opts.truncate || opts.causal_tokens)
+    {
+        eprintln!("--sync-mode state and delta can't be used with --truncate or --causal-tokens");
+        std::process::exit(2)
+    }
+    // only broadcast ops are stamped, and every op from each origin has to reach every peer
+    if opts.causal_delivery
+        && (opts.sync_mode != SyncMode::Op || opts.replication_factor.is_some() || opts.epochs)
+    {
+        eprintln!(
+            "--causal-delivery needs --sync-mode op, and can't be used with --replication-factor \
+             or --epochs"
+        );
+        std::process::exit(2)
+    }
+    // digests cover every key, and repaired sets don't carry the writes they depend on
+    if opts.anti_entropy
+        && (opts.sync_mode != SyncMode::Op
+            || opts.replication_factor.is_some()
+            || opts.truncate
+            || opts.epochs
+            || opts.causal_tokens
+            || opts.causal_delivery)
+    {
+        eprintln!(
+            "--anti-entropy needs --sync-mode op, and can't be used with --replication-factor, \
+             --truncate, --epochs, --causal-tokens or --causal-delivery"
+        );
+        std::process::exit(2)
+    }
+    // only plain sets and deletes are acknowledged
+    if opts.retransmit
+        && (opts.sync_mode != SyncMode::Op
+            || opts.truncate
+            || opts.epochs
+            || opts.causal_tokens
+            || opts.causal_delivery)
+    {
+        eprintln!(
+            "--retransmit needs --sync-mode op, and can't be used with --truncate, --epochs, \
+             --causal-tokens or --causal-delivery"
+        );
+        std::process::exit(2)
+    }
+    if opts.late_joiners + opts.leavers > 0 {
+        if opts.late_joiners + opts.leavers >= opts.servers {
+            eprintln!("--late-joiners and --leavers need to leave at least one server for clients");
+            std::process::exit(2)
+        }
+        // joiners catch up by merging in maps, which carry nothing else
+        if opts.sync_mode != SyncMode::Op
+            || opts.replication_factor.is_some()
+            || opts.truncate
+            || opts.epochs
+            || opts.causal_delivery
+            || opts.set_clients
+                + opts.counter_clients
+                + opts.mv_register_clients
+                + opts.flag_enable_clients
+                + opts.flag_disable_clients
+                + opts.list_insert_clients
+                + opts.list_delete_clients
+                + opts.nested_clients
+                > 0
+        {
+            eprintln!(
+                "--late-joiners and --leavers need --sync-mode op, and can't be used with \
+                 --replication-factor, --truncate, --epochs, --causal-delivery or clients of \
+                 other CRDTs than the map"
+            );
+            std::process::exit(2)
+        }
+    }
+    // ops only carry what they depend on in op mode, the other modes sending states or logs
+    if opts.suite == Suite::Causal && opts.sync_mode != SyncMode::Op {
+        eprintln!("--suite causal needs --sync-mode op");
+        std::process::exit(2)
+    }
+    if opts.snapshot_every.is_some() && (!opts.crash || opts.restart != Restart::Snapshot) {
+        eprintln!("--snapshot-every needs --crash and --restart snapshot");
+        std::process::exit(2)
+    }
+    if opts.snapshot_every == Some(0) {
+        eprintln!("--snapshot-every must be positive");
+        std::process::exit(2)
+    }
+
+    // the byzantine server only misbehaves with plain broadcasts
+    if opts.byzantine && (opts.sync_mode != SyncMode::Op || opts.causal_delivery) {
+        eprintln!("--byzantine needs --sync-mode op and can't be combined with --causal-delivery");
+        std::process::exit(2)
+    }
+
+    // a duplicating network never runs out of messages to deliver, so no path ends
+    if opts.network.duplicates() && opts.fairness != Fairness::None {
+        eprintln!("--network duplicating can't be used with --fairness");
+        std::process::exit(2)
+    }
+
+    let prefix = match &opts.resume_from {
+        Some(path) => load_trace(path),
+        None => Trace::default(),
+    };
+
+    let cfg = ModelCfg {
+        put_clients: opts.put_clients,
+        delete_clients: opts.delete_clients,
+        truncate: opts.truncate,
+        epochs: opts.epochs,
+        gc: opts.gc,
+        crash: opts.crash,
+        restart: opts.restart,
+        snapshot_every: opts.snapshot_every,
+        partitions: opts.partitions,
+        byzantine: opts.byzantine,
+        late_joiners: opts.late_joiners,
+        leavers: opts.leavers,
+        churn: opts.churn,
+        resolve_clients: opts.resolve_clients,
+        conflict_notifications: opts.conflict_notifications,
+        read_clients: opts.read_clients,
+        set_clients: opts.set_clients,
+        set_impl: opts.set_impl,
+        set_re_add: opts.set_re_add,
+        counter_clients: opts.counter_clients,
+        mv_register_clients: opts.mv_register_clients,
+        register_impl: opts.register_impl,
+        flag_enable_clients: opts.flag_enable_clients,
+        flag_disable_clients: opts.flag_disable_clients,
+        flag_bias: opts.flag_bias,
+        list_insert_clients: opts.list_insert_clients,
+        list_delete_clients: opts.list_delete_clients,
+        nested_clients: opts.nested_clients,
+        get_clients: opts.get_clients,
+        mixed_clients: opts.mixed_clients,
+        mix: mix(&opts),
+        replication_factor: opts.replication_factor,
+        servers: opts.servers,
+        follow_up_gets: opts.follow_up_gets,
+        key_workload: key_workload(&opts),
+        client_loops: opts.client_loops.clone(),
+        prefix,
+        fairness: opts.fairness,
+        sync_mode: opts.sync_mode,
+        network: opts.network,
+        suite: opts.suite,
+        validation: Validation {
+            servers: (0..opts.servers).collect(),
+            // hybrid logical clock counters count fractions of a wall clock tick
+            max_counter_gap: match opts.clock {
+                Clock::Lamport => opts.max_counter_gap,
+                Clock::Hlc => opts.max_counter_gap << hlc::LOGICAL_BITS,
+            },
+        },
+        clock_skews: opts.clock_skews.clone(),
+        clock: opts.clock,
+        map_policy: opts.map_policy,
+        causal_tokens: opts.causal_tokens,
+        causal_delivery: opts.causal_delivery,
+        anti_entropy: opts.anti_entropy,
+        retransmit: opts.retransmit,
+        retries: opts.retries,
+        dedup: !opts.no_dedup,
+        reduction: !opts.no_reduction,
+        abstract_clients: opts.abstract_clients,
+        strategy: opts.command.strategy(),
+        invariants: parse_invariants(&opts.invariants),
+        expected_violations: opts.expected_violations.clone(),
+        save_counterexamples: opts.save_counterexamples.clone(),
+        #[cfg(feature = "scripting")]
+        script: opts.script.as_deref().map(|path| {
+            Arc::new(Script::load(path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(2)
+            }))
+        }),
+    };
+
+    let map_impl = match (opts.broken, opts.lww, opts.map_impl) {
+        (false, false, map_impl) => map_impl,
+        (true, _, MapImpl::Fixed) => MapImpl::Broken,
+        (_, true, MapImpl::Fixed) => MapImpl::Lww,
+        _ => {
+            eprintln!("--broken and --lww can't be combined with --map-impl");
+            std::process::exit(2)
+        }
+    };
+    if opts.clock == Clock::Hlc && map_impl == MapImpl::Broken {
+        eprintln!("--clock hlc can't be used with the broken map");
+        std::process::exit(2)
+    }
+    // only the fixed map remembers what removes need to win, and only ops applied one by one
+    // carry it, while dropping late copies of removed values would look like deletes
+    if opts.map_policy == MapPolicy::RemoveWins
+        && (map_impl != MapImpl::Fixed
+            || matches!(opts.sync_mode, SyncMode::State | SyncMode::Delta)
+            || opts.anti_entropy
+            || opts.retransmit
+            || opts.truncate
+            || opts.crash
+            || opts.late_joiners + opts.leavers > 0)
+    {
+        eprintln!(
+            "--map-policy remove-wins needs the fixed map, and can't be used with --sync-mode \
+             state or delta, --anti-entropy, --retransmit, --truncate, --crash, --late-joiners or \
+             --leavers"
+        );
+        std::process::exit(2)
+    }
+
+    if let SubCmd::Compare { trace } = &opts.command {
+        compare::compare(cfg, &load_trace(trace));
+    } else if let SubCmd::ApplyOps { ops, watch } = &opts.command {
+        let ops = oplog::load(ops).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2)
+        });
+        match map_impl {
+            MapImpl::Fixed => print!("{}", oplog::render(&oplog::apply::<FixedMap>(&ops, watch))),
+            MapImpl::Broken => print!("{}", oplog::render(&oplog::apply::<BrokenMap>(&ops, watch))),
+            MapImpl::Lww => print!("{}", oplog::render(&oplog::apply::<LwwMap>(&ops, watch))),
+        }
+    } else if let SubCmd::Run {
+        listen,
+        peers,
+        frontends,
+    } = &opts.command
+    {
+        node::validate(&cfg, *listen, peers);
+        match map_impl {
+            MapImpl::Fixed => node::run::<FixedMap>(&cfg, *listen, peers, frontends),
+            MapImpl::Broken => node::run::<BrokenMap>(&cfg, *listen, peers, frontends),
+            MapImpl::Lww => node::run::<LwwMap>(&cfg, *listen, peers, frontends),
+        }
+    } else if let SubCmd::Repl { replicas } = &opts.command {
+        if *replicas == 0 {
+            eprintln!("--replicas must be positive");
+            std::process::exit(2)
+        }
+        match map_impl {
+            MapImpl::Fixed => repl::repl::<FixedMap>(*replicas),
+            MapImpl::Broken => repl::repl::<BrokenMap>(*replicas),
+            MapImpl::Lww => repl::repl::<LwwMap>(*replicas),
+        }
+    } else if let SubCmd::ScaleSim {
+        replicas,
+        ops,
+        seed,
+        min_latency,
+        max_latency,
+        op_interval,
+    } = &opts.command
+    {
+        if *replicas == 0 || min_latency > max_latency {
+            eprintln!("--replicas must be positive and --min-latency at most --max-latency");
+            std::process::exit(2)
+        }
+        let sim = SimCfg {
+            replicas: *replicas,
+            ops: *ops,
+            seed: *seed,
+            latency: *min_latency..=*max_latency,
+            op_interval: *op_interval,
+            keys: key_workload(&opts).schedule(),
+        };
+        match map_impl {
+            MapImpl::Fixed => sim::simulate::<FixedMap>(&sim),
+            MapImpl::Broken => sim::simulate::<BrokenMap>(&sim),
+            MapImpl::Lww => sim::simulate::<LwwMap>(&sim),
+        }
     } else {
-        let model = ModelCfg {
-            put_clients: opts.put_clients,
-            delete_clients: opts.delete_clients,
-            servers: opts.servers,
-            follow_up_gets: opts.follow_up_gets,
+        match map_impl {
+            MapImpl::Fixed => run(opts, cfg.into_actor_model::<FixedMap>()),
+            MapImpl::Broken => run(opts, cfg.into_actor_model::<BrokenMap>()),
+            MapImpl::Lww => run(opts, cfg.into_actor_model::<LwwMap>()),
+        }
+    }
+}
+
+fn write_report<M: Clone + Debug + PartialEq + Hash + Map>(
+    path: &Path,
+    checker: &impl Checker<RegisterModel<M>>,
+    complete: bool,
+) {
+    match report::write_report(path, checker, complete) {
+        Ok(()) => println!("Wrote report to {:?}", path),
+        Err(e) => eprintln!("Failed to write report to {:?}: {}", path, e),
+    }
+}
+
+fn parse_invariants(sources: &[String]) -> Vec<Invariant> {
+    if sources.len() > properties::MAX_INVARIANTS {
+        eprintln!(
+            "At most {} invariants are supported",
+            properties::MAX_INVARIANTS
+        );
+        std::process::exit(2)
+    }
+    sources
+        .iter()
+        .map(|source| {
+            Invariant::parse(source).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(2)
+            })
+        })
+        .collect()
+}
+
+fn key_workload(opts: &Opts) -> KeyWorkload {
+    if let Some(weights) = &opts.key_weights {
+        KeyWorkload::parse_weights(weights).unwrap_or_else(|e| {
+            eprintln!("Invalid key weights: {}", e);
+            std::process::exit(2)
+        })
+    } else if let Some(keys) = opts.zipf_keys {
+        if keys == 0 || keys > 26 {
+            eprintln!("--zipf-keys must be between 1 and 26");
+            std::process::exit(2)
+        }
+        KeyWorkload::zipf(keys, opts.zipf_exponent)
+    } else if let Some(keys) = opts.keys {
+        if keys == 0 || keys > 26 {
+            eprintln!("--keys must be between 1 and 26");
+            std::process::exit(2)
         }
-        .into_actor_model::<FixedMap>()
-        .checker()
-        .threads(num_cpus::get());
-        run(opts, model)
+        KeyWorkload::uniform(keys)
+    } else {
+        KeyWorkload::single(KEY)
     }
 }
 
+fn mix(opts: &Opts) -> Vec<MixedOp> {
+    parse_mix(&opts.mix).unwrap_or_else(|e| {
+        eprintln!("Invalid mix: {}", e);
+        std::process::exit(2)
+    })
+}
+
+fn load_trace(path: &Path) -> Trace {
+    Trace::load(path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2)
+    })
+}
+
 fn run<M: Clone + Debug + PartialEq + Hash + Send + Sync + 'static + Map>(
     opts: Opts,
-    model: CheckerBuilder<ActorModel<MyRegisterActor<M>>>,
+    model: RegisterModel<M>,
 ) {
-    match opts.command {
+    // catch type errors in invariants before starting
+    let init_state = trace::init_state(&model);
+    for invariant in &model.cfg().invariants {
+        if let Err(e) = invariant.eval(&init_state) {
+            eprintln!("{}", e);
+            std::process::exit(2)
+        }
+    }
+
+    let properties = model.properties();
+    for name in &model.cfg().expected_violations {
+        if !properties.iter().any(|property| property.name == name) {
+            eprintln!(
+                "--expect-violation names no property of this model: {:?}",
+                name
+            );
+            std::process::exit(2)
+        }
+    }
+
+    let time_limit = opts.time_limit.map(Duration::from_secs);
+    let bounds = bounds(&opts);
+    let shape = ShapeRecorder::default();
+    let audit = CommutativityAudit::default();
+    let memory = MemoryAccounting::default();
+    let build_checker = |model: RegisterModel<M>| {
+        let mut checker = model.checker().threads(num_cpus::get());
+        if let Some(depth) = bounds.depth {
+            checker = checker.target_max_depth(depth);
+        }
+        if let Some(states) = bounds.states {
+            checker = checker.target_state_count(states);
+        }
+        let mut visitors: Vec<Box<dyn CheckerVisitor<RegisterModel<M>> + Send + Sync>> = Vec::new();
+        if opts.shape {
+            visitors.push(Box::new(shape.clone()));
+        }
+        if opts.audit_commutativity {
+            visitors.push(Box::new(audit.clone()));
+        }
+        if opts.memory {
+            visitors.push(Box::new(memory.clone()));
+        }
+        if visitors.is_empty() {
+            checker
+        } else {
+            checker.visitor(check::Visitors(visitors))
+        }
+    };
+    match &opts.command {
         SubCmd::Serve => {
             println!("Serving web ui on http://127.0.0.1:8080");
-            model.serve("127.0.0.1:8080");
-        }
-        SubCmd::CheckDfs => {
-            model
-                .spawn_dfs()
-                .report(&mut std::io::stdout())
-                .join()
-                .assert_properties();
-        }
-        SubCmd::CheckBfs => {
-            model
-                .spawn_bfs()
-                .report(&mut std::io::stdout())
-                .join()
-                .assert_properties();
+            SUMMARIZE_SERVERS.store(true, Ordering::Relaxed);
+            build_checker(model).serve("127.0.0.1:8080");
+        }
+        SubCmd::CheckDfs
+        | SubCmd::Check {
+            strategy: Strategy::Dfs,
+        } => {
+            if opts.disk_visited.is_some() || opts.bloom.is_some() {
+                eprintln!(
+                    "--disk-visited and --bloom are only supported by check-bfs and check-guided"
+                );
+                std::process::exit(2)
+            }
+            let (checker, complete) = check::wait(build_checker(model).spawn_dfs(), time_limit);
+            let truncated = check::truncated(&checker, bounds);
+            let complete = complete && !truncated;
+            if opts.shape {
+                shape.report();
+            }
+            if opts.audit_commutativity {
+                audit.report();
+            }
+            if opts.memory {
+                memory.report();
+            }
+            if let Some(path) = &opts.report {
+                write_report(path, &checker, complete);
+            }
+            check::check_properties(&checker, opts.keep_going, complete);
+            if truncated {
+                std::process::exit(check::TRUNCATED_EXIT);
+            }
         }
+        SubCmd::CheckBfs
+        | SubCmd::Check {
+            strategy: Strategy::Bfs,
+        } => {
+            if opts.disk_visited.is_some() || opts.bloom.is_some() {
+                return run_explorer(&opts, &model, time_limit, bounds, None);
+            }
+            let (checker, complete) = check::wait(build_checker(model).spawn_bfs(), time_limit);
+            let truncated = check::truncated(&checker, bounds);
+            let complete = complete && !truncated;
+            if opts.shape {
+                shape.report();
+            }
+            if opts.audit_commutativity {
+                audit.report();
+            }
+            if opts.memory {
+                memory.report();
+            }
+            if let Some(path) = &opts.report {
+                write_report(path, &checker, complete);
+            }
+            check::check_properties(&checker, opts.keep_going, complete);
+            if truncated {
+                std::process::exit(check::TRUNCATED_EXIT);
+            }
+        }
+        SubCmd::CheckGuided { heuristic } => {
+            run_explorer(&opts, &model, time_limit, bounds, Some(*heuristic))
+        }
+        SubCmd::Swarm { listen, workers } => {
+            if *workers == 0 {
+                eprintln!("--workers must be positive");
+                std::process::exit(2)
+            }
+            let result =
+                swarm::coordinate(&model, listen, *workers, time_limit).unwrap_or_else(|e| {
+                    eprintln!("Swarm failed: {}", e);
+                    std::process::exit(2)
+                });
+            let violations = swarm::check_properties(&model, result, opts.keep_going);
+            if violations > 0 {
+                println!("{} properties violated", violations);
+                std::process::exit(1);
+            }
+        }
+        SubCmd::SwarmWorker { coordinator } => {
+            if let Err(e) = swarm::work(&model, coordinator) {
+                eprintln!("Worker failed: {}", e);
+                std::process::exit(2)
+            }
+        }
+        SubCmd::Simulate {
+            seed,
+            runs,
+            max_depth,
+        } => {
+            if *runs == 0 {
+                eprintln!("--runs must be positive");
+                std::process::exit(2)
+            }
+            let simulation = simulate::simulate(&model, *seed, *runs, *max_depth, time_limit);
+            let violations = simulate::check_properties(&model, simulation, opts.keep_going);
+            if violations > 0 {
+                println!("{} properties violated", violations);
+                std::process::exit(1);
+            }
+        }
+        SubCmd::Debug => debug::debug(model),
+        SubCmd::Profile => {
+            let profiler = Profiler::default();
+            let checker = model
+                .checker()
+                .threads(num_cpus::get())
+                .visitor(profiler.clone())
+                .spawn_bfs();
+            check::wait(checker, Some(time_limit.unwrap_or(PROFILE_TIME_LIMIT)));
+            profiler.report();
+        }
+        SubCmd::CheckRegister => register::check::<M>(model.cfg()),
+        SubCmd::Replay { trace } => trace::print_replay(&model, &load_trace(trace)),
+        SubCmd::ExportOps { trace } => print!("{}", oplog::export(&model, &load_trace(trace))),
+        SubCmd::Dag { trace, key } => print!("{}", dag::dag(&model, &load_trace(trace), *key)),
+        SubCmd::Compare { .. }
+        | SubCmd::ApplyOps { .. }
+        | SubCmd::Run { .. }
+        | SubCmd::Repl { .. }
+        | SubCmd::ScaleSim { .. } => {
+            unreachable!("handled before choosing a map")
+        }
+    }
+}
+
+/// Check with the single-threaded explorer rather than stateright's checkers, exiting with a
+/// failure if any properties were violated.
+fn run_explorer<M: Clone + Debug + PartialEq + Hash + Map>(
+    opts: &Opts,
+    model: &RegisterModel<M>,
+    time_limit: Option<Duration>,
+    bounds: Bounds,
+    heuristic: Option<Heuristic>,
+) {
+    let (violations, truncated) = match (&opts.disk_visited, opts.bloom) {
+        (_, Some(expected_states)) => {
+            if !(opts.bloom_fp_rate > 0.0 && opts.bloom_fp_rate < 1.0) {
+                eprintln!("--bloom-fp-rate must be between 0 and 1");
+                std::process::exit(2)
+            }
+            let mut visited = BloomVisited::new(expected_states, opts.bloom_fp_rate);
+            let exploration = explore::explore(model, &mut visited, time_limit, bounds, heuristic);
+            let truncated = exploration.truncated.is_some();
+            let violations =
+                explore::check_properties(model, exploration, &mut visited, opts.keep_going);
+            (violations, truncated)
+        }
+        (Some(dir), None) => {
+            let mut visited = DiskVisited::create(dir, opts.visited_cache).unwrap_or_else(|e| {
+                eprintln!("Failed to create visited states in {:?}: {}", dir, e);
+                std::process::exit(2)
+            });
+            let exploration = explore::explore(model, &mut visited, time_limit, bounds, heuristic);
+            let truncated = exploration.truncated.is_some();
+            visited.report();
+            // the file is removed when this arm ends, as exiting below skips destructors
+            let violations =
+                explore::check_properties(model, exploration, &mut visited, opts.keep_going);
+            (violations, truncated)
+        }
+        (None, None) => {
+            let mut visited = InMemory::default();
+            let exploration = explore::explore(model, &mut visited, time_limit, bounds, heuristic);
+            let truncated = exploration.truncated.is_some();
+            let violations =
+                explore::check_properties(model, exploration, &mut visited, opts.keep_going);
+            (violations, truncated)
+        }
+    };
+    if violations > 0 {
+        println!("{} properties violated", violations);
+        std::process::exit(1);
+    }
+    if truncated {
+        std::process::exit(check::TRUNCATED_EXIT);
+    }
+}
+
+/// The bounds on the state space from the options.
+fn bounds(opts: &Opts) -> Bounds {
+    let bounded = opts.depth_limit.is_some() || opts.max_states.is_some();
+    let supported = opts.command.strategy().is_some()
+        || matches!(opts.command, SubCmd::CheckGuided { .. } | SubCmd::Serve);
+    if bounded && !supported {
+        eprintln!("--depth-limit and --max-states are only supported by check, check-dfs, check-bfs, check-guided and serve");
+        std::process::exit(2)
+    }
+    if opts.depth_limit == Some(0) || opts.max_states == Some(0) {
+        eprintln!("--depth-limit and --max-states must be positive");
+        std::process::exit(2)
+    }
+    Bounds {
+        depth: opts.depth_limit,
+        states: opts.max_states,
     }
 }
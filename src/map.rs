@@ -1,27 +1,228 @@
-use stateright::actor::Id;
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 
-pub(crate) type Timestamp = (u32, usize);
+use serde::Deserialize;
+use serde::Serialize;
 
-pub(crate) trait Map {
+use crate::context::CausalContext;
+use crate::id::Id;
+
+/// Names a value by the counter of the op that set it and the actor id of the server that
+/// prepared the op, which together are unique.
+pub type Timestamp = (u32, usize);
+
+/// An operation on a map, prepared at its origin and then effected exactly once at every server,
+/// the origin included, as in the op-based CRDT literature.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum MapOp {
+    /// Set a key to a value, overwriting the values in the context.
+    Set {
+        /// The values this set overwrites.
+        context: CausalContext,
+        timestamp: Timestamp,
+        key: char,
+        value: char,
+    },
+    /// Remove the values in the context.
+    Delete {
+        /// The values this delete removes.
+        context: CausalContext,
+    },
+}
+
+impl MapOp {
+    /// The timestamp of a set, which names it.
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        match self {
+            MapOp::Set { timestamp, .. } => Some(*timestamp),
+            MapOp::Delete { .. } => None,
+        }
+    }
+}
+
+/// How a map resolves a set and a delete that are concurrent, where both removed a value that
+/// neither had seen the other remove.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Policy {
+    /// The set's value is kept, as the delete only removes the values it had seen.
+    AddWins,
+    /// The delete removes the set's value too, along with the values of the sets that
+    /// overwrote it without seeing the delete.
+    RemoveWins,
+}
+
+/// Which clock a map draws the counters of its timestamps from.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum ClockKind {
+    /// The map's own clock: a Lamport clock, one past the highest counter seen, or the wall
+    /// clock for the last-writer-wins map.
+    Lamport,
+    /// A [`Hlc`](crate::Hlc), later than every timestamp seen while staying close to the wall
+    /// clock.
+    Hlc,
+}
+
+/// A delta state: what a change, or several joined together, adds to a map and what it removes.
+/// It is much smaller than the whole map, but joining it into a map has the same effect as
+/// merging in the map the change was made on.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct MapDelta {
+    /// The values added, with their timestamps and keys.
+    pub values: BTreeSet<(Timestamp, char, char)>,
+    /// The timestamps of the values removed.
+    pub removed: BTreeSet<Timestamp>,
+}
+
+impl MapDelta {
+    /// Join another delta into this one, so that this covers the changes of both.
+    pub fn join(&mut self, other: &MapDelta) {
+        self.removed.extend(other.removed.iter().copied());
+        self.values.extend(other.values.iter().copied());
+        let removed = &self.removed;
+        self.values
+            .retain(|(timestamp, _, _)| !removed.contains(timestamp));
+    }
+
+    /// Whether the delta changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl From<MapOp> for MapDelta {
+    /// The delta of an op: a set adds its value and removes the values in its context, and a
+    /// delete removes the values in its context.
+    fn from(op: MapOp) -> Self {
+        match op {
+            MapOp::Set {
+                context,
+                timestamp,
+                key,
+                value,
+            } => MapDelta {
+                values: [(timestamp, key, value)].into_iter().collect(),
+                removed: context.iter().collect(),
+            },
+            MapOp::Delete { context } => MapDelta {
+                values: BTreeSet::new(),
+                removed: context.iter().collect(),
+            },
+        }
+    }
+}
+
+/// The state of a map as persisted to disk: enough to restore it after a crash, in a form that
+/// can be serialized.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapSnapshot {
+    /// The highest op counter the map has seen.
+    pub max_counter: u32,
+    /// The map's wall clock reading, for maps that keep one.
+    pub clock: u32,
+    /// Every value the map holds, with its timestamp and key.
+    pub values: Vec<(Timestamp, char, char)>,
+}
+
+/// Merge the siblings of a key read from several servers into the value a map resolves them to:
+/// the one with the greatest timestamp, the first read if several share it.
+///
+/// Servers only drop values that a newer write overwrote, so the merged value is at least as
+/// recent as what any one of the servers would have answered.
+pub fn merge_reads(reads: &[Vec<(Timestamp, char)>]) -> Option<(Timestamp, char)> {
+    reads
+        .iter()
+        .flatten()
+        .rev()
+        .max_by_key(|(timestamp, _)| timestamp)
+        .copied()
+}
+
+/// The interface the checker uses to drive a map, and the one to embed a map with.
+///
+/// Each server keeps its own map. A change is prepared with `prepare_set` or `prepare_delete`,
+/// which leave the map as it is, and the op is then effected exactly once on every server's map,
+/// the preparing server's included.
+///
+/// The maps only use `core` and `alloc`, so they build without the crate's `std` feature, when
+/// [`Id`] stands in for the checker's actor ids.
+pub trait Map {
+    /// A map for the server with the given actor id, which must be unique among the servers.
     fn new(actor_id: Id) -> Self;
 
+    /// Set how far ahead of true time each server's wall clock runs, by actor id. Maps with
+    /// logical clocks ignore this.
+    fn set_clock_skews(&mut self, _skews: &[u32]) {}
+
+    /// Draw the counters of timestamps from `clock`, before any clock skews are set. Maps that
+    /// only have their own clock ignore this.
+    fn set_clock(&mut self, _clock: ClockKind) {}
+
+    /// Resolve concurrent sets and deletes by `policy` rather than keeping the set's value,
+    /// before any op is effected. Maps that can't ignore this.
+    fn set_policy(&mut self, _policy: Policy) {}
+
+    /// Forget what the map remembers about the sets in `stable`, which every op naming them
+    /// has been delivered by every server, so that none can still arrive. Maps that remember
+    /// nothing about removed values ignore this.
+    fn forget_stable(&mut self, _stable: &CausalContext) {}
+
+    /// The value `k` resolves to, if it has any.
     fn get(&self, k: &char) -> Option<&char>;
 
-    fn set(&mut self, key: char, v: char) -> (Vec<Timestamp>, Timestamp);
+    /// Prepare setting `key` to `value` based on the current state, without changing it.
+    fn prepare_set(&self, key: char, value: char) -> MapOp;
 
-    fn delete(&mut self, key: &char) -> Option<Vec<Timestamp>>;
+    /// Prepare deleting `key` based on the current state, without changing it, if there is
+    /// anything to delete.
+    fn prepare_delete(&self, key: &char) -> Option<MapOp>;
 
-    fn receive_set(
-        &mut self,
-        context: Vec<Timestamp>,
-        timestamp: Timestamp,
-        key: char,
-        value: char,
-    );
+    /// Apply an op, whether it was prepared by this map or received from another server.
+    fn effect(&mut self, op: MapOp);
 
-    fn receive_delete(&mut self, context: Vec<Timestamp>);
+    /// Merge in the values another server's map holds, for state-based sync. Maps don't remember
+    /// the values they removed, so this can bring back values this map had removed; whoever
+    /// merges has to remove those again.
+    fn merge(&mut self, other: &Self);
 
+    /// The highest op counter this map has seen.
+    fn max_counter(&self) -> u32;
+
+    /// Every value the map holds, with its timestamp and key, siblings included.
     fn values(&self) -> Vec<(Timestamp, char, char)>;
 
+    /// The values the map resolves to, which is what servers must agree on.
     fn visible_values(&self) -> Vec<(Timestamp, char, char)>;
+
+    /// The state to persist so that the map can be restored after a crash.
+    fn snapshot(&self) -> MapSnapshot;
+
+    /// Replace the map's state with a snapshot of it, as read back after a crash. The actor id
+    /// and clock skews are kept, as they are configuration rather than state.
+    fn restore(&mut self, snapshot: MapSnapshot);
+
+    /// How the web explorer shows each key: the value it resolves to, followed by all of its
+    /// siblings when it has several.
+    fn summary(&self) -> BTreeMap<char, String> {
+        let mut keys = BTreeMap::<char, Vec<char>>::new();
+        for (_, key, value) in self.values() {
+            keys.entry(key).or_default().push(value);
+        }
+        keys.into_iter()
+            .map(|(key, siblings)| {
+                let resolved = match self.get(&key) {
+                    Some(value) => format!("{:?}", value),
+                    None => "(none)".to_owned(),
+                };
+                if siblings.len() > 1 {
+                    (key, format!("{} siblings {:?}", resolved, siblings))
+                } else {
+                    (key, resolved)
+                }
+            })
+            .collect()
+    }
 }
@@ -1,59 +1,92 @@
-use std::collections::{BTreeSet, HashSet};
-
-use stateright::actor::Id;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
 
+use crate::context::CausalContext;
+use crate::id::Id;
 use crate::map::Map;
+use crate::map::MapOp;
+use crate::map::MapSnapshot;
 use crate::map::Timestamp;
 
 impl Map for BrokenMap {
     fn new(actor_id: Id) -> Self {
-        Self::new(actor_id)
+        Self::new(actor_id.into())
     }
 
     fn get(&self, k: &char) -> Option<&char> {
         self.get(k)
     }
 
-    fn set(&mut self, key: char, v: char) -> (Vec<Timestamp>, Timestamp) {
-        self.set(key, v)
+    fn prepare_set(&self, key: char, v: char) -> MapOp {
+        let (context, timestamp) = self.prepare_set(key);
+        MapOp::Set {
+            context,
+            timestamp,
+            key,
+            value: v,
+        }
     }
 
-    fn delete(&mut self, key: &char) -> Option<Vec<Timestamp>> {
-        self.delete(key)
+    fn prepare_delete(&self, key: &char) -> Option<MapOp> {
+        self.prepare_delete(key)
+            .map(|context| MapOp::Delete { context })
     }
 
-    fn receive_set(
-        &mut self,
-        context: Vec<Timestamp>,
-        timestamp: Timestamp,
-        key: char,
-        value: char,
-    ) {
-        self.receive_set(context, timestamp, key, value)
+    fn effect(&mut self, op: MapOp) {
+        match op {
+            MapOp::Set {
+                context,
+                timestamp,
+                key,
+                value,
+            } => self.receive_set(context, timestamp, key, value),
+            MapOp::Delete { context } => self.receive_delete(context),
+        }
     }
 
-    fn receive_delete(&mut self, context: Vec<Timestamp>) {
-        self.receive_delete(context)
+    fn merge(&mut self, other: &Self) {
+        self.merge(other)
+    }
+
+    fn max_counter(&self) -> u32 {
+        self.max_op
     }
 
     fn values(&self) -> Vec<(Timestamp, char, char)> {
         self.values.iter().cloned().collect()
     }
 
+    fn snapshot(&self) -> MapSnapshot {
+        MapSnapshot {
+            max_counter: self.max_op,
+            clock: 0,
+            values: self.values(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: MapSnapshot) {
+        self.max_op = snapshot.max_counter;
+        self.values = snapshot.values.into_iter().collect();
+    }
+
     fn visible_values(&self) -> Vec<(Timestamp, char, char)> {
         self.values()
     }
 }
 
+/// The first version of the map from the tweet, where the newest timestamp is meant to win but
+/// deletes only remove one value, so servers can diverge. Kept to show the checker finding it.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
-pub(crate) struct BrokenMap {
-    actor_id: Id,
+pub struct BrokenMap {
+    actor_id: usize,
     max_op: u32,
-    pub(crate) values: BTreeSet<(Timestamp, char, char)>,
+    /// Every value held, with its timestamp and key.
+    pub values: BTreeSet<(Timestamp, char, char)>,
 }
 
 impl BrokenMap {
-    pub(crate) fn new(actor_id: Id) -> Self {
+    /// An empty map for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
         Self {
             actor_id,
             max_op: 0,
@@ -61,77 +94,80 @@ impl BrokenMap {
         }
     }
 
-    pub(crate) fn get(&self, k: &char) -> Option<&char> {
+    /// The first value found for `k`, if it has any.
+    pub fn get(&self, k: &char) -> Option<&char> {
         self.values
             .iter()
             .find(|(_, kp, _)| k == kp)
             .map(|(_, _, v)| v)
     }
 
-    pub(crate) fn set(&mut self, key: char, v: char) -> (Vec<Timestamp>, Timestamp) {
-        let t = self.new_timestamp();
-        // remove the old value from ourselves if there was one
-        if let Some(previous) = self.values.iter().find(|(_t, k, _v)| k == &key).cloned() {
-            self.values.remove(&previous);
-        }
-        // add it to ourselves
-        self.values.insert((t, key, v));
-        (vec![], t)
-    }
-
-    pub(crate) fn delete(&mut self, key: &char) -> Option<Vec<Timestamp>> {
-        if let Some((t, k, v)) = self.values.iter().find(|(_, kp, _)| key == kp).cloned() {
-            // add it to ourselves
-            self.values.remove(&(t, k, v));
-            Some(vec![t])
-        } else {
-            None
-        }
+    /// The context and timestamp for setting `key`; no context is sent, the newest timestamp
+    /// is meant to win.
+    pub fn prepare_set(&self, _key: char) -> (CausalContext, Timestamp) {
+        (CausalContext::new(), self.next_timestamp())
     }
 
-    pub(crate) fn receive_set(
+    /// The context for deleting `key`: only the first value found for it.
+    pub fn prepare_delete(&self, key: &char) -> Option<CausalContext> {
+        self.values
+            .iter()
+            .find(|(_, kp, _)| key == kp)
+            .map(|(t, _, _)| [*t].into_iter().collect())
+    }
+
+    /// Effect a set, whether prepared here or by another server.
+    pub fn receive_set(
         &mut self,
-        _context: Vec<Timestamp>,
+        _context: CausalContext,
         timestamp: Timestamp,
         key: char,
         value: char,
     ) {
         self.update_max_op(timestamp);
-        let previous = self
+
+        // only replace the previous values of the key if they are all older
+        if self
             .values
             .iter()
-            .filter(|(_t, k, _v)| k == &key)
-            .cloned()
-            .collect::<HashSet<_>>();
-
-        if previous.is_empty() || previous.iter().all(|(t, _k, _v)| t < &timestamp) {
-            for p in previous {
-                self.values.remove(&p);
-            }
+            .all(|(t, k, _v)| k != &key || t < &timestamp)
+        {
+            self.values.retain(|(_t, k, _v)| k != &key);
             self.values.insert((timestamp, key, value));
         }
     }
 
-    pub(crate) fn receive_delete(&mut self, timestamp: Vec<Timestamp>) {
-        self.update_max_op(timestamp[0]);
+    /// Effect a delete, whether prepared here or by another server. A delete removing nothing,
+    /// which only a misbehaving peer sends, is ignored.
+    pub fn receive_delete(&mut self, context: CausalContext) {
+        let timestamp = match context.iter().next() {
+            Some(timestamp) => timestamp,
+            None => return,
+        };
+        self.update_max_op(timestamp);
         if let Some(tuple) = self
             .values
             .iter()
-            .find(|(t, _k, _v)| t == &timestamp[0])
+            .find(|(t, _k, _v)| t == &timestamp)
             .cloned()
         {
             self.values.remove(&tuple);
         }
     }
 
+    /// Merge in the values another server's map holds, as if each had just been set here.
+    pub fn merge(&mut self, other: &Self) {
+        for (timestamp, key, value) in &other.values {
+            self.receive_set(CausalContext::new(), *timestamp, *key, *value)
+        }
+    }
+
     fn update_max_op(&mut self, timestamp: Timestamp) {
-        self.max_op = std::cmp::max(self.max_op, timestamp.0);
+        self.max_op = core::cmp::max(self.max_op, timestamp.0);
     }
 
-    // globally unique
-    fn new_timestamp(&mut self) -> Timestamp {
-        self.max_op += 1;
-        let id: usize = self.actor_id.into();
-        (self.max_op, id)
+    // globally unique, once effected
+    fn next_timestamp(&self) -> Timestamp {
+        (self.max_op + 1, self.actor_id)
     }
 }
@@ -1,161 +1,446 @@
-use std::collections::BTreeSet;
-
-use stateright::actor::Id;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
 
+use crate::context::CausalContext;
+use crate::hlc::Hlc;
+use crate::id::Id;
+use crate::map::ClockKind;
 use crate::map::Map;
-
+use crate::map::MapDelta;
+use crate::map::MapOp;
+use crate::map::MapSnapshot;
+use crate::map::Policy;
 use crate::map::Timestamp;
 
 impl Map for FixedMap {
     fn new(actor_id: Id) -> Self {
-        Self::new(actor_id)
+        Self::new(actor_id.into())
+    }
+
+    fn set_clock_skews(&mut self, skews: &[u32]) {
+        self.set_clock_skews(skews)
+    }
+
+    fn set_clock(&mut self, clock: ClockKind) {
+        self.set_clock(clock)
+    }
+
+    fn set_policy(&mut self, policy: Policy) {
+        self.set_policy(policy)
+    }
+
+    fn forget_stable(&mut self, stable: &CausalContext) {
+        self.forget_stable(stable)
     }
 
     fn get(&self, k: &char) -> Option<&char> {
         self.get(k)
     }
 
-    fn set(&mut self, key: char, v: char) -> (Vec<Timestamp>, Timestamp) {
-        self.set(key, v)
+    fn prepare_set(&self, key: char, v: char) -> MapOp {
+        let (context, timestamp) = self.prepare_set(key);
+        MapOp::Set {
+            context,
+            timestamp,
+            key,
+            value: v,
+        }
     }
 
-    fn delete(&mut self, key: &char) -> Option<Vec<Timestamp>> {
-        self.delete(key)
+    fn prepare_delete(&self, key: &char) -> Option<MapOp> {
+        self.prepare_delete(key)
+            .map(|context| MapOp::Delete { context })
     }
 
-    fn receive_set(
-        &mut self,
-        context: Vec<Timestamp>,
-        timestamp: Timestamp,
-        key: char,
-        value: char,
-    ) {
-        self.receive_set(context, timestamp, key, value)
+    fn effect(&mut self, op: MapOp) {
+        match op {
+            MapOp::Set {
+                context,
+                timestamp,
+                key,
+                value,
+            } => self.receive_set(context, timestamp, key, value),
+            MapOp::Delete { context } => self.receive_delete(context),
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.merge(other)
     }
 
-    fn receive_delete(&mut self, context: Vec<Timestamp>) {
-        self.receive_delete(context)
+    fn max_counter(&self) -> u32 {
+        self.max_op
     }
 
     fn values(&self) -> Vec<(Timestamp, char, char)> {
         self.values.iter().cloned().collect()
     }
 
-    fn visible_values(&self) -> Vec<(Timestamp, char, char)> {
-        // TODO: generalise this for multiple keys
-        let big_t = self.values.iter().map(|(t, _k, _)| t).collect::<Vec<_>>();
+    fn snapshot(&self) -> MapSnapshot {
+        MapSnapshot {
+            max_counter: self.max_op,
+            clock: self.hlc.as_ref().map_or(0, |hlc| hlc.wall()),
+            values: self.values(),
+        }
+    }
 
-        if big_t.is_empty() {
-            vec![]
-        } else {
-            let max_t = big_t.iter().max().unwrap();
-            self.values
-                .iter()
-                .find(|(t, _kp, _)| &t == max_t)
-                .cloned()
-                .map(|i| vec![i])
-                .unwrap_or_default()
+    fn restore(&mut self, snapshot: MapSnapshot) {
+        self.max_op = snapshot.max_counter;
+        if let Some(hlc) = &mut self.hlc {
+            hlc.restore(snapshot.clock, snapshot.max_counter);
         }
+        self.values = snapshot.values.into_iter().collect();
+    }
+
+    fn visible_values(&self) -> Vec<(Timestamp, char, char)> {
+        // TODO: generalise this for multiple keys
+        // max_by_key picks the last of equal maxima, so iterate in reverse to pick the first
+        self.values
+            .iter()
+            .rev()
+            .max_by_key(|(t, _, _)| t)
+            .cloned()
+            .into_iter()
+            .collect()
     }
 }
 
+/// A multi-value map where a set or delete removes exactly the values of its key that its server
+/// had seen, so concurrent sets are kept as siblings and every server converges on them.
+///
+/// That makes the map add-wins: a set concurrent with a delete keeps its value. With
+/// [`Policy::RemoveWins`] the delete wins instead. A set and a delete that both removed the same
+/// value can't have seen each other, as the value would have been gone by then, so a set whose
+/// context holds a deleted value is deleted too, as is any set whose context holds the value of
+/// a deleted set. Every set's context is remembered for this, whichever order the set and
+/// delete arrive in.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
-pub(crate) struct FixedMap {
-    actor_id: Id,
+pub struct FixedMap {
+    actor_id: usize,
     max_op: u32,
-    pub(crate) values: BTreeSet<(Timestamp, char, char)>,
+    /// The clock timestamps are drawn from, if not the Lamport clock counting up from `max_op`.
+    hlc: Option<Hlc>,
+    policy: Policy,
+    /// Every value held, with its timestamp and key.
+    pub values: BTreeSet<(Timestamp, char, char)>,
+    /// The context of every set effected, by its timestamp. Only kept when removes win.
+    pub contexts: BTreeMap<Timestamp, CausalContext>,
+    /// The values deletes removed, and the sets that lost to them. Only kept when removes win.
+    pub deleted: CausalContext,
 }
 
 impl FixedMap {
-    pub(crate) fn new(actor_id: Id) -> Self {
+    /// An empty map for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
         Self {
             actor_id,
             max_op: 0,
+            hlc: None,
+            policy: Policy::AddWins,
             values: BTreeSet::new(),
+            contexts: BTreeMap::new(),
+            deleted: CausalContext::new(),
         }
     }
 
-    pub(crate) fn get(&self, key: &char) -> Option<&char> {
-        let big_t = self
-            .values
-            .iter()
-            .filter_map(|(t, k, _)| if k == key { Some(t) } else { None })
-            .collect::<Vec<_>>();
+    /// Resolve a set and a delete that are concurrent by `policy`. Deltas and snapshots don't
+    /// carry what removes winning remembers, so they only win over ops effected one by one or
+    /// merged in with [`merge`](Self::merge).
+    pub fn set_policy(&mut self, policy: Policy) {
+        self.policy = policy;
+    }
 
-        if big_t.is_empty() {
-            None
-        } else {
-            let max_t = big_t.iter().max().unwrap();
-            self.values
-                .iter()
-                .find(|(t, kp, _)| key == kp && &t == max_t)
-                .map(|(_, _, v)| v)
+    /// Draw timestamps from `clock`: the Lamport clock, which the map starts with, or a hybrid
+    /// logical clock.
+    pub fn set_clock(&mut self, clock: ClockKind) {
+        self.hlc = match clock {
+            ClockKind::Lamport => None,
+            ClockKind::Hlc => Some(Hlc::new(self.actor_id)),
+        };
+    }
+
+    /// Set how far ahead of true time each server's wall clock runs, by actor id. Only the
+    /// hybrid logical clock reads the wall clock.
+    pub fn set_clock_skews(&mut self, skews: &[u32]) {
+        if let Some(hlc) = &mut self.hlc {
+            hlc.set_skew(skews.get(self.actor_id).copied().unwrap_or(0));
         }
     }
 
-    pub(crate) fn set(&mut self, key: char, value: char) -> (Vec<Timestamp>, Timestamp) {
+    /// The value `key` resolves to, if it has any.
+    pub fn get(&self, key: &char) -> Option<&char> {
+        // the value with the greatest timestamp wins, the first found if several share it
+        self.values
+            .iter()
+            .rev()
+            .filter(|(_, k, _)| k == key)
+            .max_by_key(|(t, _, _)| t)
+            .map(|(_, _, v)| v)
+    }
+
+    /// The context and timestamp for setting `key`: the set overwrites every current value of
+    /// the key.
+    pub fn prepare_set(&self, key: char) -> (CausalContext, Timestamp) {
         let big_t = self
             .values
             .iter()
             .filter_map(|(t, k, _)| if k == &key { Some(t) } else { None })
             .cloned()
-            .collect::<Vec<_>>();
+            .collect::<CausalContext>();
 
-        let t = self.new_timestamp();
-
-        // retain all values that aren't in the context
-        self.values.retain(|(t, _k, _v)| !big_t.contains(t));
-        // then insert the new one
-        self.values.insert((t, key, value));
-
-        (big_t, t)
+        (big_t, self.next_timestamp())
     }
 
-    pub(crate) fn delete(&mut self, key: &char) -> Option<Vec<Timestamp>> {
+    /// The context for deleting `key`: every current value of the key, if it has any.
+    pub fn prepare_delete(&self, key: &char) -> Option<CausalContext> {
         let big_t = self
             .values
             .iter()
             .filter_map(|(t, k, _)| if k == key { Some(t) } else { None })
             .cloned()
-            .collect::<Vec<_>>();
+            .collect::<CausalContext>();
 
-        // retain all values that aren't in the context
-        self.values.retain(|(t, _k, _v)| !big_t.contains(t));
-        Some(big_t)
+        // there is nothing to tell the other servers about
+        (!big_t.is_empty()).then_some(big_t)
     }
 
-    pub(crate) fn receive_set(
+    /// Effect a set, whether prepared here or by another server.
+    pub fn receive_set(
         &mut self,
-        context: Vec<Timestamp>,
+        context: CausalContext,
         timestamp: Timestamp,
         key: char,
         value: char,
     ) {
         self.update_max_op(timestamp);
 
-        // retain all values that aren't in the context
-        self.values.retain(|(t, _k, _v)| !context.contains(t));
+        // remove the values in the context
+        self.remove_timestamps(context.iter());
         // then insert the new one
         self.values.insert((timestamp, key, value));
+
+        if self.policy == Policy::RemoveWins {
+            self.contexts.insert(timestamp, context);
+            self.remove_deleted();
+        }
     }
 
-    pub(crate) fn receive_delete(&mut self, context: Vec<Timestamp>) {
-        if let Some(t) = context.iter().max() {
-            self.update_max_op(*t)
+    /// Effect a delete, whether prepared here or by another server.
+    pub fn receive_delete(&mut self, context: CausalContext) {
+        if let Some(t) = context.max() {
+            self.update_max_op(t)
         }
 
-        // retain all values that aren't in the context
-        self.values.retain(|(t, _k, _v)| !context.contains(t));
+        // remove the values in the context
+        self.remove_timestamps(context.iter());
+
+        if self.policy == Policy::RemoveWins {
+            self.deleted.union(&context);
+            self.remove_deleted();
+        }
+    }
+
+    /// Set `key` to `value` here, returning the delta for the other servers to join: the new
+    /// value, and the values it overwrote.
+    pub fn set(&mut self, key: char, value: char) -> MapDelta {
+        let (context, timestamp) = self.prepare_set(key);
+        self.receive_set(context.clone(), timestamp, key, value);
+        MapOp::Set {
+            context,
+            timestamp,
+            key,
+            value,
+        }
+        .into()
+    }
+
+    /// Delete `key` here, returning the delta for the other servers to join, if there was
+    /// anything to delete.
+    pub fn delete(&mut self, key: &char) -> Option<MapDelta> {
+        let context = self.prepare_delete(key)?;
+        self.receive_delete(context.clone());
+        Some(MapOp::Delete { context }.into())
+    }
+
+    /// Join a delta from another server into this map. The map doesn't remember what it
+    /// removed, so a delta must not be joined after one removing its values.
+    pub fn join(&mut self, delta: &MapDelta) {
+        if let Some((timestamp, _, _)) = delta.values.iter().next_back() {
+            self.update_max_op(*timestamp)
+        }
+        if let Some(timestamp) = delta.removed.iter().next_back() {
+            self.update_max_op(*timestamp)
+        }
+        self.values.extend(delta.values.iter().copied());
+        self.remove_timestamps(delta.removed.iter().copied());
+    }
+
+    /// Merge in the values another server's map holds, keeping them all as siblings, along with
+    /// the contexts and deletes it remembers, so that what lost to a delete there loses here too.
+    pub fn merge(&mut self, other: &Self) {
+        self.update_max_op((other.max_op, other.actor_id));
+        self.values.extend(other.values.iter().cloned());
+        for (timestamp, context) in &other.contexts {
+            self.contexts
+                .entry(*timestamp)
+                .or_insert_with(|| context.clone());
+        }
+        self.deleted.union(&other.deleted);
+        if self.policy == Policy::RemoveWins {
+            self.remove_deleted();
+        }
+    }
+
+    /// Forget the contexts and deletes remembered about the sets in `stable`, which every set
+    /// and delete naming them has been delivered by every server.
+    ///
+    /// A stable set is in `deleted` or not for good, and every set whose context holds it has
+    /// already lost to its delete or never will, so it is dropped from both. A set's context is
+    /// forgotten once all of it is stable, as no delete can still arrive for it to lose to.
+    pub fn forget_stable(&mut self, stable: &CausalContext) {
+        self.deleted.retain(|timestamp| !stable.contains(timestamp));
+        for context in self.contexts.values_mut() {
+            context.retain(|timestamp| !stable.contains(timestamp));
+        }
+        self.contexts.retain(|_, context| !context.is_empty());
+    }
+
+    /// Remove the values with the given timestamps. Values are ordered by timestamp first, so
+    /// each is looked up rather than scanning every value for every timestamp.
+    fn remove_timestamps(&mut self, timestamps: impl IntoIterator<Item = Timestamp>) {
+        for t in timestamps {
+            while let Some(value) = self
+                .values
+                .range((t, char::MIN, char::MIN)..=(t, char::MAX, char::MAX))
+                .next()
+                .cloned()
+            {
+                self.values.remove(&value);
+            }
+        }
+    }
+
+    /// Delete the sets that overwrote a deleted value, which can't have seen the delete, until
+    /// there are no more, then remove every deleted value still held.
+    fn remove_deleted(&mut self) {
+        loop {
+            let lost = self
+                .contexts
+                .iter()
+                .filter(|(timestamp, context)| {
+                    !self.deleted.contains(timestamp)
+                        && context.iter().any(|t| self.deleted.contains(&t))
+                })
+                .map(|(timestamp, _)| *timestamp)
+                .collect::<Vec<_>>();
+            if lost.is_empty() {
+                break;
+            }
+            self.deleted.extend(lost);
+        }
+        let deleted = self.deleted.iter().collect::<Vec<_>>();
+        self.remove_timestamps(deleted);
     }
 
     fn update_max_op(&mut self, timestamp: Timestamp) {
-        self.max_op = std::cmp::max(self.max_op, timestamp.0);
+        self.max_op = core::cmp::max(self.max_op, timestamp.0);
+        if let Some(hlc) = &mut self.hlc {
+            hlc.observe(timestamp);
+        }
+    }
+
+    // globally unique, once effected
+    fn next_timestamp(&self) -> Timestamp {
+        match &self.hlc {
+            Some(hlc) => (hlc.next(), self.actor_id),
+            None => (self.max_op + 1, self.actor_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    /// A remove-wins map that has set `key` to `value` and a copy of it on another server.
+    fn replicas(key: char, value: char) -> (FixedMap, FixedMap) {
+        let mut a = FixedMap::new(0);
+        a.set_policy(Policy::RemoveWins);
+        let mut b = FixedMap::new(1);
+        b.set_policy(Policy::RemoveWins);
+        let (context, timestamp) = a.prepare_set(key);
+        a.receive_set(context.clone(), timestamp, key, value);
+        b.receive_set(context, timestamp, key, value);
+        (a, b)
+    }
+
+    #[test]
+    fn merge_keeps_deletes_winning() {
+        let (mut a, mut b) = replicas('k', 'A');
+        // a overwrites the value while b concurrently deletes it
+        a.set('k', 'B');
+        b.delete(&'k');
+
+        let mut ab = a.clone();
+        ab.merge(&b);
+        let mut ba = b.clone();
+        ba.merge(&a);
+        assert_eq!(ab.get(&'k'), None);
+        assert_eq!(ab.values, ba.values);
+        assert_eq!(ab.deleted, ba.deleted);
+        assert_eq!(ab.contexts, ba.contexts);
+    }
+
+    #[test]
+    fn forget_stable_shrinks_contexts_and_deletes() {
+        let (mut a, _) = replicas('k', 'A');
+        let first = a.values.iter().next().unwrap().0;
+        a.set('k', 'B');
+        let second = a.values.iter().next().unwrap().0;
+        a.delete(&'k');
+        assert_eq!(a.contexts.len(), 2);
+        assert_eq!(a.deleted.len(), 1);
+
+        // only the first set is stable: the second's context held it, and its delete remains
+        a.forget_stable(&CausalContext::from(vec![first]));
+        assert_eq!(a.contexts.len(), 0);
+        assert!(a.deleted.contains(&second));
+
+        a.forget_stable(&CausalContext::from(vec![second]));
+        assert!(a.deleted.is_empty());
+        assert!(a.contexts.is_empty());
+        assert_eq!(a.get(&'k'), None);
+    }
+
+    #[test]
+    fn forget_stable_keeps_contexts_until_they_are_all_stable() {
+        let (mut a, _) = replicas('k', 'A');
+        a.set('j', 'B');
+        let first = (1, 0);
+        let (context, timestamp) = a.prepare_set('k');
+        a.receive_set(context, timestamp, 'k', 'C');
+        assert!(a.contexts[&timestamp].contains(&first));
+
+        // a delete of the first set could still arrive and have the overwrite lose
+        a.forget_stable(&CausalContext::from(vec![(2, 0)]));
+        assert!(a.contexts.contains_key(&timestamp));
+
+        a.forget_stable(&CausalContext::from(vec![first]));
+        assert!(!a.contexts.contains_key(&timestamp));
+        assert_eq!(a.get(&'k'), Some(&'C'));
     }
 
-    // globally unique
-    fn new_timestamp(&mut self) -> Timestamp {
-        self.max_op += 1;
-        let id: usize = self.actor_id.into();
-        (self.max_op, id)
+    #[test]
+    fn merge_is_idempotent() {
+        let (mut a, _) = replicas('k', 'A');
+        a.set('k', 'B');
+        a.delete(&'k');
+        a.set('j', 'C');
+        let mut merged = a.clone();
+        merged.merge(&a);
+        assert_eq!(merged, a);
     }
 }
@@ -0,0 +1,254 @@
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::context::CausalContext;
+use crate::hlc::Hlc;
+use crate::id::Id;
+use crate::map::ClockKind;
+use crate::map::Map;
+use crate::map::MapOp;
+use crate::map::MapSnapshot;
+use crate::map::Timestamp;
+
+impl Map for LwwMap {
+    fn new(actor_id: Id) -> Self {
+        Self::new(actor_id.into())
+    }
+
+    fn set_clock_skews(&mut self, skews: &[u32]) {
+        self.set_clock_skews(skews)
+    }
+
+    fn set_clock(&mut self, clock: ClockKind) {
+        self.set_clock(clock)
+    }
+
+    fn get(&self, k: &char) -> Option<&char> {
+        self.get(k)
+    }
+
+    fn prepare_set(&self, key: char, v: char) -> MapOp {
+        let (context, timestamp) = self.prepare_set(key);
+        MapOp::Set {
+            context,
+            timestamp,
+            key,
+            value: v,
+        }
+    }
+
+    fn prepare_delete(&self, key: &char) -> Option<MapOp> {
+        self.prepare_delete(key)
+            .map(|context| MapOp::Delete { context })
+    }
+
+    fn effect(&mut self, op: MapOp) {
+        match op {
+            MapOp::Set {
+                timestamp,
+                key,
+                value,
+                ..
+            } => self.receive_set(timestamp, key, value),
+            MapOp::Delete { context } => self.receive_delete(context),
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.merge(other)
+    }
+
+    fn max_counter(&self) -> u32 {
+        self.max_op
+    }
+
+    fn values(&self) -> Vec<(Timestamp, char, char)> {
+        self.values.iter().cloned().collect()
+    }
+
+    fn snapshot(&self) -> MapSnapshot {
+        MapSnapshot {
+            max_counter: self.max_op,
+            clock: self.hlc.as_ref().map_or(self.clock, |hlc| hlc.wall()),
+            values: self.values(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: MapSnapshot) {
+        self.max_op = snapshot.max_counter;
+        self.clock = snapshot.clock;
+        if let Some(hlc) = &mut self.hlc {
+            hlc.restore(snapshot.clock, snapshot.max_counter);
+        }
+        self.values = snapshot.values.into_iter().collect();
+    }
+
+    fn visible_values(&self) -> Vec<(Timestamp, char, char)> {
+        self.values()
+    }
+
+    fn summary(&self) -> BTreeMap<char, String> {
+        // keys never have siblings here, so show the clock reading that won instead
+        self.values
+            .iter()
+            .map(|((clock, actor), key, value)| {
+                (*key, format!("{:?} at {}@{}", value, clock, actor))
+            })
+            .collect()
+    }
+}
+
+/// A last-writer-wins map that timestamps ops with each server's wall clock, so a set only
+/// overwrites the values of its key that have older timestamps, whatever the server setting it
+/// had seen.
+///
+/// Wall clocks are modelled as running at the same rate, each some skew ahead of true time:
+/// preparing an op takes a tick, and receiving an op means true time has reached at least the
+/// time it was prepared at.
+///
+/// With a hybrid logical clock instead, a set is timestamped after every value its server has
+/// seen, so it overwrites them even when its server's wall clock is behind.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct LwwMap {
+    actor_id: usize,
+    /// How far ahead of true time each server's clock runs, by actor id.
+    skews: Vec<u32>,
+    /// This server's clock reading.
+    clock: u32,
+    max_op: u32,
+    /// The clock timestamps are drawn from instead of the wall clock, if any.
+    hlc: Option<Hlc>,
+    /// Every value held, with its timestamp and key.
+    pub values: BTreeSet<(Timestamp, char, char)>,
+}
+
+impl LwwMap {
+    /// An empty map for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            skews: Vec::new(),
+            clock: 0,
+            max_op: 0,
+            hlc: None,
+            values: BTreeSet::new(),
+        }
+    }
+
+    /// Draw timestamps from `clock`: the wall clock, which the map starts with, or a hybrid
+    /// logical clock.
+    pub fn set_clock(&mut self, clock: ClockKind) {
+        self.hlc = match clock {
+            ClockKind::Lamport => None,
+            ClockKind::Hlc => Some(Hlc::new(self.actor_id)),
+        };
+    }
+
+    /// Set how far ahead of true time each server's clock runs, by actor id.
+    pub fn set_clock_skews(&mut self, skews: &[u32]) {
+        self.skews = skews.to_vec();
+        self.clock = self.skew(self.actor_id);
+        let skew = self.clock;
+        if let Some(hlc) = &mut self.hlc {
+            hlc.set_skew(skew);
+        }
+    }
+
+    fn skew(&self, actor_id: usize) -> u32 {
+        self.skews.get(actor_id).copied().unwrap_or(0)
+    }
+
+    /// The value `key` holds, if it has one.
+    pub fn get(&self, key: &char) -> Option<&char> {
+        self.values
+            .iter()
+            .find(|(_, k, _)| k == key)
+            .map(|(_, _, v)| v)
+    }
+
+    /// The context and timestamp for setting `key`. The context is what the set is meant to
+    /// overwrite, but whether it does is down to the timestamps.
+    pub fn prepare_set(&self, key: char) -> (CausalContext, Timestamp) {
+        let counter = match &self.hlc {
+            Some(hlc) => hlc.next(),
+            None => self.clock + 1,
+        };
+        (self.context(key), (counter, self.actor_id))
+    }
+
+    /// The context for deleting `key`: its current value, if it has one.
+    pub fn prepare_delete(&self, key: &char) -> Option<CausalContext> {
+        let context = self.context(*key);
+        (!context.is_empty()).then_some(context)
+    }
+
+    fn context(&self, key: char) -> CausalContext {
+        self.values
+            .iter()
+            .filter_map(|(t, k, _)| if k == &key { Some(t) } else { None })
+            .cloned()
+            .collect()
+    }
+
+    /// Effect a set, whether prepared here or by another server.
+    pub fn receive_set(&mut self, timestamp: Timestamp, key: char, value: char) {
+        self.observe(timestamp);
+
+        // the set is lost if the key already has a later value
+        if self
+            .values
+            .iter()
+            .any(|(t, k, _)| k == &key && t > &timestamp)
+        {
+            return;
+        }
+        self.values.retain(|(_, k, _)| k != &key);
+        self.values.insert((timestamp, key, value));
+    }
+
+    /// Effect a delete, whether prepared here or by another server.
+    pub fn receive_delete(&mut self, context: CausalContext) {
+        if let Some(t) = context.max() {
+            self.observe(t)
+        }
+
+        self.remove_timestamps(&context);
+    }
+
+    /// Merge in the values another server's map holds, each key keeping whichever value has the
+    /// later timestamp.
+    pub fn merge(&mut self, other: &Self) {
+        for (timestamp, key, value) in &other.values {
+            self.receive_set(*timestamp, *key, *value)
+        }
+    }
+
+    /// Remove the values with the given timestamps. Values are ordered by timestamp first, so
+    /// each is looked up rather than scanning every value for every timestamp.
+    fn remove_timestamps(&mut self, timestamps: &CausalContext) {
+        for t in timestamps.iter() {
+            while let Some(value) = self
+                .values
+                .range((t, char::MIN, char::MIN)..=(t, char::MAX, char::MAX))
+                .next()
+                .cloned()
+            {
+                self.values.remove(&value);
+            }
+        }
+    }
+
+    /// Advance the clock to at least the reading it would have had when the op was prepared.
+    fn observe(&mut self, timestamp: Timestamp) {
+        self.max_op = core::cmp::max(self.max_op, timestamp.0);
+        if let Some(hlc) = &mut self.hlc {
+            hlc.observe(timestamp);
+            return;
+        }
+        let true_time = timestamp.0.saturating_sub(self.skew(timestamp.1));
+        self.clock = core::cmp::max(self.clock, true_time + self.skew(self.actor_id));
+    }
+}
@@ -0,0 +1,235 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use crate::context::CausalContext;
+use crate::map::Timestamp;
+
+/// An operation on a [`NestedMap`], addressed by the path of keys from the root, prepared at its
+/// origin and then effected exactly once at every server, as [`MapOp`](crate::map::MapOp)s are.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum NestedOp {
+    /// Set the key at the end of the path to a value, overwriting the values in the context.
+    Set {
+        /// The values this set overwrites: those at the path, under it, and at the paths above
+        /// it, which become maps.
+        context: CausalContext,
+        timestamp: Timestamp,
+        path: Vec<char>,
+        value: char,
+    },
+    /// Remove the values in the context, which are those in a subtree.
+    Delete {
+        /// The values this delete removes.
+        context: CausalContext,
+    },
+}
+
+impl NestedOp {
+    /// The timestamp of a set, which names it.
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        match self {
+            NestedOp::Set { timestamp, .. } => Some(*timestamp),
+            NestedOp::Delete { .. } => None,
+        }
+    }
+}
+
+/// A map whose values can themselves be maps, kept as the values at the end of each path of keys
+/// from the root. A path holds a map while any value is held under it.
+///
+/// As in [`FixedMap`](crate::map_fixed::FixedMap), sets and deletes remove exactly the values
+/// their server had seen, so deleting a subtree concurrently with a set inside it keeps the set,
+/// and the subtree along with it: adds win. Ops aren't delivered in causal order, so the values
+/// removed are remembered, and a set that arrives after an op that removed it is dropped.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct NestedMap {
+    actor_id: usize,
+    max_op: u32,
+    /// Every value held, with its timestamp and path.
+    pub values: BTreeSet<(Timestamp, Vec<char>, char)>,
+    /// The values that sets and deletes have removed.
+    pub removed: CausalContext,
+}
+
+impl NestedMap {
+    /// An empty map for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            max_op: 0,
+            values: BTreeSet::new(),
+            removed: CausalContext::new(),
+        }
+    }
+
+    /// The highest op counter this map has seen.
+    pub fn max_counter(&self) -> u32 {
+        self.max_op
+    }
+
+    /// The siblings at the end of `path`.
+    pub fn get(&self, path: &[char]) -> Vec<char> {
+        self.values
+            .iter()
+            .filter(|(_, p, _)| p == path)
+            .map(|(_, _, v)| *v)
+            .collect()
+    }
+
+    /// Every value at `path` or under it, with its timestamp and path.
+    pub fn subtree(&self, path: &[char]) -> Vec<(Timestamp, Vec<char>, char)> {
+        self.values
+            .iter()
+            .filter(|(_, p, _)| p.starts_with(path))
+            .cloned()
+            .collect()
+    }
+
+    /// Every path that holds a value or a map.
+    pub fn paths(&self) -> BTreeSet<Vec<char>> {
+        self.values
+            .iter()
+            .flat_map(|(_, p, _)| (1..=p.len()).map(move |len| p[..len].to_vec()))
+            .collect()
+    }
+
+    /// The context and timestamp for setting the key at the end of `path`.
+    pub fn prepare_set(&self, path: &[char]) -> (CausalContext, Timestamp) {
+        let context = self
+            .values
+            .iter()
+            .filter(|(_, p, _)| p.starts_with(path) || path.starts_with(p))
+            .map(|(t, _, _)| *t)
+            .collect();
+        (context, (self.max_op + 1, self.actor_id))
+    }
+
+    /// The context for deleting the subtree at `path`: every value at it or under it, if any.
+    pub fn prepare_delete(&self, path: &[char]) -> Option<CausalContext> {
+        let context = self
+            .subtree(path)
+            .into_iter()
+            .map(|(t, _, _)| t)
+            .collect::<CausalContext>();
+        (!context.is_empty()).then_some(context)
+    }
+
+    /// Effect a set, whether prepared here or by another server.
+    pub fn receive_set(
+        &mut self,
+        context: CausalContext,
+        timestamp: Timestamp,
+        path: Vec<char>,
+        value: char,
+    ) {
+        self.max_op = core::cmp::max(self.max_op, timestamp.0);
+
+        self.remove(context);
+        // an op that overwrote this set got here first
+        if !self.removed.contains(&timestamp) {
+            self.values.insert((timestamp, path, value));
+        }
+    }
+
+    /// Effect a delete, whether prepared here or by another server.
+    pub fn receive_delete(&mut self, context: CausalContext) {
+        if let Some(t) = context.max() {
+            self.max_op = core::cmp::max(self.max_op, t.0);
+        }
+
+        self.remove(context);
+    }
+
+    fn remove(&mut self, context: CausalContext) {
+        self.values.retain(|(t, _, _)| !context.contains(t));
+        self.removed.union(&context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn set(map: &mut NestedMap, path: &[char], value: char) -> NestedOp {
+        let (context, timestamp) = map.prepare_set(path);
+        let op = NestedOp::Set {
+            context,
+            timestamp,
+            path: path.to_vec(),
+            value,
+        };
+        effect(map, op.clone());
+        op
+    }
+
+    fn effect(map: &mut NestedMap, op: NestedOp) {
+        match op {
+            NestedOp::Set {
+                context,
+                timestamp,
+                path,
+                value,
+            } => map.receive_set(context, timestamp, path, value),
+            NestedOp::Delete { context } => map.receive_delete(context),
+        }
+    }
+
+    /// Two servers that both hold `a/b = x`, set at the first.
+    fn replicas() -> (NestedMap, NestedMap) {
+        let mut a = NestedMap::new(0);
+        let mut b = NestedMap::new(1);
+        let op = set(&mut a, &['a', 'b'], 'x');
+        effect(&mut b, op);
+        (a, b)
+    }
+
+    #[test]
+    fn concurrent_set_inside_a_deleted_subtree_survives() {
+        let (mut a, mut b) = replicas();
+        // a deletes the subtree at `a` while b sets a new key inside it
+        let context = a.prepare_delete(&['a']).unwrap();
+        let delete = NestedOp::Delete { context };
+        effect(&mut a, delete.clone());
+        let set_op = set(&mut b, &['a', 'c'], 'y');
+
+        effect(&mut a, set_op);
+        effect(&mut b, delete);
+        assert_eq!(a.values, b.values);
+        assert!(a.get(&['a', 'b']).is_empty());
+        assert_eq!(a.get(&['a', 'c']), vec!['y']);
+        assert!(a.paths().contains(&vec!['a']));
+    }
+
+    #[test]
+    fn delete_arriving_first_drops_the_set_it_removed() {
+        let (a, _) = replicas();
+        let context = a.prepare_delete(&['a']).unwrap();
+        let mut c = NestedMap::new(2);
+        c.receive_delete(context);
+        c.receive_set(CausalContext::new(), (1, 0), vec!['a', 'b'], 'x');
+        assert!(c.values.is_empty());
+        assert_eq!(c.max_counter(), 1);
+    }
+
+    #[test]
+    fn set_overwrites_the_values_above_and_below_it() {
+        let (mut a, _) = replicas();
+        set(&mut a, &['a'], 'z');
+        assert!(a.get(&['a', 'b']).is_empty());
+        assert_eq!(a.get(&['a']), vec!['z']);
+        assert!(a.removed.contains(&(1, 0)));
+    }
+
+    #[test]
+    fn concurrent_sets_at_a_path_are_siblings() {
+        let (mut a, mut b) = replicas();
+        let from_a = set(&mut a, &['a', 'b'], 'p');
+        let from_b = set(&mut b, &['a', 'b'], 'q');
+        effect(&mut a, from_b);
+        effect(&mut b, from_a);
+        assert_eq!(a.values, b.values);
+        assert_eq!(a.get(&['a', 'b']), vec!['p', 'q']);
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeSet;
+
+use stateright::actor::Id;
+
+/// What part a server plays when servers join and leave.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Role {
+    /// A member from the start that never leaves, which clients send their requests to.
+    Member,
+    /// A member from the start that leaves when its timer fires.
+    Leaver,
+    /// A server that asks to join when its timer fires.
+    Joiner,
+}
+
+/// Whether a server is currently a member.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Status {
+    /// A late joiner that hasn't asked to join yet.
+    Joining,
+    Member,
+    /// A server that has left, which ignores its peers from then on.
+    Left,
+}
+
+/// Which servers a server sends its ops to, when servers join and leave.
+///
+/// A late joiner asks every server to join. Each server that hasn't left adds it to its members
+/// and replies with its whole map, so the joiner gets every op a server applied before hearing
+/// of it and every op after through broadcasts. A leaver tells its members it is leaving, and
+/// they stop sending it ops.
+///
+/// Servers only track this when servers join or leave, so that states don't otherwise differ in
+/// it.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct Membership {
+    pub(crate) role: Role,
+    pub(crate) status: Status,
+    /// The other servers this one sends its ops to.
+    pub(crate) members: BTreeSet<usize>,
+}
+
+impl Membership {
+    /// A server's membership at the start, given the servers that are members from the start.
+    pub(crate) fn new(role: Role, initial: impl Iterator<Item = Id>) -> Self {
+        Self {
+            role,
+            status: match role {
+                Role::Member | Role::Leaver => Status::Member,
+                Role::Joiner => Status::Joining,
+            },
+            members: initial.map(usize::from).collect(),
+        }
+    }
+
+    /// Whether ops go to the given peer.
+    pub(crate) fn includes(&self, peer: Id) -> bool {
+        self.members.contains(&peer.into())
+    }
+}
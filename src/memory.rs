@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::mem::size_of;
+use std::mem::size_of_val;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use stateright::actor::Envelope;
+use stateright::CheckerVisitor;
+use stateright::Path;
+
+use crate::map::Map;
+use crate::map::Timestamp;
+use crate::model::RegisterAction;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::sync::PeerState;
+use crate::Key;
+use crate::MyRegisterActorState;
+use crate::MyRegisterMsg;
+use crate::PeerMsg;
+
+/// Approximate bytes in each part of the visited states, by component.
+#[derive(Debug, Default)]
+struct MemoryStats {
+    states: usize,
+    bytes: BTreeMap<&'static str, usize>,
+}
+
+/// Estimates how much memory the visited states take, to compare state representations.
+///
+/// Sizes are estimated from the shape of the state (entries, messages, contexts) rather than
+/// measured, and states are counted as if they shared nothing, although the checker shares actor
+/// states that haven't changed between a state and its successors.
+#[derive(Clone, Default)]
+pub(crate) struct MemoryAccounting(Arc<Mutex<MemoryStats>>);
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> CheckerVisitor<RegisterModel<M>>
+    for MemoryAccounting
+{
+    fn visit(&self, _: &RegisterModel<M>, path: Path<RegisterState<M>, RegisterAction<M>>) {
+        let (state, _) = path.into_vec().pop().expect("paths always have a state");
+
+        let mut bytes = BTreeMap::<&'static str, usize>::new();
+        for actor in &state.actor_states {
+            match &**actor {
+                MyRegisterActorState::Server(server) => {
+                    *bytes.entry("map entries").or_default() +=
+                        server.values().len() * size_of::<(Timestamp, char, char)>();
+                    *bytes.entry("servers, besides map entries").or_default() +=
+                        size_of::<PeerState<M>>();
+                }
+                MyRegisterActorState::PutClient { .. }
+                | MyRegisterActorState::DeleteClient { .. }
+                | MyRegisterActorState::ResolveClient { .. }
+                | MyRegisterActorState::ReadClient { .. }
+                | MyRegisterActorState::SetClient { .. }
+                | MyRegisterActorState::CounterClient { .. }
+                | MyRegisterActorState::MvRegisterClient { .. }
+                | MyRegisterActorState::FlagClient { .. }
+                | MyRegisterActorState::ListInsertClient { .. }
+                | MyRegisterActorState::ListDeleteClient { .. }
+                | MyRegisterActorState::NestedClient { .. }
+                | MyRegisterActorState::GetClient { .. }
+                | MyRegisterActorState::MixedClient { .. } => {
+                    *bytes.entry("client state").or_default() +=
+                        size_of::<MyRegisterActorState<M>>()
+                }
+            }
+        }
+        *bytes.entry("network").or_default() += state
+            .network
+            .iter_all()
+            .map(|envelope| size_of::<Envelope<MyRegisterMsg>>() + msg_heap_bytes(envelope.msg))
+            .sum::<usize>();
+        *bytes.entry("timers and history").or_default() +=
+            state.is_timer_set.len() * size_of::<bool>() + size_of_val(&state.history);
+
+        let mut stats = self.0.lock().unwrap();
+        stats.states += 1;
+        for (component, bytes) in bytes {
+            *stats.bytes.entry(component).or_default() += bytes;
+        }
+    }
+}
+
+impl MemoryAccounting {
+    pub(crate) fn report(&self) {
+        let stats = self.0.lock().unwrap();
+        let total = stats.bytes.values().sum::<usize>();
+        println!(
+            "Approximate memory: {} bytes across {} states, {} bytes/state",
+            total,
+            stats.states,
+            total / stats.states.max(1)
+        );
+        for (component, bytes) in &stats.bytes {
+            println!(
+                "  {:>10} bytes/state {}",
+                bytes / stats.states.max(1),
+                component
+            );
+        }
+    }
+}
+
+/// Bytes a message holds on the heap, beyond its own size.
+fn msg_heap_bytes(msg: &MyRegisterMsg) -> usize {
+    match msg {
+        MyRegisterMsg::Internal(msg) => peer_msg_heap_bytes(msg),
+        _ => 0,
+    }
+}
+
+fn peer_msg_heap_bytes(msg: &PeerMsg) -> usize {
+    match msg {
+        PeerMsg::PutSync { context, .. }
+        | PeerMsg::DeleteSync { context }
+        | PeerMsg::DeleteSyncAck { context }
+        | PeerMsg::NestedDeleteSync { context }
+        | PeerMsg::RemoveSync { context }
+        | PeerMsg::Truncate { barrier: context } => context.len() * size_of::<Timestamp>(),
+        PeerMsg::Have { heads, .. } => heads.len() * size_of::<u64>(),
+        PeerMsg::Need { hashes } => hashes.len() * size_of::<u64>(),
+        PeerMsg::Changes { changes } => changes
+            .iter()
+            .map(|change| {
+                size_of_val(change)
+                    + change.deps.len() * size_of::<u64>()
+                    + peer_msg_heap_bytes(&change.op)
+            })
+            .sum(),
+        PeerMsg::After { op, .. } => size_of::<PeerMsg>() + peer_msg_heap_bytes(op),
+        PeerMsg::Stamped { clock, op, .. } => {
+            clock.len() * size_of::<(usize, usize)>()
+                + size_of::<PeerMsg>()
+                + peer_msg_heap_bytes(op)
+        }
+        PeerMsg::Pull { vector } => vector.len() * size_of::<(usize, usize)>(),
+        PeerMsg::Ops { ops } => ops
+            .iter()
+            .map(|pulled| size_of_val(pulled) + peer_msg_heap_bytes(&pulled.op))
+            .sum(),
+        PeerMsg::Digest { held, removed } => (held.len() + removed.len()) * size_of::<Timestamp>(),
+        PeerMsg::Repair { ops } => ops
+            .iter()
+            .map(|op| size_of::<PeerMsg>() + peer_msg_heap_bytes(op))
+            .sum(),
+        PeerMsg::CounterState {
+            increments,
+            decrements,
+        } => (increments.len() + decrements.len()) * size_of::<u64>(),
+        PeerMsg::RegisterSync { clock, .. } | PeerMsg::FlagSync { clock, .. } => {
+            clock.len() * size_of::<u32>()
+        }
+        PeerMsg::StateSync { values, removed } | PeerMsg::DeltaSync { values, removed } => {
+            values.len() * size_of::<(Timestamp, Key, Key)>()
+                + removed.len() * size_of::<Timestamp>()
+        }
+        PeerMsg::NestedSetSync { context, path, .. } => {
+            context.len() * size_of::<Timestamp>() + path.len() * size_of::<Key>()
+        }
+        PeerMsg::AddSync { .. }
+        | PeerMsg::LwwRegisterSync { .. }
+        | PeerMsg::ListInsertSync { .. }
+        | PeerMsg::ListDeleteSync { .. }
+        | PeerMsg::DepCheck { .. }
+        | PeerMsg::DepOk { .. }
+        | PeerMsg::Join
+        | PeerMsg::Leave
+        | PeerMsg::PutSyncAck { .. }
+        | PeerMsg::EpochClose { .. }
+        | PeerMsg::EpochDone { .. } => 0,
+        PeerMsg::Watermark { clock } => clock.len() * size_of::<(usize, usize)>(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use stateright::actor::ActorModel;
+use stateright::actor::Envelope;
+use stateright::Model;
+use stateright::Path;
+use stateright::Property;
+
+use crate::causality;
+use crate::causality::CausalHistory;
+use crate::map::Map;
+use crate::partition;
+use crate::properties;
+use crate::reduction;
+use crate::render;
+use crate::sec;
+use crate::sec::SecHistory;
+use crate::trace;
+use crate::trace::PrefixProgress;
+use crate::ModelCfg;
+use crate::MyRegisterActor;
+use crate::MyRegisterMsg;
+
+pub(crate) type RegisterActorModel<M> = ActorModel<MyRegisterActor<M>, ModelCfg, History>;
+pub(crate) type RegisterState<M> = <RegisterActorModel<M> as Model>::State;
+pub(crate) type RegisterAction<M> = <RegisterActorModel<M> as Model>::Action;
+
+/// What the model records about the run alongside the actor states.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct History {
+    pub(crate) prefix: PrefixProgress,
+    pub(crate) sec: SecHistory,
+    pub(crate) causality: CausalHistory,
+}
+
+pub(crate) fn record_msg_in(
+    cfg: &ModelCfg,
+    history: &History,
+    envelope: Envelope<&MyRegisterMsg>,
+) -> Option<History> {
+    let prefix = trace::record_delivery(cfg, &history.prefix, &envelope);
+    let sec = sec::record_in(cfg, &history.sec, &envelope);
+    let causality = causality::record_in(cfg, &history.causality, &envelope);
+    if prefix.is_none() && sec.is_none() && causality.is_none() {
+        return None;
+    }
+    Some(History {
+        prefix: prefix.unwrap_or_else(|| history.prefix.clone()),
+        sec: sec.unwrap_or_else(|| history.sec.clone()),
+        causality: causality.unwrap_or_else(|| history.causality.clone()),
+    })
+}
+
+pub(crate) fn record_msg_out(
+    cfg: &ModelCfg,
+    history: &History,
+    envelope: Envelope<&MyRegisterMsg>,
+) -> Option<History> {
+    let sec = sec::record_out(cfg, &history.sec, &envelope);
+    let causality = causality::record_out(cfg, &history.causality, &envelope);
+    if sec.is_none() && causality.is_none() {
+        return None;
+    }
+    Some(History {
+        prefix: history.prefix.clone(),
+        sec: sec.unwrap_or_else(|| history.sec.clone()),
+        causality: causality.unwrap_or_else(|| history.causality.clone()),
+    })
+}
+
+/// The actor model of the register system along with its properties.
+///
+/// This wraps the [`ActorModel`] so that the actions it explores can be reduced.
+pub(crate) struct RegisterModel<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    pub(crate) actor_model: RegisterActorModel<M>,
+}
+
+impl<M> RegisterModel<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    pub(crate) fn cfg(&self) -> &ModelCfg {
+        &self.actor_model.cfg
+    }
+}
+
+impl<M> Model for RegisterModel<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    type State = RegisterState<M>;
+
+    type Action = RegisterAction<M>;
+
+    fn init_states(&self) -> Vec<Self::State> {
+        self.actor_model.init_states()
+    }
+
+    fn actions(&self, state: &Self::State, actions: &mut Vec<Self::Action>) {
+        self.actor_model.actions(state, actions);
+        // before reducing, so that the delivery kept of those that commute isn't blocked
+        if self.cfg().partitions {
+            partition::block(state, actions);
+        }
+        if self.cfg().reduction {
+            reduction::reduce(state, actions);
+        }
+    }
+
+    fn next_state(&self, last_state: &Self::State, action: Self::Action) -> Option<Self::State> {
+        self.actor_model.next_state(last_state, action)
+    }
+
+    fn format_action(&self, action: &Self::Action) -> String {
+        self.actor_model.format_action(action)
+    }
+
+    fn format_step(&self, last_state: &Self::State, action: Self::Action) -> Option<String> {
+        let next_state = self.actor_model.next_state(last_state, action.clone())?;
+        let outcome = self.actor_model.format_step(last_state, action)?;
+        Some(format!(
+            "{}\n\n{}",
+            render::server_tables(&next_state),
+            outcome
+        ))
+    }
+
+    fn as_svg(&self, path: Path<Self::State, Self::Action>) -> Option<String> {
+        self.actor_model.as_svg(path)
+    }
+
+    fn properties(&self) -> Vec<Property<Self>> {
+        properties::properties(self.cfg())
+    }
+
+    fn within_boundary(&self, state: &Self::State) -> bool {
+        self.actor_model.within_boundary(state)
+    }
+}
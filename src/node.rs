@@ -0,0 +1,119 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::net::SocketAddrV4;
+
+use stateright::actor::spawn;
+use stateright::actor::Id;
+
+#[cfg(feature = "grpc")]
+use crate::grpc;
+use crate::http;
+use crate::map::Map;
+use crate::watch::Feed;
+use crate::websocket;
+use crate::wire;
+use crate::ModelCfg;
+use crate::MyRegisterMsg;
+
+/// Frontends serving a running server's keys to clients that don't speak its own protocol, each
+/// translating their requests into the server's.
+#[derive(clap::Args)]
+pub(crate) struct Frontends {
+    /// Also serve GET, PUT and DELETE on `/keys/{k}` over HTTP on this address.
+    #[clap(long)]
+    http: Option<SocketAddrV4>,
+    /// Also serve the `Store` gRPC service from `proto/toy_crdt.proto` on this address.
+    #[cfg(feature = "grpc")]
+    #[clap(long)]
+    grpc: Option<SocketAddrV4>,
+    /// Also stream every change to the value a key resolves to, as JSON, to WebSocket clients on
+    /// this address.
+    #[clap(long)]
+    websocket: Option<SocketAddrV4>,
+}
+
+/// Run a server for real, with the same actor the checker explores, exchanging messages with
+/// its peers as UDP datagrams in the wire format. Servers are named by their addresses, so each
+/// peer has to be started with this server's `listen` address among its `peers`.
+///
+/// Clients talk to the server the same way, sending it requests such as `Put` and `Get` from
+/// their own address and getting the responses back there.
+///
+/// The frontends serve the server's keys over other protocols, on addresses of their own. The
+/// map is kept in a [`Feed`], so that they can follow its changes.
+///
+/// Ops are only accepted from the servers listed, this one included, as named by their addresses.
+///
+/// This only returns if the server's thread stops.
+pub(crate) fn run<M>(
+    cfg: &ModelCfg,
+    listen: SocketAddrV4,
+    peers: &[SocketAddrV4],
+    frontends: &Frontends,
+) where
+    M: Clone + Debug + PartialEq + Hash + Map + Send + 'static,
+{
+    let mut cfg = cfg.clone();
+    cfg.validation.servers = peers
+        .iter()
+        .chain(Some(&listen))
+        .map(|addr| usize::from(Id::from(*addr)))
+        .collect();
+    let server = cfg.server::<Feed<M>>(0, peers.iter().copied().map(Id::from).collect());
+    let handles = spawn(
+        |msg: &MyRegisterMsg| Ok::<_, String>(wire::encode(msg)),
+        wire::decode::<MyRegisterMsg>,
+        vec![(listen, server)],
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("Failed to listen on {}: {}", listen, e);
+        std::process::exit(2)
+    });
+    println!(
+        "Running server on udp://{} with {} peers",
+        listen,
+        peers.len()
+    );
+    if let Some(http) = frontends.http {
+        http::serve(http, listen)
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(grpc) = frontends.grpc {
+        grpc::serve(grpc, listen)
+    }
+    if let Some(addr) = frontends.websocket {
+        websocket::serve(addr)
+    }
+    for handle in handles {
+        let _ = handle.join();
+    }
+}
+
+/// Check that the options describe servers that can run for real, exiting if not. Faults the
+/// checker injects, and servers whose behaviour depends on their index rather than their
+/// address, aren't supported. Neither are counters, registers and flags, which keep an entry for
+/// every actor id up to their own and so can't be keyed by addresses.
+pub(crate) fn validate(cfg: &ModelCfg, listen: SocketAddrV4, peers: &[SocketAddrV4]) {
+    if cfg.crash
+        || cfg.partitions
+        || cfg.byzantine
+        || cfg.late_joiners > 0
+        || cfg.leavers > 0
+        || cfg.replication_factor.is_some()
+    {
+        eprintln!("run doesn't support --crash, --partitions, --byzantine, --late-joiners, --leavers or --replication-factor");
+        std::process::exit(2)
+    }
+    if cfg.counter_clients > 0
+        || cfg.mv_register_clients > 0
+        || cfg.flag_enable_clients > 0
+        || cfg.flag_disable_clients > 0
+    {
+        eprintln!("run doesn't support --counter-clients, --mv-register-clients, --flag-enable-clients or --flag-disable-clients");
+        std::process::exit(2)
+    }
+    if peers.contains(&listen) {
+        eprintln!("--peers can't include the --listen address");
+        std::process::exit(2)
+    }
+}
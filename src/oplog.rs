@@ -0,0 +1,124 @@
+use std::fmt::Debug;
+use std::fmt::Write;
+use std::hash::Hash;
+use std::path::Path;
+
+use stateright::actor::ActorModelAction;
+use stateright::actor::Id;
+
+use crate::explain::applied_ops;
+use crate::explain::Op;
+use crate::map::Map;
+use crate::model::RegisterModel;
+use crate::trace::replay;
+use crate::trace::Trace;
+use crate::watch::Watched;
+use crate::MyRegisterMsg;
+use crate::PeerMsg;
+
+/// Load a list of ops from a JSON file, either as an array or one op per line (as exported), e.g.
+///
+/// ```json
+/// [
+///   { "op": "set", "context": [], "timestamp": [1, 0], "key": "k", "value": "A" },
+///   { "op": "delete", "context": [[1, 0]] }
+/// ]
+/// ```
+pub(crate) fn load(path: &Path) -> Result<Vec<Op>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read ops {:?}: {}", path, e))?;
+    if contents.trim_start().starts_with('[') {
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse ops {:?}: {}", path, e))
+    } else {
+        contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("Failed to parse op {:?}:{}: {}", path, i + 1, e))
+            })
+            .collect()
+    }
+}
+
+/// The ops generated by the servers while replaying a trace, in the order they were generated,
+/// as JSON Lines.
+///
+/// Each op is exported once, by the server it originated at: sets carry the origin in their
+/// timestamp and deletes name it explicitly.
+pub(crate) fn export<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    trace: &Trace,
+) -> String {
+    let steps = replay(model, trace);
+    let mut out = String::new();
+    for (i, (_, action)) in steps.iter().enumerate() {
+        let server = match action {
+            Some(ActorModelAction::Deliver {
+                src: _,
+                dst,
+                msg:
+                    MyRegisterMsg::Put(_, _, _)
+                    | MyRegisterMsg::PutAfter(_, _, _, _)
+                    | MyRegisterMsg::PutResolved(_, _, _, _)
+                    | MyRegisterMsg::Delete(_, _)
+                    | MyRegisterMsg::Truncate(_)
+                    // dependent writes held for a check across shards are applied on its answer
+                    | MyRegisterMsg::Internal(PeerMsg::DepOk { .. }),
+            }) => usize::from(*dst),
+            _ => continue,
+        };
+        for op in applied_ops(&steps[i..=i + 1], server) {
+            writeln!(out, "{}", serde_json::to_string(&op).unwrap()).unwrap();
+        }
+    }
+    out
+}
+
+/// Apply the ops in order to a fresh map, as if they had all been received from other servers,
+/// printing how the resolved values of the watched keys change along the way.
+pub(crate) fn apply<M: Map>(ops: &[Op], watch: &[char]) -> M {
+    let mut map = Watched::<M>::new(Id::from(0));
+    let feeds = watch
+        .iter()
+        .map(|key| map.subscribe(*key))
+        .collect::<Vec<_>>();
+    for op in ops {
+        op.apply(&mut map);
+        for event in feeds.iter().flat_map(|feed| feed.try_iter()) {
+            println!("{}", event);
+        }
+    }
+    map.into_inner()
+}
+
+/// Render the resolved value of each key along with every value the map holds.
+pub(crate) fn render<M: Map>(map: &M) -> String {
+    let mut out = String::new();
+    let values = map.values();
+    let visible = map.visible_values();
+    let mut keys = values.iter().map(|(_, key, _)| *key).collect::<Vec<_>>();
+    keys.sort_unstable();
+    keys.dedup();
+    for key in keys {
+        match map.get(&key) {
+            Some(value) => writeln!(out, "{:?} = {:?}", key, value).unwrap(),
+            None => writeln!(out, "{:?} = (none)", key).unwrap(),
+        }
+    }
+    for value in &values {
+        let (timestamp, key, v) = value;
+        let hidden = if visible.contains(value) {
+            ""
+        } else {
+            " (hidden)"
+        };
+        writeln!(out, "  {:?} {:?} = {:?}{}", timestamp, key, v, hidden).unwrap();
+    }
+    if values.is_empty() {
+        writeln!(out, "(empty)").unwrap();
+    }
+    out
+}
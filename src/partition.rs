@@ -0,0 +1,49 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use stateright::actor::ActorModelAction;
+
+use crate::map::Map;
+use crate::model::RegisterAction;
+use crate::model::RegisterState;
+use crate::MyRegisterActorState;
+
+/// Whether a server is cut off from its peers with `--partitions`.
+///
+/// Each server is cut off once, when its timer first fires, and heals when it fires again. The
+/// checker fires timers at any point, so partitions start and heal between any two steps and
+/// servers cut off at the same time form partitions of one. Messages between a cut off server
+/// and its peers stay in the network until it heals, while its clients still reach it.
+///
+/// Servers only track this with partitions, so that states don't otherwise differ in it.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub(crate) enum Isolation {
+    /// Yet to be cut off.
+    Pending,
+    /// Cut off until the timer fires again.
+    Isolated,
+    Healed,
+}
+
+/// Drop the deliveries between servers that a partition separates, leaving the messages in the
+/// network for after it heals.
+pub(crate) fn block<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+    actions: &mut Vec<RegisterAction<M>>,
+) {
+    let isolated = |index: usize| {
+        matches!(
+            &*state.actor_states[index],
+            MyRegisterActorState::Server(server) if server.isolation == Some(Isolation::Isolated)
+        )
+    };
+    let is_server =
+        |index: usize| matches!(&*state.actor_states[index], MyRegisterActorState::Server(_));
+    actions.retain(|action| match action {
+        ActorModelAction::Deliver { src, dst, .. } => {
+            let (src, dst) = (usize::from(*src), usize::from(*dst));
+            !(is_server(src) && is_server(dst) && (isolated(src) || isolated(dst)))
+        }
+        _ => true,
+    });
+}
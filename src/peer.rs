@@ -0,0 +1,483 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::context::CausalContext;
+use crate::fingerprint::fingerprint;
+use crate::flag::FlagWrite;
+use crate::id::Id;
+use crate::list::ListOp;
+use crate::map::Map;
+use crate::map::MapOp;
+use crate::map::Timestamp;
+use crate::map_nested::NestedOp;
+use crate::register_lww::LwwWrite;
+use crate::register_mv::Write;
+use crate::set::SetOp;
+
+/// How many ops from each server a server has delivered, its own included.
+pub type VectorClock = BTreeMap<usize, usize>;
+
+/// The fingerprint of a [`Change`], which names it in the have/need exchange.
+pub type ChangeHash = u64;
+
+/// An op along with the changes the server had most recently seen when it applied it, which
+/// gives the changes a causal order as in Automerge.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    pub deps: Vec<ChangeHash>,
+    pub op: PeerMsg,
+}
+
+impl Change {
+    /// The change's fingerprint, which stays the same across builds and machines.
+    pub fn hash(&self) -> ChangeHash {
+        fingerprint(self)
+    }
+}
+
+/// A 64-bit Bloom filter of change hashes, setting two bits per change.
+///
+/// It is tiny on purpose so that false positives show up in the explored state space.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bloom(u64);
+
+impl Bloom {
+    fn bits(hash: ChangeHash) -> u64 {
+        (1 << (hash % 64)) | (1 << ((hash >> 32) % 64))
+    }
+
+    pub fn insert(&mut self, hash: ChangeHash) {
+        self.0 |= Self::bits(hash)
+    }
+
+    /// Whether the change may have been inserted; false means it definitely wasn't.
+    pub fn may_contain(&self, hash: ChangeHash) -> bool {
+        self.0 & Self::bits(hash) == Self::bits(hash)
+    }
+}
+
+/// An op sent in reply to a pull, with the server it originated at and its position among that
+/// server's ops.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PulledOp {
+    pub origin: usize,
+    pub seq: usize,
+    pub op: PeerMsg,
+}
+
+/// A message between servers: an op on one of their CRDTs, the state of one to merge, or part
+/// of one of the sync protocols the binary checks.
+///
+/// The ops, from `PutSync` to `NestedDeleteSync` along with `Truncate`, are stable and are what
+/// [`publish`], [`receive_sync`] and the `From` impls deal in. The rest belong to the binary's
+/// sync protocols and may change or go away between versions, so embedders matching on a message
+/// need a wildcard arm.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum PeerMsg {
+    /// Sets the key in the servers' maps, overwriting the values in the context.
+    PutSync {
+        context: CausalContext,
+        timestamp: Timestamp,
+        key: char,
+        value: char,
+    },
+    /// Removes the values in the context from the servers' maps.
+    DeleteSync { context: CausalContext },
+    /// Adds an element to the servers' sets under a tag unique to the add.
+    AddSync { tag: Timestamp, element: char },
+    /// Removes the tags of an element that the remove's origin had observed from the servers'
+    /// sets.
+    RemoveSync { context: CausalContext },
+    /// The whole state of the sender's counter, for the receivers to merge into theirs.
+    CounterState {
+        increments: Vec<u64>,
+        decrements: Vec<u64>,
+    },
+    /// Writes a value to the servers' multi-value registers, overwriting the writes its clock
+    /// has seen.
+    RegisterSync {
+        timestamp: Timestamp,
+        clock: Vec<u32>,
+        value: char,
+    },
+    /// Writes a value to the servers' last-writer-wins registers, where it wins if its timestamp
+    /// is greater than the write's they hold.
+    LwwRegisterSync { timestamp: Timestamp, value: char },
+    /// Enables or disables the servers' flags, overwriting the writes its clock has seen.
+    FlagSync {
+        timestamp: Timestamp,
+        clock: Vec<u32>,
+        enable: bool,
+    },
+    /// Inserts an element into the servers' lists after the element with id `after`, or at the
+    /// start.
+    ListInsertSync {
+        id: Timestamp,
+        after: Option<Timestamp>,
+        value: char,
+    },
+    /// Deletes an element from the servers' lists.
+    ListDeleteSync { id: Timestamp },
+    /// Sets the key at the end of a path in the servers' nested maps, overwriting the values in
+    /// the context.
+    NestedSetSync {
+        context: CausalContext,
+        timestamp: Timestamp,
+        path: Vec<char>,
+        value: char,
+    },
+    /// Removes the values in the context from the servers' nested maps.
+    NestedDeleteSync { context: CausalContext },
+    /// The sender's whole map and the timestamps of the values it has removed, for the receivers
+    /// to merge into theirs.
+    StateSync {
+        values: Vec<(Timestamp, char, char)>,
+        removed: CausalContext,
+    },
+    /// The deltas of the sender's ops on its map since it last sent one, joined together, for
+    /// the receiver to merge into its map.
+    DeltaSync {
+        values: Vec<(Timestamp, char, char)>,
+        removed: CausalContext,
+    },
+    /// An op on the map stamped with its origin's vector clock, only delivered once every op its
+    /// origin had delivered before it has been.
+    Stamped {
+        origin: usize,
+        clock: VectorClock,
+        op: Box<PeerMsg>,
+    },
+    /// The sender's latest changes and a Bloom filter of all the changes it has.
+    Have {
+        heads: Vec<ChangeHash>,
+        bloom: Bloom,
+    },
+    /// Request for the changes with these hashes.
+    Need { hashes: Vec<ChangeHash> },
+    /// Changes that the receiver is missing or asked for.
+    Changes { changes: Vec<Change> },
+    /// An op that must only be applied after the set with the `dependency` timestamp.
+    After {
+        dependency: Timestamp,
+        op: Box<PeerMsg>,
+    },
+    /// Request to be told once the receiver has applied the set with the `dependency`
+    /// timestamp, sent to the servers holding its key by a server on another shard.
+    DepCheck { dependency: Timestamp },
+    /// Confirmation that the sender has applied the set with the `dependency` timestamp.
+    DepOk { dependency: Timestamp },
+    /// Request for the ops the sender is missing, given how many ops from each origin it has.
+    Pull { vector: BTreeMap<usize, usize> },
+    /// Reply to a pull with the ops the puller was missing, possibly none.
+    Ops { ops: Vec<PulledOp> },
+    /// Request from a late joiner to be sent ops from now on, along with the whole map.
+    Join,
+    /// Notice that the sender is leaving, so that it is no longer sent ops.
+    Leave,
+    /// Acknowledgement of the set with the `timestamp`, so that the sender stops retransmitting it.
+    PutSyncAck { timestamp: Timestamp },
+    /// Acknowledgement of the delete with the `context`, so that the sender stops retransmitting
+    /// it.
+    DeleteSyncAck { context: CausalContext },
+    /// The values the sender's map holds and has removed, for the receiver to reply with the ops
+    /// the sender is missing.
+    Digest {
+        held: CausalContext,
+        removed: CausalContext,
+    },
+    /// Reply to a digest with the ops the sender of the digest was missing, possibly none.
+    Repair { ops: Vec<PeerMsg> },
+    /// Removes every set its origin had seen, and any of them that arrive later.
+    Truncate { barrier: CausalContext },
+    /// Marker closing the sender's epoch, after every op it prepared in it.
+    EpochClose { epoch: u32 },
+    /// Tells peers that the markers closing the epoch have arrived from all of the sender's
+    /// peers.
+    EpochDone { epoch: u32 },
+    /// The sender's vector clock, sent after it delivers ops so that its peers can tell which
+    /// ops every server has delivered.
+    Watermark { clock: VectorClock },
+}
+
+impl PeerMsg {
+    /// Whether this is a single op on the map, rather than part of the have/need exchange or an
+    /// op on the set, the counter, the multi-value register, the flag, the list or the nested map.
+    pub fn is_op(&self) -> bool {
+        matches!(
+            self,
+            PeerMsg::PutSync { .. }
+                | PeerMsg::DeleteSync { .. }
+                | PeerMsg::After { .. }
+                | PeerMsg::Truncate { .. }
+        )
+    }
+
+    /// Whether this is a set, an add to the set, a write to the register or the flag, an insert
+    /// into the list or a set in the nested map, that the given server prepared.
+    pub fn prepared_by(&self, server: Id) -> bool {
+        match self {
+            PeerMsg::PutSync { timestamp, .. }
+            | PeerMsg::AddSync { tag: timestamp, .. }
+            | PeerMsg::RegisterSync { timestamp, .. }
+            | PeerMsg::LwwRegisterSync { timestamp, .. }
+            | PeerMsg::FlagSync { timestamp, .. }
+            | PeerMsg::ListInsertSync { id: timestamp, .. }
+            | PeerMsg::NestedSetSync { timestamp, .. } => timestamp.1 == usize::from(server),
+            PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => op.prepared_by(server),
+            _ => false,
+        }
+    }
+}
+
+impl From<MapOp> for PeerMsg {
+    fn from(op: MapOp) -> Self {
+        match op {
+            MapOp::Set {
+                context,
+                timestamp,
+                key,
+                value,
+            } => PeerMsg::PutSync {
+                context,
+                timestamp,
+                key,
+                value,
+            },
+            MapOp::Delete { context } => PeerMsg::DeleteSync { context },
+        }
+    }
+}
+
+impl From<SetOp> for PeerMsg {
+    fn from(op: SetOp) -> Self {
+        match op {
+            SetOp::Add { tag, element } => PeerMsg::AddSync { tag, element },
+            SetOp::Remove { context } => PeerMsg::RemoveSync { context },
+        }
+    }
+}
+
+impl From<Write> for PeerMsg {
+    fn from(write: Write) -> Self {
+        PeerMsg::RegisterSync {
+            timestamp: write.timestamp,
+            clock: write.clock,
+            value: write.value,
+        }
+    }
+}
+
+impl From<LwwWrite> for PeerMsg {
+    fn from(write: LwwWrite) -> Self {
+        PeerMsg::LwwRegisterSync {
+            timestamp: write.timestamp,
+            value: write.value,
+        }
+    }
+}
+
+impl From<FlagWrite> for PeerMsg {
+    fn from(write: FlagWrite) -> Self {
+        PeerMsg::FlagSync {
+            timestamp: write.timestamp,
+            clock: write.clock,
+            enable: write.value,
+        }
+    }
+}
+
+impl From<ListOp> for PeerMsg {
+    fn from(op: ListOp) -> Self {
+        match op {
+            ListOp::Insert { id, after, value } => PeerMsg::ListInsertSync { id, after, value },
+            ListOp::Delete { id } => PeerMsg::ListDeleteSync { id },
+        }
+    }
+}
+
+impl From<NestedOp> for PeerMsg {
+    fn from(op: NestedOp) -> Self {
+        match op {
+            NestedOp::Set {
+                context,
+                timestamp,
+                path,
+                value,
+            } => PeerMsg::NestedSetSync {
+                context,
+                timestamp,
+                path,
+                value,
+            },
+            NestedOp::Delete { context } => PeerMsg::NestedDeleteSync { context },
+        }
+    }
+}
+
+/// Apply an op that was broadcast by another peer.
+///
+/// A truncate only removes the sets it names that the map already holds; receive through
+/// [`Barriers::receive_sync`] for those that arrive after it to be dropped too.
+pub fn receive_sync<M: Map>(map: &mut M, msg: PeerMsg) {
+    match msg {
+        PeerMsg::PutSync {
+            context,
+            timestamp,
+            key,
+            value,
+        } => map.effect(MapOp::Set {
+            context,
+            timestamp,
+            key,
+            value,
+        }),
+        PeerMsg::DeleteSync { context } => map.effect(MapOp::Delete { context }),
+        PeerMsg::Truncate { barrier } => map.effect(MapOp::Delete { context: barrier }),
+        PeerMsg::Changes { changes } => {
+            for change in changes {
+                receive_sync(map, change.op)
+            }
+        }
+        PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => receive_sync(map, *op),
+        PeerMsg::Ops { ops } => {
+            for pulled in ops {
+                receive_sync(map, pulled.op)
+            }
+        }
+        PeerMsg::Repair { ops } => {
+            for op in ops {
+                receive_sync(map, op)
+            }
+        }
+        // set ops, counter states, register and flag writes, list ops and nested map ops go to
+        // the server's set, counter, register, flag, list and nested map instead, and states and
+        // deltas are merged along with what the sender removed
+        PeerMsg::AddSync { .. }
+        | PeerMsg::RemoveSync { .. }
+        | PeerMsg::CounterState { .. }
+        | PeerMsg::RegisterSync { .. }
+        | PeerMsg::LwwRegisterSync { .. }
+        | PeerMsg::FlagSync { .. }
+        | PeerMsg::ListInsertSync { .. }
+        | PeerMsg::ListDeleteSync { .. }
+        | PeerMsg::NestedSetSync { .. }
+        | PeerMsg::NestedDeleteSync { .. }
+        | PeerMsg::StateSync { .. }
+        | PeerMsg::DeltaSync { .. }
+        | PeerMsg::Have { .. }
+        | PeerMsg::Need { .. }
+        | PeerMsg::DepCheck { .. }
+        | PeerMsg::DepOk { .. }
+        | PeerMsg::Pull { .. }
+        | PeerMsg::Join
+        | PeerMsg::Leave
+        | PeerMsg::PutSyncAck { .. }
+        | PeerMsg::DeleteSyncAck { .. }
+        | PeerMsg::Digest { .. }
+        | PeerMsg::EpochClose { .. }
+        | PeerMsg::EpochDone { .. }
+        | PeerMsg::Watermark { .. } => {}
+    }
+}
+
+/// Effect an op prepared on this server, returning the message to send the other servers for
+/// them to effect it with [`receive_sync`].
+pub fn publish<M: Map>(map: &mut M, op: MapOp) -> PeerMsg {
+    map.effect(op.clone());
+    op.into()
+}
+
+/// The sets removed by the truncates a replica has received, so that a truncate acts as a causal
+/// barrier as it does on the servers: it removes every set its origin had seen, wherever and
+/// whenever those sets arrive, and nothing else.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Barriers {
+    removed: CausalContext,
+}
+
+impl Barriers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an op that was broadcast by another peer, as [`receive_sync`] does, then drop the
+    /// sets that a truncate received so far removed before they arrived.
+    pub fn receive_sync<M: Map>(&mut self, map: &mut M, msg: PeerMsg) {
+        self.record(&msg);
+        receive_sync(map, msg);
+        let late = map
+            .values()
+            .into_iter()
+            .map(|(timestamp, _, _)| timestamp)
+            .filter(|timestamp| self.removed.contains(timestamp))
+            .collect::<CausalContext>();
+        if !late.is_empty() {
+            map.effect(MapOp::Delete { context: late });
+        }
+    }
+
+    fn record(&mut self, msg: &PeerMsg) {
+        match msg {
+            PeerMsg::Truncate { barrier } => self.removed.union(barrier),
+            PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => self.record(op),
+            PeerMsg::Changes { changes } => {
+                for change in changes {
+                    self.record(&change.op)
+                }
+            }
+            PeerMsg::Ops { ops } => {
+                for pulled in ops {
+                    self.record(&pulled.op)
+                }
+            }
+            PeerMsg::Repair { ops } => {
+                for op in ops {
+                    self.record(op)
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::map_fixed::FixedMap;
+
+    #[test]
+    fn truncate_drops_a_set_arriving_after_it() {
+        let a = <FixedMap as Map>::new(Id::from(0));
+        let mut b = <FixedMap as Map>::new(Id::from(1));
+        let set = PeerMsg::from(Map::prepare_set(&a, 'x', 'A'));
+        let truncate = PeerMsg::Truncate {
+            barrier: CausalContext::from(vec![(1, 0)]),
+        };
+
+        let mut barriers = Barriers::new();
+        barriers.receive_sync(&mut b, truncate);
+        barriers.receive_sync(&mut b, set);
+        assert_eq!(b.get(&'x'), None);
+    }
+
+    #[test]
+    fn truncate_keeps_a_concurrent_set() {
+        let mut b = <FixedMap as Map>::new(Id::from(1));
+        let truncate = PeerMsg::Truncate {
+            barrier: CausalContext::from(vec![(1, 0)]),
+        };
+
+        let mut barriers = Barriers::new();
+        barriers.receive_sync(&mut b, truncate);
+        let set = Map::prepare_set(&b, 'x', 'B');
+        barriers.receive_sync(&mut b, set.into());
+        assert_eq!(b.get(&'x'), Some(&'B'));
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use stateright::CheckerVisitor;
+use stateright::Model;
+use stateright::Path;
+
+use crate::fingerprint::fingerprint;
+use crate::map::Map;
+use crate::model::RegisterAction;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::MyRegisterActorState;
+
+#[derive(Debug, Default)]
+struct ProfileStats {
+    states: usize,
+    /// Time spent evaluating each property, by name.
+    properties: BTreeMap<&'static str, Duration>,
+    /// Time spent hashing each part of the states, by component.
+    hashing: BTreeMap<&'static str, Duration>,
+}
+
+/// Times property evaluation and state hashing for each visited state.
+///
+/// The checker doesn't expose its own timings, so this does the same work again on each state;
+/// the totals are comparable with each other rather than with the checker's overall run time.
+#[derive(Clone, Default)]
+pub(crate) struct Profiler(Arc<Mutex<ProfileStats>>);
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> CheckerVisitor<RegisterModel<M>> for Profiler {
+    fn visit(&self, model: &RegisterModel<M>, path: Path<RegisterState<M>, RegisterAction<M>>) {
+        let (state, _) = path.into_vec().pop().expect("paths always have a state");
+
+        let mut properties = Vec::new();
+        for property in model.properties() {
+            let start = Instant::now();
+            (property.condition)(model, &state);
+            properties.push((property.name, start.elapsed()));
+        }
+
+        let mut hashing = Vec::new();
+        let mut time = |component: &'static str, hash: &dyn Fn()| {
+            let start = Instant::now();
+            hash();
+            hashing.push((component, start.elapsed()));
+        };
+        for actor in &state.actor_states {
+            match &**actor {
+                MyRegisterActorState::Server(server) => {
+                    time("server maps", &|| {
+                        fingerprint(&**server);
+                    });
+                    time("servers, including their maps", &|| {
+                        fingerprint(server);
+                    });
+                }
+                MyRegisterActorState::PutClient { .. }
+                | MyRegisterActorState::DeleteClient { .. }
+                | MyRegisterActorState::ResolveClient { .. }
+                | MyRegisterActorState::ReadClient { .. }
+                | MyRegisterActorState::SetClient { .. }
+                | MyRegisterActorState::CounterClient { .. }
+                | MyRegisterActorState::MvRegisterClient { .. }
+                | MyRegisterActorState::FlagClient { .. }
+                | MyRegisterActorState::ListInsertClient { .. }
+                | MyRegisterActorState::ListDeleteClient { .. }
+                | MyRegisterActorState::NestedClient { .. }
+                | MyRegisterActorState::GetClient { .. }
+                | MyRegisterActorState::MixedClient { .. } => time("clients", &|| {
+                    fingerprint(&**actor);
+                }),
+            }
+        }
+        time("network", &|| {
+            fingerprint(&state.network);
+        });
+        time("timers", &|| {
+            fingerprint(&state.is_timer_set);
+        });
+        time("history", &|| {
+            fingerprint(&state.history);
+        });
+
+        let mut stats = self.0.lock().unwrap();
+        stats.states += 1;
+        for (name, elapsed) in properties {
+            *stats.properties.entry(name).or_default() += elapsed;
+        }
+        for (component, elapsed) in hashing {
+            *stats.hashing.entry(component).or_default() += elapsed;
+        }
+    }
+}
+
+impl Profiler {
+    pub(crate) fn report(&self) {
+        let stats = self.0.lock().unwrap();
+        println!("Profiled {} states", stats.states);
+        report_times("Property evaluation", &stats.properties, stats.states);
+        report_times("State hashing", &stats.hashing, stats.states);
+    }
+}
+
+/// Print the times from slowest to fastest, with their share of the total and mean per state.
+fn report_times(title: &str, times: &BTreeMap<&'static str, Duration>, states: usize) {
+    let total = times.values().sum::<Duration>();
+    println!("{}: {:?}", title, total);
+    let mut times = times.iter().collect::<Vec<_>>();
+    times.sort_by(|(na, a), (nb, b)| b.cmp(a).then(na.cmp(nb)));
+    for (name, elapsed) in times {
+        println!(
+            "  {:>5.1}% {:>12?} ({:?}/state) {}",
+            100.0 * elapsed.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON),
+            elapsed,
+            *elapsed / states.max(1) as u32,
+            name
+        );
+    }
+}
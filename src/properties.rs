@@ -0,0 +1,1428 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use stateright::actor::Id;
+use stateright::Property;
+
+use crate::causality;
+use crate::dependent_value;
+use crate::durability::Restart;
+use crate::fairness::is_fair_end;
+use crate::fairness::Fairness;
+use crate::flag::Bias;
+use crate::map::Map;
+use crate::map::Timestamp;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::put_value;
+use crate::register_mv::concurrent;
+use crate::retransmit;
+#[cfg(feature = "scripting")]
+use crate::script::ScriptExpectation;
+use crate::sec;
+use crate::shard::Placement;
+use crate::sync::SyncMode;
+use crate::validate::valid_key;
+use crate::validate::valid_value;
+use crate::workload::MixedOp;
+use crate::FlagBias;
+use crate::ModelCfg;
+use crate::MyRegisterActorState;
+use crate::MyRegisterMsg;
+use crate::PeerMsg;
+use crate::SetImpl;
+use crate::SET_ELEMENT;
+
+type Condition<M> = fn(&RegisterModel<M>, &RegisterState<M>) -> bool;
+
+/// Instantiate a per-key property template, which takes the key as a const parameter since
+/// property conditions can't capture it. There is an instance for every key `--keys` allows,
+/// `'a'..='z'`.
+macro_rules! for_key {
+    ($template:ident, $key:expr) => {
+        match $key {
+            'a' => Some($template::<M, 'a'> as Condition<M>),
+            'b' => Some($template::<M, 'b'> as Condition<M>),
+            'c' => Some($template::<M, 'c'> as Condition<M>),
+            'd' => Some($template::<M, 'd'> as Condition<M>),
+            'e' => Some($template::<M, 'e'> as Condition<M>),
+            'f' => Some($template::<M, 'f'> as Condition<M>),
+            'g' => Some($template::<M, 'g'> as Condition<M>),
+            'h' => Some($template::<M, 'h'> as Condition<M>),
+            'i' => Some($template::<M, 'i'> as Condition<M>),
+            'j' => Some($template::<M, 'j'> as Condition<M>),
+            'k' => Some($template::<M, 'k'> as Condition<M>),
+            'l' => Some($template::<M, 'l'> as Condition<M>),
+            'm' => Some($template::<M, 'm'> as Condition<M>),
+            'n' => Some($template::<M, 'n'> as Condition<M>),
+            'o' => Some($template::<M, 'o'> as Condition<M>),
+            'p' => Some($template::<M, 'p'> as Condition<M>),
+            'q' => Some($template::<M, 'q'> as Condition<M>),
+            'r' => Some($template::<M, 'r'> as Condition<M>),
+            's' => Some($template::<M, 's'> as Condition<M>),
+            't' => Some($template::<M, 't'> as Condition<M>),
+            'u' => Some($template::<M, 'u'> as Condition<M>),
+            'v' => Some($template::<M, 'v'> as Condition<M>),
+            'w' => Some($template::<M, 'w'> as Condition<M>),
+            'x' => Some($template::<M, 'x'> as Condition<M>),
+            'y' => Some($template::<M, 'y'> as Condition<M>),
+            'z' => Some($template::<M, 'z'> as Condition<M>),
+            _ => None,
+        }
+    };
+}
+
+/// Maximum number of user-defined invariants, as each needs its own instance of
+/// [`invariant_holds`].
+pub(crate) const MAX_INVARIANTS: usize = 8;
+
+/// Instantiate [`invariant_holds`] for the invariant at the given index.
+macro_rules! for_invariant {
+    ($index:expr) => {
+        match $index {
+            0 => Some(invariant_holds::<M, 0> as Condition<M>),
+            1 => Some(invariant_holds::<M, 1> as Condition<M>),
+            2 => Some(invariant_holds::<M, 2> as Condition<M>),
+            3 => Some(invariant_holds::<M, 3> as Condition<M>),
+            4 => Some(invariant_holds::<M, 4> as Condition<M>),
+            5 => Some(invariant_holds::<M, 5> as Condition<M>),
+            6 => Some(invariant_holds::<M, 6> as Condition<M>),
+            7 => Some(invariant_holds::<M, 7> as Condition<M>),
+            _ => None,
+        }
+    };
+}
+
+/// Instantiate [`script_property_holds`] for the script property at the given index.
+#[cfg(feature = "scripting")]
+macro_rules! for_script_property {
+    ($index:expr) => {
+        match $index {
+            0 => Some(script_property_holds::<M, 0> as Condition<M>),
+            1 => Some(script_property_holds::<M, 1> as Condition<M>),
+            2 => Some(script_property_holds::<M, 2> as Condition<M>),
+            3 => Some(script_property_holds::<M, 3> as Condition<M>),
+            4 => Some(script_property_holds::<M, 4> as Condition<M>),
+            5 => Some(script_property_holds::<M, 5> as Condition<M>),
+            6 => Some(script_property_holds::<M, 6> as Condition<M>),
+            7 => Some(script_property_holds::<M, 7> as Condition<M>),
+            _ => None,
+        }
+    };
+}
+
+/// A named set of properties to check.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Suite {
+    /// Convergence and sync properties, including per-key ones.
+    Default,
+    /// Strong eventual consistency: convergence of servers that applied the same ops, respect
+    /// for causality and no spurious values.
+    Sec,
+    /// Causal consistency: servers apply each op after the ops it happens after.
+    Causal,
+}
+
+impl Suite {
+    /// Whether the model needs to record the ops each server applied for this suite.
+    pub(crate) fn records_sec_history(&self) -> bool {
+        *self == Suite::Sec
+    }
+
+    /// Whether the model needs to record the ops each server received for this suite.
+    pub(crate) fn records_causal_history(&self) -> bool {
+        *self == Suite::Causal
+    }
+}
+
+/// The properties checked for the given configuration.
+pub(crate) fn properties<M: Clone + Debug + PartialEq + Hash + Map>(
+    cfg: &ModelCfg,
+) -> Vec<Property<RegisterModel<M>>> {
+    let mut properties = match cfg.suite {
+        Suite::Default => default_properties(cfg),
+        Suite::Sec => sec::properties(cfg),
+        Suite::Causal => causality::properties(),
+    };
+
+    for (i, invariant) in cfg.invariants.iter().enumerate() {
+        if let Some(condition) = for_invariant!(i) {
+            properties.push(Property::always(
+                intern(format!("invariant {}", invariant.source)),
+                condition,
+            ));
+        }
+    }
+
+    #[cfg(feature = "scripting")]
+    if let Some(script) = &cfg.script {
+        for (i, (expectation, name)) in script.properties.iter().enumerate() {
+            let condition = match for_script_property!(i) {
+                Some(condition) => condition,
+                None => continue,
+            };
+            let name = intern(format!("script {}", name));
+            properties.push(match expectation {
+                ScriptExpectation::Always => Property::always(name, condition),
+                ScriptExpectation::Eventually => Property::eventually(name, condition),
+                ScriptExpectation::Sometimes => Property::sometimes(name, condition),
+            });
+        }
+    }
+
+    properties
+}
+
+fn default_properties<M: Clone + Debug + PartialEq + Hash + Map>(
+    cfg: &ModelCfg,
+) -> Vec<Property<RegisterModel<M>>> {
+    let mut properties = Vec::new();
+    if cfg.fairness != Fairness::None {
+        // stateright only checks eventually properties at the end of complete paths, which
+        // implicitly assumes that every message is delivered
+        properties.push(Property::eventually(
+            "all actors have the same value for all keys",
+            |model, state| servers_agree(model.cfg(), state),
+        ));
+        if cfg.sync_mode == SyncMode::HaveNeed {
+            // the exchange must not keep replying to itself forever
+            properties.push(Property::eventually(
+                "have/need sync terminates",
+                |model, state| !sync_in_flight(model.cfg(), state),
+            ));
+        }
+        if cfg.sync_mode == SyncMode::Pull {
+            // a pull that finds the puller ahead pulls back, which must not go on forever
+            properties.push(Property::eventually(
+                "pull sync terminates",
+                |model, state| !sync_in_flight(model.cfg(), state),
+            ));
+        }
+    }
+    properties.push(Property::always(
+        "all actors have the same value at the end of every fair path",
+        |model, state| {
+            !is_fair_end(model.cfg().fairness, state) || servers_agree(model.cfg(), state)
+        },
+    ));
+    // only valid for broken one as conflicting values are retained in the fixed version
+    // properties.push(Property::always(
+    //     "only have one value for each key",
+    //     |_, state| only_one_of_each_key(&state.actor_states),
+    // ));
+    properties.push(Property::always(
+        "in sync when syncing is done and no in-flight requests",
+        |model, state| syncing_done_and_in_sync(model.cfg(), state),
+    ));
+    properties.push(Property::always("servers reject no ops", |_, state| {
+        no_rejected_ops(state)
+    }));
+    properties.push(Property::always(
+        "sets are ordered after the values they overwrite",
+        |_, state| no_lost_updates(state),
+    ));
+    properties.push(Property::always(
+        "servers only hold well-formed values",
+        |model, state| only_well_formed_values(model.cfg().servers, state),
+    ));
+    // servers only merge whole maps when syncing by state, where merge has to be the join of a
+    // semilattice for them to converge whatever order the states arrive in
+    if cfg.sync_mode == SyncMode::State {
+        properties.push(Property::always(
+            "merging a server's map into itself changes nothing",
+            |_, state| merge_idempotent(state),
+        ));
+        properties.push(Property::always(
+            "merging servers' maps is commutative",
+            |_, state| merge_commutative(state),
+        ));
+        properties.push(Property::always(
+            "merging servers' maps is associative",
+            |_, state| merge_associative(state),
+        ));
+    }
+    if cfg.follow_up_gets {
+        properties.push(Property::always(
+            "put clients read their own writes",
+            |_, state| reads_own_writes(state),
+        ));
+    }
+    if cfg.retries {
+        properties.push(Property::always(
+            "each acknowledged request mutates a map exactly once",
+            |_, state| requests_applied_once(state),
+        ));
+    }
+    if cfg.causal_tokens && cfg.placement().is_sharded() {
+        properties.push(Property::always(
+            "dependent writes are only visible once their dependency is on all of its shard",
+            |model, state| dependencies_on_shard(&model.cfg().placement(), state),
+        ));
+    } else if cfg.causal_tokens {
+        properties.push(Property::always(
+            "dependent writes are only visible with their dependency",
+            |_, state| dependencies_applied(state),
+        ));
+    }
+    if cfg.causal_tokens {
+        properties.push(Property::always(
+            "causal buffers only hold ops waiting on in-flight ops",
+            |model, state| buffered_ops_waiting(model.cfg().sync_mode, state),
+        ));
+        properties.push(Property::always(
+            "causal buffers are empty when syncing is done",
+            |model, state| sync_in_flight(model.cfg(), state) || causal_buffers_empty(state),
+        ));
+    }
+
+    if cfg.causal_delivery {
+        properties.push(Property::always(
+            "delivery buffers are empty when syncing is done",
+            |model, state| sync_in_flight(model.cfg(), state) || delivery_buffers_empty(state),
+        ));
+    }
+
+    if cfg.placement().is_sharded() {
+        properties.push(Property::always(
+            "servers only hold keys of their shards",
+            |model, state| keys_on_their_shards(&model.cfg().placement(), state),
+        ));
+    }
+
+    if cfg.truncate {
+        properties.push(Property::always(
+            "sets a truncate saw stay removed, wherever they arrive",
+            |_, state| truncated_sets_removed(state),
+        ));
+        properties.push(Property::always(
+            "sets a truncate saw are removed everywhere once syncing is done",
+            |model, state| sync_in_flight(model.cfg(), state) || truncated_everywhere(state),
+        ));
+    }
+
+    if cfg.churn {
+        properties.push(Property::always(
+            "servers that stay converge under churn once syncing is done",
+            |model, state| sync_in_flight(model.cfg(), state) || survivors_agree(state),
+        ));
+    }
+
+    if cfg.epochs {
+        properties.push(Property::always(
+            "servers agree on the epoch once syncing is done",
+            |model, state| sync_in_flight(model.cfg(), state) || same_epoch(state),
+        ));
+    }
+
+    if cfg.gc {
+        properties.push(Property::always(
+            "garbage collection never changes a server's values",
+            |_, state| collection_unobservable(state),
+        ));
+    }
+
+    // servers restarting empty lose acknowledged ops too, leaving the convergence properties to
+    // show whether peers make up for them
+    if cfg.crash && cfg.restart == Restart::Snapshot {
+        if cfg.snapshot_every.is_none() {
+            properties.push(Property::always(
+                "a recovered server only lacks ops it hadn't acknowledged",
+                |_, state| recovered_unacknowledged(state, true),
+            ));
+        } else {
+            // snapshots taken every so many ops may miss acknowledged ops
+            properties.push(Property::always(
+                "a recovered server only lacks the ops applied since its snapshot",
+                |_, state| recovered_unacknowledged(state, false),
+            ));
+        }
+    }
+
+    if cfg.read_clients > 0 {
+        properties.push(Property::always(
+            "merged reads are at least as up to date as each server's answer",
+            |_, state| merged_reads_up_to_date(state),
+        ));
+    }
+
+    if cfg.get_clients > 0 {
+        properties.push(Property::always(
+            "reads only return values that were written",
+            |model, state| reads_written(model.cfg(), state),
+        ));
+    }
+
+    // the two-phase set's removes win over every add, concurrent or later, so an element once
+    // removed can't be added back
+    if cfg.set_clients > 0 && cfg.set_impl == SetImpl::TwoPhase {
+        properties.push(Property::always(
+            "removed set elements stay removed, even if added again, once syncing is done",
+            |model, state| sync_in_flight(model.cfg(), state) || removed_elements_absent(state),
+        ));
+    }
+
+    if cfg.mv_register_clients > 0 {
+        properties.push(Property::always(
+            "register siblings are only kept for concurrent writes",
+            |_, state| register_siblings_concurrent(state),
+        ));
+    }
+
+    if cfg.flag_enable_clients + cfg.flag_disable_clients > 0 {
+        let name = match cfg.flag_bias {
+            FlagBias::EnableWins => "concurrent enables and disables leave the flag enabled",
+            FlagBias::DisableWins => "concurrent enables and disables leave the flag disabled",
+        };
+        properties.push(Property::always(name, |_, state| flag_bias_holds(state)));
+    }
+
+    if cfg.list_insert_clients + cfg.list_delete_clients > 0 {
+        properties.push(Property::always(
+            "lists converge once syncing is done",
+            |model, state| sync_in_flight(model.cfg(), state) || same_lists(state),
+        ));
+    }
+
+    if cfg.nested_clients > 0 {
+        properties.push(Property::always(
+            "nested maps agree on every subtree once syncing is done",
+            |model, state| sync_in_flight(model.cfg(), state) || same_subtrees(state),
+        ));
+    }
+
+    if cfg.counter_clients > 0 {
+        properties.push(Property::always(
+            "counters converge to the increments minus the decrements",
+            |model, state| sync_in_flight(model.cfg(), state) || counters_net(state),
+        ));
+    }
+
+    if cfg.resolve_clients > 0 {
+        properties.push(Property::always(
+            "resolved siblings stay overwritten once syncing is done",
+            |model, state| sync_in_flight(model.cfg(), state) || resolutions_hold(state),
+        ));
+    }
+
+    // only instantiate the per-key templates for keys that the clients actually use
+    for key in cfg.workload_keys() {
+        if let Some(condition) = for_key!(key_converges, key) {
+            properties.push(Property::always(
+                intern(format!(
+                    "key {:?} converges at the end of every fair path",
+                    key
+                )),
+                condition,
+            ));
+        }
+        if let Some(condition) = for_key!(key_in_sync, key) {
+            properties.push(Property::always(
+                intern(format!("key {:?} is in sync when syncing is done", key)),
+                condition,
+            ));
+        }
+    }
+
+    properties
+}
+
+/// Property names have to be static, so leak each distinct generated name once.
+fn intern(name: String) -> &'static str {
+    static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    let mut names = NAMES.lock().unwrap();
+    if let Some(interned) = names.get(name.as_str()) {
+        return *interned;
+    }
+    let interned: &'static str = Box::leak(name.into_boxed_str());
+    names.insert(interned);
+    interned
+}
+
+fn key_converges<M: Clone + Debug + PartialEq + Hash + Map, const KEY: char>(
+    model: &RegisterModel<M>,
+    state: &RegisterState<M>,
+) -> bool {
+    !is_fair_end(model.cfg().fairness, state)
+        || key_same_on_replicas(&model.cfg().placement(), state, KEY)
+}
+
+fn key_in_sync<M: Clone + Debug + PartialEq + Hash + Map, const KEY: char>(
+    model: &RegisterModel<M>,
+    state: &RegisterState<M>,
+) -> bool {
+    sync_in_flight(model.cfg(), state) || key_same_on_replicas(&model.cfg().placement(), state, KEY)
+}
+
+fn invariant_holds<M: Clone + Debug + PartialEq + Hash + Map, const INDEX: usize>(
+    model: &RegisterModel<M>,
+    state: &RegisterState<M>,
+) -> bool {
+    // invariants are checked against the initial state up front, so errors here are rare type
+    // errors in unexplored branches which count as violations
+    model.cfg().invariants[INDEX].eval(state).unwrap_or(false)
+}
+
+#[cfg(feature = "scripting")]
+fn script_property_holds<M: Clone + Debug + PartialEq + Hash + Map, const INDEX: usize>(
+    model: &RegisterModel<M>,
+    state: &RegisterState<M>,
+) -> bool {
+    match &model.cfg().script {
+        Some(script) => script.eval(INDEX, state),
+        None => true,
+    }
+}
+
+/// Whether the servers holding the key have the same value for it.
+fn key_same_on_replicas<M: Clone + Debug + PartialEq + Hash + Map>(
+    placement: &Placement,
+    state: &RegisterState<M>,
+    key: char,
+) -> bool {
+    let mut values = state
+        .actor_states
+        .iter()
+        .enumerate()
+        .filter_map(|(index, actor)| match &**actor {
+            MyRegisterActorState::Server(map) if placement.holds(index, key) => {
+                Some(map.get(&key).cloned())
+            }
+            _ => None,
+        });
+    match values.next() {
+        Some(first) => values.all(|value| value == first),
+        None => true,
+    }
+}
+
+/// Whether the servers are in the same state, or with sharding, whether the servers holding each
+/// key have the same visible values for it.
+fn servers_agree<M: Clone + Debug + PartialEq + Hash + Map>(
+    cfg: &ModelCfg,
+    state: &RegisterState<M>,
+) -> bool {
+    let placement = cfg.placement();
+    if !placement.is_sharded() {
+        // servers that left no longer get the members' ops
+        let members = state
+            .actor_states
+            .iter()
+            .filter(|actor| !matches!(&***actor, MyRegisterActorState::Server(server) if server.has_left()))
+            .cloned()
+            .collect::<Vec<_>>();
+        return all_same_state(&members);
+    }
+    cfg.workload_keys().into_iter().all(|key| {
+        let mut values = state
+            .actor_states
+            .iter()
+            .enumerate()
+            .filter_map(|(index, actor)| match &**actor {
+                MyRegisterActorState::Server(server) if placement.holds(index, key) => Some(
+                    server
+                        .visible_values()
+                        .into_iter()
+                        .filter(|(_, k, _)| *k == key)
+                        .collect::<Vec<_>>(),
+                ),
+                _ => None,
+            });
+        match values.next() {
+            Some(first) => values.all(|values| values == first),
+            None => true,
+        }
+    })
+}
+
+/// Whether the servers that are members, having joined and not left, show the same values,
+/// whichever of them crashed on the way.
+fn survivors_agree<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    let mut survivors = state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::Server(server) if server.is_member() => {
+                Some(server.visible_values())
+            }
+            _ => None,
+        });
+    match survivors.next() {
+        Some(first) => survivors.all(|values| values == first),
+        None => true,
+    }
+}
+
+/// Whether every value a server holds is for a key on one of its shards, so ops only reached the
+/// servers holding their key.
+fn keys_on_their_shards<M: Clone + Debug + PartialEq + Hash + Map>(
+    placement: &Placement,
+    state: &RegisterState<M>,
+) -> bool {
+    state
+        .actor_states
+        .iter()
+        .enumerate()
+        .all(|(index, actor)| match &**actor {
+            MyRegisterActorState::Server(server) => server
+                .values()
+                .iter()
+                .all(|(_, key, _)| placement.holds(index, *key)),
+            _ => true,
+        })
+}
+
+fn sync_in_flight<M: Clone + Debug + PartialEq + Hash + Map>(
+    cfg: &ModelCfg,
+    state: &RegisterState<M>,
+) -> bool {
+    state
+        .network
+        .iter_deliverable()
+        .any(|envelope| match envelope.msg {
+            MyRegisterMsg::Internal(msg) => {
+                !redelivery_settled(cfg, state, envelope.src, envelope.dst, msg)
+            }
+            _ => false,
+        })
+        || timers_pending(state)
+}
+
+/// Whether a copy of a peer message that a duplicating network keeps around would leave the
+/// server it is addressed to as it is if delivered again, so that it no longer counts as in
+/// flight. Messages that servers answer are always in flight, as delivering them again sends
+/// another answer.
+fn redelivery_settled<M: Clone + Debug + PartialEq + Hash + Map>(
+    cfg: &ModelCfg,
+    state: &RegisterState<M>,
+    src: Id,
+    dst: Id,
+    msg: &PeerMsg,
+) -> bool {
+    if !cfg.network.duplicates() {
+        return false;
+    }
+    let server = match &*state.actor_states[usize::from(dst)] {
+        MyRegisterActorState::Server(server) => server,
+        _ => return false,
+    };
+    // servers acknowledge every set and delete they receive when they retransmit them
+    if server.outbound.is_some() && retransmit::ack(msg).is_some() {
+        return false;
+    }
+    match msg {
+        PeerMsg::Have { .. }
+        | PeerMsg::Need { .. }
+        | PeerMsg::Changes { .. }
+        | PeerMsg::DepCheck { .. }
+        | PeerMsg::DepOk { .. }
+        | PeerMsg::Pull { .. }
+        | PeerMsg::Ops { .. }
+        | PeerMsg::Join
+        | PeerMsg::Leave
+        | PeerMsg::Digest { .. }
+        | PeerMsg::Repair { .. }
+        | PeerMsg::EpochClose { .. }
+        | PeerMsg::EpochDone { .. }
+        | PeerMsg::Watermark { .. } => false,
+        PeerMsg::PutSync { .. }
+        | PeerMsg::DeleteSync { .. }
+        | PeerMsg::AddSync { .. }
+        | PeerMsg::RemoveSync { .. }
+        | PeerMsg::CounterState { .. }
+        | PeerMsg::RegisterSync { .. }
+        | PeerMsg::LwwRegisterSync { .. }
+        | PeerMsg::FlagSync { .. }
+        | PeerMsg::ListInsertSync { .. }
+        | PeerMsg::ListDeleteSync { .. }
+        | PeerMsg::NestedSetSync { .. }
+        | PeerMsg::NestedDeleteSync { .. }
+        | PeerMsg::StateSync { .. }
+        | PeerMsg::DeltaSync { .. }
+        | PeerMsg::After { .. }
+        | PeerMsg::Stamped { .. }
+        | PeerMsg::Truncate { .. } => {
+            let mut redelivered = server.clone();
+            redelivered.receive(msg.clone(), Some(src.into()), &cfg.validation);
+            redelivered == *server
+        }
+        PeerMsg::PutSyncAck { .. } | PeerMsg::DeleteSyncAck { .. } => {
+            let mut redelivered = server.clone();
+            redelivered.on_ack(src.into(), msg);
+            redelivered == *server
+        }
+        _ => false,
+    }
+}
+
+/// Whether a server is waiting to pull, flush its deltas, gossip or retransmit after applying
+/// ops locally, or is yet to crash, after which it asks its peers for the ops it lost.
+fn timers_pending<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    state
+        .actor_states
+        .iter()
+        .zip(&state.is_timer_set)
+        .any(|(actor, set)| *set && matches!(**actor, MyRegisterActorState::Server(_)))
+}
+
+pub(crate) fn all_same_state<M: Clone + Debug + PartialEq + Hash + Map>(
+    actors: &[Arc<MyRegisterActorState<M>>],
+) -> bool {
+    actors.windows(2).all(|w| match (&*w[0], &*w[1]) {
+        (MyRegisterActorState::PutClient { .. }, MyRegisterActorState::PutClient { .. }) => true,
+        (MyRegisterActorState::PutClient { .. }, MyRegisterActorState::DeleteClient { .. }) => true,
+        (MyRegisterActorState::PutClient { .. }, MyRegisterActorState::Server(_)) => true,
+        (MyRegisterActorState::DeleteClient { .. }, MyRegisterActorState::DeleteClient { .. }) => {
+            true
+        }
+        (MyRegisterActorState::DeleteClient { .. }, MyRegisterActorState::PutClient { .. }) => true,
+        (MyRegisterActorState::DeleteClient { .. }, MyRegisterActorState::Server(_)) => true,
+        (MyRegisterActorState::Server(_), MyRegisterActorState::PutClient { .. }) => true,
+        (MyRegisterActorState::Server(_), MyRegisterActorState::DeleteClient { .. }) => true,
+        (MyRegisterActorState::ResolveClient { .. }, _) => true,
+        (_, MyRegisterActorState::ResolveClient { .. }) => true,
+        (MyRegisterActorState::ReadClient { .. }, _) => true,
+        (_, MyRegisterActorState::ReadClient { .. }) => true,
+        (MyRegisterActorState::SetClient { .. }, _) => true,
+        (_, MyRegisterActorState::SetClient { .. }) => true,
+        (MyRegisterActorState::CounterClient { .. }, _) => true,
+        (_, MyRegisterActorState::CounterClient { .. }) => true,
+        (MyRegisterActorState::MvRegisterClient { .. }, _) => true,
+        (_, MyRegisterActorState::MvRegisterClient { .. }) => true,
+        (MyRegisterActorState::FlagClient { .. }, _) => true,
+        (_, MyRegisterActorState::FlagClient { .. }) => true,
+        (MyRegisterActorState::ListInsertClient { .. }, _) => true,
+        (_, MyRegisterActorState::ListInsertClient { .. }) => true,
+        (MyRegisterActorState::ListDeleteClient { .. }, _) => true,
+        (_, MyRegisterActorState::ListDeleteClient { .. }) => true,
+        (MyRegisterActorState::NestedClient { .. }, _) => true,
+        (_, MyRegisterActorState::NestedClient { .. }) => true,
+        (MyRegisterActorState::GetClient { .. }, _) => true,
+        (_, MyRegisterActorState::GetClient { .. }) => true,
+        (MyRegisterActorState::MixedClient { .. }, _) => true,
+        (_, MyRegisterActorState::MixedClient { .. }) => true,
+        // compares the cached fingerprints rather than the visible values themselves
+        (MyRegisterActorState::Server(a), MyRegisterActorState::Server(b)) => {
+            a.observable() == b.observable()
+        }
+    })
+}
+
+// fn only_one_of_each_key<M: Clone + Debug + PartialEq + Hash + Map>(
+//     actors: &[Arc<MyRegisterActorState<M>>],
+// ) -> bool {
+//     for actor in actors {
+//         if let MyRegisterActorState::Server(actor) = &**actor {
+//             let keys = actor
+//                 .values()
+//                 .into_iter()
+//                 .map(|(_, k, _)| k)
+//                 .collect::<HashSet<_>>();
+//             if keys.len() != actor.values().len() {
+//                 return false;
+//             }
+//         }
+//     }
+//     true
+// }
+
+fn no_rejected_ops<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => server.rejected.is_empty(),
+        _ => true,
+    })
+}
+
+/// Whether every set in flight has a later timestamp than the values its server saw for the key,
+/// so that last-writer-wins maps don't lose it.
+fn no_lost_updates<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    fn ordered_after_context(msg: &PeerMsg) -> bool {
+        match msg {
+            PeerMsg::PutSync {
+                context, timestamp, ..
+            } => context.iter().all(|t| t < *timestamp),
+            PeerMsg::Changes { changes } => changes
+                .iter()
+                .all(|change| ordered_after_context(&change.op)),
+            PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => ordered_after_context(op),
+            PeerMsg::Ops { ops } => ops.iter().all(|pulled| ordered_after_context(&pulled.op)),
+            PeerMsg::Repair { ops } => ops.iter().all(ordered_after_context),
+            PeerMsg::DeleteSync { .. }
+            | PeerMsg::AddSync { .. }
+            | PeerMsg::RemoveSync { .. }
+            | PeerMsg::CounterState { .. }
+            | PeerMsg::RegisterSync { .. }
+            | PeerMsg::LwwRegisterSync { .. }
+            | PeerMsg::FlagSync { .. }
+            | PeerMsg::ListInsertSync { .. }
+            | PeerMsg::ListDeleteSync { .. }
+            | PeerMsg::NestedSetSync { .. }
+            | PeerMsg::NestedDeleteSync { .. }
+            | PeerMsg::StateSync { .. }
+            | PeerMsg::DeltaSync { .. }
+            | PeerMsg::Have { .. }
+            | PeerMsg::Need { .. }
+            | PeerMsg::DepCheck { .. }
+            | PeerMsg::DepOk { .. }
+            | PeerMsg::Pull { .. }
+            | PeerMsg::Join
+            | PeerMsg::Leave
+            | PeerMsg::PutSyncAck { .. }
+            | PeerMsg::DeleteSyncAck { .. }
+            | PeerMsg::Digest { .. }
+            | PeerMsg::Truncate { .. }
+            | PeerMsg::EpochClose { .. }
+            | PeerMsg::EpochDone { .. }
+            | PeerMsg::Watermark { .. } => true,
+            _ => true,
+        }
+    }
+    state
+        .network
+        .iter_deliverable()
+        .all(|envelope| match envelope.msg {
+            MyRegisterMsg::Internal(msg) => ordered_after_context(msg),
+            _ => true,
+        })
+}
+
+/// Whether every response in flight to a put client's follow-up get holds the client's last
+/// acknowledged write, unless the server that answered has since overwritten it with a later
+/// set or removed the key.
+fn reads_own_writes<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    state.network.iter_deliverable().all(|envelope| {
+        let (request_id, value) = match envelope.msg {
+            MyRegisterMsg::GetOk(request_id, value) => (request_id, value),
+            _ => return true,
+        };
+        let (key, written, timestamp) = match &*state.actor_states[usize::from(envelope.dst)] {
+            MyRegisterActorState::PutClient {
+                awaiting: Some(awaiting),
+                last_write: Some(last_write),
+                ..
+            } if awaiting == request_id => *last_write,
+            _ => return true,
+        };
+        if *value == written {
+            return true;
+        }
+        match &*state.actor_states[usize::from(envelope.src)] {
+            MyRegisterActorState::Server(server) => {
+                let held = server
+                    .values()
+                    .into_iter()
+                    .filter(|(_, k, _)| *k == key)
+                    .collect::<Vec<_>>();
+                held.is_empty() || held.iter().any(|(t, _, _)| *t > timestamp)
+            }
+            _ => true,
+        }
+    })
+}
+
+/// Whether no request has mutated the maps more than once, counting across servers since a retry
+/// may be sent to a different server. Requests that didn't mutate anything aren't counted.
+fn requests_applied_once<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    let mut mutations = BTreeMap::<_, usize>::new();
+    for actor in &state.actor_states {
+        if let MyRegisterActorState::Server(server) = &**actor {
+            for (request, count) in server.requests.iter().flat_map(|r| &r.mutations) {
+                *mutations.entry(*request).or_default() += count;
+            }
+        }
+    }
+    mutations.values().all(|count| *count == 1)
+}
+
+/// Whether no server holds a set that a truncate it applied removed, including sets that arrived
+/// after the truncate.
+fn truncated_sets_removed<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => match &server.truncation {
+            Some(truncation) => server
+                .values()
+                .iter()
+                .all(|(timestamp, _, _)| !truncation.removed.contains(timestamp)),
+            None => true,
+        },
+        _ => true,
+    })
+}
+
+/// Whether no server holds a set that a truncate applied anywhere removed. Sets that no truncate
+/// saw aren't in any barrier, so this leaves them be.
+fn truncated_everywhere<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    let removed = state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::Server(server) => server.truncation.as_ref(),
+            _ => None,
+        })
+        .flat_map(|truncation| truncation.removed.iter().copied())
+        .collect::<BTreeSet<_>>();
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => server
+            .values()
+            .iter()
+            .all(|(timestamp, _, _)| !removed.contains(timestamp)),
+        _ => true,
+    })
+}
+
+/// The maps of the servers, for checking the laws of merging them.
+fn server_maps<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> Vec<&M> {
+    state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::Server(server) => Some(&**server),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A copy of `map` with `other` merged into it.
+fn merged<M: Clone + Map>(map: &M, other: &M) -> M {
+    let mut merged = map.clone();
+    merged.merge(other);
+    merged
+}
+
+fn merge_idempotent<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    server_maps(state)
+        .into_iter()
+        .all(|map| merged(map, map).values() == map.values())
+}
+
+fn merge_commutative<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    let maps = server_maps(state);
+    maps.iter().all(|a| {
+        maps.iter()
+            .all(|b| merged(*a, *b).values() == merged(*b, *a).values())
+    })
+}
+
+fn merge_associative<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    let maps = server_maps(state);
+    maps.iter().all(|a| {
+        maps.iter().all(|b| {
+            maps.iter().all(|c| {
+                merged(&merged(*a, *b), *c).values() == merged(*a, &merged(*b, *c)).values()
+            })
+        })
+    })
+}
+
+/// Whether every server holds the same values as it would without garbage collection.
+fn collection_unobservable<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => match &server.uncollected {
+            Some(uncollected) => server.values() == uncollected.map.values(),
+            None => true,
+        },
+        _ => true,
+    })
+}
+
+fn same_epoch<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    let epochs = state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::Server(server) => server.epochs.as_ref().map(|e| e.epoch),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    epochs.len() <= 1
+}
+
+/// Whether each server that crashed recovered a state that, with the ops it hadn't flushed
+/// replayed, is the state it crashed in, and, if `acknowledged` ops are meant to be durable,
+/// whether those ops all came from peers, so none were acknowledged to a client. Peers send the
+/// lost ops back, which the convergence properties cover.
+fn recovered_unacknowledged<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+    acknowledged: bool,
+) -> bool {
+    state
+        .actor_states
+        .iter()
+        .enumerate()
+        .all(|(index, actor)| match &**actor {
+            MyRegisterActorState::Server(server) => match &server.recovery {
+                Some(recovery) => {
+                    recovery.equivalent()
+                        && (!acknowledged
+                            || recovery
+                                .lost
+                                .iter()
+                                .all(|op| !op.prepared_by(Id::from(index))))
+                }
+                None => true,
+            },
+            _ => true,
+        })
+}
+
+fn dependencies_applied<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => match &server.causal {
+            Some(causal) => server.values().iter().all(|(timestamp, _, _)| {
+                causal
+                    .dependencies
+                    .get(timestamp)
+                    .map_or(true, |dependency| causal.applied.contains(dependency))
+            }),
+            None => true,
+        },
+        _ => true,
+    })
+}
+
+/// Whether every op held back by a server is waiting on a set that is still on its way to it, so
+/// the buffer is bounded by the ops in flight and can't leak.
+fn buffered_ops_waiting<M: Clone + Debug + PartialEq + Hash + Map>(
+    sync_mode: SyncMode,
+    state: &RegisterState<M>,
+) -> bool {
+    fn sets(msg: &PeerMsg, timestamps: &mut BTreeSet<Timestamp>) {
+        match msg {
+            PeerMsg::PutSync { timestamp, .. } => {
+                timestamps.insert(*timestamp);
+            }
+            PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => sets(op, timestamps),
+            PeerMsg::Changes { changes } => {
+                for change in changes {
+                    sets(&change.op, timestamps)
+                }
+            }
+            PeerMsg::Ops { ops } => {
+                for pulled in ops {
+                    sets(&pulled.op, timestamps)
+                }
+            }
+            PeerMsg::Repair { ops } => {
+                for op in ops {
+                    sets(op, timestamps)
+                }
+            }
+            PeerMsg::DeleteSync { .. }
+            | PeerMsg::AddSync { .. }
+            | PeerMsg::RemoveSync { .. }
+            | PeerMsg::CounterState { .. }
+            | PeerMsg::RegisterSync { .. }
+            | PeerMsg::LwwRegisterSync { .. }
+            | PeerMsg::FlagSync { .. }
+            | PeerMsg::ListInsertSync { .. }
+            | PeerMsg::ListDeleteSync { .. }
+            | PeerMsg::NestedSetSync { .. }
+            | PeerMsg::NestedDeleteSync { .. }
+            | PeerMsg::StateSync { .. }
+            | PeerMsg::DeltaSync { .. }
+            | PeerMsg::Have { .. }
+            | PeerMsg::Need { .. }
+            | PeerMsg::DepCheck { .. }
+            | PeerMsg::DepOk { .. }
+            | PeerMsg::Pull { .. }
+            | PeerMsg::Join
+            | PeerMsg::Leave
+            | PeerMsg::PutSyncAck { .. }
+            | PeerMsg::DeleteSyncAck { .. }
+            | PeerMsg::Digest { .. }
+            | PeerMsg::Truncate { .. }
+            | PeerMsg::EpochClose { .. }
+            | PeerMsg::EpochDone { .. }
+            | PeerMsg::Watermark { .. } => {}
+            _ => {}
+        }
+    }
+
+    // the have/need exchange and pulls only send ops when asked, so there a set is on its way
+    // as long as some server has it
+    let mut anywhere = BTreeSet::new();
+    if sync_mode != SyncMode::Op {
+        for actor in &state.actor_states {
+            if let MyRegisterActorState::Server(server) = &**actor {
+                anywhere.extend(server.causal.iter().flat_map(|c| c.applied.iter().copied()));
+            }
+        }
+    }
+
+    state
+        .actor_states
+        .iter()
+        .enumerate()
+        .all(|(index, actor)| match &**actor {
+            MyRegisterActorState::Server(server) => {
+                let causal = match &server.causal {
+                    Some(causal) => causal,
+                    None => return true,
+                };
+                let mut coming = anywhere.clone();
+                for envelope in state.network.iter_deliverable() {
+                    if let (true, MyRegisterMsg::Internal(msg)) =
+                        (usize::from(envelope.dst) == index, envelope.msg)
+                    {
+                        sets(msg, &mut coming)
+                    }
+                }
+                for op in &causal.deferred {
+                    sets(op, &mut coming)
+                }
+                causal.deferred.iter().all(|op| match op {
+                    PeerMsg::After { dependency, .. } => coming.contains(dependency),
+                    _ => false,
+                })
+            }
+            _ => true,
+        })
+}
+
+fn causal_buffers_empty<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => server.causal.iter().all(|causal| {
+            causal.deferred.is_empty() && causal.pending.is_empty() && causal.checks.is_empty()
+        }),
+        _ => true,
+    })
+}
+
+/// Whether no server is still holding back ops from its peers until their causal dependencies
+/// are delivered.
+fn delivery_buffers_empty<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => server
+            .delivery
+            .iter()
+            .all(|delivery| delivery.buffered.is_empty()),
+        _ => true,
+    })
+}
+
+/// Whether every server holding a dependent write has it only once every server holding the
+/// dependency's key has applied the dependency, so a client that reads the dependent write on
+/// one shard and then the dependency's key on another can't miss the dependency.
+///
+/// Dependent writes are published as plain sets once confirmed, so their dependencies are looked
+/// up from the servers that accepted them.
+fn dependencies_on_shard<M: Clone + Debug + PartialEq + Hash + Map>(
+    placement: &Placement,
+    state: &RegisterState<M>,
+) -> bool {
+    let causal = |index: usize| match &*state.actor_states[index] {
+        MyRegisterActorState::Server(server) => server.causal.as_ref(),
+        _ => None,
+    };
+    let confirmed = (0..placement.servers())
+        .filter_map(causal)
+        .flat_map(|causal| &causal.confirmed)
+        .collect::<BTreeMap<_, _>>();
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => server.values().iter().all(|(timestamp, _, _)| {
+            confirmed.get(timestamp).map_or(true, |dependency| {
+                placement
+                    .replicas(dependency.key)
+                    .into_iter()
+                    .all(|replica| {
+                        causal(replica).map_or(false, |causal| {
+                            causal.applied.contains(&dependency.timestamp)
+                        })
+                    })
+            })
+        }),
+        _ => true,
+    })
+}
+
+/// No server holds a sibling that a resolve client has written a resolution over.
+///
+/// Siblings from writes the resolver didn't see are legitimately kept, so this only covers the
+/// ones it saw, which would leave a key with more than one value even after it was resolved.
+fn resolutions_hold<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    let resolved = state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::ResolveClient {
+                awaiting: None,
+                resolved,
+                ..
+            } => Some(resolved),
+            _ => None,
+        })
+        .flatten()
+        .collect::<BTreeSet<_>>();
+    if resolved.is_empty() {
+        return true;
+    }
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => server
+            .values()
+            .iter()
+            .all(|(timestamp, _, _)| !resolved.contains(timestamp)),
+        _ => true,
+    })
+}
+
+/// Whether each read client's merged value is at least as recent as every value any of the servers
+/// answered it with.
+fn merged_reads_up_to_date<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::ReadClient {
+            awaiting: None,
+            responses,
+            merged,
+            abstracted: _,
+        } => responses
+            .iter()
+            .flatten()
+            .all(|(timestamp, _)| merged.map_or(false, |(merged, _)| merged >= *timestamp)),
+        _ => true,
+    })
+}
+
+/// Whether every value the get clients read is one that a put or mixed client has written.
+fn reads_written<M: Clone + Debug + PartialEq + Hash + Map>(
+    cfg: &ModelCfg,
+    state: &RegisterState<M>,
+) -> bool {
+    let written = state
+        .actor_states
+        .iter()
+        .enumerate()
+        .flat_map(|(index, actor)| {
+            let puts = match &**actor {
+                MyRegisterActorState::PutClient { op_count, .. } => (0..*op_count).collect(),
+                MyRegisterActorState::MixedClient { op_count, .. } => (0..*op_count)
+                    .filter(|op| cfg.mix[*op] == MixedOp::Put)
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let dependent =
+                matches!(&**actor, MyRegisterActorState::PutClient { .. }) && cfg.causal_tokens;
+            puts.into_iter()
+                .map(move |op| put_value(index, cfg.client_servers(), op))
+                .chain(dependent.then(|| dependent_value(index, cfg.client_servers())))
+        })
+        .collect::<BTreeSet<_>>();
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::GetClient { reads, .. } => {
+            reads.iter().all(|value| written.contains(value))
+        }
+        _ => true,
+    })
+}
+
+/// Whether every server's counter holds the increments minus the decrements acknowledged to the
+/// counter clients, once they have all finished.
+fn counters_net<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    let mut total = 0;
+    for actor in &state.actor_states {
+        match &**actor {
+            MyRegisterActorState::CounterClient {
+                awaiting: None,
+                net,
+                ..
+            } => total += net,
+            MyRegisterActorState::CounterClient { .. } => return true,
+            _ => {}
+        }
+    }
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => server.counter_value() == total,
+        _ => true,
+    })
+}
+
+/// Whether no server's set holds the set clients' element once any of them has removed it.
+fn removed_elements_absent<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    // set clients have had their remove acknowledged from their third request on
+    let removed = state.actor_states.iter().any(|actor| {
+        matches!(&**actor, MyRegisterActorState::SetClient { op_count, .. } if *op_count >= 3)
+    });
+    !removed
+        || state.actor_states.iter().all(|actor| match &**actor {
+            MyRegisterActorState::Server(server) => !server.set_contains(SET_ELEMENT),
+            _ => true,
+        })
+}
+
+/// Whether every pair of siblings in each server's multi-value register comes from writes where
+/// neither had seen the other.
+fn register_siblings_concurrent<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => server.mv_register.as_ref().map_or(true, |r| {
+            let writes = r.writes.iter().collect::<Vec<_>>();
+            writes.iter().enumerate().all(|(i, a)| {
+                writes[i + 1..]
+                    .iter()
+                    .all(|b| concurrent(&a.clock, &b.clock))
+            })
+        }),
+        _ => true,
+    })
+}
+
+/// Whether each server's flag resolves to its bias when it holds an enable and a disable where
+/// neither had seen the other.
+fn flag_bias_holds<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => server.flag.as_ref().map_or(true, |flag| {
+            let writes = &flag.register.writes;
+            let conflict = writes.iter().filter(|a| a.value).any(|a| {
+                writes
+                    .iter()
+                    .filter(|b| !b.value)
+                    .any(|b| concurrent(&a.clock, &b.clock))
+            });
+            !conflict || flag.value() == (flag.bias() == Bias::EnableWins)
+        }),
+        _ => true,
+    })
+}
+
+/// Whether every server's list has the same elements in the same order.
+fn same_lists<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    let lists = state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::Server(server) => Some(server.list_values()),
+            _ => None,
+        })
+        .collect::<BTreeSet<_>>();
+    lists.len() <= 1
+}
+
+/// Whether the servers hold the same values under every path in their nested maps, so that
+/// deleting a subtree while setting inside it was resolved the same way everywhere.
+fn same_subtrees<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    let nested = state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::Server(server) => server.nested.as_ref(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let paths = nested
+        .iter()
+        .flat_map(|nested| nested.paths())
+        .collect::<BTreeSet<_>>();
+    paths.iter().all(|path| {
+        nested
+            .iter()
+            .map(|nested| nested.subtree(path))
+            .collect::<BTreeSet<_>>()
+            .len()
+            <= 1
+    })
+}
+
+fn only_well_formed_values<M: Clone + Debug + PartialEq + Hash + Map>(
+    servers: usize,
+    state: &RegisterState<M>,
+) -> bool {
+    state.actor_states.iter().all(|actor| match &**actor {
+        MyRegisterActorState::Server(server) => {
+            server.values().iter().all(|((_, actor), key, value)| {
+                *actor < servers && valid_key(*key) && valid_value(*value)
+            })
+        }
+        _ => true,
+    })
+}
+
+fn syncing_done_and_in_sync<M: Clone + Debug + PartialEq + Hash + Map>(
+    cfg: &ModelCfg,
+    state: &RegisterState<M>,
+) -> bool {
+    // first check that the network has no sync messages in-flight, other than copies of ones
+    // already delivered that a duplicating network keeps.
+    for envelope in state.network.iter_deliverable() {
+        if let MyRegisterMsg::Internal(msg) = envelope.msg {
+            if redelivery_settled(cfg, state, envelope.src, envelope.dst, msg) {
+                continue;
+            }
+        }
+        match envelope.msg {
+            MyRegisterMsg::Internal(PeerMsg::PutSync { .. }) => {
+                return true;
+            }
+            MyRegisterMsg::Internal(PeerMsg::DeleteSync { .. }) => {
+                return true;
+            }
+            // every other message between servers
+            MyRegisterMsg::Internal(_) => {
+                return true;
+            }
+            MyRegisterMsg::Put(_, _, _)
+            | MyRegisterMsg::PutAfter(_, _, _, _)
+            | MyRegisterMsg::Get(_, _)
+            | MyRegisterMsg::Delete(_, _)
+            | MyRegisterMsg::Truncate(_)
+            | MyRegisterMsg::PutOk(_, _)
+            | MyRegisterMsg::GetOk(_, _)
+            | MyRegisterMsg::DeleteOk(_)
+            | MyRegisterMsg::GetAll(_, _)
+            | MyRegisterMsg::PutResolved(_, _, _, _)
+            | MyRegisterMsg::GetAllOk(_, _)
+            | MyRegisterMsg::Subscribe(_)
+            | MyRegisterMsg::Siblings(_, _)
+            | MyRegisterMsg::Token(_)
+            | MyRegisterMsg::Add(_, _)
+            | MyRegisterMsg::Remove(_, _)
+            | MyRegisterMsg::Contains(_, _)
+            | MyRegisterMsg::ContainsOk(_, _)
+            | MyRegisterMsg::Increment(_)
+            | MyRegisterMsg::Decrement(_)
+            | MyRegisterMsg::Read(_)
+            | MyRegisterMsg::CountOk(_, _)
+            | MyRegisterMsg::WriteRegister(_, _)
+            | MyRegisterMsg::ReadRegister(_)
+            | MyRegisterMsg::Enable(_)
+            | MyRegisterMsg::Disable(_)
+            | MyRegisterMsg::ReadFlag(_)
+            | MyRegisterMsg::FlagOk(_, _)
+            | MyRegisterMsg::Insert(_, _, _)
+            | MyRegisterMsg::DeleteAt(_, _)
+            | MyRegisterMsg::ReadList(_)
+            | MyRegisterMsg::ListOk(_, _)
+            | MyRegisterMsg::SetPath(_, _, _)
+            | MyRegisterMsg::DeletePath(_, _)
+            | MyRegisterMsg::GetPath(_, _) => {}
+        }
+    }
+
+    // servers that are yet to pull or crash haven't finished syncing either
+    if timers_pending(state) {
+        return true;
+    }
+
+    // next, check that all actors are in the same states (using sub-property checker)
+    servers_agree(cfg, state)
+}
@@ -0,0 +1,233 @@
+//! Python bindings, built with the `python` feature as a module named `toy_crdt`.
+//!
+//! `Map` is a fixed map for a single replica: `set` and `delete` effect an op on it and give the
+//! `Op` to hand the other replicas, which they effect with `apply`. `Replicas` runs several maps
+//! in memory instead, holding the ops sent between them until they are delivered, in whatever
+//! order the caller picks, so that a notebook can step through how the replicas converge.
+//! Keys and values are single character strings, as in the maps themselves.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use pyo3::exceptions::PyIndexError;
+use pyo3::prelude::*;
+
+use crate::id::Id;
+use crate::map::Map;
+use crate::map::Timestamp;
+use crate::map_fixed::FixedMap;
+use crate::peer::publish;
+use crate::peer::receive_sync;
+use crate::peer::PeerMsg;
+
+/// An op effected on one replica, for the others to apply.
+#[pyclass(name = "Op", frozen)]
+#[derive(Clone)]
+pub struct PyOp {
+    msg: PeerMsg,
+}
+
+#[pymethods]
+impl PyOp {
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.msg)
+    }
+}
+
+/// A replica's map.
+#[pyclass(name = "Map")]
+#[derive(Clone)]
+pub struct PyMap {
+    map: FixedMap,
+}
+
+#[pymethods]
+impl PyMap {
+    #[new]
+    fn new(actor_id: usize) -> Self {
+        Self {
+            map: <FixedMap as Map>::new(Id::from(actor_id)),
+        }
+    }
+
+    fn get(&self, key: char) -> Option<char> {
+        Map::get(&self.map, &key).copied()
+    }
+
+    /// Set `key` to `value`, giving the op for the other replicas.
+    fn set(&mut self, key: char, value: char) -> PyOp {
+        let set = Map::prepare_set(&self.map, key, value);
+        PyOp {
+            msg: publish(&mut self.map, set),
+        }
+    }
+
+    /// Delete `key`, giving the op for the other replicas, or `None` if it isn't in the map.
+    fn delete(&mut self, key: char) -> Option<PyOp> {
+        let delete = Map::prepare_delete(&self.map, &key)?;
+        Some(PyOp {
+            msg: publish(&mut self.map, delete),
+        })
+    }
+
+    /// Effect an op from another replica.
+    fn apply(&mut self, op: &PyOp) {
+        receive_sync(&mut self.map, op.msg.clone())
+    }
+
+    /// The values held, concurrent sets of a key included, as `(timestamp, key, value)`.
+    fn values(&self) -> Vec<(Timestamp, char, char)> {
+        Map::values(&self.map)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", Map::summary(&self.map))
+    }
+}
+
+/// Replicas of a map that send each other their ops in memory.
+#[pyclass(name = "Replicas")]
+pub struct PyReplicas {
+    maps: Vec<FixedMap>,
+    /// The ops sent but not yet delivered, as `(from, to, op)`, oldest first.
+    in_flight: Vec<(usize, usize, PeerMsg)>,
+}
+
+impl PyReplicas {
+    fn replica(&mut self, replica: usize) -> PyResult<&mut FixedMap> {
+        let count = self.maps.len();
+        self.maps
+            .get_mut(replica)
+            .ok_or_else(|| PyIndexError::new_err(format!("only {} replicas", count)))
+    }
+
+    fn send(&mut self, from: usize, msg: PeerMsg) {
+        for to in (0..self.maps.len()).filter(|to| *to != from) {
+            self.in_flight.push((from, to, msg.clone()))
+        }
+    }
+}
+
+#[pymethods]
+impl PyReplicas {
+    #[new]
+    fn new(count: usize) -> Self {
+        Self {
+            maps: (0..count)
+                .map(|i| <FixedMap as Map>::new(Id::from(i)))
+                .collect(),
+            in_flight: Vec::new(),
+        }
+    }
+
+    fn get(&mut self, replica: usize, key: char) -> PyResult<Option<char>> {
+        Ok(Map::get(self.replica(replica)?, &key).copied())
+    }
+
+    /// Set `key` to `value` on a replica, sending the op to the others.
+    fn set(&mut self, replica: usize, key: char, value: char) -> PyResult<()> {
+        let map = self.replica(replica)?;
+        let set = Map::prepare_set(map, key, value);
+        let msg = publish(map, set);
+        self.send(replica, msg);
+        Ok(())
+    }
+
+    /// Delete `key` on a replica, sending the op to the others, or return false if the replica
+    /// doesn't hold it.
+    fn delete(&mut self, replica: usize, key: char) -> PyResult<bool> {
+        let map = self.replica(replica)?;
+        let delete = match Map::prepare_delete(map, &key) {
+            Some(delete) => delete,
+            None => return Ok(false),
+        };
+        let msg = publish(map, delete);
+        self.send(replica, msg);
+        Ok(true)
+    }
+
+    /// The ops sent but not yet delivered, as `(from, to, op)`, oldest first.
+    fn in_flight(&self) -> Vec<(usize, usize, PyOp)> {
+        self.in_flight
+            .iter()
+            .map(|(from, to, msg)| (*from, *to, PyOp { msg: msg.clone() }))
+            .collect()
+    }
+
+    /// Deliver the op at `index` in `in_flight`, the oldest by default.
+    #[pyo3(signature = (index = 0))]
+    fn deliver(&mut self, index: usize) -> PyResult<()> {
+        if index >= self.in_flight.len() {
+            return Err(PyIndexError::new_err(format!(
+                "only {} ops in flight",
+                self.in_flight.len()
+            )));
+        }
+        let (_, to, msg) = self.in_flight.remove(index);
+        receive_sync(&mut self.maps[to], msg);
+        Ok(())
+    }
+
+    /// Deliver every op in flight, oldest first.
+    fn deliver_all(&mut self) {
+        for (_, to, msg) in self.in_flight.drain(..) {
+            receive_sync(&mut self.maps[to], msg)
+        }
+    }
+
+    /// Whether every replica holds the same values.
+    fn converged(&self) -> bool {
+        self.maps
+            .windows(2)
+            .all(|pair| Map::values(&pair[0]) == Map::values(&pair[1]))
+    }
+
+    /// A copy of a replica's map.
+    fn map(&mut self, replica: usize) -> PyResult<PyMap> {
+        Ok(PyMap {
+            map: self.replica(replica)?.clone(),
+        })
+    }
+}
+
+#[pymodule]
+fn toy_crdt(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyMap>()?;
+    m.add_class::<PyOp>()?;
+    m.add_class::<PyReplicas>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ops_replicate() {
+        let mut a = PyMap::new(0);
+        let mut b = PyMap::new(1);
+        b.apply(&a.set('x', 'A'));
+        assert_eq!(b.get('x'), Some('A'));
+        a.apply(&b.delete('x').unwrap());
+        assert_eq!(a.get('x'), None);
+        assert!(b.delete('x').is_none());
+    }
+
+    #[test]
+    fn replicas_converge_once_delivered() {
+        let mut replicas = PyReplicas::new(3);
+        replicas.set(0, 'x', 'A').unwrap();
+        replicas.set(1, 'x', 'B').unwrap();
+        assert_eq!(replicas.in_flight.len(), 4);
+        assert!(!replicas.converged());
+
+        replicas.deliver(3).unwrap();
+        assert_eq!(replicas.in_flight.len(), 3);
+        assert!(replicas.deliver(3).is_err());
+        replicas.deliver_all();
+        assert!(replicas.converged());
+        assert_eq!(replicas.map(2).unwrap().values().len(), 2);
+        assert!(replicas.get(3, 'x').is_err());
+    }
+}
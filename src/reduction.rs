@@ -0,0 +1,95 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use stateright::actor::ActorModelAction;
+
+use crate::map::Map;
+use crate::model::RegisterAction;
+use crate::model::RegisterState;
+use crate::receive_sync;
+use crate::MyRegisterActorState;
+use crate::MyRegisterMsg;
+use crate::PeerMsg;
+
+/// Partial order reduction of sync deliveries.
+///
+/// When two sync messages can be delivered to the same server and applying them in either order
+/// to that server's current state gives the same result, only the delivery of the first one is
+/// kept. The other is still delivered later, after the first. This relies on sync ops commuting,
+/// which is exactly what the CRDT should guarantee, so it should be disabled when checking
+/// implementations that are expected to be broken.
+pub(crate) fn reduce<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+    actions: &mut Vec<RegisterAction<M>>,
+) {
+    let mut pruned = vec![false; actions.len()];
+    for (i, first) in actions.iter().enumerate() {
+        if pruned[i] {
+            continue;
+        }
+        let (dst, first_msg) = match first {
+            ActorModelAction::Deliver {
+                src: _,
+                dst,
+                msg: MyRegisterMsg::Internal(msg),
+            } => (dst, msg),
+            _ => continue,
+        };
+        let map = match &*state.actor_states[usize::from(*dst)] {
+            // applying a dependency that servers on other shards wait on also answers them,
+            // which the map alone doesn't show
+            MyRegisterActorState::Server(server)
+                if server
+                    .causal
+                    .as_ref()
+                    .map_or(false, |causal| !causal.checks.is_empty()) =>
+            {
+                continue
+            }
+            MyRegisterActorState::Server(map) => &**map,
+            _ => continue,
+        };
+
+        for (j, second) in actions.iter().enumerate().skip(i + 1) {
+            if let ActorModelAction::Deliver {
+                src: _,
+                dst: second_dst,
+                msg: MyRegisterMsg::Internal(second_msg),
+            } = second
+            {
+                if second_dst == dst && !pruned[j] && commute(map, first_msg, second_msg) {
+                    pruned[j] = true;
+                }
+            }
+        }
+    }
+
+    let mut pruned = pruned.into_iter();
+    actions.retain(|_| !pruned.next().unwrap());
+}
+
+/// Whether applying the two ops to the map in either order gives the same result.
+pub(crate) fn commute<M: Clone + PartialEq + Map>(
+    map: &M,
+    first: &PeerMsg,
+    second: &PeerMsg,
+) -> bool {
+    // the have/need exchange sends replies, so its messages are never pruned
+    if !first.is_op() || !second.is_op() {
+        return false;
+    }
+    // dependent writes may wait for their dependency, which the map alone doesn't show
+    if matches!(first, PeerMsg::After { .. }) || matches!(second, PeerMsg::After { .. }) {
+        return false;
+    }
+
+    let mut first_second = map.clone();
+    receive_sync(&mut first_second, first.clone());
+    receive_sync(&mut first_second, second.clone());
+
+    let mut second_first = map.clone();
+    receive_sync(&mut second_first, second.clone());
+    receive_sync(&mut second_first, first.clone());
+
+    first_second == second_first
+}
@@ -0,0 +1,204 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use stateright::actor::model_peers;
+use stateright::actor::register::RegisterActor;
+use stateright::actor::register::RegisterMsg;
+use stateright::actor::Actor;
+use stateright::actor::ActorModel;
+use stateright::actor::Command;
+use stateright::actor::Id;
+use stateright::actor::Network;
+use stateright::actor::Out;
+use stateright::semantics::register::Register;
+use stateright::semantics::LinearizabilityTester;
+use stateright::Checker;
+use stateright::Expectation;
+use stateright::Model;
+
+use crate::map::Map;
+use crate::shard::Placement;
+use crate::sync::PeerState;
+use crate::ModelCfg;
+use crate::MyRegisterMsg;
+use crate::Peer;
+use crate::PeerMsg;
+use crate::KEY;
+
+/// Stateright's standard register protocol, with this crate's sync messages as the internal ones.
+pub(crate) type StandardMsg = RegisterMsg<u64, char, PeerMsg>;
+
+/// Adapts a [`Peer`] to stateright's standard register protocol on a single key, so that
+/// stateright's client actors and consistency testers can be used against the maps.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub(crate) struct RegisterServer<M>(Peer<M>);
+
+impl<M> Actor for RegisterServer<M>
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    type Msg = StandardMsg;
+
+    type State = PeerState<M>;
+
+    fn on_start(&self, id: Id, o: &mut Out<Self>) -> Self::State {
+        let mut peer_out = Out::new();
+        let state = self.0.on_start(id, &mut peer_out);
+        forward(peer_out, o);
+        state
+    }
+
+    fn on_msg(
+        &self,
+        id: Id,
+        state: &mut Cow<Self::State>,
+        src: Id,
+        msg: Self::Msg,
+        o: &mut Out<Self>,
+    ) {
+        let msg = match msg {
+            RegisterMsg::Put(request_id, value) => {
+                MyRegisterMsg::Put(request_id as usize, KEY, value)
+            }
+            RegisterMsg::Get(request_id) => {
+                // the standard protocol always replies to a get, with the default value if unset
+                let value = state.get(&KEY).copied().unwrap_or_default();
+                o.send(src, RegisterMsg::GetOk(request_id, value));
+                return;
+            }
+            RegisterMsg::Internal(msg) => MyRegisterMsg::Internal(msg),
+            RegisterMsg::PutOk(_) | RegisterMsg::GetOk(_, _) => return,
+        };
+        let mut peer_out = Out::new();
+        self.0.on_msg(id, state, src, msg, &mut peer_out);
+        forward(peer_out, o);
+    }
+}
+
+/// Translate the peer's outgoing messages into the standard protocol.
+fn forward<M>(peer_out: Out<Peer<M>>, o: &mut Out<RegisterServer<M>>)
+where
+    M: Clone + Debug + PartialEq + Hash + Map,
+{
+    for command in peer_out {
+        match command {
+            Command::Send(dst, msg) => match msg {
+                MyRegisterMsg::Internal(msg) => o.send(dst, RegisterMsg::Internal(msg)),
+                MyRegisterMsg::PutOk(request_id, _) => {
+                    o.send(dst, RegisterMsg::PutOk(request_id as u64))
+                }
+                MyRegisterMsg::GetOk(request_id, value) => {
+                    o.send(dst, RegisterMsg::GetOk(request_id as u64, value))
+                }
+                // not part of the standard protocol, and peers don't send them
+                MyRegisterMsg::Put(_, _, _)
+                | MyRegisterMsg::PutAfter(_, _, _, _)
+                | MyRegisterMsg::Get(_, _)
+                | MyRegisterMsg::Delete(_, _)
+                | MyRegisterMsg::Truncate(_)
+                | MyRegisterMsg::DeleteOk(_)
+                | MyRegisterMsg::GetAll(_, _)
+                | MyRegisterMsg::PutResolved(_, _, _, _)
+                | MyRegisterMsg::GetAllOk(_, _)
+                | MyRegisterMsg::Subscribe(_)
+                | MyRegisterMsg::Siblings(_, _)
+                | MyRegisterMsg::Token(_)
+                | MyRegisterMsg::Add(_, _)
+                | MyRegisterMsg::Remove(_, _)
+                | MyRegisterMsg::Contains(_, _)
+                | MyRegisterMsg::ContainsOk(_, _)
+                | MyRegisterMsg::Increment(_)
+                | MyRegisterMsg::Decrement(_)
+                | MyRegisterMsg::Read(_)
+                | MyRegisterMsg::CountOk(_, _)
+                | MyRegisterMsg::WriteRegister(_, _)
+                | MyRegisterMsg::ReadRegister(_)
+                | MyRegisterMsg::Enable(_)
+                | MyRegisterMsg::Disable(_)
+                | MyRegisterMsg::ReadFlag(_)
+                | MyRegisterMsg::FlagOk(_, _)
+                | MyRegisterMsg::Insert(_, _, _)
+                | MyRegisterMsg::DeleteAt(_, _)
+                | MyRegisterMsg::ReadList(_)
+                | MyRegisterMsg::ListOk(_, _)
+                | MyRegisterMsg::SetPath(_, _, _)
+                | MyRegisterMsg::DeletePath(_, _)
+                | MyRegisterMsg::GetPath(_, _) => {}
+            },
+            Command::SetTimer(duration) => o.set_timer(duration),
+            Command::CancelTimer => o.cancel_timer(),
+        }
+    }
+}
+
+/// Check the servers with stateright's register clients, recording the history in a
+/// linearizability tester.
+///
+/// Concurrent puts leave siblings that different servers may resolve differently until they
+/// sync, so this is expected to find non-linearizable histories; it is meant for comparing the
+/// maps against the standard consistency models rather than as a pass/fail check.
+pub(crate) fn check<M>(cfg: &ModelCfg)
+where
+    M: Clone + Debug + PartialEq + Hash + Send + Sync + 'static + Map,
+{
+    let servers = cfg.servers;
+    let mut model = ActorModel::new((), LinearizabilityTester::new(Register(char::default())));
+    for i in 0..servers {
+        model = model.actor(RegisterActor::Server(RegisterServer(Peer {
+            peers: model_peers(i, servers),
+            sync_mode: cfg.sync_mode,
+            validation: cfg.validation,
+            clock_skews: cfg.clock_skews.clone(),
+            clock: cfg.clock,
+            map_policy: cfg.map_policy,
+            causal_tokens: false,
+            causal_delivery: false,
+            anti_entropy: false,
+            retransmit: false,
+            retries: false,
+            dedup: false,
+            conflict_notifications: false,
+            placement: Placement::full(servers),
+            truncate: false,
+            epochs: false,
+            gc: false,
+            crash: false,
+            set: false,
+            set_impl: cfg.set_impl,
+            counter: false,
+            mv_register: false,
+            register_impl: cfg.register_impl,
+            flag: false,
+            flag_bias: cfg.flag_bias,
+            list: false,
+            nested: false,
+            membership: None,
+            _t: PhantomData::<M>::default(),
+        })));
+    }
+    for _ in 0..cfg.put_clients {
+        model = model.actor(RegisterActor::Client {
+            put_count: 2,
+            server_count: servers,
+        });
+    }
+    let checker = model
+        .init_network(Network::new_ordered(vec![]))
+        .property(Expectation::Always, "linearizable", |_, state| {
+            state.history.serialized_history().is_some()
+        })
+        .property(Expectation::Sometimes, "value chosen", |_, state| {
+            state.network.iter_deliverable().any(|envelope| {
+                matches!(envelope.msg, RegisterMsg::GetOk(_, value) if *value != char::default())
+            })
+        })
+        .record_msg_in(RegisterMsg::record_returns)
+        .record_msg_out(RegisterMsg::record_invocations)
+        .checker()
+        .threads(num_cpus::get())
+        .spawn_bfs()
+        .join();
+    checker.report(&mut std::io::stdout());
+}
@@ -0,0 +1,122 @@
+use alloc::vec::Vec;
+
+use crate::map::Timestamp;
+
+/// A value written to a [`LwwRegister`], stamped with the writer's Lamport clock.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LwwWrite {
+    /// Orders the write: the writer's counter, one past every write it had seen, and the writer.
+    pub timestamp: Timestamp,
+    pub value: char,
+}
+
+/// A last-writer-wins register: it only keeps the write with the greatest timestamp, whether or
+/// not its writer had seen the others, so of two concurrent writes one is silently lost.
+///
+/// Timestamps come from a Lamport clock, so a write is still ordered after every write its
+/// replica had seen, without carrying a version vector.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct LwwRegister {
+    actor_id: usize,
+    max_op: u32,
+    /// The write that won, if there has been one.
+    pub write: Option<LwwWrite>,
+}
+
+impl LwwRegister {
+    /// An empty register for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            max_op: 0,
+            write: None,
+        }
+    }
+
+    /// The value, with the timestamp of the write that wrote it, as the single sibling.
+    pub fn values(&self) -> Vec<(Timestamp, char)> {
+        self.write
+            .iter()
+            .map(|write| (write.timestamp, write.value))
+            .collect()
+    }
+
+    /// The highest op counter this register has seen.
+    pub fn max_counter(&self) -> u32 {
+        self.max_op
+    }
+
+    /// The write of `value`, timestamped after every write seen, without changing the register.
+    pub fn prepare_write(&self, value: char) -> LwwWrite {
+        LwwWrite {
+            timestamp: (self.max_op + 1, self.actor_id),
+            value,
+        }
+    }
+
+    /// Effect a write, whether prepared here or by another server.
+    pub fn receive_write(&mut self, write: LwwWrite) {
+        self.max_op = core::cmp::max(self.max_op, write.timestamp.0);
+        // the write is lost if a later one got here first
+        if self
+            .write
+            .as_ref()
+            .is_none_or(|current| current.timestamp < write.timestamp)
+        {
+            self.write = Some(write);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn equal_counters_are_broken_by_the_writer() {
+        let a = LwwRegister::new(0);
+        let b = LwwRegister::new(1);
+        let from_a = a.prepare_write('a');
+        let from_b = b.prepare_write('b');
+        assert_eq!(from_a.timestamp.0, from_b.timestamp.0);
+
+        let mut ab = a.clone();
+        ab.receive_write(from_a.clone());
+        ab.receive_write(from_b.clone());
+        let mut ba = b.clone();
+        ba.receive_write(from_b);
+        ba.receive_write(from_a);
+        assert_eq!(ab.values(), vec![((1, 1), 'b')]);
+        assert_eq!(ab.write, ba.write);
+    }
+
+    #[test]
+    fn rewriting_the_same_timestamp_changes_nothing() {
+        let mut register = LwwRegister::new(0);
+        let write = register.prepare_write('a');
+        register.receive_write(write.clone());
+        register.receive_write(LwwWrite {
+            value: 'z',
+            ..write
+        });
+        assert_eq!(register.values(), vec![((1, 0), 'a')]);
+    }
+
+    #[test]
+    fn an_earlier_write_arriving_late_is_lost() {
+        let mut writer = LwwRegister::new(0);
+        let first = writer.prepare_write('a');
+        writer.receive_write(first.clone());
+        let second = writer.prepare_write('b');
+        writer.receive_write(second.clone());
+
+        let mut reader = LwwRegister::new(1);
+        reader.receive_write(second);
+        reader.receive_write(first);
+        assert_eq!(reader.values(), vec![((2, 0), 'b')]);
+        assert_eq!(reader.max_counter(), 2);
+        assert_eq!(reader.prepare_write('c').timestamp, (3, 1));
+    }
+}
@@ -0,0 +1,166 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use crate::map::Timestamp;
+
+/// A value written to a [`MVRegister`], with the version vector of the write.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Write<V = char> {
+    /// Names the write: the writer's entry in the clock, and the writer.
+    pub timestamp: Timestamp,
+    /// How many writes from each replica, by actor id, the write causally follows, its own
+    /// included.
+    pub clock: Vec<u32>,
+    pub value: V,
+}
+
+/// A multi-value register: a write overwrites every write its replica had seen, and writes that
+/// are concurrent, neither having seen the other, are all kept as siblings.
+///
+/// Each write carries a version vector, so a write that arrives after one that overwrote it is
+/// recognised as overwritten and dropped, whatever order writes are delivered in.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct MVRegister<V = char> {
+    actor_id: usize,
+    /// The writes that haven't been overwritten.
+    pub writes: BTreeSet<Write<V>>,
+}
+
+impl<V: Clone + Ord> MVRegister<V> {
+    /// An empty register for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            writes: BTreeSet::new(),
+        }
+    }
+
+    /// The siblings, with the timestamps of the writes that wrote them.
+    pub fn values(&self) -> Vec<(Timestamp, V)> {
+        self.writes
+            .iter()
+            .map(|write| (write.timestamp, write.value.clone()))
+            .collect()
+    }
+
+    /// The highest entry in any sibling's clock.
+    pub fn max_counter(&self) -> u32 {
+        self.writes
+            .iter()
+            .flat_map(|write| write.clock.iter().copied())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The write of `value`, following every sibling, without changing the register.
+    pub fn prepare_write(&self, value: V) -> Write<V> {
+        let mut clock = Vec::new();
+        for write in &self.writes {
+            merge_max(&mut clock, &write.clock);
+        }
+        if clock.len() <= self.actor_id {
+            clock.resize(self.actor_id + 1, 0);
+        }
+        clock[self.actor_id] += 1;
+        Write {
+            timestamp: (clock[self.actor_id], self.actor_id),
+            clock,
+            value,
+        }
+    }
+
+    /// Effect a write, whether prepared here or by another server.
+    pub fn receive_write(&mut self, write: Write<V>) {
+        // a write that overwrote this one got here first
+        if self
+            .writes
+            .iter()
+            .any(|sibling| descends(&sibling.clock, &write.clock))
+        {
+            return;
+        }
+        self.writes
+            .retain(|sibling| !descends(&write.clock, &sibling.clock));
+        self.writes.insert(write);
+    }
+}
+
+/// Whether a write with clock `a` has seen the write with clock `b`, or is it.
+pub fn descends(a: &[u32], b: &[u32]) -> bool {
+    (0..a.len().max(b.len()))
+        .all(|i| a.get(i).copied().unwrap_or(0) >= b.get(i).copied().unwrap_or(0))
+}
+
+/// Whether neither of the writes with clocks `a` and `b` has seen the other.
+pub fn concurrent(a: &[u32], b: &[u32]) -> bool {
+    !descends(a, b) && !descends(b, a)
+}
+
+fn merge_max(counts: &mut Vec<u32>, other: &[u32]) {
+    if counts.len() < other.len() {
+        counts.resize(other.len(), 0);
+    }
+    for (count, other) in counts.iter_mut().zip(other) {
+        *count = core::cmp::max(*count, *other);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_writes_are_kept_as_siblings() {
+        let mut a = MVRegister::new(0);
+        let mut b = MVRegister::new(1);
+        let from_a = a.prepare_write('a');
+        let from_b = b.prepare_write('b');
+        a.receive_write(from_a.clone());
+        a.receive_write(from_b.clone());
+        b.receive_write(from_b);
+        b.receive_write(from_a);
+        assert_eq!(a.values(), vec![((1, 0), 'a'), ((1, 1), 'b')]);
+        assert_eq!(a, MVRegister { actor_id: 0, ..b });
+    }
+
+    #[test]
+    fn a_write_overwrites_the_siblings_it_saw() {
+        let mut a = MVRegister::new(0);
+        let from_b = MVRegister::new(1).prepare_write('b');
+        a.receive_write(a.prepare_write('a'));
+        a.receive_write(from_b);
+        let resolved = a.prepare_write('c');
+        assert_eq!(resolved.clock, vec![2, 1]);
+        a.receive_write(resolved);
+        assert_eq!(a.values(), vec![((2, 0), 'c')]);
+    }
+
+    #[test]
+    fn an_overwritten_write_arriving_late_is_dropped() {
+        let mut a = MVRegister::new(0);
+        let first = a.prepare_write('a');
+        a.receive_write(first.clone());
+        let second = a.prepare_write('b');
+        a.receive_write(second.clone());
+
+        let mut b = MVRegister::new(1);
+        b.receive_write(second);
+        b.receive_write(first.clone());
+        assert_eq!(b.values(), vec![((2, 0), 'b')]);
+        // and delivering a write again changes nothing
+        let before = b.clone();
+        b.receive_write(first);
+        assert_eq!(b, before);
+    }
+
+    #[test]
+    fn clocks_order_writes() {
+        assert!(descends(&[2, 1], &[1, 1]));
+        assert!(descends(&[1], &[1, 0]));
+        assert!(!descends(&[1, 0], &[0, 1]));
+        assert!(concurrent(&[1, 0], &[0, 1]));
+        assert!(!concurrent(&[1, 1], &[1]));
+    }
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Write;
+use std::hash::Hash;
+
+use crate::map::Map;
+use crate::model::RegisterState;
+use crate::sync::PeerState;
+use crate::MyRegisterActorState;
+
+/// Render each server's resolved key-value table, listing the siblings when a key has concurrent
+/// values.
+pub(crate) fn server_tables<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> String {
+    let mut out = String::new();
+    for (i, actor) in state.actor_states.iter().enumerate() {
+        let map = match &**actor {
+            MyRegisterActorState::Server(map) => map,
+            _ => continue,
+        };
+
+        let mut keys = BTreeMap::<char, Vec<char>>::new();
+        for (_, key, value) in map.values() {
+            keys.entry(key).or_default().push(value);
+        }
+
+        writeln!(out, "server {}:", i).unwrap();
+        if keys.is_empty() {
+            writeln!(out, "  (empty)").unwrap();
+        }
+        for (key, siblings) in keys {
+            match map.get(&key) {
+                Some(value) => write!(out, "  {:?} = {:?}", key, value).unwrap(),
+                None => write!(out, "  {:?} = (none)", key).unwrap(),
+            }
+            if siblings.len() > 1 {
+                write!(out, " siblings {:?}", siblings).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        for rejection in &map.rejected {
+            writeln!(out, "  rejected {:?}", rejection).unwrap();
+        }
+    }
+    out
+}
+
+/// Text shown as is in Debug output, rather than quoted.
+struct Text(String);
+
+impl Debug for Text {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Write a concise summary of a server in place of its Debug output, for the web explorer: the
+/// summary of each key its map gives, the highest op counter it has seen, and how many ops it
+/// is holding back or has rejected.
+pub(crate) fn server_summary<M: Clone + Debug + PartialEq + Hash + Map>(
+    server: &PeerState<M>,
+    f: &mut Formatter<'_>,
+) -> fmt::Result {
+    let keys = server
+        .summary()
+        .into_iter()
+        .map(|(key, summary)| (key, Text(summary)))
+        .collect::<BTreeMap<_, _>>();
+    let (deferred, pending) = server.causal.as_ref().map_or((0, 0), |causal| {
+        (causal.deferred.len(), causal.pending.len())
+    });
+    f.debug_struct("Server")
+        .field("keys", &keys)
+        .field("max_op", &server.max_counter())
+        .field("deferred", &deferred)
+        .field("pending", &pending)
+        .field("rejected", &server.rejected.len())
+        .finish()
+}
@@ -0,0 +1,197 @@
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::io::Write;
+
+use stateright::actor::Id;
+
+use crate::map::Map;
+use crate::map::MapOp;
+use crate::oplog::render;
+
+const HELP: &str = "\
+Replicas are numbered from 1.
+  set R K V     set key K to value V on replica R
+  delete R K    delete key K on replica R
+  get R K       show what key K resolves to on replica R
+  sync A B      deliver the ops from replica A that replica B hasn't received yet
+  sync          deliver every op to every replica that isn't partitioned
+  partition R   cut replica R off, holding ops to and from it until it heals
+  heal R        reconnect replica R
+  show          show every replica's state
+  help          show this
+  q             quit";
+
+/// Replicas of a map driven by hand, with the ops each replica prepares queued for every other
+/// replica until they are synced, in the order they were prepared.
+struct Replicas<M> {
+    maps: Vec<M>,
+    /// The ops queued from each replica to each other replica, by `from * len + to`.
+    queued: Vec<VecDeque<MapOp>>,
+    partitioned: BTreeSet<usize>,
+}
+
+/// Experiment with replicas of a map without the checker, taking commands from stdin such as
+/// `set 1 a x`, `sync 1 2` and `get 2 a`, and printing every replica's state after each command
+/// that changes one.
+///
+/// Commands can also be piped in to replay a session. `q` or end of input stops it.
+pub(crate) fn repl<M: Map>(replicas: usize) {
+    let mut replicas = Replicas::<M>::new(replicas);
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    println!("{}", HELP);
+    loop {
+        print!("> ");
+        std::io::stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(_)) | None => return,
+        };
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            [] => {}
+            ["q"] => return,
+            ["help"] => println!("{}", HELP),
+            ["show"] => replicas.show(),
+            ["get", replica, key] => {
+                if let Err(e) = replicas.get(replica, key) {
+                    println!("{}", e)
+                }
+            }
+            words => match replicas.run(words) {
+                Ok(()) => replicas.show(),
+                Err(e) => println!("{}", e),
+            },
+        }
+    }
+}
+
+impl<M: Map> Replicas<M> {
+    fn new(count: usize) -> Self {
+        Self {
+            maps: (0..count).map(|i| M::new(Id::from(i))).collect(),
+            queued: (0..count * count).map(|_| VecDeque::new()).collect(),
+            partitioned: BTreeSet::new(),
+        }
+    }
+
+    /// Run a command that changes the replicas.
+    fn run(&mut self, words: &[&str]) -> Result<(), String> {
+        match words {
+            ["set", replica, key, value] => {
+                let replica = self.replica(replica)?;
+                let op = self.maps[replica].prepare_set(char_arg(key)?, char_arg(value)?);
+                self.prepared(replica, op);
+            }
+            ["delete", replica, key] => {
+                let replica = self.replica(replica)?;
+                match self.maps[replica].prepare_delete(&char_arg(key)?) {
+                    Some(op) => self.prepared(replica, op),
+                    None => return Err(format!("Replica {} has nothing to delete", replica + 1)),
+                }
+            }
+            ["sync", from, to] => {
+                let (from, to) = (self.replica(from)?, self.replica(to)?);
+                for replica in [from, to] {
+                    if self.partitioned.contains(&replica) {
+                        return Err(format!("Replica {} is partitioned", replica + 1));
+                    }
+                }
+                self.sync(from, to);
+            }
+            ["sync"] => {
+                for from in 0..self.maps.len() {
+                    for to in 0..self.maps.len() {
+                        if !self.partitioned.contains(&from) && !self.partitioned.contains(&to) {
+                            self.sync(from, to);
+                        }
+                    }
+                }
+            }
+            ["partition", replica] => {
+                let replica = self.replica(replica)?;
+                self.partitioned.insert(replica);
+            }
+            ["heal", replica] => {
+                let replica = self.replica(replica)?;
+                self.partitioned.remove(&replica);
+            }
+            _ => return Err("Unknown command, try help".to_owned()),
+        }
+        Ok(())
+    }
+
+    /// Print what a key resolves to on a replica, along with its siblings.
+    fn get(&self, replica: &str, key: &str) -> Result<(), String> {
+        let replica = self.replica(replica)?;
+        let key = char_arg(key)?;
+        let siblings = self.maps[replica]
+            .values()
+            .into_iter()
+            .filter(|(_, k, _)| *k == key)
+            .map(|(timestamp, _, value)| (timestamp, value))
+            .collect::<Vec<_>>();
+        match self.maps[replica].get(&key) {
+            Some(value) => println!("{:?} = {:?}, siblings {:?}", key, value, siblings),
+            None => println!("{:?} = (none)", key),
+        }
+        Ok(())
+    }
+
+    /// Effect an op on the replica that prepared it and queue it for the others.
+    fn prepared(&mut self, replica: usize, op: MapOp) {
+        self.maps[replica].effect(op.clone());
+        for to in (0..self.maps.len()).filter(|to| *to != replica) {
+            self.queued[replica * self.maps.len() + to].push_back(op.clone());
+        }
+    }
+
+    fn sync(&mut self, from: usize, to: usize) {
+        let queued = std::mem::take(&mut self.queued[from * self.maps.len() + to]);
+        for op in queued {
+            self.maps[to].effect(op);
+        }
+    }
+
+    fn show(&self) {
+        for (i, map) in self.maps.iter().enumerate() {
+            let queued = (0..self.maps.len())
+                .map(|to| self.queued[i * self.maps.len() + to].len())
+                .sum::<usize>();
+            let partitioned = if self.partitioned.contains(&i) {
+                ", partitioned"
+            } else {
+                ""
+            };
+            println!(
+                "Replica {} ({} ops queued for others{}):",
+                i + 1,
+                queued,
+                partitioned
+            );
+            for line in render(map).lines() {
+                println!("  {}", line);
+            }
+        }
+    }
+
+    /// The index of a replica from its number.
+    fn replica(&self, word: &str) -> Result<usize, String> {
+        match word.parse::<usize>() {
+            Ok(n) if (1..=self.maps.len()).contains(&n) => Ok(n - 1),
+            _ => Err(format!(
+                "Expected a replica number from 1 to {}",
+                self.maps.len()
+            )),
+        }
+    }
+}
+
+fn char_arg(word: &str) -> Result<char, String> {
+    let mut chars = word.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("Expected a single character, got {:?}", word)),
+    }
+}
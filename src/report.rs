@@ -0,0 +1,245 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::Write;
+use std::path::Path;
+
+use stateright::Checker;
+use stateright::Expectation;
+use stateright::Model;
+use stateright::Property;
+
+use crate::fingerprint::fingerprint;
+use crate::map::Map;
+use crate::model::RegisterAction;
+use crate::model::RegisterModel;
+use crate::render;
+use crate::trace::init_state;
+
+/// Write a markdown report of a check run: the configuration, the outcome of each property, the
+/// statistics and the (minimized) counterexamples.
+pub(crate) fn write_report<M: Clone + Debug + PartialEq + Hash + Map>(
+    path: &Path,
+    checker: &impl Checker<RegisterModel<M>>,
+    complete: bool,
+) -> std::io::Result<()> {
+    let model = checker.model();
+    let mut out = File::create(path)?;
+
+    writeln!(out, "# Check report")?;
+    writeln!(out)?;
+    writeln!(out, "## Configuration")?;
+    writeln!(out)?;
+    if let Some(strategy) = model.cfg().strategy {
+        writeln!(out, "- Strategy: {:?}", strategy)?;
+    }
+    writeln!(out, "- Servers: {}", model.cfg().servers)?;
+    if let Some(replication) = model.cfg().replication_factor {
+        writeln!(out, "- Replication factor: {}", replication)?;
+    }
+    writeln!(out, "- Network: {:?}", model.cfg().network)?;
+    writeln!(out, "- Clock: {:?}", model.cfg().clock)?;
+    writeln!(out, "- Map policy: {:?}", model.cfg().map_policy)?;
+    writeln!(out, "- Put clients: {}", model.cfg().put_clients)?;
+    writeln!(out, "- Delete clients: {}", model.cfg().delete_clients)?;
+    if model.cfg().truncate {
+        writeln!(out, "- Delete clients truncate")?;
+    }
+    if model.cfg().epochs {
+        writeln!(out, "- Epochs: yes")?;
+    }
+    if model.cfg().gc {
+        writeln!(out, "- Garbage collection: yes")?;
+    }
+    if model.cfg().byzantine {
+        writeln!(out, "- Byzantine server: yes")?;
+    }
+    if model.cfg().partitions {
+        writeln!(out, "- Partitions: yes")?;
+    }
+    if model.cfg().crash {
+        writeln!(
+            out,
+            "- Servers crash, restarting from: {:?}",
+            model.cfg().restart
+        )?;
+        if let Some(every) = model.cfg().snapshot_every {
+            writeln!(out, "- Snapshots every: {} ops", every)?;
+        }
+    }
+    if model.cfg().late_joiners > 0 {
+        writeln!(out, "- Late joiners: {}", model.cfg().late_joiners)?;
+    }
+    if model.cfg().leavers > 0 {
+        writeln!(out, "- Leavers: {}", model.cfg().leavers)?;
+    }
+    if model.cfg().causal_delivery {
+        writeln!(out, "- Causal delivery: yes")?;
+    }
+    if model.cfg().anti_entropy {
+        writeln!(out, "- Anti-entropy: yes")?;
+    }
+    if model.cfg().retransmit {
+        writeln!(out, "- Retransmission: yes")?;
+    }
+    writeln!(out, "- Resolve clients: {}", model.cfg().resolve_clients)?;
+    writeln!(out, "- Read clients: {}", model.cfg().read_clients)?;
+    writeln!(out, "- Set clients: {}", model.cfg().set_clients)?;
+    if model.cfg().set_clients > 0 {
+        writeln!(out, "- Set: {:?}", model.cfg().set_impl)?;
+        if model.cfg().set_re_add {
+            writeln!(out, "- Set clients add again after removing")?;
+        }
+    }
+    writeln!(out, "- Counter clients: {}", model.cfg().counter_clients)?;
+    writeln!(
+        out,
+        "- Multi-value register clients: {}",
+        model.cfg().mv_register_clients
+    )?;
+    if model.cfg().mv_register_clients > 0 {
+        writeln!(out, "- Register: {:?}", model.cfg().register_impl)?;
+    }
+    writeln!(
+        out,
+        "- Flag enable clients: {}",
+        model.cfg().flag_enable_clients
+    )?;
+    writeln!(
+        out,
+        "- Flag disable clients: {}",
+        model.cfg().flag_disable_clients
+    )?;
+    if model.cfg().flag_enable_clients + model.cfg().flag_disable_clients > 0 {
+        writeln!(out, "- Flag bias: {:?}", model.cfg().flag_bias)?;
+    }
+    writeln!(
+        out,
+        "- List insert clients: {}",
+        model.cfg().list_insert_clients
+    )?;
+    writeln!(
+        out,
+        "- List delete clients: {}",
+        model.cfg().list_delete_clients
+    )?;
+    writeln!(out, "- Nested map clients: {}", model.cfg().nested_clients)?;
+    writeln!(out, "- Get clients: {}", model.cfg().get_clients)?;
+    writeln!(out, "- Mixed clients: {}", model.cfg().mixed_clients)?;
+    writeln!(out, "- Follow up gets: {}", model.cfg().follow_up_gets)?;
+    writeln!(out, "- Fairness: {:?}", model.cfg().fairness)?;
+    writeln!(out, "- Reduction: {}", model.cfg().reduction)?;
+    writeln!(
+        out,
+        "- Trace prefix steps: {}",
+        model.cfg().prefix.steps.len()
+    )?;
+    writeln!(out)?;
+
+    writeln!(out, "## Statistics")?;
+    writeln!(out)?;
+    writeln!(out, "- States: {}", checker.state_count())?;
+    writeln!(out, "- Unique states: {}", checker.unique_state_count())?;
+    writeln!(out, "- Max depth: {}", checker.max_depth())?;
+    if complete {
+        writeln!(out, "- Complete: the whole state space was explored")?;
+    } else {
+        writeln!(
+            out,
+            "- Bounded: the state space was not exhausted, results only cover the explored portion"
+        )?;
+    }
+    writeln!(out)?;
+
+    writeln!(out, "## Properties")?;
+    writeln!(out)?;
+    writeln!(out, "| Property | Expectation | Outcome |")?;
+    writeln!(out, "| --- | --- | --- |")?;
+    let properties = model.properties();
+    for property in &properties {
+        let outcome = match (&property.expectation, checker.discovery(property.name)) {
+            (Expectation::Always | Expectation::Eventually, Some(_)) => "violated",
+            (Expectation::Sometimes, None) if complete => "violated",
+            (Expectation::Sometimes, Some(_)) => "holds",
+            (_, None) if complete => "holds",
+            (_, None) => "not violated (bounded)",
+        };
+        writeln!(
+            out,
+            "| {} | {:?} | {} |",
+            property.name, property.expectation, outcome
+        )?;
+    }
+    writeln!(out)?;
+
+    for property in &properties {
+        let path = match (&property.expectation, checker.discovery(property.name)) {
+            (Expectation::Always | Expectation::Eventually, Some(path)) => path,
+            _ => continue,
+        };
+        let mut actions = path.into_actions();
+        if let Expectation::Always = property.expectation {
+            actions = minimize(model, property, actions);
+        }
+
+        writeln!(out, "## Counterexample: {}", property.name)?;
+        writeln!(out)?;
+        let mut state = init_state(model);
+        for (i, action) in actions.into_iter().enumerate() {
+            writeln!(out, "{}. `{}`", i + 1, model.format_action(&action))?;
+            state = match model.next_state(&state, action) {
+                Some(state) => state,
+                None => break,
+            };
+        }
+        writeln!(out)?;
+        writeln!(out, "Final state `{:016x}`:", fingerprint(&state))?;
+        writeln!(out)?;
+        writeln!(out, "```")?;
+        write!(out, "{}", render::server_tables(&state))?;
+        writeln!(out, "```")?;
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+/// Greedily drop actions from a counterexample to an always property while it still ends in a
+/// violating state.
+fn minimize<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    property: &Property<RegisterModel<M>>,
+    mut actions: Vec<RegisterAction<M>>,
+) -> Vec<RegisterAction<M>> {
+    let mut i = 0;
+    while i < actions.len() {
+        let mut candidate = actions.clone();
+        candidate.remove(i);
+        if violates(model, property, &candidate) {
+            actions = candidate;
+        } else {
+            i += 1;
+        }
+    }
+    actions
+}
+
+fn violates<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    property: &Property<RegisterModel<M>>,
+    actions: &[RegisterAction<M>],
+) -> bool {
+    let mut state = init_state(model);
+    for action in actions {
+        let mut enabled = Vec::new();
+        model.actor_model.actions(&state, &mut enabled);
+        if !enabled.contains(action) {
+            return false;
+        }
+        state = match model.next_state(&state, action.clone()) {
+            Some(state) => state,
+            None => return false,
+        };
+    }
+    !(property.condition)(model, &state)
+}
@@ -0,0 +1,66 @@
+use std::collections::BTreeMap;
+
+use crate::PeerMsg;
+
+/// What a server tracks to retransmit sets and deletes until each peer acknowledges them, so
+/// that they survive a network that drops messages.
+///
+/// Peers acknowledge every set and delete they receive, including retransmitted copies whose
+/// acknowledgement got lost. A retransmitted set that arrives after the value was removed is
+/// dropped, as servers remember the values their maps removed.
+///
+/// Servers only track this with retransmission, so that states don't otherwise differ in it.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct Outbound {
+    /// The sets and deletes each peer is yet to acknowledge, in the order they were sent.
+    unacked: BTreeMap<usize, Vec<PeerMsg>>,
+}
+
+impl Outbound {
+    /// Keep a set or delete sent to the given peers until they acknowledge it.
+    pub(crate) fn buffer(&mut self, peers: impl Iterator<Item = usize>, op: &PeerMsg) {
+        if ack(op).is_none() {
+            return;
+        }
+        for peer in peers {
+            self.unacked.entry(peer).or_default().push(op.clone());
+        }
+    }
+
+    /// Note a peer's acknowledgement, dropping the op it acknowledges.
+    pub(crate) fn acked(&mut self, peer: usize, ack_msg: &PeerMsg) {
+        if let Some(unacked) = self.unacked.get_mut(&peer) {
+            unacked.retain(|op| ack(op).as_ref() != Some(ack_msg));
+            if unacked.is_empty() {
+                self.unacked.remove(&peer);
+            }
+        }
+    }
+
+    /// Stop resending to a peer that left.
+    pub(crate) fn forget(&mut self, peer: usize) {
+        self.unacked.remove(&peer);
+    }
+
+    /// The ops to send again, with the peer each is for.
+    pub(crate) fn unacked(&self) -> Vec<(usize, PeerMsg)> {
+        self.unacked
+            .iter()
+            .flat_map(|(peer, ops)| ops.iter().map(move |op| (*peer, op.clone())))
+            .collect()
+    }
+}
+
+/// The acknowledgement of a set or delete, or `None` for other messages, which aren't
+/// retransmitted.
+pub(crate) fn ack(op: &PeerMsg) -> Option<PeerMsg> {
+    match op {
+        PeerMsg::PutSync { timestamp, .. } => Some(PeerMsg::PutSyncAck {
+            timestamp: *timestamp,
+        }),
+        PeerMsg::DeleteSync { context } => Some(PeerMsg::DeleteSyncAck {
+            context: context.clone(),
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::path::Path;
+
+use rhai::Array;
+use rhai::Dynamic;
+use rhai::Engine;
+use rhai::Scope;
+use rhai::AST;
+
+use crate::map::Map;
+use crate::map::Timestamp;
+use crate::model::RegisterState;
+use crate::MyRegisterActorState;
+use crate::MyRegisterMsg;
+
+/// Maximum number of properties a script can define, as each needs its own instance of the
+/// property condition.
+pub(crate) const MAX_SCRIPT_PROPERTIES: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ScriptExpectation {
+    Always,
+    Eventually,
+    Sometimes,
+}
+
+/// A rhai script defining extra properties.
+///
+/// Every function taking one parameter named `always_*`, `eventually_*` or `sometimes_*` is a
+/// property with that expectation. It is given the state as a map:
+///
+/// ```text
+/// #{
+///     servers: [#{ values: [[counter, actor, key, value], ...], visible: [...] }, ...],
+///     in_flight: <number of deliverable sync messages>,
+/// }
+/// ```
+///
+/// and returns whether the property's condition holds.
+pub(crate) struct Script {
+    engine: Engine,
+    ast: AST,
+    pub(crate) properties: Vec<(ScriptExpectation, String)>,
+}
+
+impl Script {
+    pub(crate) fn load(path: &Path) -> Result<Self, String> {
+        let engine = Engine::new();
+        let ast = engine
+            .compile_file(path.to_path_buf())
+            .map_err(|e| format!("failed to compile script {:?}: {}", path, e))?;
+        let properties = ast
+            .iter_functions()
+            .filter(|f| f.params.len() == 1)
+            .filter_map(|f| {
+                let expectation = if f.name.starts_with("always_") {
+                    ScriptExpectation::Always
+                } else if f.name.starts_with("eventually_") {
+                    ScriptExpectation::Eventually
+                } else if f.name.starts_with("sometimes_") {
+                    ScriptExpectation::Sometimes
+                } else {
+                    return None;
+                };
+                Some((expectation, f.name.to_owned()))
+            })
+            .collect::<Vec<_>>();
+        if properties.len() > MAX_SCRIPT_PROPERTIES {
+            return Err(format!(
+                "script {:?} defines {} properties but at most {} are supported",
+                path,
+                properties.len(),
+                MAX_SCRIPT_PROPERTIES
+            ));
+        }
+        Ok(Self {
+            engine,
+            ast,
+            properties,
+        })
+    }
+
+    /// Evaluate the property at `index`, treating script errors as the condition not holding.
+    pub(crate) fn eval<M: Clone + Debug + PartialEq + Hash + Map>(
+        &self,
+        index: usize,
+        state: &RegisterState<M>,
+    ) -> bool {
+        let (_, name) = &self.properties[index];
+        self.engine
+            .call_fn::<bool>(&mut Scope::new(), &self.ast, name, (to_dynamic(state),))
+            .unwrap_or_else(|e| {
+                eprintln!("Script property {:?} failed: {}", name, e);
+                false
+            })
+    }
+}
+
+impl Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script")
+            .field("properties", &self.properties)
+            .finish()
+    }
+}
+
+fn to_dynamic<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> Dynamic {
+    let values_to_dynamic = |values: Vec<(Timestamp, char, char)>| -> Dynamic {
+        values
+            .into_iter()
+            .map(|((counter, actor), key, value)| {
+                Dynamic::from(vec![
+                    Dynamic::from(counter as i64),
+                    Dynamic::from(actor as i64),
+                    Dynamic::from(key),
+                    Dynamic::from(value),
+                ])
+            })
+            .collect::<Array>()
+            .into()
+    };
+
+    let servers = state
+        .actor_states
+        .iter()
+        .filter_map(|actor| match &**actor {
+            MyRegisterActorState::Server(map) => {
+                let mut server = rhai::Map::new();
+                server.insert("values".into(), values_to_dynamic(map.values()));
+                server.insert("visible".into(), values_to_dynamic(map.visible_values()));
+                Some(Dynamic::from(server))
+            }
+            _ => None,
+        })
+        .collect::<Array>();
+    let in_flight = state
+        .network
+        .iter_deliverable()
+        .filter(|envelope| matches!(envelope.msg, MyRegisterMsg::Internal(_)))
+        .count();
+
+    let mut map = rhai::Map::new();
+    map.insert("servers".into(), servers.into());
+    map.insert("in_flight".into(), Dynamic::from(in_flight as i64));
+    map.into()
+}
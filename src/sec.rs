@@ -0,0 +1,364 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use stateright::actor::Envelope;
+use stateright::Property;
+
+use crate::map::Map;
+use crate::map::Timestamp;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::sync::PeerState;
+use crate::Key;
+use crate::MapPolicy;
+use crate::ModelCfg;
+use crate::MyRegisterActorState;
+use crate::MyRegisterMsg;
+use crate::PeerMsg;
+use crate::Value;
+
+/// An op that a server applied, identified the same way on every server.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+enum AppliedOp {
+    Set {
+        timestamp: Timestamp,
+        key: Key,
+        context: Vec<Timestamp>,
+    },
+    Delete {
+        origin: usize,
+        context: Vec<Timestamp>,
+    },
+    Add {
+        tag: Timestamp,
+    },
+    Remove {
+        origin: usize,
+        context: Vec<Timestamp>,
+    },
+    CounterState {
+        increments: Vec<u64>,
+        decrements: Vec<u64>,
+    },
+    RegisterWrite {
+        timestamp: Timestamp,
+    },
+    FlagWrite {
+        timestamp: Timestamp,
+    },
+    ListInsert {
+        id: Timestamp,
+    },
+    ListDelete {
+        origin: usize,
+        id: Timestamp,
+    },
+    NestedSet {
+        timestamp: Timestamp,
+    },
+    NestedDelete {
+        origin: usize,
+        context: Vec<Timestamp>,
+    },
+    Merge {
+        values: Vec<(Timestamp, char, char)>,
+        removed: Vec<Timestamp>,
+    },
+}
+
+/// What the strong eventual consistency suite needs to know about the run so far.
+///
+/// This is only recorded when the suite is selected, since it makes otherwise equal states
+/// distinct.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct SecHistory {
+    /// The ops each server has applied, by actor index.
+    applied: BTreeMap<usize, BTreeSet<AppliedOp>>,
+    /// The values that clients asked to be written.
+    written: BTreeSet<(Key, Value)>,
+}
+
+impl SecHistory {
+    fn applied(&self, server: usize) -> BTreeSet<AppliedOp> {
+        self.applied.get(&server).cloned().unwrap_or_default()
+    }
+
+    /// Record that `server` applied the ops in the message, returning whether any were new.
+    fn record_ops(&mut self, server: usize, msg: &PeerMsg, origin: usize) -> bool {
+        let op = match msg {
+            PeerMsg::PutSync {
+                context,
+                timestamp,
+                key,
+                ..
+            } => AppliedOp::Set {
+                timestamp: *timestamp,
+                key: *key,
+                context: context.iter().collect(),
+            },
+            // a truncate removes the values in its barrier as a delete would
+            PeerMsg::DeleteSync { context } | PeerMsg::Truncate { barrier: context } => {
+                AppliedOp::Delete {
+                    origin,
+                    context: context.iter().collect(),
+                }
+            }
+            PeerMsg::AddSync { tag, .. } => AppliedOp::Add { tag: *tag },
+            PeerMsg::RemoveSync { context } => AppliedOp::Remove {
+                origin,
+                context: context.iter().collect(),
+            },
+            PeerMsg::CounterState {
+                increments,
+                decrements,
+            } => AppliedOp::CounterState {
+                increments: increments.clone(),
+                decrements: decrements.clone(),
+            },
+            PeerMsg::RegisterSync { timestamp, .. }
+            | PeerMsg::LwwRegisterSync { timestamp, .. } => AppliedOp::RegisterWrite {
+                timestamp: *timestamp,
+            },
+            PeerMsg::FlagSync { timestamp, .. } => AppliedOp::FlagWrite {
+                timestamp: *timestamp,
+            },
+            PeerMsg::ListInsertSync { id, .. } => AppliedOp::ListInsert { id: *id },
+            PeerMsg::ListDeleteSync { id } => AppliedOp::ListDelete { origin, id: *id },
+            PeerMsg::NestedSetSync { timestamp, .. } => AppliedOp::NestedSet {
+                timestamp: *timestamp,
+            },
+            PeerMsg::NestedDeleteSync { context } => AppliedOp::NestedDelete {
+                origin,
+                context: context.iter().collect(),
+            },
+            PeerMsg::StateSync { values, removed } | PeerMsg::DeltaSync { values, removed } => {
+                AppliedOp::Merge {
+                    values: values.clone(),
+                    removed: removed.iter().collect(),
+                }
+            }
+            PeerMsg::Changes { changes } => {
+                return changes.iter().fold(false, |changed, change| {
+                    self.record_ops(server, &change.op, origin) || changed
+                })
+            }
+            PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => {
+                return self.record_ops(server, op, origin)
+            }
+            PeerMsg::Ops { ops } => {
+                return ops.iter().fold(false, |changed, pulled| {
+                    self.record_ops(server, &pulled.op, pulled.origin) || changed
+                })
+            }
+            PeerMsg::Repair { ops } => {
+                return ops.iter().fold(false, |changed, op| {
+                    self.record_ops(server, op, origin) || changed
+                })
+            }
+            PeerMsg::Have { .. }
+            | PeerMsg::Need { .. }
+            | PeerMsg::DepCheck { .. }
+            | PeerMsg::DepOk { .. }
+            | PeerMsg::Pull { .. }
+            | PeerMsg::Join
+            | PeerMsg::Leave
+            | PeerMsg::PutSyncAck { .. }
+            | PeerMsg::DeleteSyncAck { .. }
+            | PeerMsg::Digest { .. }
+            | PeerMsg::EpochClose { .. }
+            | PeerMsg::EpochDone { .. }
+            | PeerMsg::Watermark { .. } => return false,
+            _ => return false,
+        };
+        self.applied.entry(server).or_default().insert(op)
+    }
+}
+
+/// Record the ops applied by the receiver of a sync message, and the values clients write.
+pub(crate) fn record_in(
+    cfg: &ModelCfg,
+    history: &SecHistory,
+    envelope: &Envelope<&MyRegisterMsg>,
+) -> Option<SecHistory> {
+    if !cfg.suite.records_sec_history() {
+        return None;
+    }
+    let mut history = history.clone();
+    let changed = match envelope.msg {
+        MyRegisterMsg::Put(_, key, value)
+        | MyRegisterMsg::PutAfter(_, key, value, _)
+        | MyRegisterMsg::PutResolved(_, key, value, _) => history.written.insert((*key, *value)),
+        MyRegisterMsg::Internal(msg) => {
+            history.record_ops(envelope.dst.into(), msg, envelope.src.into())
+        }
+        _ => false,
+    };
+    changed.then(|| history)
+}
+
+/// Record the ops that a server has applied itself when it sends them on.
+pub(crate) fn record_out(
+    cfg: &ModelCfg,
+    history: &SecHistory,
+    envelope: &Envelope<&MyRegisterMsg>,
+) -> Option<SecHistory> {
+    if !cfg.suite.records_sec_history() {
+        return None;
+    }
+    let mut history = history.clone();
+    let changed = match envelope.msg {
+        MyRegisterMsg::Internal(msg) => {
+            history.record_ops(envelope.src.into(), msg, envelope.src.into())
+        }
+        _ => false,
+    };
+    changed.then(|| history)
+}
+
+/// Strong eventual consistency: servers that applied the same ops agree, ops are applied with
+/// respect to what they causally overwrote, and only values that clients wrote show up. Sets and
+/// deletes that are concurrent are resolved by the map's policy.
+pub(crate) fn properties<M: Clone + Debug + PartialEq + Hash + Map>(
+    cfg: &ModelCfg,
+) -> Vec<Property<RegisterModel<M>>> {
+    let mut properties = vec![
+        Property::always(
+            "sec: servers that applied the same ops have the same state",
+            |_, state| convergence(state),
+        ),
+        Property::always(
+            "sec: values overwritten by an applied op stay overwritten",
+            |_, state| respects_causality(state),
+        ),
+        Property::always("sec: no spurious values", |_, state| {
+            no_spurious_values(state)
+        }),
+    ];
+    properties.push(match cfg.map_policy {
+        MapPolicy::AddWins => Property::always(
+            "sec: sets concurrent with a delete keep their values",
+            |_, state| concurrent_sets_resolved(state, true),
+        ),
+        MapPolicy::RemoveWins => Property::always(
+            "sec: sets concurrent with a delete lose their values",
+            |_, state| concurrent_sets_resolved(state, false),
+        ),
+    });
+    properties
+}
+
+fn servers<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+) -> impl Iterator<Item = (usize, &PeerState<M>)> {
+    state
+        .actor_states
+        .iter()
+        .enumerate()
+        .filter_map(|(i, actor)| match &**actor {
+            MyRegisterActorState::Server(server) => Some((i, server)),
+            _ => None,
+        })
+}
+
+fn convergence<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    let servers = servers(state)
+        .map(|(i, server)| (state.history.sec.applied(i), server.observable()))
+        .collect::<Vec<_>>();
+    servers.iter().enumerate().all(|(i, (a_ops, a_values))| {
+        servers[i + 1..]
+            .iter()
+            .all(|(b_ops, b_values)| a_ops != b_ops || a_values == b_values)
+    })
+}
+
+fn respects_causality<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    servers(state).all(|(i, map)| {
+        let values = map.values();
+        state.history.sec.applied(i).iter().all(|op| {
+            let context = match op {
+                // a merged state or delta removes the values its sender had removed as a delete
+                // would
+                AppliedOp::Set { context, .. }
+                | AppliedOp::Delete { context, .. }
+                | AppliedOp::Merge {
+                    removed: context, ..
+                } => context,
+                // set ops, counter states, register and flag writes, list ops and nested map ops
+                // don't touch the map
+                AppliedOp::Add { .. }
+                | AppliedOp::Remove { .. }
+                | AppliedOp::CounterState { .. }
+                | AppliedOp::RegisterWrite { .. }
+                | AppliedOp::FlagWrite { .. }
+                | AppliedOp::ListInsert { .. }
+                | AppliedOp::ListDelete { .. }
+                | AppliedOp::NestedSet { .. }
+                | AppliedOp::NestedDelete { .. } => return true,
+            };
+            values.iter().all(|(t, _, _)| !context.contains(t))
+        })
+    })
+}
+
+/// Whether each server holds the value of every set it applied that removed a value a delete it
+/// applied also removed, if `kept`, or none of them otherwise. Neither op can have seen the
+/// other, as the value would have been gone. Sets that an applied op went on to overwrite or
+/// remove, or that lost to a later value of their key as with the last-writer-wins map, aren't
+/// expected to be held either way.
+fn concurrent_sets_resolved<M: Clone + Debug + PartialEq + Hash + Map>(
+    state: &RegisterState<M>,
+    kept: bool,
+) -> bool {
+    servers(state).all(|(i, map)| {
+        let applied = state.history.sec.applied(i);
+        let values = map.values();
+        let held = |timestamp: &Timestamp| values.iter().any(|(t, _, _)| t == timestamp);
+        let superseded = |timestamp: &Timestamp, key: &Key| {
+            values.iter().any(|(t, k, _)| k == key && t > timestamp)
+        };
+        let deleted = applied
+            .iter()
+            .filter_map(|op| match op {
+                AppliedOp::Delete { context, .. } => Some(context),
+                _ => None,
+            })
+            .flatten()
+            .collect::<BTreeSet<_>>();
+        let removed = applied
+            .iter()
+            .filter_map(|op| match op {
+                AppliedOp::Set { context, .. }
+                | AppliedOp::Delete { context, .. }
+                | AppliedOp::Merge {
+                    removed: context, ..
+                } => Some(context),
+                _ => None,
+            })
+            .flatten()
+            .collect::<BTreeSet<_>>();
+        applied.iter().all(|op| match op {
+            AppliedOp::Set {
+                timestamp,
+                key,
+                context,
+            } if context.iter().any(|t| deleted.contains(t)) => {
+                if kept {
+                    held(timestamp) || removed.contains(timestamp) || superseded(timestamp, key)
+                } else {
+                    !held(timestamp)
+                }
+            }
+            _ => true,
+        })
+    })
+}
+
+fn no_spurious_values<M: Clone + Debug + PartialEq + Hash + Map>(state: &RegisterState<M>) -> bool {
+    servers(state).all(|(_, map)| {
+        map.values()
+            .iter()
+            .all(|(_, key, value)| state.history.sec.written.contains(&(*key, *value)))
+    })
+}
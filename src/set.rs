@@ -0,0 +1,55 @@
+use alloc::vec::Vec;
+
+use crate::context::CausalContext;
+use crate::id::Id;
+use crate::map::Timestamp;
+
+/// An operation on a set, prepared at its origin and then effected exactly once at every server,
+/// the origin included, as map ops are.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SetOp {
+    /// Add an element under a tag unique to this add.
+    Add { tag: Timestamp, element: char },
+    /// Remove the tags in the context, which are the adds of an element that the remove's
+    /// origin had observed.
+    Remove { context: CausalContext },
+}
+
+impl SetOp {
+    /// The tag of an add, which names it.
+    pub fn tag(&self) -> Option<Timestamp> {
+        match self {
+            SetOp::Add { tag, .. } => Some(*tag),
+            SetOp::Remove { .. } => None,
+        }
+    }
+}
+
+/// The interface the checker uses to drive a set, and the one to embed a set with.
+///
+/// As with [`Map`](crate::map::Map), a change is prepared with `prepare_add` or
+/// `prepare_remove`, which leave the set as it is, and the op is then effected exactly once on
+/// every server's set, the preparing server's included.
+pub trait Set {
+    /// A set for the server with the given actor id, which must be unique among the servers.
+    fn new(actor_id: Id) -> Self;
+
+    /// Whether `element` is in the set.
+    fn contains(&self, element: &char) -> bool;
+
+    /// The elements in the set, in order.
+    fn elements(&self) -> Vec<char>;
+
+    /// Prepare adding `element`, without changing the set.
+    fn prepare_add(&self, element: char) -> SetOp;
+
+    /// Prepare removing `element` based on the current state, without changing it, if the
+    /// element is in the set.
+    fn prepare_remove(&self, element: &char) -> Option<SetOp>;
+
+    /// Apply an op, whether it was prepared by this set or received from another server.
+    fn effect(&mut self, op: SetOp);
+
+    /// The highest op counter this set has seen.
+    fn max_counter(&self) -> u32;
+}
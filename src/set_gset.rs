@@ -0,0 +1,121 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use crate::id::Id;
+use crate::map::Timestamp;
+use crate::set::Set;
+use crate::set::SetOp;
+
+impl Set for GSet {
+    fn new(actor_id: Id) -> Self {
+        Self::new(actor_id.into())
+    }
+
+    fn contains(&self, element: &char) -> bool {
+        self.elements.contains(element)
+    }
+
+    fn elements(&self) -> Vec<char> {
+        self.elements.iter().copied().collect()
+    }
+
+    fn prepare_add(&self, element: char) -> SetOp {
+        SetOp::Add {
+            tag: self.prepare_add(),
+            element,
+        }
+    }
+
+    fn prepare_remove(&self, _element: &char) -> Option<SetOp> {
+        // elements can't be removed
+        None
+    }
+
+    fn effect(&mut self, op: SetOp) {
+        match op {
+            SetOp::Add { tag, element } => self.receive_add(tag, element),
+            // never prepared, so never effected
+            SetOp::Remove { .. } => {}
+        }
+    }
+
+    fn max_counter(&self) -> u32 {
+        self.max_op
+    }
+}
+
+/// A grow-only set: elements can only be added, and removing one has no effect. Adds commute
+/// and are idempotent, so the set converges whatever order they arrive in, with nothing to
+/// remember but the elements.
+///
+/// Adds are still tagged, as every set's are, though only to count the ops seen.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct GSet {
+    actor_id: usize,
+    max_op: u32,
+    /// The elements added.
+    pub elements: BTreeSet<char>,
+}
+
+impl GSet {
+    /// An empty set for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            max_op: 0,
+            elements: BTreeSet::new(),
+        }
+    }
+
+    /// The tag for adding an element, globally unique once effected.
+    pub fn prepare_add(&self) -> Timestamp {
+        (self.max_op + 1, self.actor_id)
+    }
+
+    /// Effect an add, whether prepared here or by another server.
+    pub fn receive_add(&mut self, tag: Timestamp, element: char) {
+        self.max_op = core::cmp::max(self.max_op, tag.0);
+        self.elements.insert(element);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn elements_cant_be_removed() {
+        let mut set = GSet::new(0);
+        let tag = set.prepare_add();
+        set.receive_add(tag, 'x');
+        assert_eq!(Set::prepare_remove(&set, &'x'), None);
+        assert!(Set::contains(&set, &'x'));
+    }
+
+    #[test]
+    fn readding_changes_nothing() {
+        let mut set = GSet::new(0);
+        set.receive_add((1, 0), 'x');
+        let once = set.clone();
+        set.receive_add((1, 0), 'x');
+        set.receive_add((1, 1), 'x');
+        assert_eq!(set.elements, once.elements);
+    }
+
+    #[test]
+    fn adds_commute() {
+        let adds = [((1, 0), 'x'), ((1, 1), 'y'), ((2, 0), 'z')];
+        let mut forwards = GSet::new(0);
+        for (tag, element) in adds {
+            forwards.receive_add(tag, element);
+        }
+        let mut backwards = GSet::new(0);
+        for (tag, element) in adds.into_iter().rev() {
+            backwards.receive_add(tag, element);
+        }
+        assert_eq!(Set::elements(&forwards), vec!['x', 'y', 'z']);
+        assert_eq!(forwards, backwards);
+    }
+}
@@ -0,0 +1,209 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use crate::context::CausalContext;
+use crate::id::Id;
+use crate::map::Timestamp;
+use crate::set::Set;
+use crate::set::SetOp;
+
+impl Set for ORSet {
+    fn new(actor_id: Id) -> Self {
+        Self::new(actor_id.into())
+    }
+
+    fn contains(&self, element: &char) -> bool {
+        self.contains(element)
+    }
+
+    fn elements(&self) -> Vec<char> {
+        self.elements()
+    }
+
+    fn prepare_add(&self, element: char) -> SetOp {
+        SetOp::Add {
+            tag: self.prepare_add(),
+            element,
+        }
+    }
+
+    fn prepare_remove(&self, element: &char) -> Option<SetOp> {
+        self.prepare_remove(element)
+            .map(|context| SetOp::Remove { context })
+    }
+
+    fn effect(&mut self, op: SetOp) {
+        match op {
+            SetOp::Add { tag, element } => self.receive_add(tag, element),
+            SetOp::Remove { context } => self.receive_remove(context),
+        }
+    }
+
+    fn max_counter(&self) -> u32 {
+        self.max_op
+    }
+}
+
+/// An add-wins observed-remove set: each add tags its element uniquely and a remove only removes
+/// the tags its server had observed, so an add concurrent with a remove of the same element
+/// survives it.
+///
+/// Ops aren't delivered in causal order, so a remove can arrive before an add it removes. The
+/// tags removed are remembered so that such an add is dropped when it arrives.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ORSet {
+    actor_id: usize,
+    max_op: u32,
+    /// The tagged adds that haven't been removed, with their elements.
+    pub tags: BTreeSet<(Timestamp, char)>,
+    /// The tags that removes have removed.
+    pub removed: CausalContext,
+}
+
+impl ORSet {
+    /// An empty set for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            max_op: 0,
+            tags: BTreeSet::new(),
+            removed: CausalContext::new(),
+        }
+    }
+
+    /// Whether any add of `element` hasn't been removed.
+    pub fn contains(&self, element: &char) -> bool {
+        self.tags.iter().any(|(_, e)| e == element)
+    }
+
+    /// The elements with adds that haven't been removed, in order.
+    pub fn elements(&self) -> Vec<char> {
+        self.tags
+            .iter()
+            .map(|(_, e)| *e)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// The tag for adding an element, globally unique once effected.
+    pub fn prepare_add(&self) -> Timestamp {
+        (self.max_op + 1, self.actor_id)
+    }
+
+    /// The context for removing `element`: every tag of it this server has observed, if any.
+    pub fn prepare_remove(&self, element: &char) -> Option<CausalContext> {
+        let context = self
+            .tags
+            .iter()
+            .filter_map(|(t, e)| if e == element { Some(*t) } else { None })
+            .collect::<CausalContext>();
+        (!context.is_empty()).then_some(context)
+    }
+
+    /// Effect an add, whether prepared here or by another server.
+    pub fn receive_add(&mut self, tag: Timestamp, element: char) {
+        self.max_op = core::cmp::max(self.max_op, tag.0);
+
+        // a remove that observed this add got here first
+        if !self.removed.contains(&tag) {
+            self.tags.insert((tag, element));
+        }
+    }
+
+    /// Effect a remove, whether prepared here or by another server.
+    pub fn receive_remove(&mut self, context: CausalContext) {
+        if let Some(t) = context.max() {
+            self.max_op = core::cmp::max(self.max_op, t.0);
+        }
+
+        self.tags.retain(|(t, _)| !context.contains(t));
+        self.removed.union(&context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    /// Two servers that both hold an add of `element` from the first.
+    fn replicas(element: char) -> (ORSet, ORSet) {
+        let mut a = ORSet::new(0);
+        let mut b = ORSet::new(1);
+        let tag = a.prepare_add();
+        a.receive_add(tag, element);
+        b.receive_add(tag, element);
+        (a, b)
+    }
+
+    #[test]
+    fn concurrent_add_wins_over_remove() {
+        let (mut a, mut b) = replicas('x');
+        // a removes the add it saw while b adds the element again
+        let context = a.prepare_remove(&'x').unwrap();
+        a.receive_remove(context.clone());
+        let tag = b.prepare_add();
+        b.receive_add(tag, 'x');
+
+        a.receive_add(tag, 'x');
+        b.receive_remove(context);
+        assert!(a.contains(&'x'));
+        assert_eq!(a.tags, b.tags);
+    }
+
+    #[test]
+    fn readd_after_remove() {
+        let (mut a, _) = replicas('x');
+        let context = a.prepare_remove(&'x').unwrap();
+        a.receive_remove(context);
+        assert!(!a.contains(&'x'));
+        assert_eq!(a.prepare_remove(&'x'), None);
+
+        let tag = a.prepare_add();
+        a.receive_add(tag, 'x');
+        assert!(a.contains(&'x'));
+    }
+
+    #[test]
+    fn remove_before_its_add_drops_the_add() {
+        let (a, _) = replicas('x');
+        let context = a.prepare_remove(&'x').unwrap();
+        let mut c = ORSet::new(2);
+        c.receive_remove(context);
+        c.receive_add((1, 0), 'x');
+        assert!(!c.contains(&'x'));
+    }
+
+    #[test]
+    fn ops_commute() {
+        let ops = vec![
+            SetOp::Add {
+                tag: (1, 0),
+                element: 'x',
+            },
+            SetOp::Add {
+                tag: (1, 1),
+                element: 'y',
+            },
+            SetOp::Remove {
+                context: CausalContext::from(vec![(1, 0)]),
+            },
+            SetOp::Add {
+                tag: (2, 1),
+                element: 'x',
+            },
+        ];
+        let mut forwards = <ORSet as Set>::new(Id::from(0));
+        for op in ops.iter().cloned() {
+            forwards.effect(op);
+        }
+        let mut backwards = <ORSet as Set>::new(Id::from(0));
+        for op in ops.into_iter().rev() {
+            backwards.effect(op);
+        }
+        assert_eq!(forwards.elements(), vec!['x', 'y']);
+        assert_eq!(forwards, backwards);
+    }
+}
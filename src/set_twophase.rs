@@ -0,0 +1,189 @@
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+
+use crate::context::CausalContext;
+use crate::id::Id;
+use crate::map::Timestamp;
+use crate::set::Set;
+use crate::set::SetOp;
+
+impl Set for TwoPhaseSet {
+    fn new(actor_id: Id) -> Self {
+        Self::new(actor_id.into())
+    }
+
+    fn contains(&self, element: &char) -> bool {
+        self.contains(element)
+    }
+
+    fn elements(&self) -> Vec<char> {
+        self.elements()
+    }
+
+    fn prepare_add(&self, element: char) -> SetOp {
+        SetOp::Add {
+            tag: self.prepare_add(),
+            element,
+        }
+    }
+
+    fn prepare_remove(&self, element: &char) -> Option<SetOp> {
+        self.prepare_remove(element)
+            .map(|context| SetOp::Remove { context })
+    }
+
+    fn effect(&mut self, op: SetOp) {
+        match op {
+            SetOp::Add { tag, element } => self.receive_add(tag, element),
+            SetOp::Remove { context } => self.receive_remove(context),
+        }
+    }
+
+    fn max_counter(&self) -> u32 {
+        self.max_op
+    }
+}
+
+/// A two-phase set: a grow-only set of adds and a grow-only set of removes, where an element is
+/// in the set once added until it is removed, and can never be added again after that. Removes
+/// win over every add of their element, concurrent or later.
+///
+/// Removes name the adds their server had observed, as the observed-remove set's do, rather
+/// than the element, so a remove that arrives before the add it observed is remembered until
+/// the add tells which element it removed. Every add is kept, removed or not, for this.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct TwoPhaseSet {
+    actor_id: usize,
+    max_op: u32,
+    /// Every tagged add, with its element.
+    pub added: BTreeSet<(Timestamp, char)>,
+    /// The tags that removes have removed, whose elements are removed for good.
+    pub removed: CausalContext,
+}
+
+impl TwoPhaseSet {
+    /// An empty set for the server with the given actor id.
+    pub fn new(actor_id: usize) -> Self {
+        Self {
+            actor_id,
+            max_op: 0,
+            added: BTreeSet::new(),
+            removed: CausalContext::new(),
+        }
+    }
+
+    /// Whether `element` has been added and none of its adds has been removed.
+    pub fn contains(&self, element: &char) -> bool {
+        let mut adds = self.added.iter().filter(|(_, e)| e == element).peekable();
+        adds.peek().is_some() && adds.all(|(t, _)| !self.removed.contains(t))
+    }
+
+    /// The elements in the set, in order.
+    pub fn elements(&self) -> Vec<char> {
+        self.added
+            .iter()
+            .map(|(_, e)| *e)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|e| self.contains(e))
+            .collect()
+    }
+
+    /// The tag for adding an element, globally unique once effected.
+    pub fn prepare_add(&self) -> Timestamp {
+        (self.max_op + 1, self.actor_id)
+    }
+
+    /// The context for removing `element`: every add of it this server has observed, if the set
+    /// holds it.
+    pub fn prepare_remove(&self, element: &char) -> Option<CausalContext> {
+        if !self.contains(element) {
+            return None;
+        }
+        Some(
+            self.added
+                .iter()
+                .filter_map(|(t, e)| if e == element { Some(*t) } else { None })
+                .collect(),
+        )
+    }
+
+    /// Effect an add, whether prepared here or by another server. An add of an element that
+    /// was removed is kept but doesn't bring it back.
+    pub fn receive_add(&mut self, tag: Timestamp, element: char) {
+        self.max_op = core::cmp::max(self.max_op, tag.0);
+        self.added.insert((tag, element));
+    }
+
+    /// Effect a remove, whether prepared here or by another server.
+    pub fn receive_remove(&mut self, context: CausalContext) {
+        if let Some(t) = context.max() {
+            self.max_op = core::cmp::max(self.max_op, t.0);
+        }
+        self.removed.union(&context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn remove_wins_over_concurrent_add() {
+        let mut a = TwoPhaseSet::new(0);
+        let mut b = TwoPhaseSet::new(1);
+        a.receive_add((1, 0), 'x');
+        b.receive_add((1, 0), 'x');
+        // a removes the add it saw while b adds the element again
+        let context = a.prepare_remove(&'x').unwrap();
+        a.receive_remove(context.clone());
+        b.receive_add((2, 1), 'x');
+
+        a.receive_add((2, 1), 'x');
+        b.receive_remove(context);
+        assert!(!a.contains(&'x'));
+        assert_eq!(a.elements(), b.elements());
+        assert_eq!(a.added, b.added);
+    }
+
+    #[test]
+    fn readd_after_remove_doesnt_bring_it_back() {
+        let mut set = TwoPhaseSet::new(0);
+        set.receive_add((1, 0), 'x');
+        let context = set.prepare_remove(&'x').unwrap();
+        set.receive_remove(context);
+        let tag = set.prepare_add();
+        set.receive_add(tag, 'x');
+        assert!(!set.contains(&'x'));
+        assert_eq!(set.prepare_remove(&'x'), None);
+    }
+
+    #[test]
+    fn ops_commute() {
+        let ops = vec![
+            SetOp::Add {
+                tag: (1, 0),
+                element: 'x',
+            },
+            SetOp::Remove {
+                context: CausalContext::from(vec![(1, 0)]),
+            },
+            SetOp::Add {
+                tag: (1, 1),
+                element: 'y',
+            },
+        ];
+        let mut forwards = TwoPhaseSet::new(0);
+        for op in ops.iter().cloned() {
+            forwards.effect(op);
+        }
+        let mut backwards = TwoPhaseSet::new(0);
+        for op in ops.into_iter().rev() {
+            backwards.effect(op);
+        }
+        assert_eq!(forwards.elements(), vec!['y']);
+        assert_eq!(forwards, backwards);
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use stateright::CheckerVisitor;
+use stateright::Model;
+use stateright::Path;
+
+use crate::fingerprint::fingerprint;
+use crate::fingerprint::Fingerprint;
+use crate::map::Map;
+use crate::model::RegisterAction;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::MyRegisterActorState;
+
+/// How many of the most frequently reached states to report.
+const TOP_STATES: usize = 10;
+
+#[derive(Debug, Default)]
+struct ShapeStats {
+    /// Number of unique states at each depth.
+    states_per_depth: BTreeMap<usize, usize>,
+    /// Number of states with each out-degree.
+    branching: BTreeMap<usize, usize>,
+    /// Number of transitions leading into each state.
+    in_degree: HashMap<Fingerprint, usize>,
+    /// Most ops any server held back waiting for a dependency, if any server tracks them.
+    max_buffered: Option<usize>,
+}
+
+/// Collects the shape of the state space as the checker visits states.
+#[derive(Clone, Default)]
+pub(crate) struct ShapeRecorder(Arc<Mutex<ShapeStats>>);
+
+impl<M: Clone + Debug + PartialEq + Hash + Map> CheckerVisitor<RegisterModel<M>> for ShapeRecorder {
+    fn visit(&self, model: &RegisterModel<M>, path: Path<RegisterState<M>, RegisterAction<M>>) {
+        let mut states = path.into_vec();
+        let depth = states.len() - 1;
+        let (state, _) = states.pop().expect("paths always have a state");
+
+        let mut actions = Vec::new();
+        model.actions(&state, &mut actions);
+        let successors = actions
+            .into_iter()
+            .filter_map(|action| model.next_state(&state, action))
+            .map(|next| fingerprint(&next))
+            .collect::<Vec<_>>();
+
+        let buffered = state
+            .actor_states
+            .iter()
+            .filter_map(|actor| match &**actor {
+                MyRegisterActorState::Server(server) => server.causal.as_ref(),
+                _ => None,
+            })
+            .map(|causal| causal.deferred.len())
+            .max();
+
+        let mut stats = self.0.lock().unwrap();
+        stats.max_buffered = stats.max_buffered.max(buffered);
+        *stats.states_per_depth.entry(depth).or_default() += 1;
+        *stats.branching.entry(successors.len()).or_default() += 1;
+        for successor in successors {
+            *stats.in_degree.entry(successor).or_default() += 1;
+        }
+    }
+}
+
+impl ShapeRecorder {
+    pub(crate) fn report(&self) {
+        let stats = self.0.lock().unwrap();
+
+        println!("States per depth:");
+        for (depth, count) in &stats.states_per_depth {
+            println!("  {:>4}: {}", depth, count);
+        }
+
+        let states: usize = stats.branching.values().sum();
+        let transitions: usize = stats
+            .branching
+            .iter()
+            .map(|(degree, count)| degree * count)
+            .sum();
+        println!(
+            "Branching factor: min={}, max={}, mean={:.2}",
+            stats.branching.keys().next().unwrap_or(&0),
+            stats.branching.keys().next_back().unwrap_or(&0),
+            transitions as f64 / states.max(1) as f64
+        );
+        for (degree, count) in &stats.branching {
+            println!("  {:>4}: {}", degree, count);
+        }
+
+        if let Some(max_buffered) = stats.max_buffered {
+            println!("Largest causal buffer: {} ops", max_buffered);
+        }
+
+        let mut in_degree = stats.in_degree.iter().collect::<Vec<_>>();
+        in_degree.sort_by(|(fa, a), (fb, b)| b.cmp(a).then(fa.cmp(fb)));
+        println!("Most reached states:");
+        for (fingerprint, count) in in_degree.into_iter().take(TOP_STATES) {
+            println!("  [{:016x}]: {}", fingerprint, count);
+        }
+    }
+}
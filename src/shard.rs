@@ -0,0 +1,66 @@
+use stateright::actor::Id;
+
+use crate::fingerprint::fingerprint;
+use crate::Key;
+
+/// Which servers hold each key.
+///
+/// With sharding, each key is held by `replication` consecutive servers starting from one picked
+/// by the key's hash, and only those servers sync its ops. Without it every server holds every
+/// key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Placement {
+    servers: usize,
+    replication: usize,
+}
+
+impl Placement {
+    /// Every server holds every key.
+    pub(crate) fn full(servers: usize) -> Self {
+        Self {
+            servers,
+            replication: servers,
+        }
+    }
+
+    /// Each key is held by `replication` of the servers.
+    pub(crate) fn sharded(servers: usize, replication: usize) -> Self {
+        Self {
+            servers,
+            replication: replication.min(servers),
+        }
+    }
+
+    pub(crate) fn servers(&self) -> usize {
+        self.servers
+    }
+
+    pub(crate) fn is_sharded(&self) -> bool {
+        self.replication < self.servers
+    }
+
+    /// The servers that hold the key, in order of preference.
+    pub(crate) fn replicas(&self, key: Key) -> Vec<usize> {
+        let first = if self.is_sharded() {
+            (fingerprint(&key) % self.servers as u64) as usize
+        } else {
+            0
+        };
+        (0..self.replication)
+            .map(|i| (first + i) % self.servers)
+            .collect()
+    }
+
+    /// Whether the server holds the key. Every server does without sharding, however it is
+    /// numbered, which lets servers run for real under ids taken from their addresses.
+    pub(crate) fn holds(&self, server: usize, key: Key) -> bool {
+        !self.is_sharded() || self.replicas(key).contains(&server)
+    }
+
+    /// The server the client with the given index sends its requests on the key to, spreading
+    /// clients over the key's replicas.
+    pub(crate) fn route(&self, key: Key, client: usize) -> Id {
+        let replicas = self.replicas(key);
+        Id::from(replicas[client % replicas.len()])
+    }
+}
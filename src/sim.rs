@@ -0,0 +1,236 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::collections::BinaryHeap;
+use std::ops::RangeInclusive;
+
+use stateright::actor::Id;
+
+use crate::fingerprint::fingerprint;
+use crate::map::Map;
+use crate::map::MapOp;
+use crate::Key;
+
+/// How many times to sample metadata over a run.
+const SAMPLES: usize = 10;
+
+/// Settings for a standalone simulation.
+pub(crate) struct SimCfg {
+    pub(crate) replicas: usize,
+    /// Number of client ops to issue, one at a time at random replicas.
+    pub(crate) ops: usize,
+    pub(crate) seed: u64,
+    /// Virtual milliseconds a message takes to arrive.
+    pub(crate) latency: RangeInclusive<u64>,
+    /// Virtual milliseconds between client ops.
+    pub(crate) op_interval: u64,
+    /// Keys to operate on, picked at random; repeated keys are picked more often.
+    pub(crate) keys: Vec<Key>,
+}
+
+/// SplitMix64, so that runs with the same seed are the same everywhere.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn within(&mut self, range: &RangeInclusive<u64>) -> u64 {
+        range.start() + self.next() % (range.end() - range.start() + 1)
+    }
+}
+
+enum EventKind {
+    /// A client issues an op at a replica.
+    Issue,
+    /// An op arrives at a replica.
+    Deliver { dst: usize, op: MapOp, id: usize },
+}
+
+struct Event {
+    time: u64,
+    /// Breaks ties in time by scheduling order, keeping runs deterministic.
+    seq: u64,
+    kind: EventKind,
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        (self.time, self.seq) == (other.time, other.seq)
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Event {
+    // reversed, as the heap pops the greatest
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.time, other.seq).cmp(&(self.time, self.seq))
+    }
+}
+
+/// Metadata at a point in the run.
+struct Sample {
+    time: u64,
+    issued: usize,
+    in_flight: usize,
+    mean_entries: f64,
+    max_entries: usize,
+}
+
+/// Simulate replicas of a map exchanging ops over a network with random latency, in virtual time
+/// and with a seeded scheduler, and report how long ops take to reach every replica and how the
+/// metadata grows.
+///
+/// This doesn't use the checker at all, so it reaches far more replicas and ops than exhaustive
+/// checking can, but only explores the one schedule the seed gives.
+pub(crate) fn simulate<M: Map>(cfg: &SimCfg) {
+    let mut rng = Rng(cfg.seed);
+    let mut replicas = (0..cfg.replicas)
+        .map(|i| M::new(Id::from(i)))
+        .collect::<Vec<_>>();
+    let mut events = BinaryHeap::new();
+    let mut seq = 0;
+    let mut schedule = |events: &mut BinaryHeap<Event>, time, kind| {
+        seq += 1;
+        events.push(Event { time, seq, kind });
+    };
+
+    // channels are FIFO like the checker's network, so messages don't overtake earlier ones
+    // between the same replicas
+    let mut channels = vec![0; cfg.replicas * cfg.replicas];
+    // for each op, when it was issued and how many replicas have yet to apply it
+    let mut pending = Vec::<(u64, usize)>::new();
+    let mut propagation = Vec::new();
+    let mut samples = Vec::new();
+    let sample_every = (cfg.ops / SAMPLES).max(1);
+    let mut issued = 0;
+    let mut in_flight = 0;
+    let mut last_issue = 0;
+    let mut now = 0;
+
+    if cfg.ops > 0 {
+        schedule(&mut events, 0, EventKind::Issue);
+    }
+    while let Some(event) = events.pop() {
+        now = event.time;
+        match event.kind {
+            EventKind::Issue => {
+                let origin = rng.below(cfg.replicas);
+                let key = cfg.keys[rng.below(cfg.keys.len())];
+                let op = if rng.below(4) == 0 {
+                    replicas[origin].prepare_delete(&key)
+                } else {
+                    let value = (b'A' + rng.below(26) as u8) as char;
+                    Some(replicas[origin].prepare_set(key, value))
+                };
+                if let Some(op) = op {
+                    replicas[origin].effect(op.clone());
+                    let id = pending.len();
+                    pending.push((now, cfg.replicas - 1));
+                    if cfg.replicas == 1 {
+                        propagation.push(0);
+                    }
+                    for dst in (0..cfg.replicas).filter(|dst| *dst != origin) {
+                        let channel = &mut channels[origin * cfg.replicas + dst];
+                        *channel = (*channel).max(now + rng.within(&cfg.latency));
+                        let op = op.clone();
+                        schedule(&mut events, *channel, EventKind::Deliver { dst, op, id });
+                        in_flight += 1;
+                    }
+                }
+                issued += 1;
+                last_issue = now;
+                if issued % sample_every == 0 || issued == cfg.ops {
+                    samples.push(sample(&replicas, now, issued, in_flight));
+                }
+                if issued < cfg.ops {
+                    schedule(&mut events, now + cfg.op_interval, EventKind::Issue);
+                }
+            }
+            EventKind::Deliver { dst, op, id } => {
+                replicas[dst].effect(op);
+                in_flight -= 1;
+                let (issued_at, remaining) = &mut pending[id];
+                *remaining -= 1;
+                if *remaining == 0 {
+                    propagation.push(now - *issued_at);
+                }
+            }
+        }
+    }
+
+    println!(
+        "Simulated {} replicas and {} ops (seed {}), {} of them changing the map",
+        cfg.replicas,
+        cfg.ops,
+        cfg.seed,
+        pending.len()
+    );
+    println!(
+        "Last op issued at {}ms, all ops delivered by {}ms ({}ms to converge after the last op)",
+        last_issue,
+        now,
+        now - last_issue
+    );
+    if !propagation.is_empty() {
+        propagation.sort_unstable();
+        println!(
+            "Time for an op to reach every replica: mean={:.1}ms, median={}ms, max={}ms",
+            propagation.iter().sum::<u64>() as f64 / propagation.len() as f64,
+            propagation[propagation.len() / 2],
+            propagation[propagation.len() - 1]
+        );
+    }
+    let states = replicas
+        .iter()
+        .map(|replica| fingerprint(&replica.visible_values()))
+        .collect::<BTreeSet<_>>();
+    if states.len() <= 1 {
+        println!("Converged: every replica has the same visible values");
+    } else {
+        println!(
+            "Did not converge: the replicas ended with {} different sets of visible values",
+            states.len()
+        );
+    }
+    println!("Metadata over time:");
+    println!(
+        "  {:>10} {:>10} {:>10} {:>12} {:>12}",
+        "time (ms)", "ops", "in flight", "mean entries", "max entries"
+    );
+    for sample in samples {
+        println!(
+            "  {:>10} {:>10} {:>10} {:>12.2} {:>12}",
+            sample.time, sample.issued, sample.in_flight, sample.mean_entries, sample.max_entries
+        );
+    }
+}
+
+fn sample<M: Map>(replicas: &[M], time: u64, issued: usize, in_flight: usize) -> Sample {
+    let entries = replicas
+        .iter()
+        .map(|replica| replica.values().len())
+        .collect::<Vec<_>>();
+    Sample {
+        time,
+        issued,
+        in_flight,
+        mean_entries: entries.iter().sum::<usize>() as f64 / entries.len().max(1) as f64,
+        max_entries: entries.iter().copied().max().unwrap_or(0),
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::time::Duration;
+use std::time::Instant;
+
+use stateright::Expectation;
+use stateright::Model;
+
+use crate::check::print_stats;
+use crate::check::report_properties;
+use crate::check::Steps;
+use crate::explore::evaluate;
+use crate::map::Map;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::sim::Rng;
+
+/// The outcome of a simulation.
+pub(crate) struct Simulation<M: Clone + Debug + PartialEq + Hash + Map> {
+    /// How many walks were taken, fewer than asked for if the time limit was reached.
+    runs: usize,
+    states: usize,
+    max_depth: usize,
+    /// The walk to the first discovery of each property.
+    discoveries: BTreeMap<&'static str, Steps<M>>,
+}
+
+/// Take random walks from the initial states, each choosing uniformly among the actions
+/// enabled in every state until none are or `max_depth` is reached, checking the properties
+/// along the way. Walks with the same seed and model are the same.
+pub(crate) fn simulate<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    seed: u64,
+    runs: usize,
+    max_depth: usize,
+    time_limit: Option<Duration>,
+) -> Simulation<M> {
+    let properties = model.properties();
+    let eventually = properties
+        .iter()
+        .enumerate()
+        .filter(|(_, property)| matches!(property.expectation, Expectation::Eventually))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let init_states = model.init_states();
+
+    let mut rng = Rng(seed);
+    let mut simulation = Simulation {
+        runs: 0,
+        states: 0,
+        max_depth: 0,
+        discoveries: BTreeMap::new(),
+    };
+    let start = Instant::now();
+    let mut last_progress = start;
+    let mut actions = Vec::new();
+    while simulation.runs < runs && simulation.discoveries.len() < properties.len() {
+        if time_limit.map_or(false, |time_limit| start.elapsed() >= time_limit) {
+            break;
+        }
+        if last_progress.elapsed() >= Duration::from_secs(1) {
+            println!(
+                "Simulating. runs={}, states={}, depth={}",
+                simulation.runs, simulation.states, simulation.max_depth
+            );
+            last_progress = Instant::now();
+        }
+        simulation.runs += 1;
+
+        let mut state = init_states[rng.below(init_states.len())].clone();
+        let mut steps: Steps<M> = Vec::new();
+        let mut pending = eventually.clone();
+        loop {
+            simulation.states += 1;
+            simulation.max_depth = simulation.max_depth.max(steps.len());
+            let discovered = evaluate(
+                model,
+                &properties,
+                &state,
+                &mut pending,
+                &simulation.discoveries,
+            );
+            if steps.len() == max_depth {
+                record(&mut simulation.discoveries, discovered, &steps, state);
+                break;
+            }
+
+            model.actions(&state, &mut actions);
+            // actions without a next state, or with one outside the boundary, are skipped as
+            // the checkers skip them
+            let mut next = None;
+            while !actions.is_empty() {
+                let action = actions.swap_remove(rng.below(actions.len()));
+                match model.next_state(&state, action.clone()) {
+                    Some(state) if model.within_boundary(&state) => {
+                        next = Some((action, state));
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            actions.clear();
+            match next {
+                Some((action, next)) => {
+                    record(
+                        &mut simulation.discoveries,
+                        discovered,
+                        &steps,
+                        state.clone(),
+                    );
+                    steps.push((state, Some(action)));
+                    state = next;
+                }
+                None => {
+                    // a walk only ends without any actions at the end of a complete path, so
+                    // eventually properties still pending are violated
+                    let discovered = discovered
+                        .into_iter()
+                        .chain(pending.iter().map(|i| properties[*i].name))
+                        .filter(|name| !simulation.discoveries.contains_key(name))
+                        .collect();
+                    record(&mut simulation.discoveries, discovered, &steps, state);
+                    break;
+                }
+            }
+        }
+    }
+    simulation
+}
+
+/// Record the walk so far, ending in `state`, as the discovery of the given properties.
+fn record<M: Clone + Debug + PartialEq + Hash + Map>(
+    discoveries: &mut BTreeMap<&'static str, Steps<M>>,
+    discovered: Vec<&'static str>,
+    steps: &Steps<M>,
+    state: RegisterState<M>,
+) {
+    for name in discovered {
+        let mut steps = steps.clone();
+        steps.push((state.clone(), None));
+        discoveries.entry(name).or_insert(steps);
+    }
+}
+
+/// Report the outcome of each property as [`crate::check::check_properties`] does for
+/// stateright's checkers, returning how many were violated. Simulations never exhaust the state
+/// space, so properties that aren't violated are bounded results.
+pub(crate) fn check_properties<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    simulation: Simulation<M>,
+    keep_going: bool,
+) -> usize {
+    println!(
+        "Done. runs={}, states={}, max_depth={}",
+        simulation.runs, simulation.states, simulation.max_depth
+    );
+    print_stats(model, &simulation.discoveries);
+    report_properties(model, simulation.discoveries, keep_going, false)
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use crate::map::Map;
+use crate::map::MapOp;
+use crate::map::Timestamp;
+use crate::peer::VectorClock;
+use crate::receive_sync;
+use crate::truncate::Truncation;
+use crate::PeerMsg;
+
+/// What a server tracks to find which sets are causally stable, delivered at every server, so
+/// that the truncation metadata about them can be collected.
+///
+/// Each op is stamped with its origin's vector clock, and servers send their peers their clock
+/// whenever they deliver ops from them. The low watermark, the least of the latest clock known
+/// from every server, this one's own included, counts the ops from each origin that every server
+/// has delivered. An op whose stamp is within the watermark is stable, and so are the sets it
+/// names, which happened before it, once every op naming it is within the watermark too: the set
+/// itself, the sets that overwrote it and the deletes that removed it. Causal delivery drops
+/// copies of ops already delivered, so a stable set can't arrive anywhere again, and every op on
+/// it that a server prepares from then on is delivered after it everywhere. Such a set needn't
+/// be remembered as seen or removed, nor as deleted or overwritten by the map.
+///
+/// Servers only track this with garbage collection, so that states don't otherwise differ in it.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct Stability {
+    /// How many servers there are, this one included.
+    servers: usize,
+    /// The latest clock known from each peer, as stamped on its ops or sent as its watermark.
+    clocks: BTreeMap<usize, VectorClock>,
+    /// The sets not yet stable, each with the stamps of the ops that named it joined, which the
+    /// watermark has to reach.
+    pending: BTreeMap<Timestamp, VectorClock>,
+}
+
+impl Stability {
+    pub(crate) fn new(servers: usize) -> Self {
+        Self {
+            servers,
+            clocks: BTreeMap::new(),
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Note the sets a stamped op names, whether this server prepared it or a peer: the set
+    /// itself and those it overwrote, the sets a delete removed, or the sets in a truncate's
+    /// barrier.
+    pub(crate) fn record(&mut self, clock: &VectorClock, op: &PeerMsg) {
+        for timestamp in named_sets(op) {
+            let stamp = self.pending.entry(timestamp).or_default();
+            for (server, seq) in clock {
+                let entry = stamp.entry(*server).or_default();
+                *entry = (*entry).max(*seq);
+            }
+        }
+    }
+
+    /// Note a peer's clock, keeping the latest known as clocks only grow.
+    pub(crate) fn merge(&mut self, peer: usize, clock: &VectorClock) {
+        let known = self.clocks.entry(peer).or_default();
+        for (server, seq) in clock {
+            let entry = known.entry(*server).or_default();
+            *entry = (*entry).max(*seq);
+        }
+    }
+
+    /// Take the sets that have become stable, given this server's own clock.
+    pub(crate) fn collect(&mut self, own: &VectorClock) -> BTreeSet<Timestamp> {
+        if self.clocks.len() + 1 < self.servers {
+            // a peer that hasn't been heard from may not have delivered anything
+            return BTreeSet::new();
+        }
+        let watermark = |origin: &usize| {
+            self.clocks
+                .values()
+                .chain(Some(own))
+                .map(|clock| clock.get(origin).copied().unwrap_or(0))
+                .min()
+                .unwrap_or(0)
+        };
+        let stable = self
+            .pending
+            .iter()
+            .filter(|(_, stamp)| stamp.iter().all(|(origin, seq)| *seq <= watermark(origin)))
+            .map(|(timestamp, _)| *timestamp)
+            .collect::<BTreeSet<_>>();
+        self.pending
+            .retain(|timestamp, _| !stable.contains(timestamp));
+        stable
+    }
+}
+
+/// The sets an op names: the set it carries and those it overwrote, the sets a delete removed,
+/// or the sets in a truncate's barrier.
+fn named_sets(op: &PeerMsg) -> Vec<Timestamp> {
+    match op {
+        PeerMsg::PutSync {
+            timestamp, context, ..
+        } => context.iter().chain(Some(*timestamp)).collect(),
+        PeerMsg::DeleteSync { context } => context.iter().collect(),
+        PeerMsg::Truncate { barrier } => barrier.iter().collect(),
+        PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => named_sets(op),
+        _ => Vec::new(),
+    }
+}
+
+/// What a server would hold without garbage collection: its map with the ops applied against
+/// truncation metadata that is never collected. Kept only so that a property can check that
+/// collecting the metadata doesn't change the server's values.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct Uncollected<M> {
+    pub(crate) map: M,
+    pub(crate) truncation: Truncation,
+}
+
+impl<M: Map> Uncollected<M> {
+    /// Apply an op as the server applies it to its own map, dropping sets that a truncate
+    /// already removed.
+    pub(crate) fn apply(&mut self, op: PeerMsg) {
+        self.truncation.record(&op);
+        receive_sync(&mut self.map, op);
+        let values = self.map.values();
+        let late = self
+            .truncation
+            .late(values.iter().map(|(timestamp, _, _)| timestamp));
+        if !late.is_empty() {
+            self.map.effect(MapOp::Delete {
+                context: late.into(),
+            });
+        }
+    }
+}
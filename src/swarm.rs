@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Deserialize;
+use serde::Serialize;
+use stateright::Expectation;
+use stateright::Model;
+
+use crate::check::print_stats;
+use crate::check::report_properties;
+use crate::check::Steps;
+use crate::explore::evaluate;
+use crate::fingerprint::fingerprint;
+use crate::fingerprint::Fingerprint;
+use crate::map::Map;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+
+/// A state to explore, named by the path to it so that it can be sent between processes: the
+/// index of the initial state followed by the index of each action taken.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Entry {
+    path: Vec<usize>,
+    fingerprint: Fingerprint,
+    /// The eventually properties not yet seen to hold on the path, by index.
+    pending: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum ToWorker {
+    /// Explore the states owned by the worker that it hasn't visited yet, one step deep.
+    Explore {
+        entries: Vec<Entry>,
+    },
+    Done,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum ToCoordinator {
+    /// Sent on connecting, so the coordinator can check that the worker runs the same model.
+    Hello { init: Vec<Fingerprint> },
+    Explored {
+        /// How many of the entries were new to the worker.
+        unique: usize,
+        successors: Vec<Entry>,
+        /// Discoveries by property name, as paths.
+        discoveries: Vec<(String, Vec<usize>)>,
+    },
+}
+
+/// The outcome of a swarm check.
+pub(crate) struct SwarmResult {
+    states: usize,
+    unique: usize,
+    depth: usize,
+    complete: bool,
+    discoveries: BTreeMap<&'static str, Vec<usize>>,
+}
+
+/// Check the model breadth first across worker processes, level by level.
+///
+/// Each worker owns the states whose fingerprints fall in its partition and keeps only those in
+/// its visited set. After each level the workers send the successors they generated back here,
+/// which routes them to their owners for the next level. States are passed as paths and replayed
+/// by the workers, as actor states can't be serialized, so workers need the same options as the
+/// coordinator.
+pub(crate) fn coordinate<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    listen: &str,
+    workers: usize,
+    time_limit: Option<Duration>,
+) -> io::Result<SwarmResult> {
+    let properties = model.properties();
+    let init = model.init_states();
+    let init_fingerprints = init.iter().map(fingerprint).collect::<Vec<_>>();
+
+    let listener = TcpListener::bind(listen)?;
+    println!("Waiting for {} workers on {}", workers, listen);
+    let mut connections = Vec::new();
+    while connections.len() < workers {
+        let (stream, addr) = listener.accept()?;
+        let mut connection = Connection::new(stream)?;
+        match connection.receive()? {
+            ToCoordinator::Hello { init } if init == init_fingerprints => {
+                println!("Worker {} connected from {}", connections.len(), addr);
+                connections.push(connection);
+            }
+            _ => eprintln!(
+                "Ignoring worker from {} with a different model, check it has the same options",
+                addr
+            ),
+        }
+    }
+
+    let eventually = properties
+        .iter()
+        .enumerate()
+        .filter(|(_, property)| matches!(property.expectation, Expectation::Eventually))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let mut frontier = init_fingerprints
+        .iter()
+        .enumerate()
+        .map(|(i, fingerprint)| Entry {
+            path: vec![i],
+            fingerprint: *fingerprint,
+            pending: eventually.clone(),
+        })
+        .collect::<Vec<_>>();
+    let mut result = SwarmResult {
+        states: frontier.len(),
+        unique: 0,
+        depth: 0,
+        complete: true,
+        discoveries: BTreeMap::new(),
+    };
+
+    let start = Instant::now();
+    while !frontier.is_empty() && result.discoveries.len() < properties.len() {
+        if time_limit.map_or(false, |time_limit| start.elapsed() >= time_limit) {
+            result.complete = false;
+            break;
+        }
+        let mut partitions = vec![Vec::new(); workers];
+        for entry in frontier.drain(..) {
+            partitions[(entry.fingerprint % workers as u64) as usize].push(entry);
+        }
+        for (connection, entries) in connections.iter_mut().zip(partitions) {
+            connection.send(&ToWorker::Explore { entries })?;
+        }
+        for connection in &mut connections {
+            match connection.receive()? {
+                ToCoordinator::Explored {
+                    unique,
+                    successors,
+                    discoveries,
+                } => {
+                    result.unique += unique;
+                    result.states += successors.len();
+                    frontier.extend(successors);
+                    for (name, path) in discoveries {
+                        // names come from the same properties, so can be mapped back to them
+                        if let Some(property) = properties.iter().find(|p| p.name == name) {
+                            result.discoveries.entry(property.name).or_insert(path);
+                        }
+                    }
+                }
+                ToCoordinator::Hello { .. } => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "worker said hello twice",
+                    ))
+                }
+            }
+        }
+        println!(
+            "Checking. states={}, unique={}, depth={}",
+            result.states, result.unique, result.depth
+        );
+        result.depth += 1;
+    }
+
+    for connection in &mut connections {
+        connection.send(&ToWorker::Done)?;
+    }
+    Ok(result)
+}
+
+/// Explore the states the coordinator sends until it says it is done.
+pub(crate) fn work<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    coordinator: &str,
+) -> io::Result<()> {
+    let properties = model.properties();
+    let init = model.init_states();
+    let mut connection = Connection::new(TcpStream::connect(coordinator)?)?;
+    connection.send(&ToCoordinator::Hello {
+        init: init.iter().map(fingerprint).collect(),
+    })?;
+
+    let mut visited = HashSet::new();
+    // discoveries this worker has made, so it doesn't report them again
+    let mut discoveries = BTreeMap::new();
+    loop {
+        let entries = match connection.receive()? {
+            ToWorker::Explore { entries } => entries,
+            ToWorker::Done => return Ok(()),
+        };
+        let mut unique = 0;
+        let mut successors = Vec::new();
+        let mut discovered = Vec::new();
+        for mut entry in entries {
+            if !visited.insert(entry.fingerprint) {
+                continue;
+            }
+            unique += 1;
+            let state = replay(model, &init, &entry.path);
+            for name in evaluate(model, &properties, &state, &mut entry.pending, &discoveries) {
+                discoveries.insert(name, ());
+                discovered.push((name.to_owned(), entry.path.clone()));
+            }
+
+            let mut actions = Vec::new();
+            model.actions(&state, &mut actions);
+            let mut terminal = true;
+            for (i, action) in actions.into_iter().enumerate() {
+                let next = match model.next_state(&state, action) {
+                    Some(next) if model.within_boundary(&next) => next,
+                    _ => continue,
+                };
+                terminal = false;
+                let mut path = entry.path.clone();
+                path.push(i);
+                successors.push(Entry {
+                    path,
+                    fingerprint: fingerprint(&next),
+                    pending: entry.pending.clone(),
+                });
+            }
+            if terminal {
+                for i in &entry.pending {
+                    let name = properties[*i].name;
+                    if discoveries.insert(name, ()).is_none() {
+                        discovered.push((name.to_owned(), entry.path.clone()));
+                    }
+                }
+            }
+        }
+        connection.send(&ToCoordinator::Explored {
+            unique,
+            successors,
+            discoveries: discovered,
+        })?;
+    }
+}
+
+/// Report the outcome of each property, returning how many were violated.
+pub(crate) fn check_properties<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    result: SwarmResult,
+    keep_going: bool,
+) -> usize {
+    println!(
+        "Done. states={}, unique={}, max_depth={}",
+        result.states, result.unique, result.depth
+    );
+    if !result.complete {
+        println!("Bounded result: time limit reached, the state space was not exhausted");
+    }
+    let init = model.init_states();
+    let discoveries = result
+        .discoveries
+        .into_iter()
+        .map(|(name, path)| (name, steps(model, &init, &path)))
+        .collect();
+    print_stats(model, &discoveries);
+    report_properties(model, discoveries, keep_going, result.complete)
+}
+
+fn replay<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    init: &[RegisterState<M>],
+    path: &[usize],
+) -> RegisterState<M> {
+    let mut state = init[path[0]].clone();
+    for i in &path[1..] {
+        let mut actions = Vec::new();
+        model.actions(&state, &mut actions);
+        state = model
+            .next_state(&state, actions.swap_remove(*i))
+            .expect("paths only take actions with a next state");
+    }
+    state
+}
+
+fn steps<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    init: &[RegisterState<M>],
+    path: &[usize],
+) -> Steps<M> {
+    let mut state = init[path[0]].clone();
+    let mut steps = Vec::new();
+    for i in &path[1..] {
+        let mut actions = Vec::new();
+        model.actions(&state, &mut actions);
+        let action = actions.swap_remove(*i);
+        let next = model
+            .next_state(&state, action.clone())
+            .expect("paths only take actions with a next state");
+        steps.push((state, Some(action)));
+        state = next;
+    }
+    steps.push((state, None));
+    steps
+}
+
+/// Messages are sent as JSON, one per line.
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    fn send(&mut self, msg: &impl Serialize) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, msg)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    fn receive<T: for<'de> Deserialize<'de>>(&mut self) -> io::Result<T> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
@@ -0,0 +1,1430 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+use stateright::actor::Id;
+
+use crate::causal::Causal;
+use crate::context::CausalContext;
+use crate::counter::Counter;
+use crate::counter_pn::PNCounter;
+use crate::dedup::Requests;
+use crate::delivery::CausalDelivery;
+use crate::durability;
+use crate::durability::Durability;
+use crate::durability::Recovery;
+use crate::epoch::Epochs;
+use crate::fingerprint::fingerprint;
+use crate::fingerprint::Fingerprint;
+use crate::flag::Bias;
+use crate::flag::Flag;
+use crate::flag::FlagWrite;
+use crate::gossip;
+use crate::gossip::Gossip;
+use crate::list::List;
+use crate::list::ListOp;
+use crate::list_rga::Rga;
+use crate::map::Map;
+use crate::map::MapDelta;
+use crate::map::MapOp;
+use crate::map::Timestamp;
+use crate::map_nested::NestedMap;
+use crate::map_nested::NestedOp;
+use crate::membership::Membership;
+use crate::membership::Status;
+use crate::partition::Isolation;
+use crate::peer::Bloom;
+use crate::peer::Change;
+use crate::peer::ChangeHash;
+use crate::peer::PulledOp;
+use crate::peer::VectorClock;
+use crate::receive_sync;
+use crate::register_lww::LwwRegister;
+use crate::register_lww::LwwWrite;
+use crate::register_mv::MVRegister;
+use crate::register_mv::Write;
+use crate::retransmit::Outbound;
+use crate::set::Set;
+use crate::set::SetOp;
+use crate::set_gset::GSet;
+use crate::set_orset::ORSet;
+use crate::set_twophase::TwoPhaseSet;
+use crate::stability::Stability;
+use crate::stability::Uncollected;
+use crate::timer::Timers;
+use crate::truncate::Truncation;
+use crate::validate::Rejection;
+use crate::validate::Validation;
+use crate::FlagBias;
+use crate::Key;
+use crate::PeerMsg;
+use crate::SetImpl;
+use crate::Value;
+
+/// How servers reconcile their maps.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum SyncMode {
+    /// Broadcast each op to every peer as it is applied.
+    Op,
+    /// Automerge-style exchange: after each op a server announces the changes it has, and peers
+    /// reply with the changes it is missing and request the ones they are missing.
+    HaveNeed,
+    /// Pull-based anti-entropy: nothing is broadcast, instead after each op a server sends its
+    /// peers its version vector and they reply with exactly the ops it is missing, pulling back
+    /// any ops the vector shows they are missing themselves.
+    Pull,
+    /// State-based sync: after each op on the map a server sends its peers its whole map, along
+    /// with the values it has removed, and they merge it into theirs.
+    State,
+    /// Delta-state sync: a server joins the deltas of its ops on the map into a buffer for each
+    /// peer, and a while later sends each peer its buffer to merge in.
+    Delta,
+}
+
+/// The ops a server has for the pull sync mode, by the server they originated at and in the
+/// order that server applied them, so that a version vector of how many ops from each origin a
+/// server has says exactly which ops it is missing.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct PullLog {
+    id: usize,
+    ops: BTreeMap<usize, Vec<PeerMsg>>,
+    /// The peers this server is waiting on a reply to a pull from, and whether to pull from them
+    /// again once it arrives. Holding back pulls until then keeps at most one in flight to each
+    /// peer.
+    pulling: BTreeMap<usize, bool>,
+}
+
+impl PullLog {
+    fn new(id: usize) -> Self {
+        Self {
+            id,
+            ops: BTreeMap::new(),
+            pulling: BTreeMap::new(),
+        }
+    }
+
+    /// How many ops from each origin this server has.
+    pub(crate) fn vector(&self) -> BTreeMap<usize, usize> {
+        self.ops
+            .iter()
+            .map(|(origin, ops)| (*origin, ops.len()))
+            .collect()
+    }
+
+    fn len(&self, origin: usize) -> usize {
+        self.ops.get(&origin).map_or(0, Vec::len)
+    }
+
+    /// The ops that a peer with the given vector is missing.
+    pub(crate) fn missing(&self, vector: &BTreeMap<usize, usize>) -> Vec<PulledOp> {
+        self.ops
+            .iter()
+            .flat_map(|(origin, ops)| {
+                let known = vector.get(origin).copied().unwrap_or_default();
+                ops.iter()
+                    .enumerate()
+                    .skip(known)
+                    .map(|(seq, op)| PulledOp {
+                        origin: *origin,
+                        seq,
+                        op: op.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Whether a peer with the given vector has ops that this server is missing.
+    pub(crate) fn is_behind(&self, vector: &BTreeMap<usize, usize>) -> bool {
+        vector
+            .iter()
+            .any(|(origin, count)| *count > self.len(*origin))
+    }
+
+    /// Whether a pulled op is the next one from its origin, rather than one this server already
+    /// has.
+    pub(crate) fn is_next(&self, pulled: &PulledOp) -> bool {
+        pulled.seq == self.len(pulled.origin)
+    }
+
+    /// Pull from a peer, unless a pull from it is still waiting on its reply, in which case pull
+    /// again once the reply arrives.
+    pub(crate) fn pull(&mut self, peer: usize) -> Option<PeerMsg> {
+        if let Some(again) = self.pulling.get_mut(&peer) {
+            *again = true;
+            return None;
+        }
+        self.pulling.insert(peer, false);
+        Some(PeerMsg::Pull {
+            vector: self.vector(),
+        })
+    }
+
+    /// Note the reply to a pull from a peer, returning the pull held back while waiting on it.
+    pub(crate) fn pulled(&mut self, peer: usize) -> Option<PeerMsg> {
+        match self.pulling.remove(&peer) {
+            Some(true) => self.pull(peer),
+            _ => None,
+        }
+    }
+
+    /// Whether the server is still waiting on replies to its pulls.
+    pub(crate) fn is_pulling(&self) -> bool {
+        !self.pulling.is_empty()
+    }
+}
+
+/// The set a server holds for set clients, of whichever implementation was picked.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) enum AnySet {
+    Or(ORSet),
+    G(GSet),
+    TwoPhase(TwoPhaseSet),
+}
+
+impl Set for AnySet {
+    fn new(actor_id: Id) -> Self {
+        AnySet::Or(ORSet::new(actor_id.into()))
+    }
+
+    fn contains(&self, element: &char) -> bool {
+        match self {
+            AnySet::Or(set) => Set::contains(set, element),
+            AnySet::G(set) => Set::contains(set, element),
+            AnySet::TwoPhase(set) => Set::contains(set, element),
+        }
+    }
+
+    fn elements(&self) -> Vec<char> {
+        match self {
+            AnySet::Or(set) => Set::elements(set),
+            AnySet::G(set) => Set::elements(set),
+            AnySet::TwoPhase(set) => Set::elements(set),
+        }
+    }
+
+    fn prepare_add(&self, element: char) -> SetOp {
+        // the sets' inherent methods only prepare the tag
+        match self {
+            AnySet::Or(set) => Set::prepare_add(set, element),
+            AnySet::G(set) => Set::prepare_add(set, element),
+            AnySet::TwoPhase(set) => Set::prepare_add(set, element),
+        }
+    }
+
+    fn prepare_remove(&self, element: &char) -> Option<SetOp> {
+        // the sets' inherent methods only prepare the context
+        match self {
+            AnySet::Or(set) => Set::prepare_remove(set, element),
+            AnySet::G(set) => Set::prepare_remove(set, element),
+            AnySet::TwoPhase(set) => Set::prepare_remove(set, element),
+        }
+    }
+
+    fn effect(&mut self, op: SetOp) {
+        match self {
+            AnySet::Or(set) => Set::effect(set, op),
+            AnySet::G(set) => Set::effect(set, op),
+            AnySet::TwoPhase(set) => Set::effect(set, op),
+        }
+    }
+
+    fn max_counter(&self) -> u32 {
+        match self {
+            AnySet::Or(set) => Set::max_counter(set),
+            AnySet::G(set) => Set::max_counter(set),
+            AnySet::TwoPhase(set) => Set::max_counter(set),
+        }
+    }
+}
+
+/// A server's map along with the changes it knows of, which are only kept for the have/need
+/// sync mode.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct PeerState<M> {
+    map: M,
+    changes: BTreeMap<ChangeHash, Change>,
+    /// Ops received from peers that failed validation and so weren't effected.
+    pub(crate) rejected: Vec<Rejection>,
+    /// Only tracked when clients pass version tokens.
+    pub(crate) causal: Option<Causal>,
+    /// Only tracked with causal delivery.
+    pub(crate) delivery: Option<CausalDelivery>,
+    /// Only tracked when clients retry requests.
+    pub(crate) requests: Option<Requests>,
+    /// The clients subscribed to each key's conflicts, only tracked when servers notify clients
+    /// of them.
+    pub(crate) subscribers: Option<BTreeMap<Key, BTreeSet<usize>>>,
+    /// Only tracked for the pull sync mode.
+    pub(crate) pulls: Option<PullLog>,
+    /// Timestamps of the values the map has dropped, so that merging a peer's map that still
+    /// holds them doesn't bring them back. Only tracked for the state and delta sync modes, with
+    /// anti-entropy, with retransmission and when servers join and leave.
+    pub(crate) removed: Option<BTreeSet<Timestamp>>,
+    /// Only tracked with anti-entropy.
+    pub(crate) gossip: Option<Gossip>,
+    /// Only tracked with retransmission.
+    pub(crate) outbound: Option<Outbound>,
+    /// Only tracked when servers join and leave.
+    pub(crate) membership: Option<Membership>,
+    /// The deltas of the ops on the map not yet sent to each peer. Only tracked for the delta
+    /// sync mode.
+    pub(crate) deltas: Option<BTreeMap<usize, MapDelta>>,
+    /// Only tracked when clients truncate.
+    pub(crate) truncation: Option<Truncation>,
+    /// Only tracked when servers roll into new epochs.
+    pub(crate) epochs: Option<Epochs>,
+    /// Only tracked when servers crash, until they do.
+    pub(crate) durability: Option<Durability<M>>,
+    /// How the server recovered, once it has crashed.
+    pub(crate) recovery: Option<Recovery<M>>,
+    /// Only tracked with partitions.
+    pub(crate) isolation: Option<Isolation>,
+    /// The timers the server has set, for the features that need one.
+    pub(crate) timers: Timers,
+    /// Only tracked with garbage collection.
+    pub(crate) stability: Option<Stability>,
+    /// What the server would hold without garbage collection, only tracked with it.
+    pub(crate) uncollected: Option<Uncollected<M>>,
+    /// Only held when there are set clients.
+    pub(crate) set: Option<AnySet>,
+    /// Only held when there are counter clients.
+    pub(crate) counter: Option<PNCounter>,
+    /// Only held when there are register clients, unless the last-writer-wins register is held
+    /// instead.
+    pub(crate) mv_register: Option<MVRegister>,
+    /// Only held when there are register clients and they use the last-writer-wins register.
+    pub(crate) lww_register: Option<LwwRegister>,
+    /// Only held when there are flag clients.
+    pub(crate) flag: Option<Flag>,
+    /// Only held when there are list clients.
+    pub(crate) list: Option<Rga>,
+    /// Only held when there are nested map clients.
+    pub(crate) nested: Option<NestedMap>,
+    /// Fingerprint of the map's visible values and the set's elements, kept up to date as ops are applied so that
+    /// properties can compare servers by hash. Only servers changed in a step get recomputed, as
+    /// the others keep their state from the previous step.
+    observable: Fingerprint,
+}
+
+impl<M: Map> PeerState<M> {
+    pub(crate) fn new(id: Id) -> Self {
+        let map = M::new(id);
+        let observable = fingerprint(&map.visible_values());
+        Self {
+            map,
+            changes: BTreeMap::new(),
+            rejected: Vec::new(),
+            causal: None,
+            delivery: None,
+            requests: None,
+            subscribers: None,
+            pulls: None,
+            removed: None,
+            deltas: None,
+            gossip: None,
+            outbound: None,
+            membership: None,
+            truncation: None,
+            epochs: None,
+            durability: None,
+            recovery: None,
+            isolation: None,
+            timers: Timers::default(),
+            stability: None,
+            uncollected: None,
+            set: None,
+            counter: None,
+            mv_register: None,
+            lww_register: None,
+            flag: None,
+            list: None,
+            nested: None,
+            observable,
+        }
+    }
+
+    /// Fingerprint of the map's visible values, the set's elements, the counter's value, the
+    /// register's siblings, the flag's value, the list's elements and the nested map's values;
+    /// equal for servers where they are all the same.
+    pub(crate) fn observable(&self) -> Fingerprint {
+        self.observable
+    }
+
+    fn observe(&self) -> Fingerprint {
+        let values = self.map.visible_values();
+        let register = (self.mv_register.is_some() || self.lww_register.is_some())
+            .then(|| self.register_values());
+        match (
+            &self.set,
+            &self.counter,
+            register,
+            &self.flag,
+            &self.list,
+            &self.nested,
+        ) {
+            (None, None, None, None, None, None) => fingerprint(&values),
+            (set, counter, register, flag, list, nested) => fingerprint(&(
+                values,
+                set.as_ref().map(|set| set.elements()),
+                counter.as_ref().map(|counter| counter.value()),
+                register,
+                flag.as_ref().map(|flag| flag.value()),
+                list.as_ref().map(|list| list.values()),
+                nested.as_ref().map(|nested| &nested.values),
+            )),
+        }
+    }
+
+    /// The highest op counter this server has seen, in its map or any of the other CRDTs it
+    /// holds.
+    fn known_counter(&self) -> u32 {
+        let set = self.set.as_ref().map_or(0, |set| set.max_counter());
+        let register = match (&self.mv_register, &self.lww_register) {
+            (Some(register), _) => register.max_counter(),
+            (None, Some(register)) => register.max_counter(),
+            (None, None) => 0,
+        };
+        let flag = self.flag.as_ref().map_or(0, |flag| flag.max_counter());
+        let list = self.list.as_ref().map_or(0, |list| list.max_counter());
+        let nested = self
+            .nested
+            .as_ref()
+            .map_or(0, |nested| nested.max_counter());
+        self.map
+            .max_counter()
+            .max(set)
+            .max(register)
+            .max(flag)
+            .max(list)
+            .max(nested)
+    }
+
+    /// Start tracking the requests handled, so that retried requests can be recognised.
+    pub(crate) fn track_requests(&mut self) {
+        self.requests = Some(Requests::default())
+    }
+
+    /// Start tracking the writes applied, so that dependent writes wait for their dependency.
+    pub(crate) fn track_causality(&mut self) {
+        self.causal = Some(Causal::default())
+    }
+
+    /// Start tracking subscriptions, so that clients can be notified of new siblings.
+    pub(crate) fn track_subscriptions(&mut self) {
+        self.subscribers = Some(BTreeMap::new())
+    }
+
+    /// Start logging ops by origin, so that peers can pull the ones they are missing.
+    pub(crate) fn track_pulls(&mut self, id: Id) {
+        self.pulls = Some(PullLog::new(id.into()))
+    }
+
+    /// Start stamping ops with a vector clock and delivering ops from peers in causal order.
+    pub(crate) fn track_delivery(&mut self, id: Id) {
+        self.delivery = Some(CausalDelivery::new(id.into()))
+    }
+
+    /// Stamp an op applied locally with this server's vector clock, if it delivers ops in causal
+    /// order.
+    pub(crate) fn stamp(&mut self, op: PeerMsg) -> PeerMsg {
+        let stamped = match &mut self.delivery {
+            Some(delivery) => delivery.stamp(op),
+            None => return op,
+        };
+        if let (Some(stability), PeerMsg::Stamped { clock, .. }) = (&mut self.stability, &stamped) {
+            stability.record(clock, &stamped);
+        }
+        stamped
+    }
+
+    /// This server's clock to send its peers, if it collects garbage.
+    pub(crate) fn watermark(&self) -> Option<PeerMsg> {
+        self.stability.as_ref()?;
+        Some(PeerMsg::Watermark {
+            clock: self.delivery.as_ref()?.clock.clone(),
+        })
+    }
+
+    /// Note a peer's clock, collecting what has become stable.
+    pub(crate) fn on_watermark(&mut self, peer: usize, clock: &VectorClock) {
+        if let Some(stability) = &mut self.stability {
+            stability.merge(peer, clock);
+        }
+        self.collect_stable()
+    }
+
+    /// Forget the truncation metadata about the sets that have become stable, and what the map
+    /// remembers about them.
+    fn collect_stable(&mut self) {
+        let stable = match (&mut self.stability, &self.delivery) {
+            (Some(stability), Some(delivery)) => stability.collect(&delivery.clock),
+            _ => return,
+        };
+        if !stable.is_empty() {
+            self.map.forget_stable(&stable.iter().copied().collect());
+        }
+        self.compact(stable)
+    }
+
+    /// Start remembering the values the map drops, so that peers' maps can be merged in.
+    pub(crate) fn track_removed(&mut self) {
+        self.removed = Some(BTreeSet::new())
+    }
+
+    /// Start tracking which servers are members, starting from the given membership.
+    pub(crate) fn track_membership(&mut self, membership: Membership) {
+        self.membership = Some(membership)
+    }
+
+    /// Become a member, having asked every server to join.
+    pub(crate) fn join(&mut self) {
+        if let Some(membership) = &mut self.membership {
+            membership.status = Status::Member;
+        }
+    }
+
+    /// Add a server that asked to join to the members, returning the whole map for it to catch
+    /// up from, unless this server has left.
+    pub(crate) fn on_join(&mut self, peer: usize) -> Option<PeerMsg> {
+        match &mut self.membership {
+            Some(membership) if membership.status != Status::Left => {
+                membership.members.insert(peer);
+            }
+            _ => return None,
+        }
+        Some(self.state_sync())
+    }
+
+    /// Leave, returning the members to tell.
+    pub(crate) fn leave(&mut self) -> Vec<Id> {
+        match &mut self.membership {
+            Some(membership) => {
+                membership.status = Status::Left;
+                std::mem::take(&mut membership.members)
+                    .into_iter()
+                    .map(Id::from)
+                    .collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Stop sending ops to a server that left, or resending it those it is yet to acknowledge.
+    pub(crate) fn on_leave(&mut self, peer: usize) {
+        if let Some(membership) = &mut self.membership {
+            membership.members.remove(&peer);
+        }
+        if let Some(outbound) = &mut self.outbound {
+            outbound.forget(peer)
+        }
+    }
+
+    /// Whether this server is a member, having joined if it joins late and not having left.
+    pub(crate) fn is_member(&self) -> bool {
+        self.membership
+            .as_ref()
+            .is_none_or(|membership| membership.status == Status::Member)
+    }
+
+    /// Whether this server has left, so that it no longer has to agree with the members.
+    pub(crate) fn has_left(&self) -> bool {
+        self.membership
+            .as_ref()
+            .map_or(false, |membership| membership.status == Status::Left)
+    }
+
+    /// Start keeping sets and deletes until peers acknowledge them.
+    pub(crate) fn track_outbound(&mut self) {
+        self.outbound = Some(Outbound::default())
+    }
+
+    /// Keep an op applied locally until the given peers acknowledge it, if ops are
+    /// retransmitted.
+    pub(crate) fn buffer_outbound(&mut self, peers: impl Iterator<Item = usize>, op: &PeerMsg) {
+        if let Some(outbound) = &mut self.outbound {
+            outbound.buffer(peers, op)
+        }
+    }
+
+    /// Note a peer's acknowledgement of a set or delete, if ops are retransmitted.
+    pub(crate) fn on_ack(&mut self, peer: usize, ack: &PeerMsg) {
+        if let Some(outbound) = &mut self.outbound {
+            outbound.acked(peer, ack)
+        }
+    }
+
+    /// Start gossiping digests of the map with peers to repair it.
+    pub(crate) fn track_gossip(&mut self) {
+        self.gossip = Some(Gossip::default())
+    }
+
+    /// A digest of the values the map holds and has dropped, for a peer to reply with the ops
+    /// this server is missing.
+    pub(crate) fn digest(&self) -> PeerMsg {
+        PeerMsg::Digest {
+            held: self
+                .map
+                .values()
+                .into_iter()
+                .map(|(timestamp, _, _)| timestamp)
+                .collect(),
+            removed: self.removed.iter().flatten().copied().collect(),
+        }
+    }
+
+    /// Reply to a peer's digest with the ops it is missing, possibly none, along with whether to
+    /// gossip with it again as it has seen values this server hasn't.
+    pub(crate) fn on_digest(
+        &mut self,
+        peer: usize,
+        held: &CausalContext,
+        their_removed: &CausalContext,
+    ) -> (PeerMsg, bool) {
+        let values = self.map.values();
+        let removed = self
+            .removed
+            .as_ref()
+            .expect("servers remember removed values with anti-entropy");
+        let ops = gossip::repair(&values, removed, held, their_removed);
+        let behind = gossip::is_behind(&values, removed, held, their_removed);
+        if behind {
+            if let Some(gossip) = &mut self.gossip {
+                gossip.wake(peer);
+            }
+        }
+        (PeerMsg::Repair { ops }, behind)
+    }
+
+    /// Apply the ops a peer replied to a digest with, returning whether they changed the map.
+    /// The ops are relayed rather than sent by their origin, and those that fail validation are
+    /// left missing.
+    pub(crate) fn on_repair(
+        &mut self,
+        peer: usize,
+        ops: Vec<PeerMsg>,
+        validation: &Validation,
+    ) -> bool {
+        let before = (self.map.values(), self.removed.clone());
+        for op in ops {
+            self.receive(op, None, validation);
+        }
+        let changed = (self.map.values(), self.removed.clone()) != before;
+        if let Some(gossip) = &mut self.gossip {
+            gossip.replied(peer, changed);
+        }
+        changed
+    }
+
+    /// The whole map along with the values it has dropped, for peers to merge into theirs.
+    pub(crate) fn state_sync(&self) -> PeerMsg {
+        PeerMsg::StateSync {
+            values: self.map.values(),
+            removed: self.removed.iter().flatten().copied().collect(),
+        }
+    }
+
+    /// Start buffering the deltas of ops on the map for each peer.
+    pub(crate) fn track_deltas(&mut self) {
+        self.deltas = Some(BTreeMap::new())
+    }
+
+    /// Join the delta of an op applied locally into the buffer of each of the peers.
+    pub(crate) fn buffer_delta(&mut self, peers: &[Id], op: &PeerMsg) {
+        let delta = match op {
+            PeerMsg::PutSync {
+                context,
+                timestamp,
+                key,
+                value,
+            } => MapDelta::from(MapOp::Set {
+                context: context.clone(),
+                timestamp: *timestamp,
+                key: *key,
+                value: *value,
+            }),
+            PeerMsg::DeleteSync { context } => MapDelta::from(MapOp::Delete {
+                context: context.clone(),
+            }),
+            _ => return,
+        };
+        if let Some(deltas) = &mut self.deltas {
+            for peer in peers {
+                deltas.entry((*peer).into()).or_default().join(&delta)
+            }
+        }
+    }
+
+    /// Empty the buffers, giving the delta to send to each peer.
+    pub(crate) fn flush_deltas(&mut self) -> Vec<(Id, PeerMsg)> {
+        let deltas = match &mut self.deltas {
+            Some(deltas) => std::mem::take(deltas),
+            None => return Vec::new(),
+        };
+        deltas
+            .into_iter()
+            .filter(|(_, delta)| !delta.is_empty())
+            .map(|(peer, delta)| {
+                let msg = PeerMsg::DeltaSync {
+                    values: delta.values.into_iter().collect(),
+                    removed: delta.removed.into_iter().collect(),
+                };
+                (Id::from(peer), msg)
+            })
+            .collect()
+    }
+
+    /// Start holding a set of the given implementation alongside the map, for set clients.
+    pub(crate) fn track_set(&mut self, id: Id, set_impl: SetImpl) {
+        self.set = Some(match set_impl {
+            SetImpl::Or => AnySet::Or(ORSet::new(id.into())),
+            SetImpl::G => AnySet::G(GSet::new(id.into())),
+            SetImpl::TwoPhase => AnySet::TwoPhase(TwoPhaseSet::new(id.into())),
+        });
+        self.observable = self.observe();
+    }
+
+    /// Prepare adding an element to the set, without changing it.
+    pub(crate) fn prepare_add(&self, element: Value) -> SetOp {
+        self.set
+            .as_ref()
+            .expect("servers hold a set when there are set clients")
+            .prepare_add(element)
+    }
+
+    /// Prepare removing the adds of an element seen so far, if the set holds it.
+    pub(crate) fn prepare_remove(&self, element: &Value) -> Option<SetOp> {
+        self.set
+            .as_ref()
+            .expect("servers hold a set when there are set clients")
+            .prepare_remove(element)
+    }
+
+    pub(crate) fn set_contains(&self, element: Value) -> bool {
+        self.set
+            .as_ref()
+            .map_or(false, |set| set.contains(&element))
+    }
+
+    /// Start holding a counter alongside the map, for counter clients.
+    pub(crate) fn track_counter(&mut self, id: Id) {
+        self.counter = Some(Counter::new(id));
+        self.observable = self.observe();
+    }
+
+    /// The counter's state after an increment here, without changing it.
+    pub(crate) fn prepare_increment(&self) -> PeerMsg {
+        self.prepare_count(PNCounter::increment)
+    }
+
+    /// The counter's state after a decrement here, without changing it.
+    pub(crate) fn prepare_decrement(&self) -> PeerMsg {
+        self.prepare_count(PNCounter::decrement)
+    }
+
+    fn prepare_count(&self, update: fn(&mut PNCounter)) -> PeerMsg {
+        let mut counter = self
+            .counter
+            .clone()
+            .expect("servers hold a counter when there are counter clients");
+        update(&mut counter);
+        PeerMsg::CounterState {
+            increments: counter.increments,
+            decrements: counter.decrements,
+        }
+    }
+
+    pub(crate) fn counter_value(&self) -> i64 {
+        self.counter.as_ref().map_or(0, |counter| counter.value())
+    }
+
+    /// Start holding a multi-value register alongside the map, for its clients.
+    pub(crate) fn track_mv_register(&mut self, id: Id) {
+        self.mv_register = Some(MVRegister::new(id.into()));
+        self.observable = self.observe();
+    }
+
+    /// Start holding a last-writer-wins register alongside the map instead, for its clients.
+    pub(crate) fn track_lww_register(&mut self, id: Id) {
+        self.lww_register = Some(LwwRegister::new(id.into()));
+        self.observable = self.observe();
+    }
+
+    /// Prepare writing a value to the register, without changing it, returning the write's
+    /// timestamp and op.
+    pub(crate) fn prepare_write(&self, value: Value) -> (Timestamp, PeerMsg) {
+        match (&self.mv_register, &self.lww_register) {
+            (Some(register), _) => {
+                let write = register.prepare_write(value);
+                (write.timestamp, write.into())
+            }
+            (None, Some(register)) => {
+                let write = register.prepare_write(value);
+                (write.timestamp, write.into())
+            }
+            (None, None) => panic!("servers hold a register when there are register clients"),
+        }
+    }
+
+    /// The register's siblings, with the timestamps of their writes.
+    pub(crate) fn register_values(&self) -> Vec<(Timestamp, Value)> {
+        match (&self.mv_register, &self.lww_register) {
+            (Some(register), _) => register.values(),
+            (None, Some(register)) => register.values(),
+            (None, None) => Vec::new(),
+        }
+    }
+
+    /// Start holding a flag alongside the map, for flag clients.
+    pub(crate) fn track_flag(&mut self, id: Id, bias: FlagBias) {
+        let bias = match bias {
+            FlagBias::EnableWins => Bias::EnableWins,
+            FlagBias::DisableWins => Bias::DisableWins,
+        };
+        self.flag = Some(Flag::new(id.into(), bias));
+        self.observable = self.observe();
+    }
+
+    /// Prepare enabling or disabling the flag, without changing it.
+    pub(crate) fn prepare_flag(&self, enable: bool) -> FlagWrite {
+        self.flag
+            .as_ref()
+            .expect("servers hold a flag when there are flag clients")
+            .prepare(enable)
+    }
+
+    pub(crate) fn flag_value(&self) -> bool {
+        self.flag.as_ref().map_or(false, |flag| flag.value())
+    }
+
+    /// Start holding a list alongside the map, for list clients.
+    pub(crate) fn track_list(&mut self, id: Id) {
+        self.list = Some(List::new(id));
+        self.observable = self.observe();
+    }
+
+    /// Prepare inserting a value at an index in the list, without changing it.
+    pub(crate) fn prepare_insert(&self, index: usize, value: Value) -> ListOp {
+        let list = self
+            .list
+            .as_ref()
+            .expect("servers hold a list when there are list clients");
+        // the list's inherent method only prepares the ids
+        List::prepare_insert(list, index, value)
+    }
+
+    /// Prepare deleting the element at an index in the list, if there is one.
+    pub(crate) fn prepare_delete_at(&self, index: usize) -> Option<ListOp> {
+        let list = self
+            .list
+            .as_ref()
+            .expect("servers hold a list when there are list clients");
+        // the list's inherent method only prepares the id
+        List::prepare_delete(list, index)
+    }
+
+    /// The list's elements, in order.
+    pub(crate) fn list_values(&self) -> Vec<Value> {
+        self.list
+            .as_ref()
+            .map_or_else(Vec::new, |list| list.values())
+    }
+
+    /// Start holding a nested map alongside the map, for nested map clients.
+    pub(crate) fn track_nested(&mut self, id: Id) {
+        self.nested = Some(NestedMap::new(id.into()));
+        self.observable = self.observe();
+    }
+
+    /// Prepare setting the key at the end of a path in the nested map, without changing it.
+    pub(crate) fn prepare_nested_set(&self, path: Vec<Key>, value: Value) -> NestedOp {
+        let (context, timestamp) = self
+            .nested
+            .as_ref()
+            .expect("servers hold a nested map when there are nested map clients")
+            .prepare_set(&path);
+        NestedOp::Set {
+            context,
+            timestamp,
+            path,
+            value,
+        }
+    }
+
+    /// Prepare deleting the subtree at a path in the nested map, if there is anything in it.
+    pub(crate) fn prepare_nested_delete(&self, path: &[Key]) -> Option<NestedOp> {
+        self.nested
+            .as_ref()
+            .expect("servers hold a nested map when there are nested map clients")
+            .prepare_delete(path)
+            .map(|context| NestedOp::Delete { context })
+    }
+
+    /// The values at a path in the nested map or under it, with their timestamps.
+    pub(crate) fn nested_values(&self, path: &[Key]) -> Vec<(Timestamp, Value)> {
+        self.nested.as_ref().map_or_else(Vec::new, |nested| {
+            nested
+                .subtree(path)
+                .into_iter()
+                .map(|(timestamp, _, value)| (timestamp, value))
+                .collect()
+        })
+    }
+
+    /// Start tracking the sets seen and truncated, so that truncates act as causal barriers.
+    pub(crate) fn track_truncation(&mut self) {
+        self.truncation = Some(Truncation::default())
+    }
+
+    pub(crate) fn track_isolation(&mut self) {
+        self.isolation = Some(Isolation::Pending)
+    }
+
+    /// Prepare truncating the map, if truncation is tracked and there is anything to truncate.
+    pub(crate) fn prepare_truncate(&self) -> Option<PeerMsg> {
+        let held = self
+            .map
+            .values()
+            .into_iter()
+            .map(|(timestamp, _, _)| timestamp);
+        self.truncation.as_ref()?.prepare(held)
+    }
+
+    /// Start tracking epochs, so that truncation metadata can be dropped as they roll over.
+    pub(crate) fn track_epochs(&mut self) {
+        self.epochs = Some(Epochs::default())
+    }
+
+    /// Record the epoch of the sets an op brings, if epochs are tracked, given the peer it came
+    /// from or `None` for ops prepared locally.
+    pub(crate) fn record_epoch(&mut self, op: &PeerMsg, from: Option<usize>) {
+        if let Some(epochs) = &mut self.epochs {
+            epochs.record(op, from)
+        }
+    }
+
+    /// Start closing the current epoch, returning the messages for every peer.
+    pub(crate) fn close_epoch(&mut self, peers: usize) -> Vec<PeerMsg> {
+        let mut out = Vec::new();
+        if let Some(epochs) = &mut self.epochs {
+            let compacted = epochs.close(peers, &mut out);
+            self.compact(compacted);
+        }
+        out
+    }
+
+    /// Note a peer's marker closing an epoch, returning the messages for every peer.
+    pub(crate) fn on_epoch_close(&mut self, peer: usize, epoch: u32, peers: usize) -> Vec<PeerMsg> {
+        let mut out = Vec::new();
+        if let Some(epochs) = &mut self.epochs {
+            let compacted = epochs.on_close(peer, epoch, peers, &mut out);
+            self.compact(compacted);
+        }
+        out
+    }
+
+    /// Note that a peer is done with the current epoch, returning the messages for every peer.
+    pub(crate) fn on_epoch_done(&mut self, peer: usize, peers: usize) -> Vec<PeerMsg> {
+        let mut out = Vec::new();
+        if let Some(epochs) = &mut self.epochs {
+            let compacted = epochs.on_done(peer, peers, &mut out);
+            self.compact(compacted);
+        }
+        out
+    }
+
+    /// Forget the sets from epochs that every server has rolled out of: none of them can still
+    /// arrive, so they needn't be remembered as seen or removed.
+    fn compact(&mut self, sets: BTreeSet<Timestamp>) {
+        if let (false, Some(truncation)) = (sets.is_empty(), &mut self.truncation) {
+            truncation
+                .seen
+                .retain(|timestamp| !sets.contains(timestamp));
+            truncation
+                .removed
+                .retain(|timestamp| !sets.contains(timestamp));
+        }
+    }
+
+    /// Log an op this server applied locally, if ops are logged for pulls.
+    pub(crate) fn log_local(&mut self, op: PeerMsg) {
+        if let Some(pulls) = &mut self.pulls {
+            pulls.ops.entry(pulls.id).or_default().push(op);
+        }
+    }
+
+    /// Apply the pulled ops that are next from their origin, skipping those this server already
+    /// got from another peer. Ops that fail validation are left missing, and so are the ops from
+    /// their origin after them.
+    pub(crate) fn on_pulled(&mut self, ops: Vec<PulledOp>, validation: &Validation) {
+        for pulled in ops {
+            if !self
+                .pulls
+                .as_ref()
+                .map_or(false, |pulls| pulls.is_next(&pulled))
+            {
+                continue;
+            }
+            // ops are pulled from whichever peer has them, so the sender needn't be their origin
+            if let Err(rejection) = validation.check(&pulled.op, None, self.known_counter()) {
+                self.rejected.push(rejection);
+                continue;
+            }
+            self.apply(pulled.op.clone());
+            if let Some(pulls) = &mut self.pulls {
+                pulls.ops.entry(pulled.origin).or_default().push(pulled.op);
+            }
+        }
+    }
+
+    /// Subscribe a client to a key's conflicts, if subscriptions are tracked.
+    pub(crate) fn subscribe(&mut self, key: Key, client: usize) {
+        if let Some(subscribers) = &mut self.subscribers {
+            subscribers.entry(key).or_default().insert(client);
+        }
+    }
+
+    /// The concurrent values (siblings) of a key, with their timestamps.
+    pub(crate) fn siblings(&self, key: Key) -> Vec<(Timestamp, Value)> {
+        self.map
+            .values()
+            .into_iter()
+            .filter(|(_, k, _)| *k == key)
+            .map(|(timestamp, _, value)| (timestamp, value))
+            .collect()
+    }
+
+    /// The siblings of each key that clients are subscribed to.
+    pub(crate) fn subscribed_siblings(&self) -> Vec<(Key, Vec<(Timestamp, Value)>)> {
+        self.subscribers
+            .iter()
+            .flat_map(|subscribers| subscribers.keys())
+            .map(|key| (*key, self.siblings(*key)))
+            .collect()
+    }
+
+    pub(crate) fn has_change(&self, hash: ChangeHash) -> bool {
+        self.changes.contains_key(&hash)
+    }
+
+    /// Changes that no other known change depends on.
+    fn heads(&self) -> Vec<ChangeHash> {
+        let deps = self
+            .changes
+            .values()
+            .flat_map(|change| change.deps.iter().copied())
+            .collect::<BTreeSet<_>>();
+        self.changes
+            .keys()
+            .filter(|hash| !deps.contains(hash))
+            .copied()
+            .collect()
+    }
+
+    /// Record an op this server applied locally and announce the changes it now has.
+    pub(crate) fn record(&mut self, op: PeerMsg) -> PeerMsg {
+        let change = Change {
+            deps: self.heads(),
+            op,
+        };
+        self.changes.insert(change.hash(), change);
+        self.have()
+    }
+
+    /// Announce the changes this server has.
+    pub(crate) fn have(&self) -> PeerMsg {
+        let mut bloom = Bloom::default();
+        for hash in self.changes.keys() {
+            bloom.insert(*hash);
+        }
+        PeerMsg::Have {
+            heads: self.heads(),
+            bloom,
+        }
+    }
+
+    /// Reply to a peer announcing its changes: send it the changes that are missing from its
+    /// Bloom filter and ask for its heads that this server is missing.
+    pub(crate) fn on_have(&self, heads: &[ChangeHash], bloom: &Bloom) -> Vec<PeerMsg> {
+        let mut replies = Vec::new();
+        let changes = self
+            .changes
+            .iter()
+            .filter(|(hash, _)| !bloom.may_contain(**hash))
+            .map(|(_, change)| change.clone())
+            .collect::<Vec<_>>();
+        if !changes.is_empty() {
+            replies.push(PeerMsg::Changes { changes });
+        }
+        if let Some(need) = self.need(heads.iter().copied()) {
+            replies.push(need);
+        }
+        replies
+    }
+
+    /// Send the requested changes that this server has.
+    pub(crate) fn on_need(&self, hashes: &[ChangeHash]) -> Option<PeerMsg> {
+        let changes = hashes
+            .iter()
+            .filter_map(|hash| self.changes.get(hash).cloned())
+            .collect::<Vec<_>>();
+        (!changes.is_empty()).then(|| PeerMsg::Changes { changes })
+    }
+
+    /// Effect an op received from a peer if it passes validation, otherwise record why it was
+    /// rejected. `sender` is given when the peer is known to be the op's origin.
+    pub(crate) fn receive(&mut self, op: PeerMsg, sender: Option<usize>, validation: &Validation) {
+        match validation.check(&op, sender, self.known_counter()) {
+            Ok(()) => self.apply(op),
+            Err(rejection) => self.rejected.push(rejection),
+        }
+    }
+
+    /// Effect an op, whether prepared locally or received and validated, unless it depends on a
+    /// write that this server hasn't applied yet, in which case it waits until that is applied.
+    pub(crate) fn apply(&mut self, op: PeerMsg) {
+        if let Some(durability) = &mut self.durability {
+            durability.unflushed.push(op.clone());
+        }
+        match op {
+            PeerMsg::AddSync { tag, element } => self.effect_set(SetOp::Add { tag, element }),
+            PeerMsg::RemoveSync { context } => self.effect_set(SetOp::Remove { context }),
+            PeerMsg::CounterState {
+                increments,
+                decrements,
+            } => self
+                .counter
+                .as_mut()
+                .expect("servers hold a counter when there are counter clients")
+                .receive_state(&increments, &decrements),
+            PeerMsg::RegisterSync {
+                timestamp,
+                clock,
+                value,
+            } => self
+                .mv_register
+                .as_mut()
+                .expect("servers hold a multi-value register when there are register clients")
+                .receive_write(Write {
+                    timestamp,
+                    clock,
+                    value,
+                }),
+            PeerMsg::LwwRegisterSync { timestamp, value } => self
+                .lww_register
+                .as_mut()
+                .expect("servers hold a last-writer-wins register with `--register-impl lww`")
+                .receive_write(LwwWrite { timestamp, value }),
+            PeerMsg::FlagSync {
+                timestamp,
+                clock,
+                enable,
+            } => self
+                .flag
+                .as_mut()
+                .expect("servers hold a flag when there are flag clients")
+                .receive(FlagWrite {
+                    timestamp,
+                    clock,
+                    value: enable,
+                }),
+            PeerMsg::ListInsertSync { id, after, value } => {
+                self.effect_list(ListOp::Insert { id, after, value })
+            }
+            PeerMsg::ListDeleteSync { id } => self.effect_list(ListOp::Delete { id }),
+            PeerMsg::NestedSetSync {
+                context,
+                timestamp,
+                path,
+                value,
+            } => self
+                .nested
+                .as_mut()
+                .expect("servers hold a nested map when there are nested map clients")
+                .receive_set(context, timestamp, path, value),
+            PeerMsg::NestedDeleteSync { context } => self
+                .nested
+                .as_mut()
+                .expect("servers hold a nested map when there are nested map clients")
+                .receive_delete(context),
+            PeerMsg::StateSync { values, removed } | PeerMsg::DeltaSync { values, removed } => {
+                self.merge_state(values, removed)
+            }
+            PeerMsg::Stamped { origin, clock, op } => {
+                if let Some(stability) = &mut self.stability {
+                    stability.record(&clock, &op);
+                    stability.merge(origin, &clock);
+                }
+                let ready = self
+                    .delivery
+                    .as_mut()
+                    .expect("servers track delivery when ops are stamped")
+                    .receive(origin, clock, *op);
+                for op in ready {
+                    self.apply(op)
+                }
+                self.collect_stable()
+            }
+            op => self.apply_map(op),
+        }
+        self.observable = self.observe();
+    }
+
+    /// Merge a peer's map, or a delta of it, into this one, then drop the values that either has
+    /// removed.
+    fn merge_state(&mut self, values: Vec<(Timestamp, Key, Value)>, removed: CausalContext) {
+        // the peer's map is only merged from, so whose actor id it has doesn't matter
+        let mut other = M::new(Id::from(0));
+        for (timestamp, key, value) in values.iter().copied() {
+            other.effect(MapOp::Set {
+                context: CausalContext::new(),
+                timestamp,
+                key,
+                value,
+            });
+        }
+        let seen = self
+            .map
+            .values()
+            .into_iter()
+            .chain(values)
+            .map(|(timestamp, _, _)| timestamp)
+            .collect::<Vec<_>>();
+        self.map.merge(&other);
+
+        let dropped = self
+            .removed
+            .as_mut()
+            .expect("servers remember removed values when syncing state");
+        dropped.extend(removed.iter());
+        let context = self
+            .map
+            .values()
+            .into_iter()
+            .map(|(timestamp, _, _)| timestamp)
+            .filter(|timestamp| dropped.contains(timestamp))
+            .collect::<CausalContext>();
+        if !context.is_empty() {
+            self.map.effect(MapOp::Delete { context });
+        }
+        self.record_removed(seen);
+    }
+
+    /// Remember which of the values the map has seen it no longer holds.
+    fn record_removed(&mut self, seen: Vec<Timestamp>) {
+        let held = self
+            .map
+            .values()
+            .into_iter()
+            .map(|(timestamp, _, _)| timestamp)
+            .collect::<BTreeSet<_>>();
+        if let Some(removed) = &mut self.removed {
+            removed.extend(
+                seen.into_iter()
+                    .filter(|timestamp| !held.contains(timestamp)),
+            );
+        }
+    }
+
+    /// Effect an op on the set, which doesn't wait on dependencies as its ops never have any.
+    fn effect_set(&mut self, op: SetOp) {
+        self.set
+            .as_mut()
+            .expect("servers hold a set when there are set clients")
+            .effect(op)
+    }
+
+    /// Effect an op on the list, which holds back inserts and deletes of elements that haven't
+    /// arrived itself.
+    fn effect_list(&mut self, op: ListOp) {
+        self.list
+            .as_mut()
+            .expect("servers hold a list when there are list clients")
+            .effect(op)
+    }
+
+    fn apply_map(&mut self, op: PeerMsg) {
+        if let Some(uncollected) = &mut self.uncollected {
+            uncollected.apply(op.clone());
+        }
+        if let Some(truncation) = &mut self.truncation {
+            truncation.record(&op);
+        }
+        // a set can be dropped as it arrives, when the map already holds a later value
+        let seen = self.removed.is_some().then(|| {
+            let mut seen = self
+                .map
+                .values()
+                .into_iter()
+                .map(|(timestamp, _, _)| timestamp)
+                .collect::<Vec<_>>();
+            // sets and deletes can arrive before the values they remove
+            match &op {
+                PeerMsg::PutSync {
+                    context, timestamp, ..
+                } => {
+                    seen.push(*timestamp);
+                    seen.extend(context.iter());
+                }
+                PeerMsg::DeleteSync { context } => seen.extend(context.iter()),
+                _ => {}
+            }
+            seen
+        });
+        self.apply_ready(op);
+        if let Some(removed) = &self.removed {
+            // a set that the map already dropped is dropped again if it arrives late
+            let late = self
+                .map
+                .values()
+                .into_iter()
+                .map(|(timestamp, _, _)| timestamp)
+                .filter(|timestamp| removed.contains(timestamp))
+                .collect::<CausalContext>();
+            if !late.is_empty() {
+                self.map.effect(MapOp::Delete { context: late });
+            }
+        }
+        if let Some(truncation) = &self.truncation {
+            // a set that a truncate removed before it arrived is dropped, after its own context
+            let values = self.map.values();
+            let late = truncation.late(values.iter().map(|(timestamp, _, _)| timestamp));
+            if !late.is_empty() {
+                self.map.effect(MapOp::Delete {
+                    context: late.into(),
+                });
+            }
+        }
+        if let Some(seen) = seen {
+            self.record_removed(seen);
+        }
+    }
+
+    fn apply_ready(&mut self, op: PeerMsg) {
+        let causal = match &mut self.causal {
+            Some(causal) => causal,
+            None => return receive_sync(&mut self.map, op),
+        };
+        if causal.must_wait(&op) {
+            causal.deferred.push(op);
+            return;
+        }
+        causal.record(&op);
+        let ready = causal.ready();
+        receive_sync(&mut self.map, op);
+        for op in ready {
+            self.apply_ready(op)
+        }
+    }
+
+    /// Apply the changes that are new to this server and ask for any of their dependencies that
+    /// it is still missing.
+    pub(crate) fn on_changes(
+        &mut self,
+        changes: Vec<Change>,
+        validation: &Validation,
+    ) -> Option<PeerMsg> {
+        let mut deps = Vec::new();
+        for change in changes {
+            let hash = change.hash();
+            if self.changes.contains_key(&hash) {
+                continue;
+            }
+            // changes are forwarded, so the sender needn't be their origin
+            if let Err(rejection) = validation.check(&change.op, None, self.known_counter()) {
+                self.rejected.push(rejection);
+                continue;
+            }
+            self.apply(change.op.clone());
+            deps.extend(change.deps.iter().copied());
+            self.changes.insert(hash, change);
+        }
+        self.need(deps.into_iter())
+    }
+
+    fn need(&self, hashes: impl Iterator<Item = ChangeHash>) -> Option<PeerMsg> {
+        let hashes = hashes
+            .filter(|hash| !self.changes.contains_key(hash))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        (!hashes.is_empty()).then(|| PeerMsg::Need { hashes })
+    }
+}
+
+impl<M: Clone + Map> PeerState<M> {
+    /// Start collecting the truncation metadata about sets once they are stable, given how many
+    /// servers there are, keeping what the server would hold without collecting it alongside.
+    pub(crate) fn track_stability(&mut self, servers: usize) {
+        self.stability = Some(Stability::new(servers));
+        self.uncollected = Some(Uncollected {
+            map: self.map.clone(),
+            truncation: self.truncation.clone().unwrap_or_default(),
+        });
+    }
+
+    /// Start keeping snapshots to recover from a crash, beginning with the current state, and
+    /// taking one after every `every` ops applied if given.
+    pub(crate) fn track_durability(&mut self, every: Option<usize>) {
+        let mut snapshot = self.clone();
+        snapshot.timers = Timers::default();
+        self.durability = Some(Durability {
+            snapshot: Box::new(snapshot),
+            unflushed: Vec::new(),
+            every,
+        })
+    }
+
+    /// Snapshot the state, making the ops applied since the last snapshot durable.
+    pub(crate) fn flush(&mut self) {
+        if let Some(durability) = &self.durability {
+            let every = durability.every;
+            let mut snapshot = self.clone();
+            snapshot.durability = None;
+            snapshot.timers = Timers::default();
+            self.durability = Some(Durability {
+                snapshot: Box::new(snapshot),
+                unflushed: Vec::new(),
+                every,
+            })
+        }
+    }
+
+    /// Crash and recover from the last snapshot, losing the ops applied since. Servers restarting
+    /// empty never snapshot, so they recover their initial state. Servers crash at most once, so
+    /// the recovered state keeps no snapshots.
+    pub(crate) fn crash(&mut self) {
+        let durability = match self.durability.take() {
+            Some(durability) => durability,
+            None => return,
+        };
+        let mut recovered = *durability.snapshot;
+        durability::reload(&mut recovered.map);
+        // partitions are the network's, the server writes its membership to disk as it changes,
+        // and its timers keep running
+        recovered.isolation = self.isolation;
+        recovered.membership = self.membership.take();
+        recovered.timers = std::mem::take(&mut self.timers);
+        recovered.observable = recovered.observe();
+        recovered.recovery = Some(Recovery {
+            before: self.map.clone(),
+            recovered: Box::new(recovered.clone()),
+            lost: durability.unflushed,
+        });
+        *self = recovered;
+    }
+}
+
+impl<M> Deref for PeerState<M> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.map
+    }
+}
+
+impl<M> DerefMut for PeerState<M> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.map
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::time::Duration;
+
+use stateright::actor::Actor;
+use stateright::actor::Out;
+
+use crate::CRASH_INTERVAL;
+use crate::DELTA_INTERVAL;
+use crate::GOSSIP_INTERVAL;
+use crate::MEMBERSHIP_INTERVAL;
+use crate::PARTITION_INTERVAL;
+use crate::PULL_INTERVAL;
+use crate::RETRANSMIT_INTERVAL;
+
+/// What a server sets a timer for, each feature that needs one having its own.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub(crate) enum Timer {
+    /// To pull from every peer after applying ops locally, with `--sync-mode pull`.
+    Pull,
+    /// To send peers the deltas buffered since applying ops locally, with `--sync-mode delta`.
+    Delta,
+    /// To resend the sets and deletes peers are yet to acknowledge, with `--retransmit`.
+    Retransmit,
+    /// To push a digest to the next peer, with `--anti-entropy`.
+    Gossip,
+    /// To crash and recover, with `--crash`.
+    Crash,
+    /// To be cut off from peers, and then to heal, with `--partitions`.
+    Partition,
+    /// To join, for late joiners, or to leave, for leavers.
+    Membership,
+}
+
+impl Timer {
+    pub(crate) fn interval(self) -> Range<Duration> {
+        match self {
+            Timer::Pull => PULL_INTERVAL,
+            Timer::Delta => DELTA_INTERVAL,
+            Timer::Retransmit => RETRANSMIT_INTERVAL,
+            Timer::Gossip => GOSSIP_INTERVAL,
+            Timer::Crash => CRASH_INTERVAL,
+            Timer::Partition => PARTITION_INTERVAL,
+            Timer::Membership => MEMBERSHIP_INTERVAL,
+        }
+    }
+}
+
+/// The timers a server has set, in the order it set them.
+///
+/// Stateright gives each actor a single timer, so a server keeps it set while any of its own
+/// timers are, and each time it fires the earliest of them fires. The checker fires the actor's
+/// timer at any point, so each of the server's timers still fires between any two steps, though
+/// always after those set before it. Setting a timer that is already set leaves it in place.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct Timers {
+    set: VecDeque<Timer>,
+}
+
+impl Timers {
+    pub(crate) fn is_set(&self, timer: Timer) -> bool {
+        self.set.contains(&timer)
+    }
+
+    /// The timer that fires next.
+    pub(crate) fn next(&self) -> Option<Timer> {
+        self.set.front().copied()
+    }
+
+    /// Take the timer that fired.
+    pub(crate) fn fire(&mut self) -> Option<Timer> {
+        self.set.pop_front()
+    }
+}
+
+/// Set one of a server's timers, setting the actor's timer if none of the others were set.
+pub(crate) fn set<A: Actor>(timers: &mut Timers, o: &mut Out<A>, timer: Timer) {
+    if timers.is_set(timer) {
+        return;
+    }
+    if timers.set.is_empty() {
+        o.set_timer(timer.interval())
+    }
+    timers.set.push_back(timer)
+}
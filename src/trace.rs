@@ -0,0 +1,254 @@
+use std::fmt::Debug;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+use stateright::actor::ActorModelAction;
+use stateright::actor::Envelope;
+use stateright::actor::Id;
+use stateright::Model;
+
+use crate::check::Steps;
+use crate::map::Map;
+use crate::model::RegisterAction;
+use crate::model::RegisterModel;
+use crate::model::RegisterState;
+use crate::ModelCfg;
+use crate::MyRegisterMsg;
+
+/// A recorded sequence of message deliveries.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Trace {
+    pub(crate) steps: Vec<TraceStep>,
+}
+
+/// The delivery of `msg` from `src` to `dst`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct TraceStep {
+    pub(crate) src: usize,
+    pub(crate) dst: usize,
+    pub(crate) msg: MyRegisterMsg,
+}
+
+impl Trace {
+    pub(crate) fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("failed to open {:?}: {}", path, e))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("failed to parse trace {:?}: {}", path, e))
+    }
+}
+
+/// A counterexample as written to a file: the deliveries along it, which can be replayed or
+/// resumed from as a trace, along with the property it violates and the state of each actor
+/// after each delivery, for reading.
+#[derive(Serialize)]
+struct Counterexample<'a> {
+    property: &'a str,
+    steps: Vec<TraceStep>,
+    states: Vec<Vec<String>>,
+}
+
+/// Write a counterexample to a JSON file named after the property in `dir`, returning the path
+/// written and how many of its steps weren't deliveries, which traces can't hold.
+pub(crate) fn save_counterexample<M: Clone + Debug + PartialEq + Hash + Map>(
+    dir: &Path,
+    property: &str,
+    steps: &Steps<M>,
+) -> Result<(PathBuf, usize), String> {
+    let mut counterexample = Counterexample {
+        property,
+        steps: Vec::new(),
+        states: Vec::new(),
+    };
+    let mut skipped = 0;
+    for (i, (_, action)) in steps.iter().enumerate() {
+        match action {
+            Some(ActorModelAction::Deliver { src, dst, msg }) => {
+                counterexample.steps.push(TraceStep {
+                    src: (*src).into(),
+                    dst: (*dst).into(),
+                    msg: msg.clone(),
+                });
+                let (next, _) = &steps[i + 1];
+                counterexample.states.push(
+                    next.actor_states
+                        .iter()
+                        .map(|actor| format!("{:?}", actor))
+                        .collect(),
+                );
+            }
+            Some(_) => skipped += 1,
+            None => {}
+        }
+    }
+
+    std::fs::create_dir_all(dir).map_err(|e| format!("failed to create {:?}: {}", dir, e))?;
+    let name = property
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    let path = dir.join(format!("{}.json", name));
+    let file = File::create(&path).map_err(|e| format!("failed to create {:?}: {}", path, e))?;
+    serde_json::to_writer_pretty(file, &counterexample)
+        .map_err(|e| format!("failed to write {:?}: {}", path, e))?;
+    Ok((path, skipped))
+}
+
+impl TraceStep {
+    fn matches(&self, envelope: &Envelope<&MyRegisterMsg>) -> bool {
+        Id::from(self.src) == envelope.src
+            && Id::from(self.dst) == envelope.dst
+            && &self.msg == envelope.msg
+    }
+}
+
+/// How much of the configured trace prefix has been followed so far.
+///
+/// This is kept in the model's history so that exploration can be bounded to the states that
+/// follow the prefix before branching out.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct PrefixProgress {
+    matched: usize,
+    diverged: bool,
+}
+
+/// Advance the prefix progress on each delivered message.
+pub(crate) fn record_delivery(
+    cfg: &ModelCfg,
+    history: &PrefixProgress,
+    envelope: &Envelope<&MyRegisterMsg>,
+) -> Option<PrefixProgress> {
+    if history.diverged {
+        return None;
+    }
+    let step = cfg.prefix.steps.get(history.matched)?;
+    if step.matches(envelope) {
+        Some(PrefixProgress {
+            matched: history.matched + 1,
+            diverged: false,
+        })
+    } else {
+        Some(PrefixProgress {
+            matched: history.matched,
+            diverged: true,
+        })
+    }
+}
+
+/// Only explore states that haven't strayed from the prefix.
+pub(crate) fn follows_prefix(history: &PrefixProgress) -> bool {
+    !history.diverged
+}
+
+pub(crate) fn init_state<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+) -> RegisterState<M> {
+    model
+        .init_states()
+        .into_iter()
+        .next()
+        .expect("actor models have an initial state")
+}
+
+/// Deliver the step's message, returning `None` if it isn't deliverable.
+pub(crate) fn replay_step<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    state: &RegisterState<M>,
+    step: &TraceStep,
+) -> Option<RegisterState<M>> {
+    let action = find_action(model, state, step)?;
+    model.next_state(state, action)
+}
+
+/// Find the action that delivers the step's message.
+///
+/// Sync messages carry implementation specific contexts, so when no message matches exactly the
+/// first deliverable message of the same kind on the same channel is used instead.
+fn find_action<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    state: &RegisterState<M>,
+    step: &TraceStep,
+) -> Option<RegisterAction<M>> {
+    let mut actions = Vec::new();
+    // use the unreduced actions so that any delivery in the trace can be found
+    model.actor_model.actions(state, &mut actions);
+
+    let on_channel = |action: &&RegisterAction<M>| {
+        matches!(action, ActorModelAction::Deliver { src, dst, .. }
+            if usize::from(*src) == step.src && usize::from(*dst) == step.dst)
+    };
+    let exact = actions
+        .iter()
+        .filter(on_channel)
+        .find(|action| matches!(action, ActorModelAction::Deliver { msg, .. } if msg == &step.msg));
+    exact
+        .or_else(|| {
+            actions.iter().filter(on_channel).find(|action| {
+                matches!(action, ActorModelAction::Deliver { msg, .. }
+                    if std::mem::discriminant(msg) == std::mem::discriminant(&step.msg))
+            })
+        })
+        .cloned()
+}
+
+/// Replay a whole trace, returning each state along with the action taken from it.
+///
+/// Stops early at the first step that can't be delivered.
+pub(crate) fn replay<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    trace: &Trace,
+) -> Vec<(RegisterState<M>, Option<RegisterAction<M>>)> {
+    let mut steps = Vec::new();
+    let mut state = init_state(model);
+    for step in &trace.steps {
+        let next = find_action(model, &state, step)
+            .and_then(|action| Some((model.next_state(&state, action.clone())?, action)));
+        match next {
+            Some((next, action)) => {
+                steps.push((state, Some(action)));
+                state = next;
+            }
+            None => {
+                eprintln!("Stopping replay, {:?} can't be delivered", step);
+                break;
+            }
+        }
+    }
+    steps.push((state, None));
+    steps
+}
+
+/// Replay a trace, printing each delivery and how it changed the actors' states.
+pub(crate) fn print_replay<M: Clone + Debug + PartialEq + Hash + Map>(
+    model: &RegisterModel<M>,
+    trace: &Trace,
+) {
+    let steps = replay(model, trace);
+    for (i, window) in steps.windows(2).enumerate() {
+        let ((state, action), (next, _)) = (&window[0], &window[1]);
+        if let Some(action) = action {
+            println!("Step {}: {:?}", i, action);
+        }
+        for (actor, (before, after)) in state
+            .actor_states
+            .iter()
+            .zip(&next.actor_states)
+            .enumerate()
+        {
+            if before != after {
+                println!("  actor {}:", actor);
+                println!("  - {:?}", before);
+                println!("  + {:?}", after);
+            }
+        }
+    }
+    println!(
+        "Replayed {} of {} steps",
+        steps.len() - 1,
+        trace.steps.len()
+    );
+}
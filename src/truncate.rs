@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+
+use crate::map::Timestamp;
+use crate::PeerMsg;
+
+/// What a server tracks so that truncates act as causal barriers: a truncate removes every set
+/// its origin had seen when preparing it, wherever and whenever those sets arrive, and nothing
+/// else, so sets concurrent with it or after it survive.
+///
+/// Servers only track this when clients truncate, so that states don't otherwise differ in it.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub(crate) struct Truncation {
+    /// Timestamps of the sets this server has seen, including those since overwritten or
+    /// removed, which a truncate it prepares removes.
+    pub(crate) seen: BTreeSet<Timestamp>,
+    /// Timestamps of the sets removed by the truncates this server has applied, which are
+    /// dropped if they arrive afterwards.
+    pub(crate) removed: BTreeSet<Timestamp>,
+}
+
+impl Truncation {
+    /// Record the sets an op brings and the sets a truncate removes.
+    pub(crate) fn record(&mut self, op: &PeerMsg) {
+        match op {
+            PeerMsg::PutSync { timestamp, .. } => {
+                self.seen.insert(*timestamp);
+            }
+            PeerMsg::After { op, .. } | PeerMsg::Stamped { op, .. } => self.record(op),
+            PeerMsg::Truncate { barrier } => {
+                self.seen.extend(barrier.iter());
+                self.removed.extend(barrier.iter());
+            }
+            PeerMsg::DeleteSync { .. }
+            | PeerMsg::AddSync { .. }
+            | PeerMsg::RemoveSync { .. }
+            | PeerMsg::CounterState { .. }
+            | PeerMsg::RegisterSync { .. }
+            | PeerMsg::LwwRegisterSync { .. }
+            | PeerMsg::FlagSync { .. }
+            | PeerMsg::ListInsertSync { .. }
+            | PeerMsg::ListDeleteSync { .. }
+            | PeerMsg::NestedSetSync { .. }
+            | PeerMsg::NestedDeleteSync { .. }
+            | PeerMsg::StateSync { .. }
+            | PeerMsg::DeltaSync { .. }
+            | PeerMsg::Have { .. }
+            | PeerMsg::Need { .. }
+            | PeerMsg::Changes { .. }
+            | PeerMsg::DepCheck { .. }
+            | PeerMsg::DepOk { .. }
+            | PeerMsg::Pull { .. }
+            | PeerMsg::Ops { .. }
+            | PeerMsg::Join
+            | PeerMsg::Leave
+            | PeerMsg::PutSyncAck { .. }
+            | PeerMsg::DeleteSyncAck { .. }
+            | PeerMsg::Digest { .. }
+            | PeerMsg::Repair { .. }
+            | PeerMsg::EpochClose { .. }
+            | PeerMsg::EpochDone { .. }
+            | PeerMsg::Watermark { .. } => {}
+            _ => {}
+        }
+    }
+
+    /// A truncate removing every set this server has seen along with the sets it holds, which
+    /// include any from rolled over epochs that are no longer remembered as seen, if there are
+    /// any.
+    pub(crate) fn prepare(&self, held: impl Iterator<Item = Timestamp>) -> Option<PeerMsg> {
+        let barrier = self
+            .seen
+            .iter()
+            .copied()
+            .chain(held)
+            .collect::<BTreeSet<_>>();
+        (!barrier.is_empty()).then(|| PeerMsg::Truncate {
+            barrier: barrier.into_iter().collect(),
+        })
+    }
+
+    /// The timestamps among the given values that a truncate already removed, as they arrived
+    /// after it.
+    pub(crate) fn late<'a>(
+        &self,
+        timestamps: impl Iterator<Item = &'a Timestamp>,
+    ) -> Vec<Timestamp> {
+        timestamps
+            .filter(|timestamp| self.removed.contains(timestamp))
+            .copied()
+            .collect()
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::BTreeSet;
+
+use crate::map::Timestamp;
+use crate::Key;
+use crate::PeerMsg;
+use crate::Value;
+
+/// Why a server refused to effect an op it received.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) enum Rejection {
+    /// A set's timestamp names a different server than the one that sent it.
+    ForeignTimestamp { timestamp: Timestamp, sender: usize },
+    /// A counter is further ahead of the highest counter the receiver has seen than an honest
+    /// server could have got.
+    ImplausibleCounter { timestamp: Timestamp, known: u32 },
+    /// A set's key isn't a lowercase letter.
+    MalformedKey { key: Key },
+    /// A set's value isn't an uppercase letter.
+    MalformedValue { value: Value },
+    /// A stamped op names a different server as its origin than the one that sent it.
+    ForeignOrigin { origin: usize, sender: usize },
+    /// A timestamp names a server that doesn't exist.
+    UnknownActor { timestamp: Timestamp },
+    /// A delete that doesn't remove anything.
+    EmptyDelete,
+}
+
+/// Whether clients could have written this key.
+pub(crate) fn valid_key(key: Key) -> bool {
+    key.is_ascii_lowercase()
+}
+
+/// Whether clients could have written this value.
+pub(crate) fn valid_value(value: Value) -> bool {
+    value.is_ascii_uppercase()
+}
+
+/// Limits on the ops that a server accepts from its peers.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct Validation {
+    /// The actor ids of the servers, which are the only actors that prepare ops: their indices
+    /// when checking, or the ids of their addresses when running for real.
+    pub(crate) servers: BTreeSet<usize>,
+    /// How far a counter may be ahead of the highest counter the receiver has seen.
+    ///
+    /// Servers advance their counters past every counter they have seen, including ones the
+    /// receiver hasn't seen yet, so this has to allow for the ops that may be in flight.
+    pub(crate) max_counter_gap: u32,
+}
+
+impl Validation {
+    /// Check that an op is well-formed and could have been prepared by an honest server.
+    ///
+    /// `sender` is the server that sent the op when it is known to be the op's origin, as it is
+    /// when ops are broadcast, but not when they are forwarded by the have/need exchange or pulled.
+    pub(crate) fn check(
+        &self,
+        op: &PeerMsg,
+        sender: Option<usize>,
+        known: u32,
+    ) -> Result<(), Rejection> {
+        let timestamps: Vec<Timestamp> = match op {
+            PeerMsg::PutSync {
+                context,
+                timestamp,
+                key,
+                value,
+            } => {
+                if !valid_key(*key) {
+                    return Err(Rejection::MalformedKey { key: *key });
+                }
+                if !valid_value(*value) {
+                    return Err(Rejection::MalformedValue { value: *value });
+                }
+                if let Some(sender) = sender {
+                    if timestamp.1 != sender {
+                        return Err(Rejection::ForeignTimestamp {
+                            timestamp: *timestamp,
+                            sender,
+                        });
+                    }
+                }
+                context.iter().chain(Some(*timestamp)).collect()
+            }
+            PeerMsg::NestedSetSync {
+                context,
+                timestamp,
+                path,
+                value,
+            } => {
+                if let Some(key) = path.iter().find(|key| !valid_key(**key)) {
+                    return Err(Rejection::MalformedKey { key: *key });
+                }
+                if !valid_value(*value) {
+                    return Err(Rejection::MalformedValue { value: *value });
+                }
+                if let Some(sender) = sender {
+                    if timestamp.1 != sender {
+                        return Err(Rejection::ForeignTimestamp {
+                            timestamp: *timestamp,
+                            sender,
+                        });
+                    }
+                }
+                context.iter().chain(Some(*timestamp)).collect()
+            }
+            // a state holds values and removals from every server, so neither names the sender
+            PeerMsg::StateSync { values, removed } => {
+                for (_, key, value) in values {
+                    if !valid_key(*key) {
+                        return Err(Rejection::MalformedKey { key: *key });
+                    }
+                    if !valid_value(*value) {
+                        return Err(Rejection::MalformedValue { value: *value });
+                    }
+                }
+                values
+                    .iter()
+                    .map(|(timestamp, _, _)| *timestamp)
+                    .chain(removed.iter())
+                    .collect()
+            }
+            // a delta only adds the values its sender set, but can remove any
+            PeerMsg::DeltaSync { values, removed } => {
+                for (timestamp, key, value) in values {
+                    if !valid_key(*key) {
+                        return Err(Rejection::MalformedKey { key: *key });
+                    }
+                    if !valid_value(*value) {
+                        return Err(Rejection::MalformedValue { value: *value });
+                    }
+                    if let Some(sender) = sender {
+                        if timestamp.1 != sender {
+                            return Err(Rejection::ForeignTimestamp {
+                                timestamp: *timestamp,
+                                sender,
+                            });
+                        }
+                    }
+                }
+                values
+                    .iter()
+                    .map(|(timestamp, _, _)| *timestamp)
+                    .chain(removed.iter().copied())
+                    .collect()
+            }
+            PeerMsg::DeleteSync { context } if context.is_empty() => {
+                return Err(Rejection::EmptyDelete)
+            }
+            PeerMsg::RemoveSync { context } | PeerMsg::NestedDeleteSync { context }
+                if context.is_empty() =>
+            {
+                return Err(Rejection::EmptyDelete)
+            }
+            PeerMsg::DeleteSync { context }
+            | PeerMsg::RemoveSync { context }
+            | PeerMsg::NestedDeleteSync { context } => context.iter().collect(),
+            PeerMsg::RegisterSync {
+                timestamp, value, ..
+            }
+            | PeerMsg::LwwRegisterSync { timestamp, value } => {
+                if !valid_value(*value) {
+                    return Err(Rejection::MalformedValue { value: *value });
+                }
+                if let Some(sender) = sender {
+                    if timestamp.1 != sender {
+                        return Err(Rejection::ForeignTimestamp {
+                            timestamp: *timestamp,
+                            sender,
+                        });
+                    }
+                }
+                vec![*timestamp]
+            }
+            PeerMsg::FlagSync { timestamp, .. } => {
+                if let Some(sender) = sender {
+                    if timestamp.1 != sender {
+                        return Err(Rejection::ForeignTimestamp {
+                            timestamp: *timestamp,
+                            sender,
+                        });
+                    }
+                }
+                vec![*timestamp]
+            }
+            PeerMsg::ListInsertSync { id, after, value } => {
+                if !valid_value(*value) {
+                    return Err(Rejection::MalformedValue { value: *value });
+                }
+                if let Some(sender) = sender {
+                    if id.1 != sender {
+                        return Err(Rejection::ForeignTimestamp {
+                            timestamp: *id,
+                            sender,
+                        });
+                    }
+                }
+                after.iter().chain(Some(id)).copied().collect()
+            }
+            PeerMsg::ListDeleteSync { id } => vec![*id],
+            PeerMsg::AddSync { tag, element } => {
+                if !valid_value(*element) {
+                    return Err(Rejection::MalformedValue { value: *element });
+                }
+                if let Some(sender) = sender {
+                    if tag.1 != sender {
+                        return Err(Rejection::ForeignTimestamp {
+                            timestamp: *tag,
+                            sender,
+                        });
+                    }
+                }
+                vec![*tag]
+            }
+            PeerMsg::Truncate { barrier } => barrier.iter().collect(),
+            PeerMsg::After { dependency, op } => {
+                self.check(op, sender, known)?;
+                vec![*dependency]
+            }
+            PeerMsg::Stamped { origin, op, .. } => {
+                if let Some(sender) = sender {
+                    if *origin != sender {
+                        return Err(Rejection::ForeignOrigin {
+                            origin: *origin,
+                            sender,
+                        });
+                    }
+                }
+                self.check(op, sender, known)?;
+                Vec::new()
+            }
+            // repaired ops are relayed, so the sender needn't be their origin
+            PeerMsg::Repair { ops } => {
+                for op in ops {
+                    self.check(op, None, known)?;
+                }
+                Vec::new()
+            }
+            // a counter state has no timestamps, and merging it is safe whatever it holds
+            PeerMsg::CounterState { .. }
+            | PeerMsg::Have { .. }
+            | PeerMsg::Need { .. }
+            | PeerMsg::Changes { .. }
+            | PeerMsg::DepCheck { .. }
+            | PeerMsg::DepOk { .. }
+            | PeerMsg::Pull { .. }
+            | PeerMsg::Ops { .. }
+            | PeerMsg::Join
+            | PeerMsg::Leave
+            | PeerMsg::PutSyncAck { .. }
+            | PeerMsg::DeleteSyncAck { .. }
+            | PeerMsg::Digest { .. }
+            | PeerMsg::EpochClose { .. }
+            | PeerMsg::EpochDone { .. }
+            | PeerMsg::Watermark { .. } => Vec::new(),
+            _ => Vec::new(),
+        };
+        for timestamp in timestamps {
+            if !self.servers.contains(&timestamp.1) {
+                return Err(Rejection::UnknownActor { timestamp });
+            }
+            if timestamp.0 > known.saturating_add(self.max_counter_gap) {
+                return Err(Rejection::ImplausibleCounter { timestamp, known });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+
+use stateright::actor::Id;
+
+use crate::context::CausalContext;
+use crate::map::ClockKind;
+use crate::map::Map;
+use crate::map::MapOp;
+use crate::map::MapSnapshot;
+use crate::map::Policy;
+use crate::map::Timestamp;
+
+/// A change to the resolved value of a key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ChangeEvent {
+    pub(crate) key: char,
+    /// The key's new resolved value, `None` once it has been deleted.
+    pub(crate) value: Option<char>,
+    /// The server that the op causing the change originated at; deletes don't record it.
+    pub(crate) origin: Option<usize>,
+    /// Timestamp of the op causing the change; deletes don't have one.
+    pub(crate) timestamp: Option<Timestamp>,
+}
+
+impl Display for ChangeEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.value {
+            Some(value) => write!(f, "{:?} = {:?}", self.key, value)?,
+            None => write!(f, "{:?} = (none)", self.key)?,
+        }
+        if let Some(origin) = self.origin {
+            write!(f, " from server {}", origin)?;
+        }
+        if let Some(timestamp) = self.timestamp {
+            write!(f, " at {:?}", timestamp)?;
+        }
+        Ok(())
+    }
+}
+
+/// A map that notifies subscribers when an effected op changes the resolved value of a key they
+/// subscribed to, whether the op was prepared locally or by another server.
+///
+/// Subscriptions are channels, so events can be consumed as an iterator from the receiver, and
+/// subscriptions whose receiver has been dropped are removed.
+pub(crate) struct Watched<M> {
+    map: M,
+    subscribers: Vec<(char, Sender<ChangeEvent>)>,
+}
+
+impl<M: Map> Watched<M> {
+    /// Subscribe to changes of the resolved value of `key`.
+    pub(crate) fn subscribe(&mut self, key: char) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.push((key, sender));
+        receiver
+    }
+
+    pub(crate) fn into_inner(self) -> M {
+        self.map
+    }
+
+    /// Apply a mutation, notifying subscribers of the keys whose resolved value it changed.
+    fn notify(
+        &mut self,
+        origin: Option<usize>,
+        timestamp: Option<Timestamp>,
+        mutate: impl FnOnce(&mut M),
+    ) {
+        let before = self
+            .subscribers
+            .iter()
+            .map(|(key, _)| self.map.get(key).copied())
+            .collect::<Vec<_>>();
+        mutate(&mut self.map);
+
+        let map = &self.map;
+        let mut before = before.into_iter();
+        self.subscribers.retain(|(key, sender)| {
+            let value = map.get(key).copied();
+            if before.next().unwrap() == value {
+                return true;
+            }
+            sender
+                .send(ChangeEvent {
+                    key: *key,
+                    value,
+                    origin,
+                    timestamp,
+                })
+                .is_ok()
+        });
+    }
+}
+
+impl<M: Map> Map for Watched<M> {
+    fn new(actor_id: Id) -> Self {
+        Self {
+            map: M::new(actor_id),
+            subscribers: Vec::new(),
+        }
+    }
+
+    fn set_clock_skews(&mut self, skews: &[u32]) {
+        self.map.set_clock_skews(skews)
+    }
+
+    fn set_clock(&mut self, clock: ClockKind) {
+        self.map.set_clock(clock)
+    }
+
+    fn set_policy(&mut self, policy: Policy) {
+        self.map.set_policy(policy)
+    }
+
+    fn forget_stable(&mut self, stable: &CausalContext) {
+        self.map.forget_stable(stable)
+    }
+
+    fn get(&self, k: &char) -> Option<&char> {
+        self.map.get(k)
+    }
+
+    fn prepare_set(&self, key: char, v: char) -> MapOp {
+        self.map.prepare_set(key, v)
+    }
+
+    fn prepare_delete(&self, key: &char) -> Option<MapOp> {
+        self.map.prepare_delete(key)
+    }
+
+    fn effect(&mut self, op: MapOp) {
+        let (origin, timestamp) = match &op {
+            MapOp::Set { timestamp, .. } => (Some(timestamp.1), Some(*timestamp)),
+            MapOp::Delete { .. } => (None, None),
+        };
+        self.notify(origin, timestamp, |map| map.effect(op))
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.notify(None, None, |map| map.merge(&other.map))
+    }
+
+    fn max_counter(&self) -> u32 {
+        self.map.max_counter()
+    }
+
+    fn values(&self) -> Vec<(Timestamp, char, char)> {
+        self.map.values()
+    }
+
+    fn snapshot(&self) -> MapSnapshot {
+        self.map.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: MapSnapshot) {
+        self.notify(None, None, |map| map.restore(snapshot))
+    }
+
+    fn visible_values(&self) -> Vec<(Timestamp, char, char)> {
+        self.map.visible_values()
+    }
+}
+
+/// The subscribers to the feed of changes of the server `run` runs in this process.
+static FEED: Mutex<Vec<Sender<ChangeEvent>>> = Mutex::new(Vec::new());
+
+/// Subscribe to changes of the resolved value of every key of the server `run` runs in this
+/// process, which keeps its map in a [`Feed`].
+pub(crate) fn subscribe_feed() -> Receiver<ChangeEvent> {
+    let (sender, receiver) = channel();
+    FEED.lock().unwrap().push(sender);
+    receiver
+}
+
+/// A map that sends every change to the resolved value of any key to the subscribers of
+/// [`subscribe_feed`].
+///
+/// Unlike a [`Watched`] map, its subscribers are kept outside of it, so it can be cloned,
+/// compared and hashed, all as the map it wraps, as the actor's state is. The actor only changes
+/// the copy of its state that it keeps, so each change is sent once.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct Feed<M> {
+    map: M,
+}
+
+impl<M: Map> Feed<M> {
+    /// Apply a mutation, sending the changes it made to the feed's subscribers, if it has any.
+    fn notify(
+        &mut self,
+        origin: Option<usize>,
+        timestamp: Option<Timestamp>,
+        mutate: impl FnOnce(&mut M),
+    ) {
+        let mut subscribers = FEED.lock().unwrap();
+        if subscribers.is_empty() {
+            mutate(&mut self.map);
+            return;
+        }
+        let before = resolved(&self.map);
+        mutate(&mut self.map);
+        let after = resolved(&self.map);
+
+        let keys = before.keys().chain(after.keys()).collect::<BTreeSet<_>>();
+        for key in keys {
+            let value = after.get(key).copied();
+            if before.get(key).copied() == value {
+                continue;
+            }
+            let event = ChangeEvent {
+                key: *key,
+                value,
+                origin,
+                timestamp,
+            };
+            subscribers.retain(|sender| sender.send(event.clone()).is_ok());
+        }
+    }
+}
+
+/// The value each key resolves to.
+fn resolved<M: Map>(map: &M) -> BTreeMap<char, char> {
+    map.values()
+        .into_iter()
+        .filter_map(|(_, key, _)| Some((key, *map.get(&key)?)))
+        .collect()
+}
+
+impl<M: Map> Map for Feed<M> {
+    fn new(actor_id: Id) -> Self {
+        Self {
+            map: M::new(actor_id),
+        }
+    }
+
+    fn set_clock_skews(&mut self, skews: &[u32]) {
+        self.map.set_clock_skews(skews)
+    }
+
+    fn set_clock(&mut self, clock: ClockKind) {
+        self.map.set_clock(clock)
+    }
+
+    fn set_policy(&mut self, policy: Policy) {
+        self.map.set_policy(policy)
+    }
+
+    fn forget_stable(&mut self, stable: &CausalContext) {
+        self.map.forget_stable(stable)
+    }
+
+    fn get(&self, k: &char) -> Option<&char> {
+        self.map.get(k)
+    }
+
+    fn prepare_set(&self, key: char, v: char) -> MapOp {
+        self.map.prepare_set(key, v)
+    }
+
+    fn prepare_delete(&self, key: &char) -> Option<MapOp> {
+        self.map.prepare_delete(key)
+    }
+
+    fn effect(&mut self, op: MapOp) {
+        let (origin, timestamp) = match &op {
+            MapOp::Set { timestamp, .. } => (Some(timestamp.1), Some(*timestamp)),
+            MapOp::Delete { .. } => (None, None),
+        };
+        self.notify(origin, timestamp, |map| map.effect(op))
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.notify(None, None, |map| map.merge(&other.map))
+    }
+
+    fn max_counter(&self) -> u32 {
+        self.map.max_counter()
+    }
+
+    fn values(&self) -> Vec<(Timestamp, char, char)> {
+        self.map.values()
+    }
+
+    fn snapshot(&self) -> MapSnapshot {
+        self.map.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: MapSnapshot) {
+        self.notify(None, None, |map| map.restore(snapshot))
+    }
+
+    fn visible_values(&self) -> Vec<(Timestamp, char, char)> {
+        self.map.visible_values()
+    }
+}
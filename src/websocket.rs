@@ -0,0 +1,83 @@
+use std::io;
+use std::net::SocketAddrV4;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use serde::Serialize;
+use stateright::actor::Id;
+use tungstenite::Message;
+
+use crate::watch;
+use crate::watch::ChangeEvent;
+
+/// How long to wait on a client completing the handshake, so that a slow one can't hold up its
+/// thread.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A change to the value a key resolves to, as sent to clients in a JSON text message.
+#[derive(Serialize)]
+struct Change {
+    key: char,
+    /// The key's new resolved value, null once it has been deleted.
+    value: Option<char>,
+    /// Address of the server the write behind the change was made at; null for deletes.
+    origin: Option<String>,
+    /// Counter of the write behind the change, which with `origin` names it; null for deletes.
+    counter: Option<u32>,
+}
+
+impl From<ChangeEvent> for Change {
+    fn from(event: ChangeEvent) -> Self {
+        Self {
+            key: event.key,
+            value: event.value,
+            origin: event
+                .timestamp
+                .map(|(_, origin)| SocketAddrV4::from(Id::from(origin)).to_string()),
+            counter: event.timestamp.map(|(counter, _)| counter),
+        }
+    }
+}
+
+/// Stream the changes of the server `run` runs in this process to WebSocket clients on the given
+/// address, on a thread of its own, and a thread for each client.
+///
+/// Each client is sent every change to the value any key resolves to from when it connected, as
+/// the server applies the ops from its clients and peers. What clients send is ignored.
+pub(crate) fn serve(listen: SocketAddrV4) {
+    let listener = TcpListener::bind(listen).unwrap_or_else(|e| {
+        eprintln!("Failed to listen for WebSockets on {}: {}", listen, e);
+        std::process::exit(2)
+    });
+    println!("Streaming changes on ws://{}/", listen);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    std::thread::spawn(move || {
+                        if let Err(e) = stream_changes(stream) {
+                            eprintln!("Failed to stream changes: {}", e);
+                        }
+                    });
+                }
+                Err(e) => eprintln!("Failed to accept a WebSocket client: {}", e),
+            }
+        }
+    });
+}
+
+/// Stream changes to a client, until a change finds it gone.
+fn stream_changes(stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut socket = tungstenite::accept(stream).map_err(|e| io::Error::other(e.to_string()))?;
+    socket.get_ref().set_read_timeout(None)?;
+    for event in watch::subscribe_feed() {
+        let change = serde_json::to_string(&Change::from(event))?;
+        if socket.send(Message::Text(change)).is_err() {
+            // the client closed the connection
+            break;
+        }
+    }
+    Ok(())
+}
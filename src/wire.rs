@@ -0,0 +1,68 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The version of the wire format, bumped whenever `PeerMsg`, `MyRegisterMsg` or `MapSnapshot`
+/// change in a way older peers can't read, so that mismatched peers and snapshots left on disk
+/// by an older build are rejected rather than misread.
+pub(crate) const VERSION: u32 = 7;
+
+/// What is sent over a socket or written to disk: the payload tagged with the version of the
+/// format it was written in.
+#[derive(Deserialize)]
+struct Frame<T> {
+    version: u32,
+    payload: T,
+}
+
+/// Just the version of a frame, read before the payload so that a frame from another version
+/// is reported as such rather than as a payload that doesn't parse.
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+/// Borrowed counterpart of `Frame`, so that encoding doesn't need to clone the payload.
+#[derive(Serialize)]
+struct FrameRef<'a, T> {
+    version: u32,
+    payload: &'a T,
+}
+
+/// Encode a message or snapshot in the current wire format: JSON, or bincode when built with
+/// `--features bincode`.
+pub(crate) fn encode<T: Serialize>(payload: &T) -> Vec<u8> {
+    let frame = FrameRef {
+        version: VERSION,
+        payload,
+    };
+    #[cfg(feature = "bincode")]
+    let bytes = bincode::serialize(&frame);
+    #[cfg(not(feature = "bincode"))]
+    let bytes = serde_json::to_vec(&frame);
+    bytes.expect("messages serialize")
+}
+
+/// Decode a message or snapshot encoded by `encode`, failing if it was written in another
+/// version of the format.
+pub(crate) fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    let Version { version } = parse(bytes)?;
+    if version != VERSION {
+        return Err(format!(
+            "wire format version {} isn't supported, expected {}",
+            version, VERSION
+        ));
+    }
+    let frame: Frame<T> = parse(bytes)?;
+    Ok(frame.payload)
+}
+
+#[cfg(feature = "bincode")]
+fn parse<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    bincode::deserialize(bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "bincode"))]
+fn parse<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    serde_json::from_slice(bytes).map_err(|e| e.to_string())
+}
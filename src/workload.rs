@@ -0,0 +1,160 @@
+use crate::Key;
+
+/// How often clients pick each key.
+///
+/// The checker explores every interleaving but not every choice of keys, so rather than sampling
+/// the clients follow a fixed schedule in which each key appears in proportion to its weight,
+/// spread out as evenly as possible. Clients start at different points in the schedule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct KeyWorkload {
+    weights: Vec<(Key, u32)>,
+}
+
+impl KeyWorkload {
+    /// Every request goes to the same key.
+    pub(crate) fn single(key: Key) -> Self {
+        Self {
+            weights: vec![(key, 1)],
+        }
+    }
+
+    /// Parse explicit weights such as `a=3,b=1`.
+    pub(crate) fn parse_weights(s: &str) -> Result<Self, String> {
+        let weights = s
+            .split(',')
+            .map(|entry| {
+                let (key, weight) = entry
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected key=weight, got {:?}", entry))?;
+                let mut chars = key.trim().chars();
+                let key = match (chars.next(), chars.next()) {
+                    (Some(key), None) => key,
+                    _ => return Err(format!("expected a single character key, got {:?}", key)),
+                };
+                let weight = weight
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("invalid weight for key {:?}: {}", key, e))?;
+                Ok((key, weight))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if weights.iter().all(|(_, weight)| *weight == 0) {
+            return Err("at least one key needs a non-zero weight".to_owned());
+        }
+        Ok(Self { weights })
+    }
+
+    /// Equal popularity over the keys `'a'`, `'b'`, ..., so that clients cycle through them.
+    pub(crate) fn uniform(keys: usize) -> Self {
+        Self {
+            weights: (0..keys)
+                .map(|rank| ((b'a' + rank as u8) as char, 1))
+                .collect(),
+        }
+    }
+
+    /// Zipfian popularity over the keys `'a'`, `'b'`, ..., where the key of rank `r` is picked
+    /// in proportion to `1 / r^exponent`.
+    pub(crate) fn zipf(keys: usize, exponent: f64) -> Self {
+        // scale so that the least popular key still shows up in the schedule
+        let scale = (keys as f64).powf(exponent);
+        let weights = (0..keys)
+            .map(|rank| {
+                let key = (b'a' + rank as u8) as char;
+                let weight = (scale / ((rank + 1) as f64).powf(exponent)).round() as u32;
+                (key, weight.max(1))
+            })
+            .collect();
+        Self { weights }
+    }
+
+    /// The keys that get picked at all.
+    pub(crate) fn keys(&self) -> Vec<Key> {
+        self.weights
+            .iter()
+            .filter(|(_, weight)| *weight > 0)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    /// The order in which keys are picked, using smooth weighted round-robin.
+    pub(crate) fn schedule(&self) -> Vec<Key> {
+        schedule(&self.weights)
+    }
+}
+
+/// A request a mixed client makes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum MixedOp {
+    Put,
+    Delete,
+    Get,
+}
+
+/// Parse the requests mixed clients make in turn, either as a script such as
+/// `put,delete,put,get` or as a ratio such as `put=5,delete=3,get=2`, which is spread out into
+/// a schedule as evenly as possible after reducing it to lowest terms.
+pub(crate) fn parse_mix(s: &str) -> Result<Vec<MixedOp>, String> {
+    let parse_op = |op: &str| match op.trim() {
+        "put" => Ok(MixedOp::Put),
+        "delete" => Ok(MixedOp::Delete),
+        "get" => Ok(MixedOp::Get),
+        op => Err(format!("expected put, delete or get, got {:?}", op)),
+    };
+    let entries = s.split(',').collect::<Vec<_>>();
+    if entries.iter().all(|entry| !entry.contains('=')) {
+        return entries.into_iter().map(parse_op).collect();
+    }
+    let mut weights = entries
+        .into_iter()
+        .map(|entry| {
+            let (op, weight) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected op=weight, got {:?}", entry))?;
+            let weight = weight
+                .trim()
+                .parse()
+                .map_err(|e| format!("invalid weight for {:?}: {}", op.trim(), e))?;
+            Ok((parse_op(op)?, weight))
+        })
+        .collect::<Result<Vec<(MixedOp, u32)>, String>>()?;
+    let divisor = weights
+        .iter()
+        .fold(0, |divisor, (_, weight)| gcd(divisor, *weight));
+    if divisor == 0 {
+        return Err("at least one op needs a non-zero weight".to_owned());
+    }
+    for (_, weight) in &mut weights {
+        *weight /= divisor;
+    }
+    Ok(schedule(&weights))
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// The order in which to pick the weighted items, each appearing in proportion to its weight and
+/// spread out as evenly as possible, using smooth weighted round-robin.
+fn schedule<T: Copy>(weights: &[(T, u32)]) -> Vec<T> {
+    let total: i64 = weights.iter().map(|(_, weight)| *weight as i64).sum();
+    let mut current = vec![0i64; weights.len()];
+    let mut schedule = Vec::with_capacity(total as usize);
+    for _ in 0..total {
+        for (current, (_, weight)) in current.iter_mut().zip(weights) {
+            *current += *weight as i64;
+        }
+        let (chosen, _) = current
+            .iter()
+            .enumerate()
+            .max_by_key(|(i, current)| (**current, std::cmp::Reverse(*i)))
+            .unwrap();
+        current[chosen] -= total;
+        schedule.push(weights[chosen].0);
+    }
+    schedule
+}